@@ -0,0 +1,95 @@
+use crate::UnconnectedUdp;
+use core::net::{IpAddr, SocketAddr};
+use embedded_nal::{
+	Clock, DhcpLeases, DhcpServerConfig, DhcpServerLease, DHCP_CLIENT_PORT, DHCP_MESSAGE_LEN,
+};
+
+/// A DHCPv4 server handing out the `N` addresses of a fixed pool on any [`UnconnectedUdp`]
+/// socket, for example to the clients of an access point.
+///
+/// Leases are tracked in a [`DhcpLeases`] table, and expire by the time of `clock`. The socket
+/// is usually bound to [`DHCP_SERVER_PORT`](crate::DHCP_SERVER_PORT) of all local addresses
+/// through [`UdpStack::bind_multiple()`](crate::UdpStack::bind_multiple), as clients without
+/// an address send to the limited broadcast address; replies are sent from the
+/// [server address](DhcpServerConfig::server) of the configuration.
+///
+/// ```
+/// use embedded_nal_async::{Clock, DhcpServer, DhcpServerConfig, UdpStack, DHCP_SERVER_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// use core::time::Duration;
+///
+/// async fn serve_access_point<S: UdpStack, C: Clock>(stack: &S, clock: C) -> Result<(), S::Error> {
+///     let address = Ipv4Addr::new(192, 168, 4, 1);
+///     let config = DhcpServerConfig {
+///         server: address,
+///         pool_start: Ipv4Addr::new(192, 168, 4, 100),
+///         netmask: Ipv4Addr::new(255, 255, 255, 0),
+///         router: Some(address),
+///         dns_servers: &[address],
+///         lease_time: Duration::from_secs(2 * 3600),
+///     };
+///
+///     let local = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), DHCP_SERVER_PORT);
+///     let mut server: DhcpServer<_, _> = DhcpServer::new(stack.bind_multiple(local).await?, clock);
+///     loop {
+///         server.serve_one(&config).await?;
+///     }
+/// }
+/// ```
+pub struct DhcpServer<U, C, const N: usize = 8> {
+	socket: U,
+	clock: C,
+	client_port: u16,
+	leases: DhcpLeases<N>,
+}
+
+impl<U: UnconnectedUdp, C: Clock, const N: usize> DhcpServer<U, C, N> {
+	/// Create a server answering the client messages received on `socket`, timing leases with
+	/// `clock`.
+	pub fn new(socket: U, clock: C) -> Self {
+		DhcpServer {
+			socket,
+			clock,
+			client_port: DHCP_CLIENT_PORT,
+			leases: DhcpLeases::new(),
+		}
+	}
+
+	/// Set the port replies are sent to; the default is [`DHCP_CLIENT_PORT`], and other ports
+	/// are only useful for testing.
+	pub fn set_client_port(&mut self, client_port: u16) {
+		self.client_port = client_port;
+	}
+
+	/// The leases that are currently granted, given the `config` the server is serving with.
+	pub fn leases<'a>(
+		&'a mut self,
+		config: &DhcpServerConfig<'_>,
+	) -> impl Iterator<Item = DhcpServerLease> + 'a {
+		let now = self.clock.now();
+		self.leases.iter(config, now)
+	}
+
+	/// Wait for a client message, and answer it, handing out leases with `config`.
+	///
+	/// Datagrams that are not DHCP client messages are received and dropped without a reply.
+	pub async fn serve_one(&mut self, config: &DhcpServerConfig<'_>) -> Result<(), U::Error> {
+		let mut request = [0; DHCP_MESSAGE_LEN];
+		let mut response = [0; DHCP_MESSAGE_LEN];
+		let (len, local, _) = self.socket.receive_into(&mut request).await?;
+		let request = &request[..len.min(DHCP_MESSAGE_LEN)];
+		let now = self.clock.now();
+		let Some((len, destination)) = self.leases.respond(config, request, now, &mut response)
+		else {
+			return Ok(());
+		};
+		let local = SocketAddr::new(IpAddr::V4(config.server), local.port());
+		let remote = SocketAddr::new(IpAddr::V4(destination), self.client_port);
+		self.socket.send(local, remote, &response[..len]).await
+	}
+
+	/// Release the socket and clock.
+	pub fn free(self) -> (U, C) {
+		(self.socket, self.clock)
+	}
+}
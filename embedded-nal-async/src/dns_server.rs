@@ -0,0 +1,56 @@
+use crate::UnconnectedUdp;
+use embedded_nal::{DnsZone, DNS_MESSAGE_LEN};
+
+/// A DNS server answering queries from a [`DnsZone`] on any [`UnconnectedUdp`] socket, for
+/// example to make the devices connected to an access point find the device, or to have them
+/// detect a captive portal.
+///
+/// The socket is usually bound to [`DNS_PORT`](crate::DNS_PORT) of all local addresses through
+/// [`UdpStack::bind_multiple()`](crate::UdpStack::bind_multiple); responses are sent from the
+/// address the query was received on.
+///
+/// ```
+/// use embedded_nal_async::{DnsServer, DnsZone, UdpStack, DNS_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+///
+/// async fn serve_portal<S: UdpStack>(stack: &S) -> Result<(), S::Error> {
+///     let portal = [IpAddr::V4(Ipv4Addr::new(192, 168, 4, 1))];
+///     let mut zone = DnsZone::new(&[]);
+///     zone.set_wildcard(&portal);
+///
+///     let local = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), DNS_PORT);
+///     let mut server = DnsServer::new(stack.bind_multiple(local).await?);
+///     loop {
+///         server.serve_one(&zone).await?;
+///     }
+/// }
+/// ```
+pub struct DnsServer<U> {
+	socket: U,
+}
+
+impl<U: UnconnectedUdp> DnsServer<U> {
+	/// Create a server answering the queries received on `socket`.
+	pub fn new(socket: U) -> Self {
+		DnsServer { socket }
+	}
+
+	/// Wait for a query, and answer it from `zone`.
+	///
+	/// Datagrams that are not DNS queries are received and dropped without an answer.
+	pub async fn serve_one(&mut self, zone: &DnsZone<'_>) -> Result<(), U::Error> {
+		let mut query = [0; DNS_MESSAGE_LEN];
+		let mut response = [0; DNS_MESSAGE_LEN];
+		let (len, local, remote) = self.socket.receive_into(&mut query).await?;
+		let query = &query[..len.min(DNS_MESSAGE_LEN)];
+		match zone.respond(query, &mut response) {
+			Some(len) => self.socket.send(local, remote, &response[..len]).await,
+			None => Ok(()),
+		}
+	}
+
+	/// Release the socket.
+	pub fn free(self) -> U {
+		self.socket
+	}
+}
@@ -0,0 +1,279 @@
+use crate::{AddrType, Dns, TcpConnect};
+use core::net::{IpAddr, SocketAddr};
+use embedded_io_async::{Read, Write};
+use embedded_nal::{
+	HttpError, HttpMethod, HttpProtocolError, HttpRedirect, HttpRequestHead, HttpResponse,
+	HttpResponseReader, HttpUrl, HTTP_MAX_REDIRECTS,
+};
+
+/// A minimal HTTP/1.1 client on top of any [`TcpConnect`] and [`Dns`] implementation.
+///
+/// Requests are sent and responses received into a buffer provided by the caller, which has to
+/// hold the request head and the complete response head and body; no allocation is performed.
+/// Bodies with a `Content-Length` as well as chunked bodies are supported. The connection is
+/// kept open after a response if the server allows it, and is reused for the next request to
+/// the same address; `GET` requests are sent again on a new connection if the server closed it
+/// in the meantime. Redirects to `http://` URLs are followed, up to 5 times by default.
+///
+/// ```
+/// use embedded_nal_async::{AddrType, Dns, HttpClient, TcpConnect, TcpConnection};
+/// use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+/// use core::cell::{Cell, RefCell};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+///
+/// # struct Server { replies: RefCell<Vec<&'static [u8]>>, connections: Cell<usize> }
+/// # struct Connection<'a> { server: &'a Server, reply: &'static [u8] }
+/// # impl TcpConnect for Server {
+/// #     type Error = ErrorKind;
+/// #     type Connection<'a> = Connection<'a>;
+/// #     async fn connect<'a>(&'a self, _: SocketAddr) -> Result<Connection<'a>, ErrorKind> {
+/// #         self.connections.set(self.connections.get() + 1);
+/// #         Ok(Connection { server: self, reply: &[] })
+/// #     }
+/// # }
+/// # impl ErrorType for Connection<'_> { type Error = ErrorKind; }
+/// # // Hands out the reply 7 bytes at a time, then reports the connection as closed.
+/// # impl Read for Connection<'_> {
+/// #     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+/// #         let len = buf.len().min(self.reply.len()).min(7);
+/// #         buf[..len].copy_from_slice(&self.reply[..len]);
+/// #         self.reply = &self.reply[len..];
+/// #         Ok(len)
+/// #     }
+/// # }
+/// # // Replies to each request head with the next reply.
+/// # impl Write for Connection<'_> {
+/// #     async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+/// #         if buf.starts_with(b"GET ") {
+/// #             self.reply = self.server.replies.borrow_mut().remove(0);
+/// #         }
+/// #         Ok(buf.len())
+/// #     }
+/// #     async fn flush(&mut self) -> Result<(), ErrorKind> { Ok(()) }
+/// # }
+/// # impl TcpConnection for Connection<'_> {
+/// #     fn local_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([127, 0, 0, 1], 50000).into()) }
+/// #     fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([127, 0, 0, 1], 80).into()) }
+/// # }
+/// # struct Resolver;
+/// # impl Dns for Resolver {
+/// #     type Error = ();
+/// #     async fn get_host_by_name(&self, _: &str, _: AddrType) -> Result<IpAddr, ()> {
+/// #         Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+/// #     }
+/// #     async fn get_host_by_address(&self, _: IpAddr, _: &mut [u8]) -> Result<usize, ()> {
+/// #         Err(())
+/// #     }
+/// # }
+/// # let stack = Server {
+/// #     replies: RefCell::new(vec![
+/// #         b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\nHello world!",
+/// #         b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nAlice\r\n0\r\n\r\n",
+/// #         b"HTTP/1.1 200 OK\r\n\r\nHave a nice day",
+/// #         b"HTTP/1.1 301 Moved Permanently\r\nLocation: /old\r\nContent-Length: 0\r\n\r\n",
+/// #         b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n",
+/// #     ]),
+/// #     connections: Cell::new(0),
+/// # };
+/// let mut client = HttpClient::new(&stack, Resolver);
+/// futures_executor::block_on(async {
+///     let mut buffer = [0; 512];
+///     let response = client.get("http://example.com/greeting", &mut buffer).await.unwrap();
+///     assert_eq!(response.status, 200);
+///     assert_eq!(response.body, b"Hello world!");
+///
+///     // The connection is kept open for the next requests, up to a response whose body is
+///     // only delimited by the server closing the connection.
+///     let response = client.get("http://example.com/name", &mut buffer).await.unwrap();
+///     assert_eq!(response.body, b"Alice");
+///     let response = client.get("http://example.com/motd", &mut buffer).await.unwrap();
+///     assert_eq!(response.body, b"Have a nice day");
+///
+///     // Once the limit is reached, the redirect itself is returned.
+///     client.set_max_redirects(1);
+///     let response = client.get("http://example.com/older", &mut buffer).await.unwrap();
+///     assert_eq!(response.status, 301);
+///     assert_eq!(response.header("Location"), Some("/new"));
+/// });
+/// assert_eq!(stack.connections.get(), 2);
+/// ```
+pub struct HttpClient<'a, T: TcpConnect + 'a, D> {
+	stack: &'a T,
+	dns: D,
+	connection: Option<(T::Connection<'a>, SocketAddr)>,
+	max_redirects: u8,
+}
+
+impl<'a, T: TcpConnect, D: Dns> HttpClient<'a, T, D> {
+	/// Create a client that connects through `stack`, resolving host names through `dns`.
+	pub fn new(stack: &'a T, dns: D) -> Self {
+		HttpClient {
+			stack,
+			dns,
+			connection: None,
+			max_redirects: HTTP_MAX_REDIRECTS,
+		}
+	}
+
+	/// Set the number of redirects that are followed for a single request.
+	///
+	/// Once the limit is reached, the redirect response is returned. A limit of 0 disables
+	/// following redirects.
+	pub fn set_max_redirects(&mut self, max_redirects: u8) {
+		self.max_redirects = max_redirects;
+	}
+
+	/// Send a `GET` request for `url`, and receive the response into `buffer`.
+	pub async fn get<'b>(
+		&mut self,
+		url: &str,
+		buffer: &'b mut [u8],
+	) -> Result<HttpResponse<'b>, HttpError<T::Error, D::Error>> {
+		self.request(HttpMethod::Get, url, &[], &[], buffer).await
+	}
+
+	/// Send a request with the given header fields and body, and receive the response into
+	/// `buffer`.
+	///
+	/// A `Content-Length` header is added for `POST` and `PUT` requests, and for any request
+	/// with a non-empty body; `headers` must not contain one.
+	///
+	/// The header fields are only sent to the host and port of `url`, and left out when
+	/// following a redirect elsewhere.
+	pub async fn request<'b>(
+		&mut self,
+		method: HttpMethod,
+		url: &str,
+		headers: &[(&str, &str)],
+		body: &[u8],
+		buffer: &'b mut [u8],
+	) -> Result<HttpResponse<'b>, HttpError<T::Error, D::Error>> {
+		let origin = HttpUrl::parse(url)?;
+		let mut method = method;
+		let mut body = body;
+		let mut location: Option<HttpRedirect> = None;
+		let mut redirects = 0;
+		loop {
+			let url = HttpUrl::parse(location.as_ref().map_or(url, HttpRedirect::location))?;
+			let head = HttpRequestHead {
+				method,
+				url,
+				// The header fields may hold credentials, which are not for other hosts.
+				headers: match url.same_origin(&origin) {
+					true => headers,
+					false => &[],
+				},
+				content_length: match method {
+					HttpMethod::Post | HttpMethod::Put => Some(body.len()),
+					_ => Some(body.len()).filter(|len| *len > 0),
+				},
+			};
+			let reader = self.exchange(&head, body, buffer).await?;
+
+			let redirect = match redirects < self.max_redirects {
+				true => reader
+					.response(buffer)
+					.and_then(|response| response.redirect(method, &url)),
+				false => None,
+			};
+			match redirect {
+				Some(redirect) => {
+					if redirect.method != method {
+						body = &[];
+					}
+					method = redirect.method;
+					location = Some(redirect);
+					redirects += 1;
+				}
+				None => return Ok(reader.response(buffer).ok_or(HttpProtocolError::Closed)?),
+			}
+		}
+	}
+
+	/// Close the connection kept open for further requests, if any.
+	pub fn close(&mut self) {
+		self.connection = None;
+	}
+
+	/// Send a request and receive the response, on a kept-open connection if possible.
+	async fn exchange(
+		&mut self,
+		head: &HttpRequestHead<'_>,
+		body: &[u8],
+		buffer: &mut [u8],
+	) -> Result<HttpResponseReader, HttpError<T::Error, D::Error>> {
+		let remote = SocketAddr::new(self.resolve(&head.url).await?, head.url.port);
+
+		let (mut connection, reused) = match self.connection.take() {
+			Some((connection, addr)) if addr == remote => (connection, true),
+			_ => (self.connect(remote).await?, false),
+		};
+
+		let mut result = exchange_on(&mut connection, head, body, buffer).await;
+		// Other requests may have been acted on already, and must not be sent twice.
+		if reused
+			&& head.method == HttpMethod::Get
+			&& matches!(
+				result,
+				Err(HttpError::Transport(_) | HttpError::Protocol(HttpProtocolError::Closed))
+			) {
+			// The server may have closed the kept-open connection in the meantime.
+			connection = self.connect(remote).await?;
+			result = exchange_on(&mut connection, head, body, buffer).await;
+		}
+
+		if matches!(&result, Ok(reader) if reader.keep_alive()) {
+			self.connection = Some((connection, remote));
+		}
+		result
+	}
+
+	async fn connect(
+		&self,
+		remote: SocketAddr,
+	) -> Result<T::Connection<'a>, HttpError<T::Error, D::Error>> {
+		self.stack
+			.connect(remote)
+			.await
+			.map_err(HttpError::Transport)
+	}
+
+	async fn resolve(&self, url: &HttpUrl<'_>) -> Result<IpAddr, HttpError<T::Error, D::Error>> {
+		match url.hostname().parse() {
+			Ok(addr) => Ok(addr),
+			Err(_) => self
+				.dns
+				.get_host_by_name(url.hostname(), AddrType::Either)
+				.await
+				.map_err(HttpError::Dns),
+		}
+	}
+}
+
+/// Send a request on `connection`, and receive the response into `buffer`.
+async fn exchange_on<C: Read + Write, D>(
+	connection: &mut C,
+	head: &HttpRequestHead<'_>,
+	body: &[u8],
+	buffer: &mut [u8],
+) -> Result<HttpResponseReader, HttpError<C::Error, D>> {
+	let len = head.write(buffer)?;
+	connection
+		.write_all(&buffer[..len])
+		.await
+		.map_err(HttpError::Transport)?;
+	connection
+		.write_all(body)
+		.await
+		.map_err(HttpError::Transport)?;
+	connection.flush().await.map_err(HttpError::Transport)?;
+
+	let mut reader = HttpResponseReader::new();
+	loop {
+		let spare = reader.spare(buffer)?;
+		let len = connection.read(spare).await.map_err(HttpError::Transport)?;
+		if reader.advance(buffer, len)? {
+			return Ok(reader);
+		}
+	}
+}
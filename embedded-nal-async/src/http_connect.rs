@@ -0,0 +1,379 @@
+use crate::{TcpConnect, TcpConnection};
+use core::fmt::Write as _;
+use core::net::SocketAddr;
+use embedded_io_async::{ErrorKind, ErrorType, Read, ReadExactError, Write};
+use embedded_nal::HttpWriter;
+
+/// Username and password for Basic authentication with an HTTP proxy (see [RFC 7617]).
+///
+/// [RFC 7617]: https://tools.ietf.org/html/rfc7617
+#[derive(Copy, Clone, Debug)]
+pub struct BasicCredentials<'a> {
+	/// The username, which must not contain a colon
+	pub username: &'a str,
+	/// The password
+	pub password: &'a str,
+}
+
+/// Errors returned by [`HttpConnect`] and its connections.
+#[derive(Debug)]
+pub enum HttpConnectError<E> {
+	/// The underlying TCP connection failed.
+	Transport(E),
+	/// The proxy sent a response that is not valid HTTP, or whose header is too long.
+	Protocol,
+	/// The proxy refused to establish the tunnel; the HTTP status code is given.
+	Rejected(u16),
+	/// The host name or credentials are too long.
+	InvalidInput,
+	/// The proxy closed the connection before establishing the tunnel.
+	Closed,
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for HttpConnectError<E> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		core::fmt::Debug::fmt(self, f)
+	}
+}
+
+impl<E: core::fmt::Debug> core::error::Error for HttpConnectError<E> {}
+
+impl<E: embedded_io_async::Error> embedded_io_async::Error for HttpConnectError<E> {
+	fn kind(&self) -> ErrorKind {
+		match self {
+			HttpConnectError::Transport(e) => e.kind(),
+			HttpConnectError::Protocol => ErrorKind::InvalidData,
+			HttpConnectError::Rejected(401 | 403 | 407) => ErrorKind::PermissionDenied,
+			HttpConnectError::Rejected(504) => ErrorKind::TimedOut,
+			HttpConnectError::Rejected(_) => ErrorKind::ConnectionRefused,
+			HttpConnectError::InvalidInput => ErrorKind::InvalidInput,
+			HttpConnectError::Closed => ErrorKind::ConnectionAborted,
+		}
+	}
+}
+
+impl<E> From<ReadExactError<E>> for HttpConnectError<E> {
+	fn from(e: ReadExactError<E>) -> Self {
+		match e {
+			ReadExactError::UnexpectedEof => HttpConnectError::Closed,
+			ReadExactError::Other(e) => HttpConnectError::Transport(e),
+		}
+	}
+}
+
+/// Longest response header accepted from the proxy
+const MAX_RESPONSE_LEN: usize = 4096;
+
+/// A client for tunnelling TCP connections through an HTTP proxy (see [RFC 9110, section 9.3.6]),
+/// on top of any [`TcpConnect`] stack.
+///
+/// Connections are made to the proxy, which is asked to connect to the actual remote host through
+/// a `CONNECT` request. Once the proxy confirmed with a 2xx response, data is tunnelled
+/// unchanged, so any protocol built on [`TcpConnect`] can use this in place of a direct
+/// connection. Remote hosts can also be given by name through
+/// [`.connect_to_host()`](HttpConnect::connect_to_host), leaving name resolution to the proxy.
+///
+/// ```
+/// use embedded_nal_async::{BasicCredentials, HttpConnect, HttpConnectError, TcpConnect, TcpConnection};
+/// use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+/// use core::cell::RefCell;
+/// use core::net::SocketAddr;
+///
+/// # struct Mock { reply: &'static [u8], sent: RefCell<Vec<u8>> }
+/// # struct MockConnection<'a> { mock: &'a Mock, reply: &'static [u8] }
+/// # impl TcpConnect for Mock {
+/// #     type Error = ErrorKind;
+/// #     type Connection<'a> = MockConnection<'a>;
+/// #     async fn connect<'a>(&'a self, _: SocketAddr) -> Result<MockConnection<'a>, ErrorKind> {
+/// #         Ok(MockConnection { mock: self, reply: self.reply })
+/// #     }
+/// # }
+/// # impl ErrorType for MockConnection<'_> { type Error = ErrorKind; }
+/// # impl Read for MockConnection<'_> {
+/// #     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+/// #         let len = buf.len().min(self.reply.len());
+/// #         buf[..len].copy_from_slice(&self.reply[..len]);
+/// #         self.reply = &self.reply[len..];
+/// #         Ok(len)
+/// #     }
+/// # }
+/// # impl Write for MockConnection<'_> {
+/// #     async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+/// #         self.mock.sent.borrow_mut().extend_from_slice(buf);
+/// #         Ok(buf.len())
+/// #     }
+/// #     async fn flush(&mut self) -> Result<(), ErrorKind> { Ok(()) }
+/// # }
+/// # impl TcpConnection for MockConnection<'_> {
+/// #     fn local_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 2], 50000).into()) }
+/// #     fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 1], 3128).into()) }
+/// # }
+/// let proxy = ([10, 0, 0, 1], 3128).into();
+/// let remote: SocketAddr = ([93, 184, 216, 34], 80).into();
+/// let credentials = Some(BasicCredentials { username: "user", password: "secret" });
+///
+/// // The proxy establishes the tunnel; the data right after its response is the remote host's.
+/// let stack = Mock {
+///     reply: b"HTTP/1.1 200 Connection established\r\nVia: proxy\r\n\r\nok",
+///     sent: RefCell::new(Vec::new()),
+/// };
+/// let proxied = HttpConnect::new(&stack, proxy, credentials);
+/// futures_executor::block_on(async {
+///     let mut connection = proxied.connect(remote).await.unwrap();
+///     assert_eq!(connection.peer_addr().unwrap(), remote);
+///     connection.write_all(b"hi").await.unwrap();
+///     let mut data = [0; 2];
+///     connection.read_exact(&mut data).await.unwrap();
+///     assert_eq!(&data, b"ok");
+/// });
+/// assert_eq!(
+///     stack.sent.borrow().as_slice(),
+///     b"CONNECT 93.184.216.34:80 HTTP/1.1\r\nHost: 93.184.216.34:80\r\n\
+///       Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n\r\nhi",
+/// );
+///
+/// // The proxy wants different credentials.
+/// let stack = Mock {
+///     reply: b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n",
+///     sent: RefCell::new(Vec::new()),
+/// };
+/// let proxied = HttpConnect::new(&stack, proxy, credentials);
+/// let result = futures_executor::block_on(proxied.connect_to_host("example.com", 80));
+/// assert!(matches!(result, Err(HttpConnectError::Rejected(407))));
+/// assert!(stack.sent.borrow().starts_with(b"CONNECT example.com:80 HTTP/1.1\r\n"));
+///
+/// // The response header does not end within the length accepted.
+/// let mut reply = b"HTTP/1.1 200 OK\r\n".to_vec();
+/// reply.resize(5000, b'x');
+/// let stack = Mock { reply: reply.leak(), sent: RefCell::new(Vec::new()) };
+/// let proxied = HttpConnect::new(&stack, proxy, None);
+/// let result = futures_executor::block_on(proxied.connect(remote));
+/// assert!(matches!(result, Err(HttpConnectError::Protocol)));
+/// ```
+///
+/// [RFC 9110, section 9.3.6]: https://www.rfc-editor.org/rfc/rfc9110#section-9.3.6
+pub struct HttpConnect<'c, T> {
+	stack: T,
+	proxy: SocketAddr,
+	credentials: Option<BasicCredentials<'c>>,
+}
+
+impl<'c, T: TcpConnect> HttpConnect<'c, T> {
+	/// Create a client that connects to the HTTP proxy at `proxy` through `stack`.
+	///
+	/// If `credentials` are given, they are sent to the proxy through the `Proxy-Authorization`
+	/// header.
+	pub fn new(stack: T, proxy: SocketAddr, credentials: Option<BasicCredentials<'c>>) -> Self {
+		HttpConnect {
+			stack,
+			proxy,
+			credentials,
+		}
+	}
+
+	/// Connect to the given remote host name and port through the proxy.
+	///
+	/// The name is resolved by the proxy.
+	pub async fn connect_to_host<'a>(
+		&'a self,
+		host: &str,
+		port: u16,
+	) -> Result<HttpConnectConnection<T::Connection<'a>>, HttpConnectError<T::Error>> {
+		let mut buffer = [0; 255 + 6];
+		let mut authority = HttpWriter::new(&mut buffer);
+		write!(authority, "{host}:{port}").map_err(|_| HttpConnectError::InvalidInput)?;
+		self.tunnel(authority.as_str(), None).await
+	}
+
+	async fn tunnel<'a>(
+		&'a self,
+		authority: &str,
+		target: Option<SocketAddr>,
+	) -> Result<HttpConnectConnection<T::Connection<'a>>, HttpConnectError<T::Error>> {
+		let mut buffer = [0; 4 * 512 / 3 + 4];
+		let mut authorization = HttpWriter::new(&mut buffer);
+		if let Some(credentials) = &self.credentials {
+			let mut buffer = [0; 512];
+			let mut user_pass = HttpWriter::new(&mut buffer);
+			write!(
+				user_pass,
+				"{}:{}",
+				credentials.username, credentials.password
+			)
+			.map_err(|_| HttpConnectError::InvalidInput)?;
+			base64(user_pass.as_str().as_bytes(), &mut authorization)
+				.map_err(|_| HttpConnectError::InvalidInput)?;
+		}
+
+		let mut connection = self
+			.stack
+			.connect(self.proxy)
+			.await
+			.map_err(HttpConnectError::Transport)?;
+
+		for part in [
+			"CONNECT ",
+			authority,
+			" HTTP/1.1\r\nHost: ",
+			authority,
+			"\r\n",
+		] {
+			connection
+				.write_all(part.as_bytes())
+				.await
+				.map_err(HttpConnectError::Transport)?;
+		}
+		if self.credentials.is_some() {
+			for part in [
+				"Proxy-Authorization: Basic ",
+				authorization.as_str(),
+				"\r\n",
+			] {
+				connection
+					.write_all(part.as_bytes())
+					.await
+					.map_err(HttpConnectError::Transport)?;
+			}
+		}
+		connection
+			.write_all(b"\r\n")
+			.await
+			.map_err(HttpConnectError::Transport)?;
+		connection
+			.flush()
+			.await
+			.map_err(HttpConnectError::Transport)?;
+
+		match read_response(&mut connection).await? {
+			200..=299 => Ok(HttpConnectConnection { connection, target }),
+			status => Err(HttpConnectError::Rejected(status)),
+		}
+	}
+}
+
+impl<'c, T: TcpConnect> TcpConnect for HttpConnect<'c, T> {
+	type Error = HttpConnectError<T::Error>;
+
+	type Connection<'a>
+		= HttpConnectConnection<T::Connection<'a>>
+	where
+		Self: 'a;
+
+	async fn connect<'a>(
+		&'a self,
+		remote: SocketAddr,
+	) -> Result<Self::Connection<'a>, Self::Error> {
+		let mut buffer = [0; 64];
+		let mut authority = HttpWriter::new(&mut buffer);
+		write!(authority, "{remote}").map_err(|_| HttpConnectError::InvalidInput)?;
+		self.tunnel(authority.as_str(), Some(remote)).await
+	}
+}
+
+/// Read the proxy's response header, returning its status code.
+///
+/// The header is read byte by byte, so that no data sent through the tunnel right after it is
+/// consumed.
+async fn read_response<C: Read>(connection: &mut C) -> Result<u16, HttpConnectError<C::Error>> {
+	// "HTTP/1.1 200"
+	let mut status_line = [0; 12];
+	connection.read_exact(&mut status_line).await?;
+	let status = match status_line {
+		[b'H', b'T', b'T', b'P', b'/', b'1', b'.', _, b' ', digits @ ..]
+			if digits.iter().all(u8::is_ascii_digit) =>
+		{
+			digits
+				.iter()
+				.fold(0, |status, digit| status * 10 + u16::from(digit - b'0'))
+		}
+		_ => return Err(HttpConnectError::Protocol),
+	};
+
+	// Skip the rest of the header, up to and including the empty line.
+	let mut last = [0; 4];
+	for _ in status_line.len()..MAX_RESPONSE_LEN {
+		let mut byte = [0];
+		connection.read_exact(&mut byte).await?;
+		last = [last[1], last[2], last[3], byte[0]];
+		if &last == b"\r\n\r\n" {
+			return Ok(status);
+		}
+	}
+	Err(HttpConnectError::Protocol)
+}
+
+/// A connection tunnelled through an [`HttpConnect`] proxy.
+pub struct HttpConnectConnection<C> {
+	connection: C,
+	target: Option<SocketAddr>,
+}
+
+impl<C: ErrorType> ErrorType for HttpConnectConnection<C> {
+	type Error = HttpConnectError<C::Error>;
+}
+
+impl<C: Read> Read for HttpConnectConnection<C> {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		self.connection
+			.read(buf)
+			.await
+			.map_err(HttpConnectError::Transport)
+	}
+}
+
+impl<C: Write> Write for HttpConnectConnection<C> {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		self.connection
+			.write(buf)
+			.await
+			.map_err(HttpConnectError::Transport)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.connection
+			.flush()
+			.await
+			.map_err(HttpConnectError::Transport)
+	}
+}
+
+impl<C: TcpConnection> TcpConnection for HttpConnectConnection<C> {
+	fn local_addr(&self) -> Result<SocketAddr, Self::Error> {
+		self.connection
+			.local_addr()
+			.map_err(HttpConnectError::Transport)
+	}
+
+	/// The remote address is the one passed to [`TcpConnect::connect`]; for connections
+	/// established by host name, it is the proxy's address.
+	fn peer_addr(&self) -> Result<SocketAddr, Self::Error> {
+		match self.target {
+			Some(remote) => Ok(remote),
+			None => self
+				.connection
+				.peer_addr()
+				.map_err(HttpConnectError::Transport),
+		}
+	}
+}
+
+/// Append the Base64 encoding (with padding) of `data` to `out`.
+fn base64(data: &[u8], out: &mut impl core::fmt::Write) -> core::fmt::Result {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	for chunk in data.chunks(3) {
+		let bytes = [
+			chunk[0],
+			*chunk.get(1).unwrap_or(&0),
+			*chunk.get(2).unwrap_or(&0),
+		];
+		let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+		let mut encoded = [b'='; 4];
+		for (i, symbol) in encoded.iter_mut().take(chunk.len() + 1).enumerate() {
+			*symbol = ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize];
+		}
+		// The alphabet and padding are ASCII.
+		out.write_str(core::str::from_utf8(&encoded).map_err(|_| core::fmt::Error)?)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,100 @@
+use core::net::IpAddr;
+use core::time::Duration;
+use embedded_nal::{EchoReply, PingStatistics};
+
+/// This trait is implemented by network stacks that can send ICMP (IPv4) and ICMPv6 echo requests
+/// and receive the corresponding replies, as used by the `ping` utility.
+pub trait IcmpEcho {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Send an echo request to `remote` and wait for the matching reply.
+	///
+	/// The request carries the given `identifier`, `sequence` number and `payload`; the ICMP or
+	/// ICMPv6 variant is picked from the address family of `remote`. The payload of the reply is
+	/// stored at the beginning of `buffer`.
+	///
+	/// Returns `Ok(None)` if no reply arrived within `timeout`.
+	async fn echo(
+		&self,
+		remote: IpAddr,
+		identifier: u16,
+		sequence: u16,
+		payload: &[u8],
+		timeout: Duration,
+		buffer: &mut [u8],
+	) -> Result<Option<EchoReply>, Self::Error>;
+}
+
+impl<T: IcmpEcho> IcmpEcho for &T {
+	type Error = T::Error;
+
+	async fn echo(
+		&self,
+		remote: IpAddr,
+		identifier: u16,
+		sequence: u16,
+		payload: &[u8],
+		timeout: Duration,
+		buffer: &mut [u8],
+	) -> Result<Option<EchoReply>, Self::Error> {
+		T::echo(self, remote, identifier, sequence, payload, timeout, buffer).await
+	}
+}
+
+/// A series of echo requests to a single host, collecting [`PingStatistics`].
+///
+/// This is the async counterpart of [`embedded_nal::PingSession`].
+#[derive(Clone, Debug)]
+pub struct PingSession {
+	remote: IpAddr,
+	identifier: u16,
+	sequence: u16,
+	timeout: Duration,
+	statistics: PingStatistics,
+}
+
+impl PingSession {
+	/// Start a session pinging `remote`, with requests carrying the given `identifier` and timing
+	/// out after `timeout`.
+	pub fn new(remote: IpAddr, identifier: u16, timeout: Duration) -> Self {
+		PingSession {
+			remote,
+			identifier,
+			sequence: 0,
+			timeout,
+			statistics: PingStatistics::default(),
+		}
+	}
+
+	/// Send the next echo request, wait for its reply, and account for the result in the
+	/// statistics.
+	pub async fn ping<S: IcmpEcho + ?Sized>(
+		&mut self,
+		stack: &S,
+		payload: &[u8],
+		buffer: &mut [u8],
+	) -> Result<Option<EchoReply>, S::Error> {
+		let reply = stack
+			.echo(
+				self.remote,
+				self.identifier,
+				self.sequence,
+				payload,
+				self.timeout,
+				buffer,
+			)
+			.await?;
+		self.sequence = self.sequence.wrapping_add(1);
+		match reply {
+			Some(reply) => self.statistics.record_reply(reply.rtt),
+			None => self.statistics.record_loss(),
+		}
+		Ok(reply)
+	}
+
+	/// The statistics of all requests completed so far.
+	pub fn statistics(&self) -> &PingStatistics {
+		&self.statistics
+	}
+}
@@ -5,10 +5,58 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
+mod config;
+mod dhcp_server;
 mod dns;
+mod dns_server;
+mod http_client;
+mod http_connect;
+mod icmp;
+mod interface;
+mod loopback;
+mod sntp;
+mod socks5;
+mod srv;
 mod stack;
+mod status;
+mod syslog;
+#[cfg(feature = "tls")]
+mod tls;
 
-pub use dns::Dns;
-pub use embedded_nal::AddrType;
-pub use stack::TcpConnect;
-pub use stack::{ConnectedUdp, UdpStack, UnconnectedUdp};
+pub use config::NetworkConfig;
+pub use dhcp_server::DhcpServer;
+pub use dns::{Dns, DnsRecords};
+pub use dns_server::DnsServer;
+#[cfg(feature = "log")]
+pub use embedded_nal::SyslogLogger;
+pub use embedded_nal::{
+	gather_datagram, AddrType, Clock, DhcpLeases, DhcpServerConfig, DhcpServerLease, DnsZone,
+	DnsZoneEntry, EchoReply, HttpError, HttpMethod, HttpProtocolError, HttpResponse, HttpWriter,
+	InterfaceAddress, InterfaceInfo, IpConfig, LoopbackError, PingStatistics, SntpClock, SntpError,
+	SntpLeap, SntpRequest, SntpResponse, SntpTimestamp, Socks5Credentials, SrvConnectError,
+	SrvRecord, SrvRecords, SyslogFacility, SyslogMessage, SyslogQueue, SyslogSeverity,
+	SyslogStructuredData, TxtRecord, TxtRecords, UdpVectoredError, DHCP_CLIENT_PORT,
+	DHCP_MESSAGE_LEN, DHCP_SERVER_PORT, DNS_MESSAGE_LEN, DNS_PORT, HTTP_MAX_REDIRECTS,
+	SNTP_MESSAGE_LEN, SNTP_PORT, SYSLOG_MESSAGE_LEN, SYSLOG_OCTET_COUNT_LEN, SYSLOG_PORT,
+	SYSLOG_TCP_PORT, UDP_VECTORED_SEND_LEN,
+};
+#[cfg(feature = "tls")]
+pub use embedded_nal::{ClientCertificate, Psk, TlsClient, TlsConfig, TlsSession};
+pub use http_client::HttpClient;
+pub use http_connect::{BasicCredentials, HttpConnect, HttpConnectConnection, HttpConnectError};
+pub use icmp::{IcmpEcho, PingSession};
+pub use interface::NetworkInterfaces;
+pub use loopback::Loopback;
+pub use sntp::SntpClient;
+pub use socks5::{Socks5Connect, Socks5Connection, Socks5Error};
+pub use srv::connect_srv;
+pub use stack::{
+	ConnectedUdp, DatagramMetadata, Ecn, TimestampedConnectedUdp, TimestampedUnconnectedUdp,
+	UdpStack, UnconnectedUdp,
+};
+pub use stack::{RawIp, RawIpStack};
+pub use stack::{TcpConnect, TcpConnection};
+pub use status::NetworkStatus;
+pub use syslog::{SyslogClient, SyslogTcpClient};
+#[cfg(feature = "tls")]
+pub use tls::{TlsConnect, TlsConnection, TlsError};
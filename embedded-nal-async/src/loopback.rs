@@ -0,0 +1,131 @@
+use crate::{IcmpEcho, NetworkConfig, NetworkStatus};
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::net::IpAddr;
+use core::task::{Poll, Waker};
+use core::time::Duration;
+use embedded_nal::{nb, EchoReply, IpConfig, LoopbackError};
+
+/// An in-memory loopback network.
+///
+/// This is the async counterpart of [`embedded_nal::Loopback`], and behaves the same way. Tasks
+/// waiting in [`.wait_config_up()`](NetworkStatus::wait_config_up) are woken when a static
+/// configuration is set, and tasks waiting in
+/// [`.wait_config_change()`](NetworkStatus::wait_config_change) when a static configuration or
+/// DNS servers are set.
+pub struct Loopback<const N: usize> {
+	network: RefCell<embedded_nal::Loopback<N>>,
+	waker: RefCell<Option<Waker>>,
+	change_waker: RefCell<Option<Waker>>,
+}
+
+impl<const N: usize> Loopback<N> {
+	/// Create a loopback network without any configuration.
+	pub const fn new() -> Self {
+		Loopback {
+			network: RefCell::new(embedded_nal::Loopback::new()),
+			waker: RefCell::new(None),
+			change_waker: RefCell::new(None),
+		}
+	}
+}
+
+impl<const N: usize> Default for Loopback<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> NetworkConfig for Loopback<N> {
+	type Error = LoopbackError;
+
+	async fn set_static_config(&self, configs: &[IpConfig]) -> Result<(), Self::Error> {
+		embedded_nal::NetworkConfig::set_static_config(&mut *self.network.borrow_mut(), configs)?;
+		for waker in [&self.waker, &self.change_waker] {
+			if let Some(waker) = waker.borrow_mut().take() {
+				waker.wake();
+			}
+		}
+		Ok(())
+	}
+
+	async fn set_dhcp(&self, enabled: bool) -> Result<(), Self::Error> {
+		embedded_nal::NetworkConfig::set_dhcp(&mut *self.network.borrow_mut(), enabled)
+	}
+
+	async fn set_dns_servers(&self, servers: &[IpAddr]) -> Result<(), Self::Error> {
+		embedded_nal::NetworkConfig::set_dns_servers(&mut *self.network.borrow_mut(), servers)?;
+		if let Some(waker) = self.change_waker.borrow_mut().take() {
+			waker.wake();
+		}
+		Ok(())
+	}
+}
+
+impl<const N: usize> NetworkStatus for Loopback<N> {
+	type Error = LoopbackError;
+
+	async fn wait_config_up(&self) -> Result<(), Self::Error> {
+		poll_fn(|cx| {
+			match embedded_nal::NetworkStatus::wait_config_up(&mut *self.network.borrow_mut()) {
+				Err(nb::Error::WouldBlock) => {
+					*self.waker.borrow_mut() = Some(cx.waker().clone());
+					Poll::Pending
+				}
+				Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+				Ok(()) => Poll::Ready(Ok(())),
+			}
+		})
+		.await
+	}
+
+	async fn wait_link_down(&self) -> Result<(), Self::Error> {
+		core::future::pending().await
+	}
+
+	async fn wait_config_change(&self) -> Result<(), Self::Error> {
+		poll_fn(|cx| {
+			match embedded_nal::NetworkStatus::wait_config_change(&mut *self.network.borrow_mut()) {
+				Err(nb::Error::WouldBlock) => {
+					*self.change_waker.borrow_mut() = Some(cx.waker().clone());
+					Poll::Pending
+				}
+				Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+				Ok(()) => Poll::Ready(Ok(())),
+			}
+		})
+		.await
+	}
+
+	fn ip_config(&self, result: &mut [IpConfig]) -> Result<usize, Self::Error> {
+		embedded_nal::NetworkStatus::ip_config(&mut *self.network.borrow_mut(), result)
+	}
+
+	fn dns_servers(&self, result: &mut [IpAddr]) -> Result<usize, Self::Error> {
+		embedded_nal::NetworkStatus::dns_servers(&mut *self.network.borrow_mut(), result)
+	}
+}
+
+impl<const N: usize> IcmpEcho for Loopback<N> {
+	type Error = LoopbackError;
+
+	async fn echo(
+		&self,
+		remote: IpAddr,
+		identifier: u16,
+		sequence: u16,
+		payload: &[u8],
+		timeout: Duration,
+		buffer: &mut [u8],
+	) -> Result<Option<EchoReply>, Self::Error> {
+		nb::block!(embedded_nal::IcmpEcho::echo(
+			&mut *self.network.borrow_mut(),
+			remote,
+			identifier,
+			sequence,
+			payload,
+			timeout,
+			buffer
+		))
+	}
+}
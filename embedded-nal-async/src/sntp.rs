@@ -0,0 +1,178 @@
+use crate::{AddrType, ConnectedUdp, Dns, UdpStack};
+use core::net::{IpAddr, SocketAddr};
+use embedded_nal::{SntpClock, SntpError, SntpRequest, SntpResponse, SNTP_MESSAGE_LEN, SNTP_PORT};
+
+/// An SNTP client (see [RFC 4330]) on top of any [`UdpStack`] and [`Dns`] implementation.
+///
+/// Servers are queried in turn until one of them returns a valid time. A query waits for a
+/// response until one arrives; as there is no timer in the traits, callers are expected to
+/// bound [`.query_addr()`](SntpClient::query_addr) with a timeout of their executor, and move
+/// on to the next server themselves if it expires.
+///
+/// ```
+/// use embedded_nal_async::{SntpClient, SntpError, SntpTimestamp};
+/// use core::net::SocketAddr;
+/// use core::time::Duration;
+/// # use embedded_nal_async::{AddrType, ConnectedUdp, Dns, UdpStack, UnconnectedUdp};
+/// # use embedded_io_async::ErrorKind;
+/// # use core::net::IpAddr;
+/// # /// Servers running 1.5 s ahead of the client, 5 ms away, answering requests after 1 ms;
+/// # /// the one at 192.0.2.1 sends a kiss-o'-death, and the one at 192.0.2.2 is unsynchronized.
+/// # struct Network;
+/// # struct Socket { server: SocketAddr, request: [u8; 48] }
+/// # struct Unbound;
+/// # impl UdpStack for Network {
+/// #     type Error = ErrorKind;
+/// #     type Connected = Socket;
+/// #     type UniquelyBound = Unbound;
+/// #     type MultiplyBound = Unbound;
+/// #     async fn connect_from(&self, local: SocketAddr, remote: SocketAddr) -> Result<(SocketAddr, Socket), ErrorKind> {
+/// #         Ok((local, Socket { server: remote, request: [0; 48] }))
+/// #     }
+/// #     async fn bind_single(&self, _: SocketAddr) -> Result<(SocketAddr, Unbound), ErrorKind> {
+/// #         Err(ErrorKind::Unsupported)
+/// #     }
+/// #     async fn bind_multiple(&self, _: SocketAddr) -> Result<Unbound, ErrorKind> {
+/// #         Err(ErrorKind::Unsupported)
+/// #     }
+/// # }
+/// # impl ConnectedUdp for Socket {
+/// #     type Error = ErrorKind;
+/// #     async fn send(&mut self, data: &[u8]) -> Result<(), ErrorKind> {
+/// #         self.request.copy_from_slice(data);
+/// #         Ok(())
+/// #     }
+/// #     async fn receive_into(&mut self, buffer: &mut [u8]) -> Result<usize, ErrorKind> {
+/// #         let ms = |ms: u64| (ms << 32) / 1000;
+/// #         let sent = u64::from_be_bytes(self.request[40..].try_into().unwrap());
+/// #         let receive = sent + ms(1505);
+/// #         let (stratum, reference) = match self.server.ip().to_string().as_str() {
+/// #             "192.0.2.1" => (0, *b"RATE"),
+/// #             "192.0.2.2" => (16, [0; 4]),
+/// #             _ => (2, [192, 0, 2, 100]),
+/// #         };
+/// #         buffer[..48].fill(0);
+/// #         buffer[0] = (4 << 3) | 4;
+/// #         buffer[1] = stratum;
+/// #         buffer[12..16].copy_from_slice(&reference);
+/// #         buffer[24..32].copy_from_slice(&sent.to_be_bytes());
+/// #         buffer[32..40].copy_from_slice(&receive.to_be_bytes());
+/// #         buffer[40..48].copy_from_slice(&(receive + ms(1)).to_be_bytes());
+/// #         Ok(48)
+/// #     }
+/// # }
+/// # impl UnconnectedUdp for Unbound {
+/// #     type Error = ErrorKind;
+/// #     async fn send(&mut self, _: SocketAddr, _: SocketAddr, _: &[u8]) -> Result<(), ErrorKind> {
+/// #         Err(ErrorKind::Unsupported)
+/// #     }
+/// #     async fn receive_into(&mut self, _: &mut [u8]) -> Result<(usize, SocketAddr, SocketAddr), ErrorKind> {
+/// #         Err(ErrorKind::Unsupported)
+/// #     }
+/// # }
+/// # struct Resolver;
+/// # impl Dns for Resolver {
+/// #     type Error = ();
+/// #     async fn get_host_by_name(&self, _: &str, _: AddrType) -> Result<IpAddr, ()> { Err(()) }
+/// #     async fn get_host_by_address(&self, _: IpAddr, _: &mut [u8]) -> Result<usize, ()> { Err(()) }
+/// # }
+/// # let stack = Network;
+/// # let dns = Resolver;
+/// // A local clock advancing by 11 ms on every reading
+/// let mut local = Duration::from_secs(1_700_000_000);
+/// let clock = move || {
+///     local += Duration::from_millis(11);
+///     SntpTimestamp::from_unix(local)
+/// };
+/// let mut client = SntpClient::new(stack, dns, clock);
+///
+/// futures_executor::block_on(async {
+///     let response = client.query(&["192.0.2.1", "192.0.2.2", "192.0.2.3"]).await.unwrap();
+///     assert_eq!(response.server, "192.0.2.3:123".parse().unwrap());
+///     assert_eq!(response.stratum, 2);
+///     assert!((response.offset - 1_500_000_000).abs() < 1_000);
+///     assert!((response.delay.as_nanos() as i64 - 10_000_000).abs() < 1_000);
+///
+///     let result = client.query_addr("192.0.2.1:123".parse().unwrap()).await;
+///     assert!(matches!(result, Err(SntpError::KissOfDeath(code)) if &code == b"RATE"));
+///     let result = client.query_addr("192.0.2.2:123".parse().unwrap()).await;
+///     assert!(matches!(result, Err(SntpError::Unsynchronized)));
+/// });
+/// ```
+///
+/// [RFC 4330]: https://tools.ietf.org/html/rfc4330
+pub struct SntpClient<S, D, C> {
+	stack: S,
+	dns: D,
+	clock: C,
+}
+
+impl<S: UdpStack, D: Dns, C: SntpClock> SntpClient<S, D, C> {
+	/// Create a client that sends requests through `stack`, resolves server names through `dns`,
+	/// and takes the local time from `clock`.
+	pub fn new(stack: S, dns: D, clock: C) -> Self {
+		SntpClient { stack, dns, clock }
+	}
+
+	/// Query the given servers, given by name or IP address, in turn until one of them returns a
+	/// valid time.
+	///
+	/// If none does, the error of the last one is returned.
+	pub async fn query(
+		&mut self,
+		servers: &[&str],
+	) -> Result<SntpResponse, SntpError<S::Error, D::Error>> {
+		let mut result = Err(SntpError::NoServers);
+		for server in servers {
+			let addr = match server.parse::<IpAddr>() {
+				Ok(addr) => Ok(addr),
+				Err(_) => self
+					.dns
+					.get_host_by_name(server, AddrType::Either)
+					.await
+					.map_err(SntpError::Dns),
+			};
+			result = match addr {
+				Ok(addr) => self.query_addr(SocketAddr::new(addr, SNTP_PORT)).await,
+				Err(e) => Err(e),
+			};
+			if result.is_ok() {
+				break;
+			}
+		}
+		result
+	}
+
+	/// Query a single server.
+	pub async fn query_addr(
+		&mut self,
+		server: SocketAddr,
+	) -> Result<SntpResponse, SntpError<S::Error, D::Error>> {
+		let (_, mut socket) = self
+			.stack
+			.connect(server)
+			.await
+			.map_err(SntpError::Transport)?;
+		let request = SntpRequest {
+			transmit: self.clock.now(),
+		};
+		socket
+			.send(&request.message())
+			.await
+			.map_err(SntpError::Transport)?;
+
+		let mut buffer = [0; SNTP_MESSAGE_LEN];
+		loop {
+			let len = socket
+				.receive_into(&mut buffer)
+				.await
+				.map_err(SntpError::Transport)?;
+			let receive = self.clock.now();
+			// Longer datagrams are truncated to the buffer, with their full length returned.
+			let message = &buffer[..len.min(buffer.len())];
+			if let Some(result) = request.evaluate(message, server, receive) {
+				return result;
+			}
+		}
+	}
+}
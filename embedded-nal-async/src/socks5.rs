@@ -0,0 +1,342 @@
+use crate::{TcpConnect, TcpConnection};
+use core::net::{IpAddr, SocketAddr};
+use embedded_io_async::{ErrorKind, ErrorType, Read, ReadExactError, Write};
+use embedded_nal::Socks5Credentials;
+
+/// Errors returned by [`Socks5Connect`] and its connections.
+#[derive(Debug)]
+pub enum Socks5Error<E> {
+	/// The underlying TCP connection failed.
+	Transport(E),
+	/// The proxy sent a message that does not follow the SOCKS5 protocol.
+	Protocol,
+	/// The proxy does not accept any of the offered authentication methods.
+	NoAcceptableMethod,
+	/// The proxy rejected the credentials.
+	AuthenticationFailed,
+	/// The proxy could not establish the connection; the SOCKS5 reply code is given.
+	Rejected(u8),
+	/// The host name or credentials are longer than 255 bytes.
+	InvalidInput,
+	/// The proxy closed the connection during the handshake.
+	Closed,
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for Socks5Error<E> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		core::fmt::Debug::fmt(self, f)
+	}
+}
+
+impl<E: core::fmt::Debug> core::error::Error for Socks5Error<E> {}
+
+impl<E: embedded_io_async::Error> embedded_io_async::Error for Socks5Error<E> {
+	fn kind(&self) -> ErrorKind {
+		match self {
+			Socks5Error::Transport(e) => e.kind(),
+			Socks5Error::Protocol => ErrorKind::InvalidData,
+			Socks5Error::NoAcceptableMethod => ErrorKind::Unsupported,
+			Socks5Error::AuthenticationFailed => ErrorKind::PermissionDenied,
+			Socks5Error::Rejected(2) => ErrorKind::PermissionDenied,
+			Socks5Error::Rejected(5) => ErrorKind::ConnectionRefused,
+			Socks5Error::Rejected(6) => ErrorKind::TimedOut,
+			Socks5Error::Rejected(7 | 8) => ErrorKind::Unsupported,
+			Socks5Error::Rejected(_) => ErrorKind::Other,
+			Socks5Error::InvalidInput => ErrorKind::InvalidInput,
+			Socks5Error::Closed => ErrorKind::ConnectionAborted,
+		}
+	}
+}
+
+impl<E> From<ReadExactError<E>> for Socks5Error<E> {
+	fn from(e: ReadExactError<E>) -> Self {
+		match e {
+			ReadExactError::UnexpectedEof => Socks5Error::Closed,
+			ReadExactError::Other(e) => Socks5Error::Transport(e),
+		}
+	}
+}
+
+/// A SOCKS5 proxy client (see [RFC 1928]) on top of any [`TcpConnect`] stack.
+///
+/// Connections are made to the proxy, which in turn connects to the actual remote host; once
+/// connected, data is tunnelled unchanged. Remote hosts can also be given by name through
+/// [`.connect_to_host()`](Socks5Connect::connect_to_host), leaving name resolution to the proxy.
+///
+/// This is the async counterpart of [`embedded_nal::Socks5Stack`].
+///
+/// ```
+/// use embedded_nal_async::{Socks5Connect, Socks5Credentials, Socks5Error, TcpConnect};
+/// use embedded_io_async::{Read, Write};
+/// # use core::cell::RefCell;
+/// # use core::net::SocketAddr;
+/// # use embedded_io_async::{ErrorKind, ErrorType};
+/// # use embedded_nal_async::TcpConnection;
+/// #
+/// # /// A stack whose connections replay `reply` and record what is written to them
+/// # struct Mock { reply: &'static [u8], sent: RefCell<Vec<u8>> }
+/// # struct MockConnection<'a> { mock: &'a Mock, reply: &'static [u8] }
+/// # impl TcpConnect for Mock {
+/// #     type Error = ErrorKind;
+/// #     type Connection<'a> = MockConnection<'a>;
+/// #     async fn connect<'a>(&'a self, _: SocketAddr) -> Result<MockConnection<'a>, ErrorKind> {
+/// #         Ok(MockConnection { mock: self, reply: self.reply })
+/// #     }
+/// # }
+/// # impl ErrorType for MockConnection<'_> { type Error = ErrorKind; }
+/// # impl Read for MockConnection<'_> {
+/// #     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+/// #         let len = buf.len().min(self.reply.len());
+/// #         buf[..len].copy_from_slice(&self.reply[..len]);
+/// #         self.reply = &self.reply[len..];
+/// #         Ok(len)
+/// #     }
+/// # }
+/// # impl Write for MockConnection<'_> {
+/// #     async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+/// #         self.mock.sent.borrow_mut().extend_from_slice(buf);
+/// #         Ok(buf.len())
+/// #     }
+/// #     async fn flush(&mut self) -> Result<(), ErrorKind> { Ok(()) }
+/// # }
+/// # impl TcpConnection for MockConnection<'_> {
+/// #     fn local_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 2], 50000).into()) }
+/// #     fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 1], 1080).into()) }
+/// # }
+/// let proxy = ([10, 0, 0, 1], 1080).into();
+/// let remote = ([93, 184, 216, 34], 80).into();
+/// let credentials = Some(Socks5Credentials { username: "user", password: "secret" });
+///
+/// // The proxy asks for a password, accepts it, and connects to the remote host.
+/// let stack = Mock {
+///     reply: &[5, 2, 1, 0, 5, 0, 0, 1, 93, 184, 216, 34, 0, 80, b'o', b'k'],
+///     sent: RefCell::new(Vec::new()),
+/// };
+/// let socks = Socks5Connect::new(&stack, proxy, credentials);
+/// futures_executor::block_on(async {
+///     let mut connection = socks.connect(remote).await.unwrap();
+///     connection.write_all(b"hi").await.unwrap();
+///     let mut data = [0; 2];
+///     connection.read_exact(&mut data).await.unwrap();
+///     assert_eq!(&data, b"ok");
+/// });
+/// assert_eq!(
+///     stack.sent.borrow().as_slice(),
+///     [
+///         &[5, 2, 0, 2][..],
+///         &[1, 4, b'u', b's', b'e', b'r', 6, b's', b'e', b'c', b'r', b'e', b't'],
+///         &[5, 1, 0, 1, 93, 184, 216, 34, 0, 80],
+///         b"hi",
+///     ]
+///     .concat(),
+/// );
+///
+/// // The remote host refuses the connection.
+/// let stack = Mock { reply: &[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0], sent: RefCell::new(Vec::new()) };
+/// let socks = Socks5Connect::new(&stack, proxy, credentials);
+/// let result = futures_executor::block_on(socks.connect(remote));
+/// assert!(matches!(result, Err(Socks5Error::Rejected(5))));
+///
+/// // The proxy rejects the password.
+/// let stack = Mock { reply: &[5, 2, 1, 1], sent: RefCell::new(Vec::new()) };
+/// let socks = Socks5Connect::new(&stack, proxy, credentials);
+/// let result = futures_executor::block_on(socks.connect_to_host("example.com", 80));
+/// assert!(matches!(result, Err(Socks5Error::AuthenticationFailed)));
+/// ```
+///
+/// [RFC 1928]: https://tools.ietf.org/html/rfc1928
+pub struct Socks5Connect<'c, T> {
+	stack: T,
+	proxy: SocketAddr,
+	credentials: Option<Socks5Credentials<'c>>,
+}
+
+impl<'c, T: TcpConnect> Socks5Connect<'c, T> {
+	/// Create a SOCKS5 client that connects to `proxy` through `stack`.
+	///
+	/// If `credentials` are given, username/password authentication is offered to the proxy in
+	/// addition to no authentication.
+	pub fn new(stack: T, proxy: SocketAddr, credentials: Option<Socks5Credentials<'c>>) -> Self {
+		Socks5Connect {
+			stack,
+			proxy,
+			credentials,
+		}
+	}
+
+	/// Connect to the given remote host name and port through the proxy.
+	///
+	/// The name is resolved by the proxy.
+	pub async fn connect_to_host<'a>(
+		&'a self,
+		host: &str,
+		port: u16,
+	) -> Result<Socks5Connection<T::Connection<'a>>, Socks5Error<T::Error>> {
+		let host = host.as_bytes();
+		let host_len = u8::try_from(host.len()).map_err(|_| Socks5Error::InvalidInput)?;
+
+		let mut request = [0; 4 + 1 + 255 + 2];
+		request[..5].copy_from_slice(&[5, 1, 0, 3, host_len]);
+		request[5..][..host.len()].copy_from_slice(host);
+		request[5 + host.len()..][..2].copy_from_slice(&port.to_be_bytes());
+		self.tunnel(&request[..7 + host.len()], None).await
+	}
+
+	async fn tunnel<'a>(
+		&'a self,
+		request: &[u8],
+		target: Option<SocketAddr>,
+	) -> Result<Socks5Connection<T::Connection<'a>>, Socks5Error<T::Error>> {
+		let mut connection = self
+			.stack
+			.connect(self.proxy)
+			.await
+			.map_err(Socks5Error::Transport)?;
+
+		let greeting: &[u8] = match self.credentials {
+			Some(_) => &[5, 2, 0, 2],
+			None => &[5, 1, 0],
+		};
+		connection
+			.write_all(greeting)
+			.await
+			.map_err(Socks5Error::Transport)?;
+		let mut reply = [0; 2];
+		connection.read_exact(&mut reply).await?;
+		match (reply, self.credentials) {
+			([5, 0], _) => {}
+			([5, 2], Some(credentials)) => authenticate(&mut connection, &credentials).await?,
+			([5, 0xff], _) => return Err(Socks5Error::NoAcceptableMethod),
+			_ => return Err(Socks5Error::Protocol),
+		}
+
+		connection
+			.write_all(request)
+			.await
+			.map_err(Socks5Error::Transport)?;
+		let mut reply = [0; 5];
+		connection.read_exact(&mut reply).await?;
+		let remaining = match reply {
+			[5, 0, 0, 1, _] => 4 - 1 + 2,
+			[5, 0, 0, 4, _] => 16 - 1 + 2,
+			[5, 0, 0, 3, len] => usize::from(len) + 2,
+			[5, code, ..] if code != 0 => return Err(Socks5Error::Rejected(code)),
+			_ => return Err(Socks5Error::Protocol),
+		};
+		let mut address = [0; 255 + 2];
+		connection.read_exact(&mut address[..remaining]).await?;
+
+		Ok(Socks5Connection { connection, target })
+	}
+}
+
+impl<'c, T: TcpConnect> TcpConnect for Socks5Connect<'c, T> {
+	type Error = Socks5Error<T::Error>;
+
+	type Connection<'a>
+		= Socks5Connection<T::Connection<'a>>
+	where
+		Self: 'a;
+
+	async fn connect<'a>(
+		&'a self,
+		remote: SocketAddr,
+	) -> Result<Self::Connection<'a>, Self::Error> {
+		let mut request = [0; 4 + 16 + 2];
+		request[..3].copy_from_slice(&[5, 1, 0]);
+		let address_len = match remote.ip() {
+			IpAddr::V4(ip) => {
+				request[3] = 1;
+				request[4..8].copy_from_slice(&ip.octets());
+				4
+			}
+			IpAddr::V6(ip) => {
+				request[3] = 4;
+				request[4..20].copy_from_slice(&ip.octets());
+				16
+			}
+		};
+		request[4 + address_len..][..2].copy_from_slice(&remote.port().to_be_bytes());
+		self.tunnel(&request[..6 + address_len], Some(remote)).await
+	}
+}
+
+/// Perform username/password authentication (see [RFC 1929]).
+///
+/// [RFC 1929]: https://tools.ietf.org/html/rfc1929
+async fn authenticate<C: Read + Write>(
+	connection: &mut C,
+	credentials: &Socks5Credentials<'_>,
+) -> Result<(), Socks5Error<C::Error>> {
+	let username = credentials.username.as_bytes();
+	let password = credentials.password.as_bytes();
+	let username_len = u8::try_from(username.len()).map_err(|_| Socks5Error::InvalidInput)?;
+	let password_len = u8::try_from(password.len()).map_err(|_| Socks5Error::InvalidInput)?;
+
+	let mut request = [0; 3 + 255 + 255];
+	request[..2].copy_from_slice(&[1, username_len]);
+	request[2..][..username.len()].copy_from_slice(username);
+	request[2 + username.len()] = password_len;
+	request[3 + username.len()..][..password.len()].copy_from_slice(password);
+	connection
+		.write_all(&request[..3 + username.len() + password.len()])
+		.await
+		.map_err(Socks5Error::Transport)?;
+
+	let mut reply = [0; 2];
+	connection.read_exact(&mut reply).await?;
+	match reply {
+		[1, 0] => Ok(()),
+		_ => Err(Socks5Error::AuthenticationFailed),
+	}
+}
+
+/// A connection tunnelled through a [`Socks5Connect`] proxy.
+pub struct Socks5Connection<C> {
+	connection: C,
+	target: Option<SocketAddr>,
+}
+
+impl<C: ErrorType> ErrorType for Socks5Connection<C> {
+	type Error = Socks5Error<C::Error>;
+}
+
+impl<C: Read> Read for Socks5Connection<C> {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		self.connection
+			.read(buf)
+			.await
+			.map_err(Socks5Error::Transport)
+	}
+}
+
+impl<C: Write> Write for Socks5Connection<C> {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		self.connection
+			.write(buf)
+			.await
+			.map_err(Socks5Error::Transport)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.connection
+			.flush()
+			.await
+			.map_err(Socks5Error::Transport)
+	}
+}
+
+impl<C: TcpConnection> TcpConnection for Socks5Connection<C> {
+	fn local_addr(&self) -> Result<SocketAddr, Self::Error> {
+		self.connection.local_addr().map_err(Socks5Error::Transport)
+	}
+
+	/// The remote address is the one passed to [`TcpConnect::connect`]; for connections
+	/// established by host name, it is the proxy's address.
+	fn peer_addr(&self) -> Result<SocketAddr, Self::Error> {
+		match self.target {
+			Some(remote) => Ok(remote),
+			None => self.connection.peer_addr().map_err(Socks5Error::Transport),
+		}
+	}
+}
@@ -0,0 +1,45 @@
+use crate::{AddrType, DnsRecords, TcpConnect};
+use core::net::SocketAddr;
+use embedded_nal::{SrvConnectError, SrvRecords};
+
+/// Size of the buffer SRV records are resolved into
+const SRV_BUFFER_LEN: usize = 512;
+
+/// Connect to the service `name` (like `_xmpp-client._tcp.example.com`) through `stack`, trying
+/// the targets of its SRV records in the order of
+/// [`SrvRecords::by_preference`](SrvRecords::by_preference) until a connection succeeds.
+///
+/// Target names are resolved through `dns`, and `random` provides the random numbers for
+/// weighting the targets. If no target can be connected to, the error of the last one is
+/// returned.
+pub async fn connect_srv<'a, T: TcpConnect, D: DnsRecords>(
+	stack: &'a T,
+	dns: &D,
+	name: &str,
+	random: impl FnMut() -> u32,
+) -> Result<T::Connection<'a>, SrvConnectError<T::Error, D::Error>> {
+	let mut buffer = [0; SRV_BUFFER_LEN];
+	let len = dns
+		.get_srv_records(name, &mut buffer)
+		.await
+		.map_err(SrvConnectError::Dns)?;
+	let records = SrvRecords::new(&buffer[..len]);
+	if records.is_unavailable() {
+		return Err(SrvConnectError::Unavailable);
+	}
+
+	let mut result = Err(SrvConnectError::NotFound);
+	for record in records.by_preference(random) {
+		result = match dns.get_host_by_name(record.target, AddrType::Either).await {
+			Ok(addr) => stack
+				.connect(SocketAddr::new(addr, record.port))
+				.await
+				.map_err(SrvConnectError::Transport),
+			Err(e) => Err(SrvConnectError::Dns(e)),
+		};
+		if result.is_ok() {
+			break;
+		}
+	}
+	result
+}
@@ -1,5 +1,10 @@
+mod raw;
 mod tcp;
 mod udp;
 
-pub use tcp::TcpConnect;
-pub use udp::{ConnectedUdp, UdpStack, UnconnectedUdp};
+pub use raw::{RawIp, RawIpStack};
+pub use tcp::{TcpConnect, TcpConnection};
+pub use udp::{
+	ConnectedUdp, DatagramMetadata, Ecn, TimestampedConnectedUdp, TimestampedUnconnectedUdp,
+	UdpStack, UnconnectedUdp,
+};
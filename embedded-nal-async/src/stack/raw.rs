@@ -0,0 +1,46 @@
+use core::net::IpAddr;
+
+/// This trait is implemented by raw IP sockets.
+///
+/// The socket exchanges the payloads of IP packets carrying a single IP protocol number (or IPv6
+/// next header value); the IP header itself is built and parsed by the stack. Like for
+/// [`UnconnectedUdp`](crate::UnconnectedUdp), both addresses are explicitly given in every call.
+pub trait RawIp {
+	/// Error type returned by send and receive operations.
+	type Error: embedded_io_async::Error;
+
+	/// Send an IP payload to a remote host.
+	///
+	/// The local address can be left unspecified (`0.0.0.0` / `[::]`), in which case the stack
+	/// picks a suitable one, like for [`UnconnectedUdp::send()`](crate::UnconnectedUdp::send).
+	async fn send(&mut self, local: IpAddr, remote: IpAddr, data: &[u8])
+		-> Result<(), Self::Error>;
+
+	/// Receive the payload of an IP packet into the provided buffer.
+	///
+	/// If the received payload exceeds the buffer's length, it is received regardless, and the
+	/// remaining bytes are discarded. The full payload size is still indicated in the result,
+	/// allowing the recipient to detect that truncation.
+	///
+	/// The local and remote address are given, in that order, in the result along with the number
+	/// of bytes.
+	async fn receive_into(
+		&mut self,
+		buffer: &mut [u8],
+	) -> Result<(usize, IpAddr, IpAddr), Self::Error>;
+}
+
+/// This trait is implemented by IP stacks that allow sending and receiving raw IP payloads. It can
+/// be used to implement protocols other than TCP and UDP, such as IGMP, directly on top of IP.
+pub trait RawIpStack {
+	/// Error type returned on socket creation failure.
+	type Error: embedded_io_async::Error;
+
+	/// Eventual socket return type of the [`.socket()`] method
+	///
+	/// [`.socket()`]: RawIpStack::socket
+	type Socket: RawIp<Error = Self::Error>;
+
+	/// Create a raw socket for the given IP protocol number.
+	async fn socket(&self, protocol: u8) -> Result<Self::Socket, Self::Error>;
+}
@@ -9,8 +9,7 @@ pub trait TcpConnect {
 	type Error: embedded_io_async::Error;
 
 	/// Type holding state of a TCP connection. Should close the connection when dropped.
-	type Connection<'a>: embedded_io_async::Read<Error = Self::Error>
-		+ embedded_io_async::Write<Error = Self::Error>
+	type Connection<'a>: TcpConnection<Error = Self::Error>
 	where
 		Self: 'a;
 
@@ -21,6 +20,32 @@ pub trait TcpConnect {
 		-> Result<Self::Connection<'a>, Self::Error>;
 }
 
+/// This trait is implemented by TCP connections created through [`TcpConnect`].
+///
+/// In addition to the I/O traits from embedded-io-async, it allows querying the addresses of both
+/// ends of the connection.
+pub trait TcpConnection: embedded_io_async::Read + embedded_io_async::Write {
+	/// Query the local address of the connection.
+	///
+	/// This is the address and port the stack picked when the connection was established. Stacks
+	/// that can not obtain the local address may present an invalid one, as described in the
+	/// notes for implementers of the UDP traits.
+	fn local_addr(&self) -> Result<SocketAddr, Self::Error>;
+
+	/// Query the remote address of the connection.
+	fn peer_addr(&self) -> Result<SocketAddr, Self::Error>;
+}
+
+impl<T: TcpConnection> TcpConnection for &mut T {
+	fn local_addr(&self) -> Result<SocketAddr, Self::Error> {
+		T::local_addr(self)
+	}
+
+	fn peer_addr(&self) -> Result<SocketAddr, Self::Error> {
+		T::peer_addr(self)
+	}
+}
+
 impl<T: TcpConnect> TcpConnect for &T {
 	type Error = T::Error;
 
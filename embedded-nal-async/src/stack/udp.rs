@@ -15,6 +15,46 @@
 //!   common choice.
 
 use core::net::SocketAddr;
+use embedded_nal::{gather_datagram, UdpVectoredError, UDP_VECTORED_SEND_LEN};
+
+/// Explicit Congestion Notification codepoint of an IP packet (see [RFC 3168]).
+///
+/// The discriminants are the values of the two ECN bits.
+///
+/// [RFC 3168]: https://tools.ietf.org/html/rfc3168
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Ecn {
+	/// Not ECN-Capable Transport
+	NotEct = 0b00,
+	/// ECN Capable Transport, codepoint 1
+	Ect1 = 0b01,
+	/// ECN Capable Transport, codepoint 0
+	Ect0 = 0b10,
+	/// Congestion Experienced
+	Ce = 0b11,
+}
+
+/// Ancillary data accompanying a single datagram.
+///
+/// When receiving, fields the stack can not report are `None`; they are never filled with
+/// made-up values. When sending, `None` leaves the stack's default in place, and fields the
+/// stack can not apply are ignored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DatagramMetadata {
+	/// ECN bits of the IP header
+	pub ecn: Option<Ecn>,
+	/// Differentiated services field of the IP header, i.e. the upper six bits of the IPv4 type of
+	/// service or IPv6 traffic class octet, right-aligned; its lower two bits are given in
+	/// [`.ecn`](DatagramMetadata::ecn)
+	pub traffic_class: Option<u8>,
+	/// IPv4 time to live or IPv6 hop limit
+	pub hop_limit: Option<u8>,
+	/// Index of the network interface the datagram was received on or is to be sent through
+	///
+	/// This uses the same numbering as the zone identifier of IPv6 addresses.
+	pub interface: Option<u32>,
+}
 
 /// This trait is implemented by UDP sockets.
 ///
@@ -33,6 +73,25 @@ pub trait ConnectedUdp {
 	/// Send the provided data to the connected peer
 	async fn send(&mut self, data: &[u8]) -> Result<(), Self::Error>;
 
+	/// Send a datagram, gathered from the concatenation of `buffers`, to the connected peer
+	///
+	/// There is a provided implementation that copies the buffers into a bounded buffer and sends
+	/// that, but may be provided more efficiently by implementers that support scatter/gather
+	/// natively.
+	///
+	/// The provided implementation gathers datagrams of up to [`UDP_VECTORED_SEND_LEN`] bytes,
+	/// and fails with [`UdpVectoredError::TooLong`] without sending anything for longer ones,
+	/// unless they are held in a single buffer. Implementers used with larger datagrams should
+	/// provide their own.
+	async fn send_vectored(
+		&mut self,
+		buffers: &[&[u8]],
+	) -> Result<(), UdpVectoredError<Self::Error>> {
+		let mut scratch = [0; UDP_VECTORED_SEND_LEN];
+		let datagram = gather_datagram(buffers, &mut scratch).ok_or(UdpVectoredError::TooLong)?;
+		self.send(datagram).await.map_err(UdpVectoredError::Send)
+	}
+
 	/// Receive a datagram into the provided buffer.
 	///
 	/// If the received datagram exceeds the buffer's length, it is received regardless, and the
@@ -46,14 +105,26 @@ pub trait ConnectedUdp {
 	/// make room for a version that is more zero-copy friendly.
 	async fn receive_into(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
 
-	// WIP to allow zero-copy operation
-	// The plain receive is simple and can be provided -- implementations that don't populate
-	// receive calls from scatter-gather can just return a slice of the raw data instead, and rely
-	// on the socket still being exclusively owned. receive_oned is harder as providing it requires
-	// alloc.
-	//
-	// async fn receive(&mut self, buffer: &mut [u8]) -> utput = Result<impl AsRef<u8> + '_, Self::Error>;
-	// async fn receive_owned(&mut self) -> Result<impl AsRef<u8> + 'static, Self::Error>;
+	/// Receive a datagram, lending out the buffer it was received into.
+	///
+	/// Implementations that keep received datagrams in an internal packet buffer can return a
+	/// view into that buffer, and ignore the provided `buffer`. The socket stays exclusively
+	/// borrowed until the returned guard is dropped, after which the implementation can release
+	/// or reuse its packet buffer.
+	///
+	/// There is a provided implementation that receives into `buffer` through
+	/// [`.receive_into()`] and returns the received part of it. Like there, a datagram exceeding
+	/// the buffer's length is truncated; unlike there, the truncation can not be detected from
+	/// the result. Implementations that lend out their internal buffers never truncate.
+	///
+	/// [`.receive_into()`]: ConnectedUdp::receive_into
+	async fn receive<'a>(
+		&'a mut self,
+		buffer: &'a mut [u8],
+	) -> Result<impl AsRef<[u8]> + 'a, Self::Error> {
+		let len = self.receive_into(buffer).await?;
+		Ok(&buffer[..len.min(buffer.len())])
+	}
 }
 
 /// This trait is implemented by UDP sockets.
@@ -75,9 +146,11 @@ pub trait UnconnectedUdp {
 	///
 	/// The local address can be left unspecified by leaving any of its component zero -- that
 	/// gives the "any" address (`[::]` / `0.0.0.0`), the uncspecified port (0) or the unspecified
-	/// zone identifier (0). Unless the operating system provides facilities exceeding this crate's traits for
-	/// enumerating local interfaces and addresses, this is the only way to initiate outbound
-	/// traffic.
+	/// zone identifier (0). Unless the stack implements [`NetworkInterfaces`] or the operating
+	/// system provides other facilities for enumerating local interfaces and addresses, this is
+	/// the only way to initiate outbound traffic.
+	///
+	/// [`NetworkInterfaces`]: crate::NetworkInterfaces
 	///
 	/// ## Responding to messages
 	///
@@ -99,6 +172,30 @@ pub trait UnconnectedUdp {
 		data: &[u8],
 	) -> Result<(), Self::Error>;
 
+	/// Send a datagram, gathered from the concatenation of `buffers`, to a peer
+	///
+	/// The addresses are used as in [`.send()`]. Like [`ConnectedUdp::send_vectored()`], there is
+	/// a provided implementation that copies the buffers into a bounded buffer.
+	///
+	/// The provided implementation gathers datagrams of up to [`UDP_VECTORED_SEND_LEN`] bytes,
+	/// and fails with [`UdpVectoredError::TooLong`] without sending anything for longer ones,
+	/// unless they are held in a single buffer. Implementers used with larger datagrams should
+	/// provide their own.
+	///
+	/// [`.send()`]: UnconnectedUdp::send
+	async fn send_vectored(
+		&mut self,
+		local: SocketAddr,
+		remote: SocketAddr,
+		buffers: &[&[u8]],
+	) -> Result<(), UdpVectoredError<Self::Error>> {
+		let mut scratch = [0; UDP_VECTORED_SEND_LEN];
+		let datagram = gather_datagram(buffers, &mut scratch).ok_or(UdpVectoredError::TooLong)?;
+		self.send(local, remote, datagram)
+			.await
+			.map_err(UdpVectoredError::Send)
+	}
+
 	/// Receive a datagram into the provided buffer.
 	///
 	/// If the received datagram exceeds the buffer's length, it is received regardless, and the
@@ -111,6 +208,102 @@ pub trait UnconnectedUdp {
 		&mut self,
 		buffer: &mut [u8],
 	) -> Result<(usize, SocketAddr, SocketAddr), Self::Error>;
+
+	/// Receive a datagram, lending out the buffer it was received into.
+	///
+	/// This behaves like [`ConnectedUdp::receive()`], and returns the local and remote address
+	/// along with the guard, in that order. The provided implementation uses
+	/// [`.receive_into()`] with the provided `buffer`.
+	///
+	/// [`.receive_into()`]: UnconnectedUdp::receive_into
+	async fn receive<'a>(
+		&'a mut self,
+		buffer: &'a mut [u8],
+	) -> Result<(impl AsRef<[u8]> + 'a, SocketAddr, SocketAddr), Self::Error> {
+		let (len, local, remote) = self.receive_into(buffer).await?;
+		Ok((&buffer[..len.min(buffer.len())], local, remote))
+	}
+
+	/// Send the provided data to a peer, along with per-datagram metadata.
+	///
+	/// This behaves like [`.send()`], and additionally applies the fields of `metadata` the stack
+	/// supports.
+	///
+	/// There is a provided implementation that ignores the metadata and sends through
+	/// [`.send()`].
+	///
+	/// [`.send()`]: UnconnectedUdp::send
+	async fn send_with_metadata(
+		&mut self,
+		local: SocketAddr,
+		remote: SocketAddr,
+		metadata: &DatagramMetadata,
+		data: &[u8],
+	) -> Result<(), Self::Error> {
+		let _ = metadata;
+		self.send(local, remote, data).await
+	}
+
+	/// Receive a datagram into the provided buffer, along with per-datagram metadata.
+	///
+	/// This behaves like [`.receive_into()`], and additionally returns the metadata of the
+	/// datagram after the addresses.
+	///
+	/// There is a provided implementation that receives through [`.receive_into()`] and reports
+	/// all metadata as absent.
+	///
+	/// [`.receive_into()`]: UnconnectedUdp::receive_into
+	async fn receive_into_with_metadata(
+		&mut self,
+		buffer: &mut [u8],
+	) -> Result<(usize, SocketAddr, SocketAddr, DatagramMetadata), Self::Error> {
+		let (len, local, remote) = self.receive_into(buffer).await?;
+		Ok((len, local, remote, DatagramMetadata::default()))
+	}
+}
+
+/// This trait is implemented by [`ConnectedUdp`] sockets that can tell when a datagram arrived.
+///
+/// The timestamp is taken by the hardware or the driver when the datagram is received, and is
+/// thus independent of when the application gets around to calling
+/// [`.receive_into_timestamped()`](TimestampedConnectedUdp::receive_into_timestamped). It is
+/// given in a clock domain defined by the stack, which can be queried through
+/// [`.now()`](TimestampedConnectedUdp::now).
+pub trait TimestampedConnectedUdp: ConnectedUdp {
+	/// A point in time in the stack's clock domain
+	type Instant: Copy + Ord + core::fmt::Debug;
+
+	/// Query the current time of the clock that receive timestamps are taken from.
+	fn now(&self) -> Self::Instant;
+
+	/// Receive a datagram into the provided buffer, along with the time it was received.
+	///
+	/// This behaves like [`ConnectedUdp::receive_into()`], and additionally returns the receive
+	/// timestamp of the datagram.
+	async fn receive_into_timestamped(
+		&mut self,
+		buffer: &mut [u8],
+	) -> Result<(usize, Self::Instant), Self::Error>;
+}
+
+/// This trait is implemented by [`UnconnectedUdp`] sockets that can tell when a datagram arrived.
+///
+/// Timestamps are taken as described for [`TimestampedConnectedUdp`].
+pub trait TimestampedUnconnectedUdp: UnconnectedUdp {
+	/// A point in time in the stack's clock domain
+	type Instant: Copy + Ord + core::fmt::Debug;
+
+	/// Query the current time of the clock that receive timestamps are taken from.
+	fn now(&self) -> Self::Instant;
+
+	/// Receive a datagram into the provided buffer, along with the time it was received.
+	///
+	/// This behaves like [`UnconnectedUdp::receive_into()`], and additionally returns the receive
+	/// timestamp of the datagram after the addresses.
+	async fn receive_into_timestamped(
+		&mut self,
+		buffer: &mut [u8],
+	) -> Result<(usize, SocketAddr, SocketAddr, Self::Instant), Self::Error>;
 }
 
 /// This trait is implemented by UDP/IP stacks. The trait allows the underlying driver to
@@ -0,0 +1,70 @@
+use core::net::IpAddr;
+use embedded_nal::IpConfig;
+
+/// This trait is implemented by network stacks that can report the state of their link and IP
+/// configuration.
+///
+/// Applications can use it to wait for the network to become usable before connecting, and to
+/// react to the link going down or the configuration changing (e.g. when a DHCP lease is renewed
+/// with a different address) through [`.wait_config_change()`](NetworkStatus::wait_config_change).
+pub trait NetworkStatus {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Wait until the link is up and an IP configuration has been applied.
+	///
+	/// Returns immediately if the stack is already configured.
+	async fn wait_config_up(&self) -> Result<(), Self::Error>;
+
+	/// Wait until the link is down.
+	///
+	/// Returns immediately if the link is already down.
+	async fn wait_link_down(&self) -> Result<(), Self::Error>;
+
+	/// Wait until the IP configurations or DNS servers change.
+	///
+	/// Returns once they changed since this function last returned, or since the stack was
+	/// created, so that no change is missed between calls; the first configuration applied counts
+	/// as a change, as does losing it when the link goes down. The new configuration can then be
+	/// queried through [`.ip_config()`](NetworkStatus::ip_config) and
+	/// [`.dns_servers()`](NetworkStatus::dns_servers).
+	async fn wait_config_change(&self) -> Result<(), Self::Error>;
+
+	/// List the IP configurations currently applied to the stack.
+	///
+	/// A stack typically has at most one IPv4 configuration, and may have several IPv6
+	/// configurations. The configurations are stored at the beginning of `result`. The total
+	/// number of configurations is returned, which is 0 while the stack is not configured; if it
+	/// exceeds the length of `result`, the remaining configurations are not stored.
+	fn ip_config(&self, result: &mut [IpConfig]) -> Result<usize, Self::Error>;
+
+	/// List the DNS servers currently used by the stack.
+	///
+	/// The addresses are stored at the beginning of `result`. The total number of servers is
+	/// returned; if it exceeds the length of `result`, the remaining addresses are not stored.
+	fn dns_servers(&self, result: &mut [IpAddr]) -> Result<usize, Self::Error>;
+}
+
+impl<T: NetworkStatus> NetworkStatus for &T {
+	type Error = T::Error;
+
+	async fn wait_config_up(&self) -> Result<(), Self::Error> {
+		T::wait_config_up(self).await
+	}
+
+	async fn wait_link_down(&self) -> Result<(), Self::Error> {
+		T::wait_link_down(self).await
+	}
+
+	async fn wait_config_change(&self) -> Result<(), Self::Error> {
+		T::wait_config_change(self).await
+	}
+
+	fn ip_config(&self, result: &mut [IpConfig]) -> Result<usize, Self::Error> {
+		T::ip_config(self, result)
+	}
+
+	fn dns_servers(&self, result: &mut [IpAddr]) -> Result<usize, Self::Error> {
+		T::dns_servers(self, result)
+	}
+}
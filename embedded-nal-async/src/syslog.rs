@@ -0,0 +1,240 @@
+use crate::{ConnectedUdp, TcpConnect};
+use core::net::SocketAddr;
+use embedded_io_async::Write;
+use embedded_nal::{SyslogMessage, SyslogQueue, SYSLOG_OCTET_COUNT_LEN};
+
+/// A syslog client sending messages to a collector in UDP datagrams on any [`ConnectedUdp`]
+/// socket, see [RFC 5426].
+///
+/// Messages are [pushed](SyslogClient::push) into a [`SyslogQueue`] of `Q` bytes without
+/// waiting, so that they can be logged from anywhere, and are sent when the client is
+/// [flushed](SyslogClient::flush), usually from a task of its own; when the network falls
+/// behind, the oldest messages are dropped.
+///
+/// ```
+/// use embedded_nal_async::{SyslogClient, SyslogFacility, SyslogMessage, SyslogSeverity};
+/// use embedded_nal_async::{UdpStack, SYSLOG_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+///
+/// async fn report<S: UdpStack>(stack: &S, temperature: f32) -> Result<(), S::Error> {
+///     let collector = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), SYSLOG_PORT);
+///     let (_, socket) = stack.connect(collector).await?;
+///     let mut client: SyslogClient<_> = SyslogClient::new(socket);
+///
+///     let text = format_args!("temperature {temperature}");
+///     let mut message = SyslogMessage::new(SyslogFacility::Local0, SyslogSeverity::Notice, text);
+///     message.app_name = "monitor";
+///     client.send(&message).await
+/// }
+/// ```
+///
+/// [RFC 5426]: https://tools.ietf.org/html/rfc5426
+pub struct SyslogClient<U, const Q: usize = 2048> {
+	socket: U,
+	queue: SyslogQueue<Q>,
+}
+
+impl<U: ConnectedUdp, const Q: usize> SyslogClient<U, Q> {
+	/// Create a client sending messages on `socket`, which is connected to the collector,
+	/// usually on [`SYSLOG_PORT`](crate::SYSLOG_PORT).
+	pub fn new(socket: U) -> Self {
+		SyslogClient {
+			socket,
+			queue: SyslogQueue::new(),
+		}
+	}
+
+	/// Queue `message` to be sent on the next flush, dropping the oldest messages if the queue
+	/// is full.
+	pub fn push(&mut self, message: &SyslogMessage<'_>) {
+		self.queue.push(message);
+	}
+
+	/// Queue `message`, and send all queued messages.
+	pub async fn send(&mut self, message: &SyslogMessage<'_>) -> Result<(), U::Error> {
+		self.queue.push(message);
+		self.flush().await
+	}
+
+	/// Send all queued messages.
+	///
+	/// Messages stay queued if sending them fails.
+	pub async fn flush(&mut self) -> Result<(), U::Error> {
+		while let Some(message) = self.queue.front() {
+			self.socket.send(message).await?;
+			self.queue.pop();
+		}
+		Ok(())
+	}
+
+	/// The queue of messages waiting to be sent
+	pub fn queue(&self) -> &SyslogQueue<Q> {
+		&self.queue
+	}
+
+	/// Release the socket; queued messages are discarded.
+	pub fn free(self) -> U {
+		self.socket
+	}
+}
+
+/// A syslog client sending messages to a collector over a TCP connection through any
+/// [`TcpConnect`] implementation, framed by octet counting as in [RFC 6587].
+///
+/// Messages are [pushed](SyslogTcpClient::push) into a [`SyslogQueue`] of `Q` bytes without
+/// waiting, and are sent when the client is [flushed](SyslogTcpClient::flush); when the
+/// network falls behind, the oldest messages are dropped. The client connects when it first
+/// sends, and again on the next flush after the connection failed.
+///
+/// ```
+/// use embedded_nal_async::{SyslogFacility, SyslogMessage, SyslogSeverity, SyslogTcpClient};
+/// use embedded_nal_async::{TcpConnect, SYSLOG_TCP_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// # use core::cell::{Cell, RefCell};
+/// # use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+/// # use embedded_nal_async::TcpConnection;
+/// #
+/// # /// A stack whose connections record what is written to them, or fail while `broken`
+/// # #[derive(Default)]
+/// # struct Mock { sent: RefCell<Vec<u8>>, broken: Cell<bool>, connections: Cell<usize> }
+/// # struct MockConnection<'a> { mock: &'a Mock }
+/// # impl TcpConnect for Mock {
+/// #     type Error = ErrorKind;
+/// #     type Connection<'a> = MockConnection<'a>;
+/// #     async fn connect<'a>(&'a self, _: SocketAddr) -> Result<MockConnection<'a>, ErrorKind> {
+/// #         self.connections.set(self.connections.get() + 1);
+/// #         Ok(MockConnection { mock: self })
+/// #     }
+/// # }
+/// # impl ErrorType for MockConnection<'_> { type Error = ErrorKind; }
+/// # impl Read for MockConnection<'_> {
+/// #     async fn read(&mut self, _: &mut [u8]) -> Result<usize, ErrorKind> { Ok(0) }
+/// # }
+/// # impl Write for MockConnection<'_> {
+/// #     async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+/// #         if self.mock.broken.get() {
+/// #             return Err(ErrorKind::ConnectionReset);
+/// #         }
+/// #         self.mock.sent.borrow_mut().extend_from_slice(buf);
+/// #         Ok(buf.len())
+/// #     }
+/// #     async fn flush(&mut self) -> Result<(), ErrorKind> { Ok(()) }
+/// # }
+/// # impl TcpConnection for MockConnection<'_> {
+/// #     fn local_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 2], 50000).into()) }
+/// #     fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 1], 601).into()) }
+/// # }
+/// # futures_executor::block_on(async {
+/// # let stack = Mock::default();
+/// let collector = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), SYSLOG_TCP_PORT);
+/// let mut client: SyslogTcpClient<_> = SyslogTcpClient::new(&stack, collector);
+/// let message = |text| SyslogMessage::new(SyslogFacility::User, SyslogSeverity::Notice, text);
+///
+/// // Each message is preceded by its length.
+/// client.push(&message(format_args!("first")));
+/// client.send(&message(format_args!("second"))).await?;
+/// # assert_eq!(stack.sent.borrow().as_slice(), b"23 <13>1 - - - - - - first24 <13>1 - - - - - - second");
+///
+/// // A message that could not be sent stays queued, and is sent on a new connection.
+/// # stack.broken.set(true);
+/// assert!(client.send(&message(format_args!("third"))).await.is_err());
+/// assert!(!client.queue().is_empty());
+/// # stack.broken.set(false);
+/// # stack.sent.borrow_mut().clear();
+/// client.flush().await?;
+/// assert!(client.queue().is_empty());
+/// # assert_eq!(stack.sent.borrow().as_slice(), b"23 <13>1 - - - - - - third");
+/// # assert_eq!(stack.connections.get(), 2);
+/// # Ok::<(), ErrorKind>(())
+/// # }).unwrap();
+/// ```
+///
+/// [RFC 6587]: https://tools.ietf.org/html/rfc6587
+pub struct SyslogTcpClient<'a, T: TcpConnect + 'a, const Q: usize = 2048> {
+	stack: &'a T,
+	collector: SocketAddr,
+	connection: Option<T::Connection<'a>>,
+	/// Whether a flush was cancelled while writing, leaving a message partly written
+	writing: bool,
+	queue: SyslogQueue<Q>,
+}
+
+impl<'a, T: TcpConnect, const Q: usize> SyslogTcpClient<'a, T, Q> {
+	/// Create a client connecting through `stack` to `collector`, which usually listens on
+	/// [`SYSLOG_TCP_PORT`](crate::SYSLOG_TCP_PORT).
+	pub fn new(stack: &'a T, collector: SocketAddr) -> Self {
+		SyslogTcpClient {
+			stack,
+			collector,
+			connection: None,
+			writing: false,
+			queue: SyslogQueue::new(),
+		}
+	}
+
+	/// Queue `message` to be sent on the next flush, dropping the oldest messages if the queue
+	/// is full.
+	pub fn push(&mut self, message: &SyslogMessage<'_>) {
+		self.queue.push(message);
+	}
+
+	/// Queue `message`, and send all queued messages.
+	pub async fn send(&mut self, message: &SyslogMessage<'_>) -> Result<(), T::Error> {
+		self.queue.push(message);
+		self.flush().await
+	}
+
+	/// Send all queued messages, connecting first if needed.
+	///
+	/// Messages stay queued if sending them fails or the flush is cancelled, and the connection
+	/// is closed, so that they are sent on a new connection by the next flush.
+	pub async fn flush(&mut self) -> Result<(), T::Error> {
+		if self.writing {
+			// The collector would not be able to tell where the next message starts.
+			self.connection = None;
+			self.writing = false;
+		}
+		if self.queue.is_empty() {
+			return Ok(());
+		}
+		let connection = match &mut self.connection {
+			Some(connection) => connection,
+			None => self
+				.connection
+				.insert(self.stack.connect(self.collector).await?),
+		};
+		self.writing = true;
+		let result = transmit(connection, &mut self.queue).await;
+		self.writing = false;
+		if result.is_err() {
+			self.connection = None;
+		}
+		result
+	}
+
+	/// The queue of messages waiting to be sent
+	pub fn queue(&self) -> &SyslogQueue<Q> {
+		&self.queue
+	}
+
+	/// Close the connection, if any; it is opened again by the next flush.
+	pub fn close(&mut self) {
+		self.connection = None;
+	}
+}
+
+/// Write the queued messages to `connection`, each preceded by its length.
+async fn transmit<W: Write, const Q: usize>(
+	connection: &mut W,
+	queue: &mut SyslogQueue<Q>,
+) -> Result<(), W::Error> {
+	while let Some(message) = queue.front() {
+		let mut count = [0; SYSLOG_OCTET_COUNT_LEN];
+		let count_len = queue.front_octet_count(&mut count);
+		connection.write_all(&count[..count_len]).await?;
+		connection.write_all(message).await?;
+		connection.flush().await?;
+		queue.pop();
+	}
+	Ok(())
+}
@@ -0,0 +1,365 @@
+use crate::{TcpConnect, TcpConnection};
+use core::net::SocketAddr;
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use embedded_nal::{TlsClient, TlsConfig, TlsSession};
+
+/// Errors returned by [`TlsConnect`] and its connections.
+#[derive(Debug)]
+pub enum TlsError<T, S> {
+	/// The underlying TCP connection failed.
+	Transport(T),
+	/// The TLS session failed, e.g. because the handshake was rejected.
+	Tls(S),
+	/// The peer closed the TCP connection before the handshake completed.
+	Closed,
+}
+
+impl<T: core::fmt::Debug, S: core::fmt::Debug> core::fmt::Display for TlsError<T, S> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		core::fmt::Debug::fmt(self, f)
+	}
+}
+
+impl<T: core::fmt::Debug, S: core::fmt::Debug> core::error::Error for TlsError<T, S> {}
+
+impl<T: embedded_io_async::Error, S: core::fmt::Debug> embedded_io_async::Error for TlsError<T, S> {
+	fn kind(&self) -> ErrorKind {
+		match self {
+			TlsError::Transport(e) => e.kind(),
+			TlsError::Tls(_) => ErrorKind::InvalidData,
+			TlsError::Closed => ErrorKind::ConnectionAborted,
+		}
+	}
+}
+
+/// Error type of the connections created by a [`TlsConnect`]
+type Error<T, C> =
+	TlsError<<T as TcpConnect>::Error, <<C as TlsClient>::Session as TlsSession>::Error>;
+
+/// A TLS client on top of any [`TcpConnect`] stack.
+///
+/// The TLS library is plugged in as a [`TlsClient`], and every connection is set up with the
+/// same [`TlsConfig`]. Each connection holds two buffers of `N` bytes for moving data between the
+/// TLS session and the TCP connection; they do not need to fit whole TLS records.
+///
+/// ```
+/// use embedded_nal_async::{TcpConnection, TlsClient, TlsConfig, TlsConnect, TlsSession};
+/// use embedded_io_async::{Read, Write};
+/// # use core::cell::RefCell;
+/// # use core::net::SocketAddr;
+/// # use embedded_io_async::{ErrorKind, ErrorType};
+/// # use embedded_nal_async::TcpConnect;
+/// #
+/// # /// A stack whose connections replay `reply` and record what is written to them
+/// # struct Mock { reply: &'static [u8], sent: RefCell<Vec<u8>> }
+/// # struct MockConnection<'a> { mock: &'a Mock, reply: &'static [u8] }
+/// # impl TcpConnect for Mock {
+/// #     type Error = ErrorKind;
+/// #     type Connection<'a> = MockConnection<'a>;
+/// #     async fn connect<'a>(&'a self, _: SocketAddr) -> Result<MockConnection<'a>, ErrorKind> {
+/// #         Ok(MockConnection { mock: self, reply: self.reply })
+/// #     }
+/// # }
+/// # impl ErrorType for MockConnection<'_> { type Error = ErrorKind; }
+/// # impl Read for MockConnection<'_> {
+/// #     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+/// #         let len = buf.len().min(self.reply.len());
+/// #         buf[..len].copy_from_slice(&self.reply[..len]);
+/// #         self.reply = &self.reply[len..];
+/// #         Ok(len)
+/// #     }
+/// # }
+/// # impl Write for MockConnection<'_> {
+/// #     async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+/// #         self.mock.sent.borrow_mut().extend_from_slice(buf);
+/// #         Ok(buf.len())
+/// #     }
+/// #     async fn flush(&mut self) -> Result<(), ErrorKind> { Ok(()) }
+/// # }
+/// # impl TcpConnection for MockConnection<'_> {
+/// #     fn local_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 2], 50000).into()) }
+/// #     fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> { Ok(([10, 0, 0, 1], 443).into()) }
+/// # }
+///
+/// /// A stand-in for a TLS library, which greets the server with the SNI in plain text and
+/// /// passes application data through unchanged.
+/// struct PlainText;
+///
+/// struct Session {
+///     hello: Option<String>,
+///     welcome: usize,
+///     received: Vec<u8>,
+///     pending: Vec<u8>,
+/// }
+///
+/// impl TlsClient for PlainText {
+///     type Session = Session;
+///     fn session(&self, server_name: &str, _: &TlsConfig<'_>) -> Result<Session, ()> {
+///         let hello = Some(format!("HELLO {server_name}\n"));
+///         Ok(Session { hello, welcome: 0, received: Vec::new(), pending: Vec::new() })
+///     }
+/// }
+///
+/// impl TlsSession for Session {
+///     type Error = ();
+///     fn is_handshaking(&self) -> bool { self.welcome < b"WELCOME\n".len() }
+///     fn is_closed(&self) -> bool { false }
+///     fn read_tls(&mut self, data: &[u8]) -> Result<usize, ()> {
+///         if !self.is_handshaking() {
+///             self.received.extend_from_slice(data);
+///             return Ok(data.len());
+///         }
+///         let len = data.len().min(b"WELCOME\n".len() - self.welcome);
+///         match data[..len] == b"WELCOME\n"[self.welcome..][..len] {
+///             true => self.welcome += len,
+///             false => return Err(()),
+///         }
+///         Ok(len)
+///     }
+///     fn write_tls(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+///         let pending = match self.hello.take() {
+///             Some(hello) => hello.into_bytes(),
+///             None => core::mem::take(&mut self.pending),
+///         };
+///         buffer[..pending.len()].copy_from_slice(&pending);
+///         Ok(pending.len())
+///     }
+///     fn read(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+///         let len = buffer.len().min(self.received.len());
+///         buffer[..len].copy_from_slice(&self.received[..len]);
+///         self.received.drain(..len);
+///         Ok(len)
+///     }
+///     fn write(&mut self, data: &[u8]) -> Result<usize, ()> {
+///         self.pending.extend_from_slice(data);
+///         Ok(data.len())
+///     }
+///     fn close(&mut self) {}
+/// }
+///
+/// let stack = Mock { reply: b"WELCOME\npong", sent: RefCell::new(Vec::new()) };
+/// let tls: TlsConnect<_, _> = TlsConnect::new(&stack, PlainText, TlsConfig::default());
+/// futures_executor::block_on(async {
+///     let remote = ([10, 0, 0, 1], 443).into();
+///     let mut connection = tls.connect(remote, "example.com").await.unwrap();
+///     assert_eq!(connection.peer_addr().unwrap(), remote);
+///
+///     connection.write_all(b"ping").await.unwrap();
+///     let mut reply = [0; 4];
+///     connection.read_exact(&mut reply).await.unwrap();
+///     assert_eq!(&reply, b"pong");
+///     // The server closed the connection after its reply.
+///     assert_eq!(connection.read(&mut reply).await.unwrap(), 0);
+/// });
+/// assert_eq!(stack.sent.borrow().as_slice(), b"HELLO example.com\nping");
+///
+/// // A server that hangs up during the handshake fails the connection.
+/// let stack = Mock { reply: b"WEL", sent: RefCell::new(Vec::new()) };
+/// let tls: TlsConnect<_, _> = TlsConnect::new(&stack, PlainText, TlsConfig::default());
+/// let result = futures_executor::block_on(tls.connect(([10, 0, 0, 1], 443).into(), "example.com"));
+/// assert!(matches!(result, Err(embedded_nal_async::TlsError::Closed)));
+/// ```
+pub struct TlsConnect<'c, T, C, const N: usize = 1024> {
+	stack: T,
+	client: C,
+	config: TlsConfig<'c>,
+}
+
+impl<'c, T, C, const N: usize> TlsConnect<'c, T, C, N>
+where
+	T: TcpConnect,
+	C: TlsClient,
+{
+	/// Create a TLS client that connects through `stack`, with sessions created by `client`.
+	pub fn new(stack: T, client: C, config: TlsConfig<'c>) -> Self {
+		TlsConnect {
+			stack,
+			client,
+			config,
+		}
+	}
+
+	/// Connect to the given remote host and port, and perform a TLS handshake with it.
+	///
+	/// The `server_name` is sent through the Server Name Indication (SNI) extension, and the
+	/// server's certificate is verified against it.
+	///
+	/// Returns `Ok` once the handshake completed.
+	pub async fn connect<'a>(
+		&'a self,
+		remote: SocketAddr,
+		server_name: &str,
+	) -> Result<TlsConnection<T::Connection<'a>, C::Session, N>, Error<T, C>> {
+		let session = self
+			.client
+			.session(server_name, &self.config)
+			.map_err(TlsError::Tls)?;
+		let transport = self
+			.stack
+			.connect(remote)
+			.await
+			.map_err(TlsError::Transport)?;
+
+		let mut connection = TlsConnection {
+			transport,
+			session,
+			rx: [0; N],
+			rx_start: 0,
+			rx_end: 0,
+			tx: [0; N],
+		};
+		connection.handshake().await?;
+		Ok(connection)
+	}
+}
+
+/// A TLS connection created by [`TlsConnect`].
+///
+/// It implements the I/O traits from embedded-io-async on the decrypted application data. The
+/// underlying TCP connection is closed when this is dropped; use [`.close()`](TlsConnection::close)
+/// to notify the peer first.
+pub struct TlsConnection<C, S, const N: usize> {
+	transport: C,
+	session: S,
+	rx: [u8; N],
+	rx_start: usize,
+	rx_end: usize,
+	tx: [u8; N],
+}
+
+impl<C, S, const N: usize> TlsConnection<C, S, N>
+where
+	C: Read + Write,
+	S: TlsSession,
+{
+	/// Send a closure alert to the peer, and drop the connection.
+	pub async fn close(mut self) -> Result<(), TlsError<C::Error, S::Error>> {
+		self.session.close();
+		self.flush_tls().await
+	}
+
+	/// Access the TLS session, e.g. for inspecting the negotiated parameters.
+	pub fn session(&self) -> &S {
+		&self.session
+	}
+
+	async fn handshake(&mut self) -> Result<(), TlsError<C::Error, S::Error>> {
+		self.flush_tls().await?;
+		while self.session.is_handshaking() {
+			if !self.fill().await? {
+				return Err(TlsError::Closed);
+			}
+			self.flush_tls().await?;
+		}
+		Ok(())
+	}
+
+	/// Send everything the session has pending.
+	async fn flush_tls(&mut self) -> Result<(), TlsError<C::Error, S::Error>> {
+		loop {
+			let len = self
+				.session
+				.write_tls(&mut self.tx)
+				.map_err(TlsError::Tls)?;
+			if len == 0 {
+				break;
+			}
+			self.transport
+				.write_all(&self.tx[..len])
+				.await
+				.map_err(TlsError::Transport)?;
+		}
+		self.transport.flush().await.map_err(TlsError::Transport)
+	}
+
+	/// Pass received data to the session, reading from the network if none is buffered.
+	///
+	/// Returns `false` if the peer closed the TCP connection.
+	async fn fill(&mut self) -> Result<bool, TlsError<C::Error, S::Error>> {
+		if self.rx_start == self.rx_end {
+			let len = self
+				.transport
+				.read(&mut self.rx)
+				.await
+				.map_err(TlsError::Transport)?;
+			if len == 0 {
+				return Ok(false);
+			}
+			self.rx_start = 0;
+			self.rx_end = len;
+		}
+
+		let used = self
+			.session
+			.read_tls(&self.rx[self.rx_start..self.rx_end])
+			.map_err(TlsError::Tls)?;
+		self.rx_start += used;
+		Ok(true)
+	}
+}
+
+impl<C, S, const N: usize> ErrorType for TlsConnection<C, S, N>
+where
+	C: ErrorType,
+	S: TlsSession,
+{
+	type Error = TlsError<C::Error, S::Error>;
+}
+
+impl<C, S, const N: usize> Read for TlsConnection<C, S, N>
+where
+	C: Read + Write,
+	S: TlsSession,
+{
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			let len = self.session.read(buf).map_err(TlsError::Tls)?;
+			if len > 0 || self.session.is_closed() {
+				return Ok(len);
+			}
+			// Records such as key updates may need a response before data can flow again.
+			self.flush_tls().await?;
+			if !self.fill().await? {
+				return Ok(0);
+			}
+		}
+	}
+}
+
+impl<C, S, const N: usize> Write for TlsConnection<C, S, N>
+where
+	C: Read + Write,
+	S: TlsSession,
+{
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		loop {
+			let len = self.session.write(buf).map_err(TlsError::Tls)?;
+			self.flush_tls().await?;
+			if len > 0 || buf.is_empty() {
+				return Ok(len);
+			}
+		}
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.flush_tls().await
+	}
+}
+
+impl<C, S, const N: usize> TcpConnection for TlsConnection<C, S, N>
+where
+	C: TcpConnection,
+	S: TlsSession,
+{
+	fn local_addr(&self) -> Result<SocketAddr, Self::Error> {
+		self.transport.local_addr().map_err(TlsError::Transport)
+	}
+
+	fn peer_addr(&self) -> Result<SocketAddr, Self::Error> {
+		self.transport.peer_addr().map_err(TlsError::Transport)
+	}
+}
@@ -0,0 +1,64 @@
+//! The stack on top of `std::net` of embedded-nal's tests, connecting through `TcpConnect` as
+//! well.
+#![allow(dead_code)]
+
+#[path = "../../../tests/common/mod.rs"]
+mod blocking;
+
+pub use blocking::*;
+use core::net::SocketAddr;
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use embedded_nal_async::{TcpConnect, TcpConnection};
+use std::io::{self, Read as _, Write as _};
+use std::net::TcpStream;
+
+/// A connection on a blocking std socket, which is fine with a single-threaded executor
+pub struct StdConnection(TcpStream);
+
+fn kind(e: io::Error) -> ErrorKind {
+	match e.kind() {
+		io::ErrorKind::ConnectionRefused => ErrorKind::ConnectionRefused,
+		io::ErrorKind::ConnectionReset => ErrorKind::ConnectionReset,
+		io::ErrorKind::BrokenPipe => ErrorKind::BrokenPipe,
+		_ => ErrorKind::Other,
+	}
+}
+
+impl TcpConnect for StdStack {
+	type Error = ErrorKind;
+	type Connection<'a> = StdConnection;
+
+	async fn connect(&self, remote: SocketAddr) -> Result<StdConnection, ErrorKind> {
+		TcpStream::connect(remote).map(StdConnection).map_err(kind)
+	}
+}
+
+impl ErrorType for StdConnection {
+	type Error = ErrorKind;
+}
+
+impl Read for StdConnection {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+		self.0.read(buf).map_err(kind)
+	}
+}
+
+impl Write for StdConnection {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+		self.0.write(buf).map_err(kind)
+	}
+
+	async fn flush(&mut self) -> Result<(), ErrorKind> {
+		self.0.flush().map_err(kind)
+	}
+}
+
+impl TcpConnection for StdConnection {
+	fn local_addr(&self) -> Result<SocketAddr, ErrorKind> {
+		self.0.local_addr().map_err(kind)
+	}
+
+	fn peer_addr(&self) -> Result<SocketAddr, ErrorKind> {
+		self.0.peer_addr().map_err(kind)
+	}
+}
@@ -0,0 +1,114 @@
+//! Runs the `HttpClient` against embedded-nal's `HttpServer` on the loopback network.
+
+mod common;
+
+use common::http_server::Server;
+use common::{free_port, StdStack};
+use embedded_nal_async::{AddrType, Dns, HttpClient, HttpMethod};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Resolves every name to the loopback address.
+struct Localhost;
+
+impl Dns for Localhost {
+	type Error = ();
+
+	async fn get_host_by_name(&self, _: &str, _: AddrType) -> Result<IpAddr, ()> {
+		Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+	}
+
+	async fn get_host_by_address(&self, _: IpAddr, _: &mut [u8]) -> Result<usize, ()> {
+		Err(())
+	}
+}
+
+#[test]
+fn receives_responses() {
+	let port = free_port();
+	let server = Server::start(port);
+	let mut client = HttpClient::new(&StdStack, Localhost);
+	let mut buffer = [0; 512];
+
+	futures_executor::block_on(async {
+		let url = format!("http://127.0.0.1:{port}/greeting");
+		let response = client.get(&url, &mut buffer).await.unwrap();
+		assert_eq!(response.status, 200);
+		assert_eq!(response.body, b"Hello world!");
+
+		let url = format!("http://localhost:{port}/chunked");
+		let response = client.get(&url, &mut buffer).await.unwrap();
+		assert_eq!(response.body, b"Hello world!");
+
+		let url = format!("http://localhost:{port}/count");
+		let response = client
+			.request(HttpMethod::Post, &url, &[], b"1", &mut buffer)
+			.await
+			.unwrap();
+		assert_eq!(response.body, b"1");
+
+		let url = format!("http://localhost:{port}/missing");
+		let response = client.get(&url, &mut buffer).await.unwrap();
+		assert_eq!(response.status, 404);
+	});
+	client.close();
+	assert_eq!(server.stop().posts, 1);
+}
+
+#[test]
+fn sends_header_fields_only_to_their_origin() {
+	let port = free_port();
+	let server = Server::start(port);
+	let mut client = HttpClient::new(&StdStack, Localhost);
+	let mut buffer = [0; 512];
+	let authorization = [("Authorization", "Bearer secret")];
+
+	futures_executor::block_on(async {
+		for (path, expected) in [
+			("headers", &b"Bearer secret"[..]),
+			("redirect/here", b"Bearer secret"),
+			("redirect/elsewhere", b"-"),
+		] {
+			let url = format!("http://127.0.0.1:{port}/{path}");
+			let response = client
+				.request(HttpMethod::Get, &url, &authorization, &[], &mut buffer)
+				.await
+				.unwrap();
+			assert_eq!(response.status, 200);
+			assert_eq!(response.body, expected, "{path}");
+		}
+	});
+	server.stop();
+}
+
+#[test]
+fn repeats_only_get_requests_on_closed_connections() {
+	let port = free_port();
+	let url = format!("http://127.0.0.1:{port}/greeting");
+	let mut client = HttpClient::new(&StdStack, Localhost);
+	let mut buffer = [0; 512];
+
+	let server = Server::start(port);
+	futures_executor::block_on(client.get(&url, &mut buffer)).unwrap();
+	server.stop();
+
+	// The kept-open connection was closed by the server in the meantime.
+	let server = Server::start(port);
+	let response = futures_executor::block_on(client.get(&url, &mut buffer)).unwrap();
+	assert_eq!(response.body, b"Hello world!");
+	server.stop();
+
+	let server = Server::start(port);
+	let url = format!("http://127.0.0.1:{port}/count");
+	futures_executor::block_on(async {
+		let result = client
+			.request(HttpMethod::Post, &url, &[], b"1", &mut buffer)
+			.await;
+		assert!(result.is_err());
+		let response = client
+			.request(HttpMethod::Post, &url, &[], b"2", &mut buffer)
+			.await
+			.unwrap();
+		assert_eq!(response.body, b"2");
+	});
+	assert_eq!(server.stop().posts, 1);
+}
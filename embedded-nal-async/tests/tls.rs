@@ -0,0 +1,197 @@
+//! Runs `TlsConnect` against a local TLS server with a self-signed certificate, using rustls on
+//! both ends.
+
+mod common;
+
+use common::StdStack;
+use core::net::SocketAddr;
+use embedded_io_async::{Read, Write};
+use embedded_nal_async::{TcpConnection, TlsClient, TlsConfig, TlsConnect, TlsError, TlsSession};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+use std::io::{self, Read as _, Write as _};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+
+/// rustls plugged in as the TLS library
+struct Rustls;
+
+struct RustlsSession {
+	connection: ClientConnection,
+	closed: bool,
+}
+
+fn tls_error(e: io::Error) -> rustls::Error {
+	rustls::Error::General(e.to_string())
+}
+
+impl TlsClient for Rustls {
+	type Session = RustlsSession;
+
+	fn session(
+		&self,
+		server_name: &str,
+		config: &TlsConfig<'_>,
+	) -> Result<RustlsSession, rustls::Error> {
+		let mut roots = RootCertStore::empty();
+		for certificate in config.ca_certificates {
+			roots.add(CertificateDer::from(certificate.to_vec()))?;
+		}
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let config = ClientConfig::builder_with_provider(provider)
+			.with_safe_default_protocol_versions()?
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+		let server_name = ServerName::try_from(server_name.to_owned())
+			.map_err(|e| rustls::Error::General(e.to_string()))?;
+		Ok(RustlsSession {
+			connection: ClientConnection::new(Arc::new(config), server_name)?,
+			closed: false,
+		})
+	}
+}
+
+impl TlsSession for RustlsSession {
+	type Error = rustls::Error;
+
+	fn is_handshaking(&self) -> bool {
+		self.connection.is_handshaking()
+	}
+
+	fn is_closed(&self) -> bool {
+		self.closed
+	}
+
+	fn read_tls(&mut self, data: &[u8]) -> Result<usize, rustls::Error> {
+		let len = self
+			.connection
+			.read_tls(&mut &data[..])
+			.map_err(tls_error)?;
+		let state = self.connection.process_new_packets()?;
+		self.closed |= state.peer_has_closed();
+		Ok(len)
+	}
+
+	fn write_tls(&mut self, buffer: &mut [u8]) -> Result<usize, rustls::Error> {
+		match self.connection.wants_write() {
+			true => self
+				.connection
+				.write_tls(&mut &mut buffer[..])
+				.map_err(tls_error),
+			false => Ok(0),
+		}
+	}
+
+	fn read(&mut self, buffer: &mut [u8]) -> Result<usize, rustls::Error> {
+		match self.connection.reader().read(buffer) {
+			Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+			result => result.map_err(tls_error),
+		}
+	}
+
+	fn write(&mut self, data: &[u8]) -> Result<usize, rustls::Error> {
+		self.connection.writer().write(data).map_err(tls_error)
+	}
+
+	fn close(&mut self) {
+		self.connection.send_close_notify();
+	}
+}
+
+/// The thread of a server started by [`serve`]
+type Server = thread::JoinHandle<Result<(), rustls::Error>>;
+
+/// Start a server for `localhost` that answers one connection, returning its address and the
+/// DER encoded certificate to trust.
+///
+/// The server replies to "ping" with "pong" and closes the session; the result of its handshake
+/// is returned when the thread is joined.
+fn serve() -> (SocketAddr, Vec<u8>, Server) {
+	let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+	let certificate = certified.cert.der().to_vec();
+	let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+	let provider = Arc::new(rustls::crypto::ring::default_provider());
+	let config = ServerConfig::builder_with_provider(provider)
+		.with_safe_default_protocol_versions()
+		.unwrap()
+		.with_no_client_auth()
+		.with_single_cert(vec![CertificateDer::from(certificate.clone())], key)
+		.unwrap();
+
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let server = thread::spawn(move || {
+		let (mut socket, _) = listener.accept().unwrap();
+		let mut connection = ServerConnection::new(Arc::new(config))?;
+		while connection.is_handshaking() {
+			connection.complete_io(&mut socket).map_err(tls_error)?;
+		}
+		let mut stream = rustls::Stream::new(&mut connection, &mut socket);
+		let mut request = [0; 4];
+		stream.read_exact(&mut request).map_err(tls_error)?;
+		assert_eq!(&request, b"ping");
+		stream.write_all(b"pong").map_err(tls_error)?;
+		connection.send_close_notify();
+		connection.complete_io(&mut socket).map_err(tls_error)?;
+		Ok(())
+	});
+	(addr, certificate, server)
+}
+
+#[test]
+fn exchanges_data_with_a_trusted_server() {
+	let (addr, certificate, server) = serve();
+	let ca_certificates = [certificate.as_slice()];
+	let config = TlsConfig {
+		ca_certificates: &ca_certificates,
+		..TlsConfig::default()
+	};
+	let tls: TlsConnect<_, _> = TlsConnect::new(StdStack, Rustls, config);
+
+	futures_executor::block_on(async {
+		let mut connection = tls.connect(addr, "localhost").await.unwrap();
+		assert_eq!(connection.peer_addr().unwrap(), addr);
+		connection.write_all(b"ping").await.unwrap();
+
+		let mut reply = [0; 4];
+		connection.read_exact(&mut reply).await.unwrap();
+		assert_eq!(&reply, b"pong");
+		// The server sent a closure alert after its reply.
+		assert_eq!(connection.read(&mut reply).await.unwrap(), 0);
+		connection.close().await.ok();
+	});
+	server.join().unwrap().unwrap();
+}
+
+#[test]
+fn rejects_a_certificate_for_another_name() {
+	let (addr, certificate, server) = serve();
+	let ca_certificates = [certificate.as_slice()];
+	let config = TlsConfig {
+		ca_certificates: &ca_certificates,
+		..TlsConfig::default()
+	};
+	let tls: TlsConnect<_, _> = TlsConnect::new(StdStack, Rustls, config);
+
+	let result = futures_executor::block_on(tls.connect(addr, "example.com"));
+	assert!(matches!(
+		result,
+		Err(TlsError::Tls(rustls::Error::InvalidCertificate(_)))
+	));
+	// The server sees the client's alert.
+	assert!(server.join().unwrap().is_err());
+}
+
+#[test]
+fn rejects_an_untrusted_server() {
+	let (addr, _, server) = serve();
+	let tls: TlsConnect<_, _> = TlsConnect::new(StdStack, Rustls, TlsConfig::default());
+
+	let result = futures_executor::block_on(tls.connect(addr, "localhost"));
+	assert!(matches!(
+		result,
+		Err(TlsError::Tls(rustls::Error::InvalidCertificate(_)))
+	));
+	assert!(server.join().unwrap().is_err());
+}
@@ -0,0 +1,16 @@
+use core::time::Duration;
+
+/// A monotonic clock, used by protocol implementations for timeouts and retransmissions.
+///
+/// The time is measured from an arbitrary point, such as the last reset, and must never go
+/// backwards. It is implemented for closures returning the time.
+pub trait Clock {
+	/// The time passed since the clock's starting point.
+	fn now(&mut self) -> Duration;
+}
+
+impl<F: FnMut() -> Duration> Clock for F {
+	fn now(&mut self) -> Duration {
+		self()
+	}
+}
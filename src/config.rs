@@ -0,0 +1,48 @@
+use crate::IpConfig;
+use core::net::IpAddr;
+
+/// This trait is implemented by network stacks whose IP configuration can be changed at runtime.
+///
+/// It covers the settings that are commonly needed for provisioning a device, and reports the
+/// effective configuration through [`NetworkStatus`](crate::NetworkStatus), which implementers
+/// are expected to implement as well.
+pub trait NetworkConfig {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Replace the static IP configurations of the stack.
+	///
+	/// Both IPv4 and IPv6 configurations can be given, each with its own default gateway. An empty
+	/// slice removes all static configurations.
+	///
+	/// While DHCP is enabled, static IPv4 configurations are not applied.
+	fn set_static_config(&mut self, configs: &[IpConfig]) -> Result<(), Self::Error>;
+
+	/// Enable or disable obtaining the IPv4 configuration through DHCP.
+	///
+	/// When DHCP is disabled, any lease is released and the static IPv4 configuration (if any) is
+	/// applied instead.
+	fn set_dhcp(&mut self, enabled: bool) -> Result<(), Self::Error>;
+
+	/// Replace the DNS servers used by the stack.
+	///
+	/// An empty slice makes the stack fall back to the servers it learns automatically, e.g.
+	/// through DHCP.
+	fn set_dns_servers(&mut self, servers: &[IpAddr]) -> Result<(), Self::Error>;
+}
+
+impl<T: NetworkConfig> NetworkConfig for &mut T {
+	type Error = T::Error;
+
+	fn set_static_config(&mut self, configs: &[IpConfig]) -> Result<(), Self::Error> {
+		T::set_static_config(self, configs)
+	}
+
+	fn set_dhcp(&mut self, enabled: bool) -> Result<(), Self::Error> {
+		T::set_dhcp(self, enabled)
+	}
+
+	fn set_dns_servers(&mut self, servers: &[IpAddr]) -> Result<(), Self::Error> {
+		T::set_dns_servers(self, servers)
+	}
+}
@@ -0,0 +1,677 @@
+use super::{
+	DhcpMessage, DhcpMessageError, DhcpMessageType, DhcpMessageWriter, DhcpOption,
+	DHCP_CLIENT_PORT, DHCP_MESSAGE_LEN, DHCP_SERVER_PORT, HTYPE_ETHERNET,
+};
+use crate::{nb, Clock, InterfaceAddress, IpConfig, UdpFullStack};
+use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use core::time::Duration;
+
+/// Most DNS servers kept from a lease
+const MAX_DNS_SERVERS: usize = 3;
+
+/// First retransmission interval while acquiring a lease, doubled for every retransmission
+const INITIAL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Longest retransmission interval while acquiring a lease
+const MAX_INTERVAL: Duration = Duration::from_secs(64);
+
+/// Shortest retransmission interval while renewing or rebinding a lease
+const MIN_RENEW_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of requests sent for an offer before starting over
+const REQUEST_ATTEMPTS: u8 = 4;
+
+/// The options asked for in discover and request messages
+const PARAMETER_REQUEST_LIST: [u8; 6] = [1, 3, 6, 51, 58, 59];
+
+/// The configuration obtained from a DHCP server, see [`DhcpClient`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DhcpLease {
+	address: Ipv4Addr,
+	netmask: Ipv4Addr,
+	router: Option<Ipv4Addr>,
+	dns_servers: [Ipv4Addr; MAX_DNS_SERVERS],
+	dns_server_count: usize,
+	server: Ipv4Addr,
+	lease_time: Duration,
+	renewal_time: Duration,
+	rebinding_time: Duration,
+	obtained: Duration,
+}
+
+impl DhcpLease {
+	/// Take the lease from an acknowledgment, for a request sent at `obtained`.
+	///
+	/// Servers identify themselves in every acknowledgment; `server` is used if one does not.
+	fn from_ack(
+		ack: &DhcpMessage<'_>,
+		server: Option<Ipv4Addr>,
+		obtained: Duration,
+	) -> Option<Self> {
+		let address = ack.your_address();
+		if address.is_unspecified() {
+			return None;
+		}
+		let server = ack.option_addr(DhcpOption::SERVER_IDENTIFIER).or(server)?;
+		// Missing times are derived as in RFC 2131, section 4.4.5; a lease without a time is
+		// taken to be infinite.
+		let seconds = |code| {
+			ack.option_u32(code)
+				.map(|time| Duration::from_secs(time.into()))
+		};
+		let lease_time =
+			seconds(DhcpOption::LEASE_TIME).unwrap_or(Duration::from_secs(u32::MAX.into()));
+		let rebinding_time = seconds(DhcpOption::REBINDING_TIME)
+			.filter(|time| *time <= lease_time)
+			.unwrap_or(lease_time * 7 / 8);
+		let renewal_time = seconds(DhcpOption::RENEWAL_TIME)
+			.filter(|time| *time <= rebinding_time)
+			.unwrap_or(lease_time / 2);
+
+		let mut dns_servers = [Ipv4Addr::UNSPECIFIED; MAX_DNS_SERVERS];
+		let mut dns_server_count = 0;
+		for (slot, server) in dns_servers
+			.iter_mut()
+			.zip(ack.option_addrs(DhcpOption::DNS_SERVERS))
+		{
+			*slot = server;
+			dns_server_count += 1;
+		}
+
+		Some(DhcpLease {
+			address,
+			netmask: ack
+				.option_addr(DhcpOption::SUBNET_MASK)
+				.unwrap_or_else(|| classful_netmask(address)),
+			router: ack.option_addrs(DhcpOption::ROUTER).next(),
+			dns_servers,
+			dns_server_count,
+			server,
+			lease_time,
+			renewal_time,
+			rebinding_time,
+			obtained,
+		})
+	}
+
+	/// The assigned address
+	pub fn address(&self) -> Ipv4Addr {
+		self.address
+	}
+
+	/// The subnet mask; if the server did not send one, the mask of the address class is assumed
+	pub fn netmask(&self) -> Ipv4Addr {
+		self.netmask
+	}
+
+	/// Number of leading bits of the address that identify the subnet
+	pub fn prefix_len(&self) -> u8 {
+		u32::from(self.netmask).leading_ones() as u8
+	}
+
+	/// The default gateway, if any
+	pub fn router(&self) -> Option<Ipv4Addr> {
+		self.router
+	}
+
+	/// The DNS servers, in order of preference; up to 3 are kept.
+	pub fn dns_servers(&self) -> &[Ipv4Addr] {
+		&self.dns_servers[..self.dns_server_count]
+	}
+
+	/// The address of the server that granted the lease
+	pub fn server(&self) -> Ipv4Addr {
+		self.server
+	}
+
+	/// The time the lease is valid for, counted from when it was requested
+	pub fn lease_time(&self) -> Duration {
+		self.lease_time
+	}
+
+	/// The time after which the lease is renewed with the server that granted it
+	pub fn renewal_time(&self) -> Duration {
+		self.renewal_time
+	}
+
+	/// The time after which the lease is renewed with any server
+	pub fn rebinding_time(&self) -> Duration {
+		self.rebinding_time
+	}
+
+	/// The lease as an IP configuration, as applied through
+	/// [`NetworkConfig::set_static_config`](crate::NetworkConfig::set_static_config).
+	pub fn ip_config(&self) -> IpConfig {
+		IpConfig {
+			address: InterfaceAddress {
+				addr: IpAddr::V4(self.address),
+				prefix_len: self.prefix_len(),
+			},
+			gateway: self.router.map(IpAddr::V4),
+		}
+	}
+
+	/// Whether the lease configures the stack the same way as `other`, regardless of its times
+	fn same_config(&self, other: &DhcpLease) -> bool {
+		self.ip_config() == other.ip_config() && self.dns_servers() == other.dns_servers()
+	}
+}
+
+/// The netmask of the class an address is in, see RFC 1122, section 3.3.1.1
+fn classful_netmask(address: Ipv4Addr) -> Ipv4Addr {
+	match address.octets()[0] {
+		0..=127 => Ipv4Addr::new(255, 0, 0, 0),
+		128..=191 => Ipv4Addr::new(255, 255, 0, 0),
+		_ => Ipv4Addr::new(255, 255, 255, 0),
+	}
+}
+
+/// A change of the configuration reported by [`DhcpClient::poll`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DhcpEvent {
+	/// A lease was obtained, or renewed with a changed configuration, which is available through
+	/// [`DhcpClient::lease`].
+	Bound,
+	/// The lease expired or was revoked by the server; its configuration must no longer be used.
+	Lost,
+}
+
+/// The states of a DHCP client, see RFC 2131, section 4.4
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+	/// No lease; a discover message is sent next.
+	Init,
+	/// Waiting for offers.
+	Selecting,
+	/// Requesting the given address offered by the given server.
+	Requesting(Ipv4Addr, Ipv4Addr),
+	/// Holding a lease.
+	Bound,
+	/// Renewing the lease with the server that granted it.
+	Renewing,
+	/// Renewing the lease with any server.
+	Rebinding,
+	/// The lease was released; nothing is done until the client is reset.
+	Released,
+}
+
+/// A DHCPv4 client (see [RFC 2131]) on top of any [`UdpFullStack`].
+///
+/// The client discovers a server, requests an address, and keeps renewing the lease as long as
+/// it is polled; the obtained configuration is available through
+/// [`.lease()`](DhcpClient::lease), and changes to it are reported by
+/// [`.poll()`](DhcpClient::poll), for the application to apply them to the stack.
+///
+/// The stack has to be able to send datagrams to and receive them from the limited broadcast
+/// address `255.255.255.255` before it has an address of its own. Replies are asked to be
+/// broadcast, so the stack does not need to accept unicast datagrams to an address it does not
+/// have yet. The client does not check whether the offered address is in use already.
+///
+/// ```
+/// use embedded_nal::{DhcpClient, DhcpEvent, DhcpMessageType, nb};
+/// use core::net::{Ipv4Addr, SocketAddr};
+/// use core::time::Duration;
+/// # use embedded_nal::{DhcpMessage, DhcpMessageWriter, DhcpOption};
+/// # use std::{cell::{Cell, RefCell}, rc::Rc};
+/// # /// The server's side: the reply to requests (none if `None`), and the messages sent to it
+/// # struct Server { answer: Option<DhcpMessageType>, sent: Vec<(SocketAddr, DhcpMessageType)> }
+/// # struct Network { reply: Option<([u8; 576], usize)>, server: Rc<RefCell<Server>> }
+/// # impl embedded_nal::UdpClientStack for Network {
+/// #   type UdpSocket = ();
+/// #   type Error = ();
+/// #   fn socket(&mut self) -> Result<(), ()> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), _: &[u8]) -> nb::Result<(), ()> { Ok(()) }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+/// #     let (reply, len) = self.reply.take().ok_or(nb::Error::WouldBlock)?;
+/// #     buffer[..len].copy_from_slice(&reply[..len]);
+/// #     Ok((len, "192.168.4.1:67".parse().unwrap()))
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # impl embedded_nal::UdpFullStack for Network {
+/// #   fn bind(&mut self, _: &mut (), _: u16) -> Result<(), ()> { Ok(()) }
+/// #   fn send_to(&mut self, _: &mut (), remote: SocketAddr, request: &[u8]) -> nb::Result<(), ()> {
+/// #     let request = DhcpMessage::parse(request).unwrap();
+/// #     let mut server = self.server.borrow_mut();
+/// #     server.sent.push((remote, request.message_type().unwrap()));
+/// #     let message_type = match request.message_type() {
+/// #       Some(DhcpMessageType::Discover) => DhcpMessageType::Offer,
+/// #       Some(DhcpMessageType::Request) => match server.answer {
+/// #         Some(answer) => answer,
+/// #         None => return Ok(()),
+/// #       },
+/// #       _ => return Ok(()),
+/// #     };
+/// #     let mut reply = [0; 576];
+/// #     let mut writer =
+/// #       DhcpMessageWriter::new(&mut reply, message_type, request.xid(), request.hardware_address()).unwrap();
+/// #     writer.set_your_address(Ipv4Addr::new(192, 168, 4, 2));
+/// #     writer.option(DhcpOption::SERVER_IDENTIFIER, &[192, 168, 4, 1]).unwrap();
+/// #     writer.option(DhcpOption::SUBNET_MASK, &[255, 255, 255, 0]).unwrap();
+/// #     writer.option(DhcpOption::ROUTER, &[192, 168, 4, 1]).unwrap();
+/// #     writer.option(DhcpOption::LEASE_TIME, &3600u32.to_be_bytes()).unwrap();
+/// #     let len = writer.finish();
+/// #     self.reply = Some((reply, len));
+/// #     Ok(())
+/// #   }
+/// # }
+/// # let server = Rc::new(RefCell::new(Server { answer: Some(DhcpMessageType::Ack), sent: Vec::new() }));
+/// # let stack = Network { reply: None, server: server.clone() };
+/// # let time = Rc::new(Cell::new(Duration::ZERO));
+/// # let clock = { let time = time.clone(); move || time.get() };
+/// # let last_sent = || server.borrow().sent.last().copied().unwrap();
+/// let mac = [0x02, 0, 0, 0, 0, 1];
+/// let mut client = DhcpClient::new(stack, clock, mac);
+///
+/// while client.poll()? != Some(DhcpEvent::Bound) {}
+/// let lease = client.lease().unwrap();
+/// assert_eq!(lease.address(), Ipv4Addr::new(192, 168, 4, 2));
+/// assert_eq!(lease.prefix_len(), 24);
+/// assert_eq!(lease.router(), Some(Ipv4Addr::new(192, 168, 4, 1)));
+/// assert_eq!(lease.renewal_time(), Duration::from_secs(1800));
+///
+/// let server_addr: SocketAddr = "192.168.4.1:67".parse().unwrap();
+/// let broadcast: SocketAddr = "255.255.255.255:67".parse().unwrap();
+///
+/// // Past the renewal time, the lease is renewed with the server that granted it; an unchanged
+/// // configuration is not reported.
+/// time.set(Duration::from_secs(1800));
+/// assert_eq!(client.poll()?, None);
+/// assert_eq!(last_sent(), (server_addr, DhcpMessageType::Request));
+/// assert_eq!(client.poll()?, None);
+/// assert!(client.lease().is_some());
+///
+/// // If that server does not answer, the lease is renewed with any server past the rebinding
+/// // time, and given up when a server refuses it.
+/// # server.borrow_mut().answer = None;
+/// time.set(Duration::from_secs(1800 + 1800));
+/// assert_eq!(client.poll()?, None);
+/// assert_eq!(last_sent(), (server_addr, DhcpMessageType::Request));
+/// time.set(Duration::from_secs(1800 + 3150));
+/// assert_eq!(client.poll()?, None);
+/// assert_eq!(last_sent(), (broadcast, DhcpMessageType::Request));
+/// # server.borrow_mut().answer = Some(DhcpMessageType::Nak);
+/// time.set(Duration::from_secs(1800 + 3150 + 225));
+/// assert_eq!(client.poll()?, None);
+/// assert_eq!(last_sent(), (broadcast, DhcpMessageType::Request));
+/// assert_eq!(client.poll()?, Some(DhcpEvent::Lost));
+/// assert!(client.lease().is_none());
+///
+/// // A new lease is obtained, and released to the server that granted it.
+/// # server.borrow_mut().answer = Some(DhcpMessageType::Ack);
+/// while client.poll()? != Some(DhcpEvent::Bound) {}
+/// client.release()?;
+/// assert_eq!(last_sent(), (server_addr, DhcpMessageType::Release));
+/// assert!(client.lease().is_none());
+/// assert_eq!(client.poll()?, None);
+/// # Ok::<(), ()>(())
+/// ```
+///
+/// [RFC 2131]: https://tools.ietf.org/html/rfc2131
+pub struct DhcpClient<S: UdpFullStack, C> {
+	stack: S,
+	clock: C,
+	socket: Option<S::UdpSocket>,
+	mac: [u8; 6],
+	local_port: u16,
+	server_addr: SocketAddr,
+	state: State,
+	lease: Option<DhcpLease>,
+	xid: u32,
+	/// When the current exchange started, for the `secs` field
+	started: Duration,
+	/// When the last message was sent
+	sent: Duration,
+	/// When the next retransmission is due
+	next_send: Duration,
+	/// The retransmission interval while acquiring a lease
+	interval: Duration,
+	/// Number of requests sent for the current offer
+	attempts: u8,
+}
+
+impl<S: UdpFullStack, C: Clock> DhcpClient<S, C> {
+	/// Create a client for the interface with the Ethernet address `mac`, communicating through
+	/// `stack` and timing retransmissions and the lease with `clock`.
+	pub fn new(stack: S, clock: C, mac: [u8; 6]) -> Self {
+		DhcpClient {
+			stack,
+			clock,
+			socket: None,
+			mac,
+			local_port: DHCP_CLIENT_PORT,
+			server_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), DHCP_SERVER_PORT),
+			state: State::Init,
+			lease: None,
+			xid: u32::from_be_bytes([mac[2], mac[3], mac[4], mac[5]]),
+			started: Duration::ZERO,
+			sent: Duration::ZERO,
+			next_send: Duration::ZERO,
+			interval: INITIAL_INTERVAL,
+			attempts: 0,
+		}
+	}
+
+	/// Set the local port messages are sent from and received on; the default is
+	/// [`DHCP_CLIENT_PORT`].
+	///
+	/// This and [`.set_server_addr()`](DhcpClient::set_server_addr) allow testing against a
+	/// server on unprivileged ports. The port takes effect when the client's socket is opened
+	/// on the next poll after creating or resetting the client.
+	pub fn set_local_port(&mut self, local_port: u16) {
+		self.local_port = local_port;
+	}
+
+	/// Set the address messages to any server are sent to; the default is the limited broadcast
+	/// address with [`DHCP_SERVER_PORT`].
+	///
+	/// Messages to the server that granted the lease are sent to its address, with the port of
+	/// `server_addr`.
+	pub fn set_server_addr(&mut self, server_addr: SocketAddr) {
+		self.server_addr = server_addr;
+	}
+
+	/// The current lease, if any
+	pub fn lease(&self) -> Option<&DhcpLease> {
+		self.lease.as_ref()
+	}
+
+	/// Receive the replies of servers, and send the messages that are due.
+	///
+	/// Returns an event if the configuration changed, which the application then has to apply
+	/// to the stack. Sending blocks (by spinning on [`nb::Error::WouldBlock`]) until the stack
+	/// accepted the message.
+	pub fn poll(&mut self) -> Result<Option<DhcpEvent>, S::Error> {
+		if self.state == State::Released {
+			return Ok(None);
+		}
+		let now = self.clock.now();
+		let mut socket = match self.socket.take() {
+			Some(socket) => socket,
+			None => {
+				let mut socket = self.stack.socket()?;
+				if let Err(e) = self.stack.bind(&mut socket, self.local_port) {
+					self.stack.close(socket).ok();
+					return Err(e);
+				}
+				socket
+			}
+		};
+		let result = self.poll_socket(&mut socket, now);
+		self.socket = Some(socket);
+		result
+	}
+
+	/// Give up the lease, telling the server that granted it.
+	///
+	/// The client then stays idle until it is [reset](DhcpClient::reset).
+	pub fn release(&mut self) -> Result<(), S::Error> {
+		let lease = self.lease.take();
+		self.state = State::Released;
+		let Some(lease) = lease else {
+			return Ok(());
+		};
+		let now = self.clock.now();
+		self.next_xid(now);
+		self.started = now;
+		let destination = SocketAddr::new(IpAddr::V4(lease.server), self.server_addr.port());
+		let mut socket = match self.socket.take() {
+			Some(socket) => socket,
+			None => return Ok(()),
+		};
+		let result = self.send(
+			&mut socket,
+			DhcpMessageType::Release,
+			Some(&lease),
+			destination,
+			now,
+		);
+		self.socket = Some(socket);
+		result
+	}
+
+	/// Drop the lease without releasing it, and start over by discovering servers, for example
+	/// after the link was down.
+	///
+	/// This also resumes a client that released its lease.
+	pub fn reset(&mut self) {
+		if let Some(socket) = self.socket.take() {
+			self.stack.close(socket).ok();
+		}
+		self.lease = None;
+		self.state = State::Init;
+	}
+
+	/// Release the stack and clock, without releasing the lease.
+	pub fn free(mut self) -> (S, C) {
+		if let Some(socket) = self.socket.take() {
+			self.stack.close(socket).ok();
+		}
+		(self.stack, self.clock)
+	}
+
+	fn poll_socket(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		now: Duration,
+	) -> Result<Option<DhcpEvent>, S::Error> {
+		let mut event = None;
+		let mut buffer = [0; DHCP_MESSAGE_LEN];
+		loop {
+			let len = match self.stack.receive(socket, &mut buffer) {
+				Ok((len, _)) => len,
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => return Err(e),
+			};
+			let Ok(message) = DhcpMessage::parse(&buffer[..len.min(DHCP_MESSAGE_LEN)]) else {
+				continue;
+			};
+			if message.is_reply()
+				&& message.xid() == self.xid
+				&& message.hardware_address() == self.mac
+			{
+				event = self.handle(socket, &message, now)?.or(event);
+			}
+		}
+
+		if let Some(lease) = self.lease {
+			let elapsed = now.saturating_sub(lease.obtained);
+			if elapsed >= lease.lease_time {
+				self.lease = None;
+				self.state = State::Init;
+				event = Some(DhcpEvent::Lost);
+			} else if elapsed >= lease.rebinding_time && self.state != State::Rebinding {
+				self.start(State::Rebinding, now);
+			} else if elapsed >= lease.renewal_time && self.state == State::Bound {
+				self.start(State::Renewing, now);
+			}
+		}
+
+		if let State::Requesting(..) = self.state {
+			if now >= self.next_send && self.attempts >= REQUEST_ATTEMPTS {
+				self.state = State::Init;
+			}
+		}
+		if self.state == State::Init {
+			self.start(State::Selecting, now);
+		}
+		let exchanging = matches!(
+			self.state,
+			State::Selecting | State::Requesting(..) | State::Renewing | State::Rebinding
+		);
+		if exchanging && now >= self.next_send {
+			self.transmit(socket, now)?;
+		}
+		Ok(event)
+	}
+
+	/// Act on a reply to the current exchange.
+	fn handle(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		message: &DhcpMessage<'_>,
+		now: Duration,
+	) -> Result<Option<DhcpEvent>, S::Error> {
+		let message_type = message.message_type();
+		match (self.state, message_type) {
+			(State::Selecting, Some(DhcpMessageType::Offer)) => {
+				let server = message.option_addr(DhcpOption::SERVER_IDENTIFIER);
+				match (message.your_address(), server) {
+					(offered, Some(server)) if !offered.is_unspecified() => {
+						self.state = State::Requesting(offered, server);
+						self.interval = INITIAL_INTERVAL;
+						self.attempts = 0;
+						self.transmit(socket, now)?;
+					}
+					_ => (),
+				}
+				Ok(None)
+			}
+			(State::Requesting(offered, server), Some(DhcpMessageType::Ack)) => {
+				match DhcpLease::from_ack(message, Some(server), self.sent) {
+					Some(lease) if lease.address == offered => {
+						self.lease = Some(lease);
+						self.state = State::Bound;
+						Ok(Some(DhcpEvent::Bound))
+					}
+					_ => Ok(None),
+				}
+			}
+			(State::Renewing | State::Rebinding, Some(DhcpMessageType::Ack)) => {
+				let previous = self.lease;
+				let server = previous.map(|lease| lease.server);
+				let Some(lease) = DhcpLease::from_ack(message, server, self.sent) else {
+					return Ok(None);
+				};
+				self.lease = Some(lease);
+				self.state = State::Bound;
+				match previous {
+					Some(previous) if previous.same_config(&lease) => Ok(None),
+					_ => Ok(Some(DhcpEvent::Bound)),
+				}
+			}
+			(State::Requesting(..), Some(DhcpMessageType::Nak)) => {
+				self.state = State::Init;
+				Ok(None)
+			}
+			(State::Renewing | State::Rebinding, Some(DhcpMessageType::Nak)) => {
+				self.lease = None;
+				self.state = State::Init;
+				Ok(Some(DhcpEvent::Lost))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	/// Send the message of the current state, and schedule its retransmission.
+	fn transmit(&mut self, socket: &mut S::UdpSocket, now: Duration) -> Result<(), S::Error> {
+		let broadcast = self.server_addr;
+		let lease = self.lease;
+		let (message_type, destination) = match (self.state, lease) {
+			(State::Selecting, _) => (DhcpMessageType::Discover, broadcast),
+			(State::Renewing, Some(lease)) => (
+				DhcpMessageType::Request,
+				SocketAddr::new(IpAddr::V4(lease.server), broadcast.port()),
+			),
+			_ => (DhcpMessageType::Request, broadcast),
+		};
+		self.send(socket, message_type, lease.as_ref(), destination, now)?;
+
+		// Renewing and rebinding retransmit after half the time remaining until the next step.
+		self.next_send = match (self.state, lease) {
+			(State::Renewing, Some(lease)) => {
+				let remaining = (lease.obtained + lease.rebinding_time).saturating_sub(now);
+				now + (remaining / 2).max(MIN_RENEW_INTERVAL)
+			}
+			(State::Rebinding, Some(lease)) => {
+				let remaining = (lease.obtained + lease.lease_time).saturating_sub(now);
+				now + (remaining / 2).max(MIN_RENEW_INTERVAL)
+			}
+			_ => {
+				// Randomize the interval by up to a second, so that clients powered up
+				// together do not keep colliding.
+				let jitter = Duration::from_millis(u64::from(self.xid % 1000));
+				let next = now + self.interval + jitter;
+				self.interval = (self.interval * 2).min(MAX_INTERVAL);
+				next
+			}
+		};
+		if let State::Requesting(..) = self.state {
+			self.attempts += 1;
+		}
+		Ok(())
+	}
+
+	fn send(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		message_type: DhcpMessageType,
+		lease: Option<&DhcpLease>,
+		destination: SocketAddr,
+		now: Duration,
+	) -> Result<(), S::Error> {
+		let mut buffer = [0; DHCP_MESSAGE_LEN];
+		let len = match self.write(&mut buffer, message_type, lease, now) {
+			Ok(len) => len,
+			// The messages always fit into the buffer.
+			Err(_) => return Ok(()),
+		};
+		self.sent = now;
+		nb::block!(self.stack.send_to(socket, destination, &buffer[..len]))
+	}
+
+	fn write(
+		&self,
+		buffer: &mut [u8],
+		message_type: DhcpMessageType,
+		lease: Option<&DhcpLease>,
+		now: Duration,
+	) -> Result<usize, DhcpMessageError> {
+		let mut writer = DhcpMessageWriter::new(buffer, message_type, self.xid, &self.mac)?;
+		let secs = now.saturating_sub(self.started).as_secs();
+		writer.set_secs(u16::try_from(secs).unwrap_or(u16::MAX));
+		let mut client_id = [HTYPE_ETHERNET; 7];
+		client_id[1..].copy_from_slice(&self.mac);
+		writer.option(DhcpOption::CLIENT_IDENTIFIER, &client_id)?;
+		match (self.state, lease) {
+			(State::Requesting(offered, server), _) => {
+				writer.set_broadcast(true);
+				writer.option(DhcpOption::REQUESTED_ADDRESS, &offered.octets())?;
+				writer.option(DhcpOption::SERVER_IDENTIFIER, &server.octets())?;
+			}
+			(State::Released, Some(lease)) => {
+				writer.set_client_address(lease.address);
+				writer.option(DhcpOption::SERVER_IDENTIFIER, &lease.server.octets())?;
+			}
+			(_, Some(lease)) => writer.set_client_address(lease.address),
+			(_, None) => writer.set_broadcast(true),
+		}
+		if message_type != DhcpMessageType::Release {
+			let max_size = DHCP_MESSAGE_LEN as u16;
+			writer.option(DhcpOption::MAX_MESSAGE_SIZE, &max_size.to_be_bytes())?;
+			writer.option(DhcpOption::PARAMETER_REQUEST_LIST, &PARAMETER_REQUEST_LIST)?;
+		}
+		Ok(writer.finish())
+	}
+
+	/// Start a new exchange in `state` at `now`, sending its first message right away.
+	fn start(&mut self, state: State, now: Duration) {
+		self.next_xid(now);
+		self.started = now;
+		self.state = state;
+		self.next_send = now;
+		self.interval = INITIAL_INTERVAL;
+	}
+
+	/// Pick a new transaction ID for an exchange starting at `now`.
+	fn next_xid(&mut self, now: Duration) {
+		// xorshift32, seeded with the hardware address and the time
+		let mut xid = self.xid ^ now.subsec_nanos() ^ 0x9e37_79b9;
+		xid ^= xid << 13;
+		xid ^= xid >> 17;
+		xid ^= xid << 5;
+		self.xid = xid;
+	}
+}
@@ -0,0 +1,386 @@
+use core::net::Ipv4Addr;
+
+mod client;
+mod server;
+
+pub use client::{DhcpClient, DhcpEvent, DhcpLease};
+pub use server::{DhcpLeases, DhcpServer, DhcpServerConfig, DhcpServerLease};
+
+/// The well-known port of DHCP servers
+pub const DHCP_SERVER_PORT: u16 = 67;
+
+/// The well-known port of DHCP clients
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+/// Size of the buffers DHCP messages are sent from and received into, the largest message every
+/// client has to accept
+pub const DHCP_MESSAGE_LEN: usize = 576;
+
+/// Offset of the options, after the fixed fields and the magic cookie
+const OPTIONS_OFFSET: usize = 240;
+
+/// The magic cookie preceding the options, see [RFC 2131, section 3]
+///
+/// [RFC 2131, section 3]: https://tools.ietf.org/html/rfc2131#section-3
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// Length messages are padded to, as some servers and relays drop shorter ones
+const MIN_MESSAGE_LEN: usize = 300;
+
+/// The flag asking for replies to be broadcast
+const BROADCAST: u16 = 0x8000;
+
+/// The hardware type of Ethernet addresses
+const HTYPE_ETHERNET: u8 = 1;
+
+/// Errors in reading or writing DHCP messages.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DhcpMessageError {
+	/// The message is truncated or otherwise not a valid DHCP message, or an option or hardware
+	/// address to be written is too long.
+	Malformed,
+	/// The message does not fit into the buffer.
+	BufferTooSmall,
+}
+
+/// The type of a DHCP message, see [RFC 2131, section 3.1].
+///
+/// [RFC 2131, section 3.1]: https://tools.ietf.org/html/rfc2131#section-3.1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DhcpMessageType {
+	/// A client looking for servers
+	Discover = 1,
+	/// A server offering an address
+	Offer = 2,
+	/// A client requesting an offered address, or renewing its lease
+	Request = 3,
+	/// A client declining an address that is in use already
+	Decline = 4,
+	/// A server granting a lease
+	Ack = 5,
+	/// A server refusing a request
+	Nak = 6,
+	/// A client giving up its lease
+	Release = 7,
+	/// A client asking for configuration parameters only
+	Inform = 8,
+}
+
+impl DhcpMessageType {
+	/// Look up a message type by its value in the message type option.
+	pub fn from_u8(value: u8) -> Option<Self> {
+		Some(match value {
+			1 => DhcpMessageType::Discover,
+			2 => DhcpMessageType::Offer,
+			3 => DhcpMessageType::Request,
+			4 => DhcpMessageType::Decline,
+			5 => DhcpMessageType::Ack,
+			6 => DhcpMessageType::Nak,
+			7 => DhcpMessageType::Release,
+			8 => DhcpMessageType::Inform,
+			_ => return None,
+		})
+	}
+
+	/// Whether messages of this type are sent by servers.
+	pub fn is_reply(self) -> bool {
+		matches!(
+			self,
+			DhcpMessageType::Offer | DhcpMessageType::Ack | DhcpMessageType::Nak
+		)
+	}
+}
+
+/// The code of a DHCP option, see [RFC 2132].
+///
+/// [RFC 2132]: https://tools.ietf.org/html/rfc2132
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DhcpOption(pub u8);
+
+impl DhcpOption {
+	/// The subnet mask
+	pub const SUBNET_MASK: Self = DhcpOption(1);
+	/// The routers on the subnet, in order of preference
+	pub const ROUTER: Self = DhcpOption(3);
+	/// The DNS servers, in order of preference
+	pub const DNS_SERVERS: Self = DhcpOption(6);
+	/// The name of the client
+	pub const HOSTNAME: Self = DhcpOption(12);
+	/// The domain name of the client
+	pub const DOMAIN_NAME: Self = DhcpOption(15);
+	/// The broadcast address of the subnet
+	pub const BROADCAST_ADDRESS: Self = DhcpOption(28);
+	/// The address requested by a client
+	pub const REQUESTED_ADDRESS: Self = DhcpOption(50);
+	/// The lease time in seconds
+	pub const LEASE_TIME: Self = DhcpOption(51);
+	/// The type of the message, see [`DhcpMessageType`]
+	pub const MESSAGE_TYPE: Self = DhcpOption(53);
+	/// The address identifying the server
+	pub const SERVER_IDENTIFIER: Self = DhcpOption(54);
+	/// The options a client asks for
+	pub const PARAMETER_REQUEST_LIST: Self = DhcpOption(55);
+	/// An error message
+	pub const MESSAGE: Self = DhcpOption(56);
+	/// The largest message a client accepts
+	pub const MAX_MESSAGE_SIZE: Self = DhcpOption(57);
+	/// The time in seconds after which a lease is renewed
+	pub const RENEWAL_TIME: Self = DhcpOption(58);
+	/// The time in seconds after which a lease is rebound
+	pub const REBINDING_TIME: Self = DhcpOption(59);
+	/// A unique identifier of the client
+	pub const CLIENT_IDENTIFIER: Self = DhcpOption(61);
+}
+
+/// A received DHCP message, see [RFC 2131, section 2].
+///
+/// [RFC 2131, section 2]: https://tools.ietf.org/html/rfc2131#section-2
+#[derive(Copy, Clone, Debug)]
+pub struct DhcpMessage<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> DhcpMessage<'a> {
+	/// Check that `data` is a well-formed DHCP message.
+	///
+	/// Options that are cut off by the end of the message are ignored, as they are by many
+	/// implementations.
+	pub fn parse(data: &'a [u8]) -> Result<Self, DhcpMessageError> {
+		if data.len() < OPTIONS_OFFSET
+			|| data[236..OPTIONS_OFFSET] != MAGIC_COOKIE
+			|| !matches!(data[0], 1 | 2)
+		{
+			return Err(DhcpMessageError::Malformed);
+		}
+		Ok(DhcpMessage { data })
+	}
+
+	/// Whether the message was sent by a server.
+	pub fn is_reply(&self) -> bool {
+		self.data[0] == 2
+	}
+
+	/// The transaction ID, chosen by the client and copied into replies
+	pub fn xid(&self) -> u32 {
+		u32::from_be_bytes([self.data[4], self.data[5], self.data[6], self.data[7]])
+	}
+
+	/// The seconds since the client started acquiring or renewing a lease
+	pub fn secs(&self) -> u16 {
+		u16::from_be_bytes([self.data[8], self.data[9]])
+	}
+
+	/// Whether the client asks for replies to be broadcast.
+	pub fn is_broadcast(&self) -> bool {
+		u16::from_be_bytes([self.data[10], self.data[11]]) & BROADCAST != 0
+	}
+
+	/// The address of a client that has one (`ciaddr`)
+	pub fn client_address(&self) -> Ipv4Addr {
+		self.addr(12)
+	}
+
+	/// The address offered or assigned to the client (`yiaddr`)
+	pub fn your_address(&self) -> Ipv4Addr {
+		self.addr(16)
+	}
+
+	/// The address of the server to use in the next step of booting (`siaddr`)
+	pub fn server_address(&self) -> Ipv4Addr {
+		self.addr(20)
+	}
+
+	/// The address of the relay agent the message was passed through, if any (`giaddr`)
+	pub fn relay_address(&self) -> Ipv4Addr {
+		self.addr(24)
+	}
+
+	/// The hardware address of the client (`chaddr`)
+	pub fn hardware_address(&self) -> &'a [u8] {
+		&self.data[28..28 + usize::from(self.data[2]).min(16)]
+	}
+
+	/// The options of the message, as their code and data, without padding.
+	pub fn options(&self) -> impl Iterator<Item = (DhcpOption, &'a [u8])> + 'a {
+		let mut data = &self.data[OPTIONS_OFFSET..];
+		core::iter::from_fn(move || loop {
+			let (&code, rest) = data.split_first()?;
+			match code {
+				0 => data = rest,
+				255 => return None,
+				_ => {
+					let (&len, rest) = rest.split_first()?;
+					let value = rest.get(..usize::from(len))?;
+					data = &rest[usize::from(len)..];
+					return Some((DhcpOption(code), value));
+				}
+			}
+		})
+	}
+
+	/// The data of the first option with the given code.
+	pub fn option(&self, code: DhcpOption) -> Option<&'a [u8]> {
+		self.options()
+			.find(|(option, _)| *option == code)
+			.map(|(_, value)| value)
+	}
+
+	/// The message type, from the message type option
+	pub fn message_type(&self) -> Option<DhcpMessageType> {
+		match self.option(DhcpOption::MESSAGE_TYPE)? {
+			[value] => DhcpMessageType::from_u8(*value),
+			_ => None,
+		}
+	}
+
+	/// The addresses in an option holding a list of IPv4 addresses, like
+	/// [`DhcpOption::DNS_SERVERS`]; the iterator is empty if the option is absent.
+	pub fn option_addrs(&self, code: DhcpOption) -> impl Iterator<Item = Ipv4Addr> + 'a {
+		self.option(code)
+			.unwrap_or_default()
+			.chunks_exact(4)
+			.map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+	}
+
+	/// The value of an option holding a single IPv4 address, like
+	/// [`DhcpOption::SERVER_IDENTIFIER`].
+	pub fn option_addr(&self, code: DhcpOption) -> Option<Ipv4Addr> {
+		let octets: [u8; 4] = self.option(code)?.try_into().ok()?;
+		Some(Ipv4Addr::from(octets))
+	}
+
+	/// The value of an option holding a 32-bit number, like [`DhcpOption::LEASE_TIME`].
+	pub fn option_u32(&self, code: DhcpOption) -> Option<u32> {
+		Some(u32::from_be_bytes(self.option(code)?.try_into().ok()?))
+	}
+
+	fn addr(&self, offset: usize) -> Ipv4Addr {
+		let octets: [u8; 4] = self.data[offset..offset + 4].try_into().unwrap_or_default();
+		Ipv4Addr::from(octets)
+	}
+}
+
+/// Writes a DHCP message into a buffer.
+///
+/// The fixed fields are set through the `set_` methods, and options are appended in order;
+/// [`.finish()`](DhcpMessageWriter::finish) terminates the options and pads the message.
+///
+/// ```
+/// use embedded_nal::{DhcpMessage, DhcpMessageType, DhcpMessageWriter, DhcpOption};
+/// use core::net::Ipv4Addr;
+///
+/// let mac = [0x02, 0, 0, 0, 0, 1];
+/// let mut buffer = [0; 576];
+/// let mut writer = DhcpMessageWriter::new(&mut buffer, DhcpMessageType::Offer, 0x1234, &mac)?;
+/// writer.set_your_address(Ipv4Addr::new(192, 168, 4, 2));
+/// writer.option(DhcpOption::SERVER_IDENTIFIER, &[192, 168, 4, 1])?;
+/// writer.option(DhcpOption::LEASE_TIME, &3600u32.to_be_bytes())?;
+/// let len = writer.finish();
+///
+/// let offer = DhcpMessage::parse(&buffer[..len])?;
+/// assert!(offer.is_reply());
+/// assert_eq!(offer.message_type(), Some(DhcpMessageType::Offer));
+/// assert_eq!(offer.hardware_address(), &mac);
+/// assert_eq!(offer.your_address(), Ipv4Addr::new(192, 168, 4, 2));
+/// assert_eq!(offer.option_u32(DhcpOption::LEASE_TIME), Some(3600));
+/// # Ok::<(), embedded_nal::DhcpMessageError>(())
+/// ```
+pub struct DhcpMessageWriter<'a> {
+	buffer: &'a mut [u8],
+	len: usize,
+}
+
+impl<'a> DhcpMessageWriter<'a> {
+	/// Start a message of the given type and transaction ID into `buffer`, concerning the
+	/// client with the Ethernet (or other) hardware address `hardware_address`.
+	///
+	/// The message is a reply if the type is one sent by servers.
+	pub fn new(
+		buffer: &'a mut [u8],
+		message_type: DhcpMessageType,
+		xid: u32,
+		hardware_address: &[u8],
+	) -> Result<Self, DhcpMessageError> {
+		if hardware_address.len() > 16 {
+			return Err(DhcpMessageError::Malformed);
+		}
+		let header = buffer
+			.get_mut(..OPTIONS_OFFSET)
+			.ok_or(DhcpMessageError::BufferTooSmall)?;
+		header.fill(0);
+		header[0] = match message_type.is_reply() {
+			true => 2,
+			false => 1,
+		};
+		header[1] = HTYPE_ETHERNET;
+		header[2] = hardware_address.len() as u8;
+		header[4..8].copy_from_slice(&xid.to_be_bytes());
+		header[28..28 + hardware_address.len()].copy_from_slice(hardware_address);
+		header[236..].copy_from_slice(&MAGIC_COOKIE);
+		let mut writer = DhcpMessageWriter {
+			buffer,
+			len: OPTIONS_OFFSET,
+		};
+		writer.option(DhcpOption::MESSAGE_TYPE, &[message_type as u8])?;
+		Ok(writer)
+	}
+
+	/// Set the seconds since the client started acquiring or renewing a lease.
+	pub fn set_secs(&mut self, secs: u16) {
+		self.buffer[8..10].copy_from_slice(&secs.to_be_bytes());
+	}
+
+	/// Set whether replies are to be broadcast.
+	pub fn set_broadcast(&mut self, broadcast: bool) {
+		let flags = match broadcast {
+			true => BROADCAST,
+			false => 0,
+		};
+		self.buffer[10..12].copy_from_slice(&flags.to_be_bytes());
+	}
+
+	/// Set the address of a client that has one (`ciaddr`).
+	pub fn set_client_address(&mut self, addr: Ipv4Addr) {
+		self.buffer[12..16].copy_from_slice(&addr.octets());
+	}
+
+	/// Set the address offered or assigned to the client (`yiaddr`).
+	pub fn set_your_address(&mut self, addr: Ipv4Addr) {
+		self.buffer[16..20].copy_from_slice(&addr.octets());
+	}
+
+	/// Set the address of the server to use in the next step of booting (`siaddr`).
+	pub fn set_server_address(&mut self, addr: Ipv4Addr) {
+		self.buffer[20..24].copy_from_slice(&addr.octets());
+	}
+
+	/// Set the address of the relay agent the message is passed through (`giaddr`).
+	pub fn set_relay_address(&mut self, addr: Ipv4Addr) {
+		self.buffer[24..28].copy_from_slice(&addr.octets());
+	}
+
+	/// Append an option, which may hold up to 255 bytes.
+	pub fn option(&mut self, code: DhcpOption, data: &[u8]) -> Result<(), DhcpMessageError> {
+		let len = u8::try_from(data.len()).map_err(|_| DhcpMessageError::Malformed)?;
+		// Leave room for the end option.
+		let end = self.len + 2 + data.len();
+		let out = self
+			.buffer
+			.get_mut(self.len..end + 1)
+			.ok_or(DhcpMessageError::BufferTooSmall)?;
+		out[0] = code.0;
+		out[1] = len;
+		out[2..2 + data.len()].copy_from_slice(data);
+		self.len = end;
+		Ok(())
+	}
+
+	/// Complete the message, returning its length.
+	pub fn finish(self) -> usize {
+		self.buffer[self.len] = 255;
+		let len = (self.len + 1).max(MIN_MESSAGE_LEN.min(self.buffer.len()));
+		self.buffer[self.len + 1..len].fill(0);
+		len
+	}
+}
@@ -0,0 +1,497 @@
+use super::{
+	DhcpMessage, DhcpMessageError, DhcpMessageType, DhcpMessageWriter, DhcpOption,
+	DHCP_CLIENT_PORT, DHCP_MESSAGE_LEN,
+};
+use crate::{nb, Clock, UdpFullStack};
+use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use core::time::Duration;
+
+/// Time an offered address is held for the client it was offered to
+const OFFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The configuration handed out by a DHCP server.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DhcpServerConfig<'a> {
+	/// The address of the server on the served network, which identifies it to clients
+	pub server: Ipv4Addr,
+	/// The first address of the pool; the pool holds as many consecutive addresses as the lease
+	/// table has entries
+	pub pool_start: Ipv4Addr,
+	/// The subnet mask of the served network
+	pub netmask: Ipv4Addr,
+	/// The default gateway, usually the server itself, or `None` for a network without one
+	pub router: Option<Ipv4Addr>,
+	/// The DNS servers, usually the server itself running a [`DnsServer`](crate::DnsServer)
+	pub dns_servers: &'a [Ipv4Addr],
+	/// The time leases are granted for
+	pub lease_time: Duration,
+}
+
+/// A lease granted by a DHCP server, see [`DhcpLeases::iter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DhcpServerLease {
+	/// The Ethernet address of the client
+	pub hardware_address: [u8; 6],
+	/// The address leased to the client
+	pub address: Ipv4Addr,
+	/// When the lease expires, in the time of the server's clock
+	pub expires: Duration,
+}
+
+/// How an address of the pool is used
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Use {
+	/// Offered to a client, which has not requested it yet
+	Offered,
+	/// Leased to a client
+	Leased,
+	/// Declined by a client, as another host uses it
+	Declined,
+}
+
+/// An entry of the lease table
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+	hardware_address: [u8; 6],
+	expires: Duration,
+	usage: Use,
+}
+
+/// The lease table of a DHCP server with a pool of `N` addresses, and the logic of answering
+/// client messages from it, see [RFC 2131, section 4.3].
+///
+/// Clients are identified by their Ethernet address, and keep their address across requests
+/// as long as their lease did not expire. Relay agents are not supported.
+///
+/// This type only turns client messages into replies; [`DhcpServer`] serves them on a port of
+/// a [`UdpFullStack`].
+///
+/// ```
+/// use embedded_nal::{
+///     DhcpLeases, DhcpMessage, DhcpMessageType, DhcpMessageWriter, DhcpOption, DhcpServerConfig,
+/// };
+/// use core::net::Ipv4Addr;
+/// use core::time::Duration;
+///
+/// let config = DhcpServerConfig {
+///     server: Ipv4Addr::new(192, 168, 4, 1),
+///     pool_start: Ipv4Addr::new(192, 168, 4, 100),
+///     netmask: Ipv4Addr::new(255, 255, 255, 0),
+///     router: None,
+///     dns_servers: &[],
+///     lease_time: Duration::from_secs(3600),
+/// };
+/// let mut leases = DhcpLeases::<2>::new();
+///
+/// let mut discover = [0; 576];
+/// let mac = [2, 0, 0, 0, 0, 1];
+/// let writer = DhcpMessageWriter::new(&mut discover, DhcpMessageType::Discover, 7, &mac)?;
+/// let len = writer.finish();
+///
+/// let mut offer = [0; 576];
+/// let now = Duration::from_secs(10);
+/// let (len, destination) = leases.respond(&config, &discover[..len], now, &mut offer).unwrap();
+/// assert_eq!(destination, Ipv4Addr::BROADCAST);
+/// let offer = DhcpMessage::parse(&offer[..len])?;
+/// assert_eq!(offer.message_type(), Some(DhcpMessageType::Offer));
+/// assert_eq!(offer.your_address(), Ipv4Addr::new(192, 168, 4, 100));
+/// # fn exchange<const N: usize>(
+/// #     leases: &mut DhcpLeases<N>,
+/// #     config: &DhcpServerConfig<'_>,
+/// #     message_type: DhcpMessageType,
+/// #     mac: u8,
+/// #     client: Ipv4Addr,
+/// #     requested: Option<Ipv4Addr>,
+/// #     now: u64,
+/// # ) -> Option<(DhcpMessageType, Ipv4Addr, Ipv4Addr)> {
+/// #     let mut request = [0; 576];
+/// #     let mac = [2, 0, 0, 0, 0, mac];
+/// #     let mut writer = DhcpMessageWriter::new(&mut request, message_type, 7, &mac).unwrap();
+/// #     writer.set_client_address(client);
+/// #     if let Some(requested) = requested {
+/// #         writer.option(DhcpOption::REQUESTED_ADDRESS, &requested.octets()).unwrap();
+/// #     }
+/// #     if message_type != DhcpMessageType::Discover {
+/// #         writer.option(DhcpOption::SERVER_IDENTIFIER, &config.server.octets()).unwrap();
+/// #     }
+/// #     let len = writer.finish();
+/// #     let mut reply = [0; 576];
+/// #     let now = Duration::from_secs(now);
+/// #     let (len, destination) = leases.respond(config, &request[..len], now, &mut reply)?;
+/// #     let reply = DhcpMessage::parse(&reply[..len]).unwrap();
+/// #     Some((reply.message_type()?, reply.your_address(), destination))
+/// # }
+/// # use DhcpMessageType::*;
+/// # let unspecified = Ipv4Addr::UNSPECIFIED;
+/// let first = Ipv4Addr::new(192, 168, 4, 100);
+/// let second = Ipv4Addr::new(192, 168, 4, 101);
+///
+/// // The client requests the offered address, and is granted a lease for it.
+/// let ack = exchange(&mut leases, &config, Request, 1, unspecified, Some(first), 10);
+/// assert_eq!(ack, Some((Ack, first, Ipv4Addr::BROADCAST)));
+/// let lease = leases.iter(&config, now).next().unwrap();
+/// assert_eq!((lease.address, lease.expires), (first, Duration::from_secs(3610)));
+///
+/// // Addresses outside of the pool are refused.
+/// let outside = Ipv4Addr::new(192, 168, 4, 200);
+/// let nak = exchange(&mut leases, &config, Request, 2, unspecified, Some(outside), 10);
+/// assert_eq!(nak, Some((Nak, unspecified, Ipv4Addr::BROADCAST)));
+///
+/// // A declined address is not offered again until a lease time passed.
+/// let offer = exchange(&mut leases, &config, Discover, 2, unspecified, None, 10);
+/// assert_eq!(offer, Some((Offer, second, Ipv4Addr::BROADCAST)));
+/// assert_eq!(exchange(&mut leases, &config, Decline, 2, unspecified, Some(second), 10), None);
+///
+/// // With both addresses of this two-address pool in use, clients get no offer.
+/// assert_eq!(exchange(&mut leases, &config, Discover, 3, unspecified, None, 20), None);
+///
+/// // Once released, the address is offered to the next client.
+/// assert_eq!(exchange(&mut leases, &config, Release, 1, first, None, 20), None);
+/// assert_eq!(leases.iter(&config, Duration::from_secs(20)).count(), 0);
+/// let offer = exchange(&mut leases, &config, Discover, 3, unspecified, None, 20);
+/// assert_eq!(offer, Some((Offer, first, Ipv4Addr::BROADCAST)));
+/// let ack = exchange(&mut leases, &config, Request, 3, unspecified, Some(first), 20);
+/// assert_eq!(ack, Some((Ack, first, Ipv4Addr::BROADCAST)));
+///
+/// // Leases expire by the clock, and so does the declined address.
+/// assert_eq!(leases.iter(&config, Duration::from_secs(3620)).count(), 0);
+/// let offer = exchange(&mut leases, &config, Discover, 4, unspecified, None, 3620);
+/// assert_eq!(offer, Some((Offer, first, Ipv4Addr::BROADCAST)));
+/// let offer = exchange(&mut leases, &config, Discover, 5, unspecified, None, 3620);
+/// assert_eq!(offer, Some((Offer, second, Ipv4Addr::BROADCAST)));
+///
+/// // Lease times too long for the clock make leases that never expire.
+/// let forever = DhcpServerConfig { lease_time: Duration::MAX, ..config };
+/// let mut leases = DhcpLeases::<2>::new();
+/// let ack = exchange(&mut leases, &forever, Request, 1, unspecified, Some(first), 10);
+/// assert_eq!(ack, Some((Ack, first, Ipv4Addr::BROADCAST)));
+/// assert_eq!(leases.iter(&forever, Duration::from_secs(u64::MAX)).count(), 1);
+/// # Ok::<(), embedded_nal::DhcpMessageError>(())
+/// ```
+///
+/// [RFC 2131, section 4.3]: https://tools.ietf.org/html/rfc2131#section-4.3
+#[derive(Clone, Debug)]
+pub struct DhcpLeases<const N: usize> {
+	entries: [Option<Entry>; N],
+}
+
+impl<const N: usize> Default for DhcpLeases<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> DhcpLeases<N> {
+	/// Create an empty lease table.
+	pub const fn new() -> Self {
+		DhcpLeases { entries: [None; N] }
+	}
+
+	/// The leases that were granted and did not expire by `now`.
+	pub fn iter<'a>(
+		&'a self,
+		config: &DhcpServerConfig<'_>,
+		now: Duration,
+	) -> impl Iterator<Item = DhcpServerLease> + 'a {
+		let pool_start = config.pool_start;
+		self.entries
+			.iter()
+			.enumerate()
+			.filter_map(move |(i, entry)| match entry {
+				Some(entry) if entry.usage == Use::Leased && entry.expires > now => {
+					Some(DhcpServerLease {
+						hardware_address: entry.hardware_address,
+						address: pool_address(pool_start, i),
+						expires: entry.expires,
+					})
+				}
+				_ => None,
+			})
+	}
+
+	/// Handle a message of a client received at `now`, writing the reply (if any) into
+	/// `response`.
+	///
+	/// Returns the length of the reply and the address it is to be sent to on
+	/// [`DHCP_CLIENT_PORT`]: the address of a client that has one, and the limited broadcast
+	/// address otherwise.
+	pub fn respond(
+		&mut self,
+		config: &DhcpServerConfig<'_>,
+		request: &[u8],
+		now: Duration,
+		response: &mut [u8],
+	) -> Option<(usize, Ipv4Addr)> {
+		let request = DhcpMessage::parse(request).ok()?;
+		let hardware_address: [u8; 6] = request.hardware_address().try_into().ok()?;
+		if request.is_reply() {
+			return None;
+		}
+		for entry in self.entries.iter_mut() {
+			if entry.is_some_and(|entry| entry.expires <= now) {
+				*entry = None;
+			}
+		}
+		let own = self.entries.iter().position(|entry| {
+			entry.is_some_and(|entry| {
+				entry.hardware_address == hardware_address && entry.usage != Use::Declined
+			})
+		});
+		let server_id = request.option_addr(DhcpOption::SERVER_IDENTIFIER);
+		let requested = request.option_addr(DhcpOption::REQUESTED_ADDRESS);
+
+		let (message_type, slot) = match request.message_type()? {
+			DhcpMessageType::Discover => {
+				let slot = own
+					.or_else(|| {
+						let slot = self.slot_of(config, requested?)?;
+						self.entries[slot].is_none().then_some(slot)
+					})
+					.or_else(|| self.entries.iter().position(Option::is_none))?;
+				if matches!(
+					self.entries[slot],
+					None | Some(Entry {
+						usage: Use::Offered,
+						..
+					})
+				) {
+					self.entries[slot] = Some(Entry {
+						hardware_address,
+						expires: now.saturating_add(OFFER_TIMEOUT),
+						usage: Use::Offered,
+					});
+				}
+				(DhcpMessageType::Offer, Some(slot))
+			}
+			DhcpMessageType::Request => {
+				// Selecting clients name the server they chose, others ask to keep the
+				// address they have.
+				if server_id.is_some_and(|server| server != config.server) {
+					if let Some(own) = own.filter(|own| self.is_offered(*own)) {
+						self.entries[own] = None;
+					}
+					return None;
+				}
+				let address = match requested {
+					Some(requested) => requested,
+					None if !request.client_address().is_unspecified() => request.client_address(),
+					None => return None,
+				};
+				match self.slot_of(config, address) {
+					Some(slot)
+						if own == Some(slot) || own.is_none() && self.entries[slot].is_none() =>
+					{
+						self.entries[slot] = Some(Entry {
+							hardware_address,
+							expires: now.saturating_add(config.lease_time),
+							usage: Use::Leased,
+						});
+						(DhcpMessageType::Ack, Some(slot))
+					}
+					_ => (DhcpMessageType::Nak, None),
+				}
+			}
+			DhcpMessageType::Decline => {
+				let slot = self
+					.slot_of(config, requested?)
+					.filter(|slot| own == Some(*slot))?;
+				self.entries[slot] = Some(Entry {
+					hardware_address: [0; 6],
+					expires: now.saturating_add(config.lease_time),
+					usage: Use::Declined,
+				});
+				return None;
+			}
+			DhcpMessageType::Release => {
+				let slot = self.slot_of(config, request.client_address());
+				if let Some(own) = own.filter(|own| slot == Some(*own)) {
+					if server_id == Some(config.server) {
+						self.entries[own] = None;
+					}
+				}
+				return None;
+			}
+			DhcpMessageType::Inform => (DhcpMessageType::Ack, None),
+			_ => return None,
+		};
+
+		let len = write_reply(config, &request, message_type, slot, response).ok()?;
+		let destination = match request.client_address() {
+			address if address.is_unspecified() || message_type == DhcpMessageType::Nak => {
+				Ipv4Addr::BROADCAST
+			}
+			address => address,
+		};
+		Some((len, destination))
+	}
+
+	/// The slot of the table that `address` is leased from.
+	fn slot_of(&self, config: &DhcpServerConfig<'_>, address: Ipv4Addr) -> Option<usize> {
+		let offset = u32::from(address).checked_sub(u32::from(config.pool_start))?;
+		usize::try_from(offset).ok().filter(|slot| *slot < N)
+	}
+
+	fn is_offered(&self, slot: usize) -> bool {
+		self.entries[slot].is_some_and(|entry| entry.usage == Use::Offered)
+	}
+}
+
+/// The address of a slot of the lease table
+fn pool_address(pool_start: Ipv4Addr, slot: usize) -> Ipv4Addr {
+	Ipv4Addr::from(u32::from(pool_start).wrapping_add(slot as u32))
+}
+
+/// Write a reply of `message_type` to `request`, leasing the address of `slot` if any.
+fn write_reply(
+	config: &DhcpServerConfig<'_>,
+	request: &DhcpMessage<'_>,
+	message_type: DhcpMessageType,
+	slot: Option<usize>,
+	response: &mut [u8],
+) -> Result<usize, DhcpMessageError> {
+	let mut writer = DhcpMessageWriter::new(
+		response,
+		message_type,
+		request.xid(),
+		request.hardware_address(),
+	)?;
+	writer.set_broadcast(request.is_broadcast());
+	writer.option(DhcpOption::SERVER_IDENTIFIER, &config.server.octets())?;
+	if message_type == DhcpMessageType::Nak {
+		return Ok(writer.finish());
+	}
+
+	writer.set_client_address(request.client_address());
+	if let Some(slot) = slot {
+		writer.set_your_address(pool_address(config.pool_start, slot));
+		let lease_time = u32::try_from(config.lease_time.as_secs()).unwrap_or(u32::MAX);
+		writer.option(DhcpOption::LEASE_TIME, &lease_time.to_be_bytes())?;
+	}
+	writer.option(DhcpOption::SUBNET_MASK, &config.netmask.octets())?;
+	if let Some(router) = config.router {
+		writer.option(DhcpOption::ROUTER, &router.octets())?;
+	}
+	if !config.dns_servers.is_empty() {
+		let mut servers = [0; 255];
+		let mut len = 0;
+		for server in config.dns_servers.iter().take(servers.len() / 4) {
+			servers[len..len + 4].copy_from_slice(&server.octets());
+			len += 4;
+		}
+		writer.option(DhcpOption::DNS_SERVERS, &servers[..len])?;
+	}
+	Ok(writer.finish())
+}
+
+/// A DHCPv4 server (see [RFC 2131]) handing out the `N` addresses of a fixed pool on a port of
+/// any [`UdpFullStack`], for example to the clients of an access point.
+///
+/// Leases are tracked in a [`DhcpLeases`] table, and expire by the time of `clock`. The stack
+/// has to be able to receive datagrams sent to the limited broadcast address, and to send
+/// them to it.
+///
+/// The server does not block on incoming messages; it is driven by calling
+/// [`.poll()`](DhcpServer::poll) repeatedly.
+///
+/// ```
+/// use embedded_nal::{Clock, DhcpServer, DhcpServerConfig, UdpFullStack, DHCP_SERVER_PORT};
+/// use core::net::Ipv4Addr;
+/// use core::time::Duration;
+///
+/// fn serve_access_point<S: UdpFullStack, C: Clock>(stack: S, clock: C) -> Result<(), S::Error> {
+///     let address = Ipv4Addr::new(192, 168, 4, 1);
+///     let config = DhcpServerConfig {
+///         server: address,
+///         pool_start: Ipv4Addr::new(192, 168, 4, 100),
+///         netmask: Ipv4Addr::new(255, 255, 255, 0),
+///         router: Some(address),
+///         dns_servers: &[address],
+///         lease_time: Duration::from_secs(2 * 3600),
+///     };
+///
+///     let mut server: DhcpServer<_, _> = DhcpServer::new(stack, clock, DHCP_SERVER_PORT)?;
+///     loop {
+///         server.poll(&config)?;
+///         // ... serve the provisioning web pages ...
+///     }
+/// }
+/// ```
+///
+/// [RFC 2131]: https://tools.ietf.org/html/rfc2131
+pub struct DhcpServer<S: UdpFullStack, C, const N: usize = 8> {
+	stack: S,
+	clock: C,
+	socket: S::UdpSocket,
+	client_port: u16,
+	leases: DhcpLeases<N>,
+}
+
+impl<S: UdpFullStack, C: Clock, const N: usize> DhcpServer<S, C, N> {
+	/// Create a server listening on `port` of `stack`, usually [`DHCP_SERVER_PORT`], timing
+	/// leases with `clock`.
+	///
+	/// [`DHCP_SERVER_PORT`]: crate::DHCP_SERVER_PORT
+	pub fn new(mut stack: S, clock: C, port: u16) -> Result<Self, S::Error> {
+		let mut socket = stack.socket()?;
+		if let Err(e) = stack.bind(&mut socket, port) {
+			stack.close(socket).ok();
+			return Err(e);
+		}
+		Ok(DhcpServer {
+			stack,
+			clock,
+			socket,
+			client_port: DHCP_CLIENT_PORT,
+			leases: DhcpLeases::new(),
+		})
+	}
+
+	/// Set the port replies are sent to; the default is [`DHCP_CLIENT_PORT`], and other ports
+	/// are only useful for testing.
+	pub fn set_client_port(&mut self, client_port: u16) {
+		self.client_port = client_port;
+	}
+
+	/// The leases that are currently granted, given the `config` the server is polled with.
+	pub fn leases<'a>(
+		&'a mut self,
+		config: &DhcpServerConfig<'_>,
+	) -> impl Iterator<Item = DhcpServerLease> + 'a {
+		let now = self.clock.now();
+		self.leases.iter(config, now)
+	}
+
+	/// Answer the client messages received since the last call, handing out leases with
+	/// `config`.
+	///
+	/// Sending a reply blocks (by spinning on [`nb::Error::WouldBlock`]) until the stack
+	/// accepted it.
+	pub fn poll(&mut self, config: &DhcpServerConfig<'_>) -> Result<(), S::Error> {
+		let mut request = [0; DHCP_MESSAGE_LEN];
+		let mut response = [0; DHCP_MESSAGE_LEN];
+		loop {
+			let len = match self.stack.receive(&mut self.socket, &mut request) {
+				Ok((len, _)) => len.min(DHCP_MESSAGE_LEN),
+				Err(nb::Error::WouldBlock) => return Ok(()),
+				Err(nb::Error::Other(e)) => return Err(e),
+			};
+			let now = self.clock.now();
+			let Some((len, destination)) =
+				self.leases
+					.respond(config, &request[..len], now, &mut response)
+			else {
+				continue;
+			};
+			let destination = SocketAddr::new(IpAddr::V4(destination), self.client_port);
+			nb::block!(self
+				.stack
+				.send_to(&mut self.socket, destination, &response[..len]))?;
+		}
+	}
+
+	/// Release the stack and clock, closing the server's socket.
+	pub fn free(mut self) -> (S, C) {
+		self.stack.close(self.socket).ok();
+		(self.stack, self.clock)
+	}
+}
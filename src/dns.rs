@@ -19,6 +19,7 @@ pub enum AddrType {
 /// embedded alternative to [`ToSocketAddrs`], and is as such meant to resolve
 /// an ip address from a hostname, or a hostname from an ip address. This means
 /// that it only deals in host address records `A` (IPv4) and `AAAA` (IPv6).
+/// Resolvers supporting other record types implement [`DnsRecords`] as well.
 ///
 /// [`TcpStack`]: crate::trait@TcpStack
 /// [`UdpStack`]: crate::trait@UdpStack
@@ -73,3 +74,55 @@ impl<T: Dns> Dns for &mut T {
 		T::get_host_by_address(self, addr, result)
 	}
 }
+
+/// This trait is an extension trait for [`Dns`] for resolving the records used to locate and
+/// configure services: `SRV` records (see [`rfc2782`]), `TXT` records and `CNAME` records.
+///
+/// Like [`Dns::get_host_by_address`], all methods store their results at the beginning of
+/// `result` and return the length; if the buffer is too small to hold them, an error should be
+/// returned. Resolving a name without records of the type is not an error, and returns a length
+/// of 0.
+///
+/// [`rfc2782`]: https://tools.ietf.org/html/rfc2782
+pub trait DnsRecords: Dns {
+	/// Resolve the `SRV` records of a service, given its name like
+	/// `_xmpp-client._tcp.example.com`.
+	///
+	/// The records are stored in the format read by [`SrvRecords`](crate::SrvRecords), through
+	/// [`SrvRecords::push`](crate::SrvRecords::push).
+	fn get_srv_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error>;
+
+	/// Resolve the `TXT` records of a name.
+	///
+	/// The records are stored in the format read by [`TxtRecords`](crate::TxtRecords), through
+	/// [`TxtRecords::push`](crate::TxtRecords::push).
+	fn get_txt_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error>;
+
+	/// Resolve the canonical name of an alias.
+	///
+	/// The canonical name is stored in dotted form; a length of 0 means that `name` is not an
+	/// alias.
+	fn get_canonical_name(
+		&mut self,
+		name: &str,
+		result: &mut [u8],
+	) -> nb::Result<usize, Self::Error>;
+}
+
+impl<T: DnsRecords> DnsRecords for &mut T {
+	fn get_srv_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error> {
+		T::get_srv_records(self, name, result)
+	}
+
+	fn get_txt_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error> {
+		T::get_txt_records(self, name, result)
+	}
+
+	fn get_canonical_name(
+		&mut self,
+		name: &str,
+		result: &mut [u8],
+	) -> nb::Result<usize, Self::Error> {
+		T::get_canonical_name(self, name, result)
+	}
+}
@@ -0,0 +1,722 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Length of the fixed header of a DNS message
+const HEADER_LEN: usize = 12;
+
+/// Longest encoded domain name, see [RFC 1035, section 2.3.4]
+///
+/// [RFC 1035, section 2.3.4]: https://tools.ietf.org/html/rfc1035#section-2.3.4
+const MAX_NAME_LEN: usize = 255;
+
+/// Longest name used for reverse lookups, an IPv6 address in `ip6.arpa`
+pub(crate) const REVERSE_NAME_LEN: usize = 72;
+
+/// Errors in reading or writing DNS messages.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DnsMessageError {
+	/// The message is truncated or otherwise not a valid DNS message.
+	Malformed,
+	/// The message does not fit into the buffer.
+	BufferTooSmall,
+	/// A domain name has empty labels, or labels or a total length that are too long.
+	InvalidName,
+}
+
+/// The type of a resource record or question, as found in DNS messages.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DnsRecordType(pub u16);
+
+impl DnsRecordType {
+	/// An IPv4 host address
+	pub const A: Self = DnsRecordType(1);
+	/// An authoritative name server
+	pub const NS: Self = DnsRecordType(2);
+	/// The canonical name for an alias
+	pub const CNAME: Self = DnsRecordType(5);
+	/// A domain name pointer
+	pub const PTR: Self = DnsRecordType(12);
+	/// Text strings
+	pub const TXT: Self = DnsRecordType(16);
+	/// An IPv6 host address
+	pub const AAAA: Self = DnsRecordType(28);
+	/// The location of a service
+	pub const SRV: Self = DnsRecordType(33);
+	/// Any type, in questions only
+	pub const ANY: Self = DnsRecordType(255);
+}
+
+/// The section of a DNS message a resource record is in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DnsSection {
+	/// Records answering the questions
+	Answer,
+	/// Records pointing to authoritative name servers
+	Authority,
+	/// Records related to the answers
+	Additional,
+}
+
+/// Follow a domain name at `offset`, calling `label` for each label.
+///
+/// Returns the offset right after the name where it starts (i.e. not following compression
+/// pointers).
+fn walk_name(
+	message: &[u8],
+	mut offset: usize,
+	mut label: impl FnMut(&[u8]),
+) -> Result<usize, DnsMessageError> {
+	let mut end = None;
+	let mut len = 0;
+	loop {
+		let byte = *message.get(offset).ok_or(DnsMessageError::Malformed)?;
+		match byte & 0xc0 {
+			0x00 if byte == 0 => return Ok(end.unwrap_or(offset + 1)),
+			0x00 => {
+				let data = message
+					.get(offset + 1..offset + 1 + usize::from(byte))
+					.ok_or(DnsMessageError::Malformed)?;
+				len += data.len() + 1;
+				if len >= MAX_NAME_LEN {
+					return Err(DnsMessageError::Malformed);
+				}
+				label(data);
+				offset += 1 + data.len();
+			}
+			0xc0 => {
+				let low = *message.get(offset + 1).ok_or(DnsMessageError::Malformed)?;
+				let target = usize::from(u16::from_be_bytes([byte & 0x3f, low]));
+				// Only pointing backwards guarantees termination.
+				if target >= offset {
+					return Err(DnsMessageError::Malformed);
+				}
+				end.get_or_insert(offset + 2);
+				offset = target;
+			}
+			_ => return Err(DnsMessageError::Malformed),
+		}
+	}
+}
+
+/// A domain name in a DNS message, possibly compressed.
+#[derive(Copy, Clone, Debug)]
+pub struct DnsName<'a> {
+	message: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> DnsName<'a> {
+	/// Call `f` for each label of the name, from the leftmost one.
+	pub fn for_each_label(&self, f: impl FnMut(&[u8])) {
+		// Names are validated when the message is parsed.
+		walk_name(self.message, self.offset, f).ok();
+	}
+
+	/// Split off the leftmost label, returning it along with the rest of the name.
+	///
+	/// Returns `None` for the root name, which has no labels.
+	pub fn split_first(&self) -> Option<(&'a [u8], DnsName<'a>)> {
+		let mut offset = self.offset;
+		loop {
+			let byte = *self.message.get(offset)?;
+			match byte {
+				0 => return None,
+				1..=0x3f => {
+					let label = self
+						.message
+						.get(offset + 1..offset + 1 + usize::from(byte))?;
+					let rest = DnsName {
+						message: self.message,
+						offset: offset + 1 + label.len(),
+					};
+					return Some((label, rest));
+				}
+				// Names are validated when the message is parsed, so pointers lead backwards.
+				_ => {
+					let low = *self.message.get(offset + 1)?;
+					offset = usize::from(u16::from_be_bytes([byte & 0x3f, low]));
+				}
+			}
+		}
+	}
+
+	/// Whether this is the name given in dotted form, comparing ASCII letters case-insensitively.
+	///
+	/// Dots and backslashes inside labels are escaped by a backslash in `name`, and a trailing
+	/// dot is ignored.
+	pub fn eq_str(&self, name: &str) -> bool {
+		let mut rest = Some(name).filter(|name| !name.is_empty() && *name != ".");
+		let mut equal = true;
+		self.for_each_label(|label| match rest {
+			Some(name) => {
+				let (head, tail) = split_label(name);
+				equal &= unescape(head)
+					.map(|byte| byte.to_ascii_lowercase())
+					.eq(label.iter().map(|byte| byte.to_ascii_lowercase()));
+				rest = tail.filter(|tail| !tail.is_empty());
+			}
+			None => equal = false,
+		});
+		equal && rest.is_none()
+	}
+
+	/// Whether the name ends in the labels of `suffix`, given in dotted form as for
+	/// [`.eq_str()`](DnsName::eq_str).
+	pub fn ends_with(&self, suffix: &str) -> bool {
+		let mut labels = 0;
+		self.for_each_label(|_| labels += 1);
+		let mut suffix_labels = 0;
+		let mut rest = Some(suffix).filter(|suffix| !suffix.is_empty() && *suffix != ".");
+		while let Some(name) = rest {
+			suffix_labels += 1;
+			rest = split_label(name).1.filter(|tail| !tail.is_empty());
+		}
+		let mut name = *self;
+		for _ in suffix_labels..labels {
+			match name.split_first() {
+				Some((_, tail)) => name = tail,
+				None => return false,
+			}
+		}
+		labels >= suffix_labels && name.eq_str(suffix)
+	}
+
+	/// Write the name in dotted form, without a trailing dot, to the beginning of `buffer`.
+	///
+	/// Dots and backslashes inside labels are escaped by a backslash. Returns `None` if the name
+	/// does not fit, or has labels that are not UTF-8.
+	pub fn to_dotted<'b>(&self, buffer: &'b mut [u8]) -> Option<&'b str> {
+		let mut len = 0;
+		let mut valid = true;
+		let mut push = |byte: u8| match buffer.get_mut(len) {
+			Some(out) => {
+				*out = byte;
+				len += 1;
+			}
+			None => valid = false,
+		};
+		let mut first = true;
+		self.for_each_label(|label| {
+			if !first {
+				push(b'.');
+			}
+			first = false;
+			for &byte in label {
+				if matches!(byte, b'.' | b'\\') {
+					push(b'\\');
+				}
+				push(byte);
+			}
+		});
+		if !valid {
+			return None;
+		}
+		core::str::from_utf8(&buffer[..len]).ok()
+	}
+}
+
+/// Split the first label off a name in dotted form, returning it still escaped, along with the
+/// rest of the name if there is a dot separating them.
+fn split_label(name: &str) -> (&str, Option<&str>) {
+	let bytes = name.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'.' => return (&name[..i], Some(&name[i + 1..])),
+			_ => i += 1,
+		}
+	}
+	(name, None)
+}
+
+/// The bytes of a label in dotted form, with escaping backslashes removed.
+fn unescape(label: &str) -> impl Iterator<Item = u8> + '_ {
+	let mut bytes = label.bytes();
+	core::iter::from_fn(move || match bytes.next()? {
+		b'\\' => bytes.next(),
+		byte => Some(byte),
+	})
+}
+
+/// A question in a DNS message.
+#[derive(Copy, Clone, Debug)]
+pub struct DnsQuestion<'a> {
+	/// The name asked about
+	pub name: DnsName<'a>,
+	/// The type of records asked for
+	pub record_type: DnsRecordType,
+	/// The class, including the unicast response flag of mDNS in the most significant bit
+	pub class: u16,
+}
+
+/// A resource record in a DNS message.
+#[derive(Copy, Clone, Debug)]
+pub struct DnsRecord<'a> {
+	/// The name the record belongs to
+	pub name: DnsName<'a>,
+	/// The type of the record
+	pub record_type: DnsRecordType,
+	/// The class, including the cache-flush flag of mDNS in the most significant bit
+	pub class: u16,
+	/// How long the record may be cached, in seconds
+	pub ttl: u32,
+	/// The record data
+	pub data: &'a [u8],
+	message: &'a [u8],
+	data_offset: usize,
+}
+
+impl<'a> DnsRecord<'a> {
+	/// The address of an `A` record.
+	pub fn ipv4(&self) -> Option<Ipv4Addr> {
+		let data: [u8; 4] = self.data.try_into().ok()?;
+		(self.record_type == DnsRecordType::A).then_some(Ipv4Addr::from(data))
+	}
+
+	/// The address of an `AAAA` record.
+	pub fn ipv6(&self) -> Option<Ipv6Addr> {
+		let data: [u8; 16] = self.data.try_into().ok()?;
+		(self.record_type == DnsRecordType::AAAA).then_some(Ipv6Addr::from(data))
+	}
+
+	/// The target name of a `PTR`, `CNAME` or `NS` record.
+	pub fn target(&self) -> Option<DnsName<'a>> {
+		match self.record_type {
+			DnsRecordType::PTR | DnsRecordType::CNAME | DnsRecordType::NS => self.name_at(0),
+			_ => None,
+		}
+	}
+
+	/// The priority, weight, port and target name of an `SRV` record.
+	pub fn srv(&self) -> Option<(u16, u16, u16, DnsName<'a>)> {
+		if self.record_type != DnsRecordType::SRV || self.data.len() < 7 {
+			return None;
+		}
+		let field = |i: usize| u16::from_be_bytes([self.data[i], self.data[i + 1]]);
+		Some((field(0), field(2), field(4), self.name_at(6)?))
+	}
+
+	/// The strings of a `TXT` record.
+	///
+	/// Returns `None` if this is not a `TXT` record.
+	pub fn txt(&self) -> Option<impl Iterator<Item = &'a [u8]> + 'a> {
+		if self.record_type != DnsRecordType::TXT {
+			return None;
+		}
+		let mut data = self.data;
+		Some(core::iter::from_fn(move || {
+			let (len, rest) = data.split_first()?;
+			let len = usize::from(*len).min(rest.len());
+			let string = &rest[..len];
+			data = &rest[len..];
+			Some(string)
+		}))
+	}
+
+	fn name_at(&self, offset: usize) -> Option<DnsName<'a>> {
+		let offset = self.data_offset + offset;
+		let end = walk_name(self.message, offset, |_| ()).ok()?;
+		(end <= self.data_offset + self.data.len()).then_some(DnsName {
+			message: self.message,
+			offset,
+		})
+	}
+}
+
+/// A received DNS message, see [RFC 1035, section 4].
+///
+/// [RFC 1035, section 4]: https://tools.ietf.org/html/rfc1035#section-4
+#[derive(Copy, Clone, Debug)]
+pub struct DnsMessage<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> DnsMessage<'a> {
+	/// The flag marking a message as a response
+	pub const RESPONSE: u16 = 0x8000;
+	/// The flag marking a response as authoritative
+	pub const AUTHORITATIVE: u16 = 0x0400;
+	/// The flag marking a message as truncated
+	pub const TRUNCATED: u16 = 0x0200;
+	/// The flag asking for recursive resolution
+	pub const RECURSION_DESIRED: u16 = 0x0100;
+	/// The flag stating that recursive resolution is available
+	pub const RECURSION_AVAILABLE: u16 = 0x0080;
+	/// The response code for a malformed query
+	pub const FORMAT_ERROR: u16 = 1;
+	/// The response code for a failure of the server
+	pub const SERVER_FAILURE: u16 = 2;
+	/// The response code for a name that does not exist
+	pub const NAME_ERROR: u16 = 3;
+	/// The response code for an unsupported kind of query
+	pub const NOT_IMPLEMENTED: u16 = 4;
+	/// The response code for a query refused by policy
+	pub const REFUSED: u16 = 5;
+	/// The Internet class
+	pub const CLASS_IN: u16 = 1;
+
+	/// Check that `data` is a well-formed DNS message.
+	pub fn parse(data: &'a [u8]) -> Result<Self, DnsMessageError> {
+		let message = DnsMessage { data };
+		if data.len() < HEADER_LEN {
+			return Err(DnsMessageError::Malformed);
+		}
+		let mut offset = HEADER_LEN;
+		for _ in 0..message.count(0) {
+			offset = walk_name(data, offset, |_| ())? + 4;
+		}
+		for _ in 0..message.count(1) + message.count(2) + message.count(3) {
+			offset = walk_name(data, offset, |_| ())? + 10;
+			let len = data
+				.get(offset - 2..offset)
+				.ok_or(DnsMessageError::Malformed)?;
+			offset += usize::from(u16::from_be_bytes([len[0], len[1]]));
+		}
+		if offset > data.len() {
+			return Err(DnsMessageError::Malformed);
+		}
+		Ok(message)
+	}
+
+	/// The message ID, copied from a query into its response
+	pub fn id(&self) -> u16 {
+		self.field(0)
+	}
+
+	/// The flags, opcode and response code
+	pub fn flags(&self) -> u16 {
+		self.field(2)
+	}
+
+	/// Whether the message is a response
+	pub fn is_response(&self) -> bool {
+		self.flags() & Self::RESPONSE != 0
+	}
+
+	/// The operation code, 0 for standard queries
+	pub fn opcode(&self) -> u8 {
+		((self.flags() >> 11) & 0xf) as u8
+	}
+
+	/// The response code
+	pub fn response_code(&self) -> u16 {
+		self.flags() & 0xf
+	}
+
+	/// The questions of the message.
+	pub fn questions(&self) -> impl Iterator<Item = DnsQuestion<'a>> + 'a {
+		let data = self.data;
+		let mut offset = HEADER_LEN;
+		(0..self.count(0)).map(move |_| {
+			let name = DnsName {
+				message: data,
+				offset,
+			};
+			// Validated in `parse`.
+			offset = walk_name(data, offset, |_| ()).unwrap_or(data.len() - 4);
+			let field = |i: usize| u16::from_be_bytes([data[offset + i], data[offset + i + 1]]);
+			let question = DnsQuestion {
+				name,
+				record_type: DnsRecordType(field(0)),
+				class: field(2),
+			};
+			offset += 4;
+			question
+		})
+	}
+
+	/// The resource records of the message, along with the section they are in.
+	pub fn records(&self) -> impl Iterator<Item = (DnsSection, DnsRecord<'a>)> + 'a {
+		let data = self.data;
+		let mut offset = HEADER_LEN;
+		for _ in 0..self.count(0) {
+			offset = walk_name(data, offset, |_| ()).unwrap_or(data.len()) + 4;
+		}
+		let answers = self.count(1);
+		let authority = self.count(2);
+		(0..answers + authority + self.count(3)).map(move |i| {
+			let name = DnsName {
+				message: data,
+				offset,
+			};
+			// Validated in `parse`.
+			offset = walk_name(data, offset, |_| ()).unwrap_or(data.len() - 10);
+			let field = |i: usize| u16::from_be_bytes([data[offset + i], data[offset + i + 1]]);
+			let len = usize::from(field(8));
+			let record = DnsRecord {
+				name,
+				record_type: DnsRecordType(field(0)),
+				class: field(2),
+				ttl: (u32::from(field(4)) << 16) | u32::from(field(6)),
+				data: &data[offset + 10..offset + 10 + len],
+				message: data,
+				data_offset: offset + 10,
+			};
+			offset += 10 + len;
+			let section = match i {
+				i if i < answers => DnsSection::Answer,
+				i if i < answers + authority => DnsSection::Authority,
+				_ => DnsSection::Additional,
+			};
+			(section, record)
+		})
+	}
+
+	fn field(&self, offset: usize) -> u16 {
+		u16::from_be_bytes([self.data[offset], self.data[offset + 1]])
+	}
+
+	/// The number of entries in a section, counting the question section as 0
+	fn count(&self, section: usize) -> u16 {
+		self.field(4 + 2 * section)
+	}
+}
+
+/// The data of a resource record to be written into a DNS message.
+#[derive(Copy, Clone, Debug)]
+pub enum DnsRecordData<'a> {
+	/// An IPv4 host address
+	A(Ipv4Addr),
+	/// An IPv6 host address
+	Aaaa(Ipv6Addr),
+	/// A domain name pointer, in dotted form
+	Ptr(&'a str),
+	/// The canonical name for an alias, in dotted form
+	Cname(&'a str),
+	/// The location of a service
+	Srv {
+		/// Priority of the target; lower values are preferred
+		priority: u16,
+		/// Relative weight among targets of equal priority
+		weight: u16,
+		/// The port the service runs on
+		port: u16,
+		/// The host name providing the service, in dotted form
+		target: &'a str,
+	},
+	/// Text strings, each at most 255 bytes long
+	Txt(&'a [&'a [u8]]),
+	/// Data of another type
+	Other(DnsRecordType, &'a [u8]),
+}
+
+impl DnsRecordData<'_> {
+	/// The type of the record
+	pub fn record_type(&self) -> DnsRecordType {
+		match self {
+			DnsRecordData::A(_) => DnsRecordType::A,
+			DnsRecordData::Aaaa(_) => DnsRecordType::AAAA,
+			DnsRecordData::Ptr(_) => DnsRecordType::PTR,
+			DnsRecordData::Cname(_) => DnsRecordType::CNAME,
+			DnsRecordData::Srv { .. } => DnsRecordType::SRV,
+			DnsRecordData::Txt(_) => DnsRecordType::TXT,
+			DnsRecordData::Other(record_type, _) => *record_type,
+		}
+	}
+}
+
+/// Builds a DNS message in a buffer.
+///
+/// Questions and records are appended in the order of the sections they belong to. If an entry
+/// does not fit, an error is returned and the message is left as it was before, so that it can
+/// still be sent with the entries that did fit.
+///
+/// Names are given in dotted form, with dots and backslashes inside labels escaped by a
+/// backslash, and are written without compression.
+pub struct DnsMessageWriter<'a> {
+	buffer: &'a mut [u8],
+	len: usize,
+	section: usize,
+}
+
+impl<'a> DnsMessageWriter<'a> {
+	/// Start a message with the given ID and flags (see [`DnsMessage`] for their values).
+	pub fn new(buffer: &'a mut [u8], id: u16, flags: u16) -> Result<Self, DnsMessageError> {
+		let header = buffer
+			.get_mut(..HEADER_LEN)
+			.ok_or(DnsMessageError::BufferTooSmall)?;
+		header.fill(0);
+		header[..2].copy_from_slice(&id.to_be_bytes());
+		header[2..4].copy_from_slice(&flags.to_be_bytes());
+		Ok(DnsMessageWriter {
+			buffer,
+			len: HEADER_LEN,
+			section: 0,
+		})
+	}
+
+	/// Append a question.
+	///
+	/// # Panics
+	///
+	/// Panics if a record was added before.
+	pub fn question(
+		&mut self,
+		name: &str,
+		record_type: DnsRecordType,
+		class: u16,
+	) -> Result<(), DnsMessageError> {
+		assert!(self.section == 0, "questions have to precede records");
+		self.entry(0, |writer| {
+			writer.name(name)?;
+			writer.bytes(&record_type.0.to_be_bytes())?;
+			writer.bytes(&class.to_be_bytes())
+		})
+	}
+
+	/// Append a resource record to a section.
+	///
+	/// # Panics
+	///
+	/// Panics if a record was added to a later section before.
+	pub fn record(
+		&mut self,
+		section: DnsSection,
+		name: &str,
+		class: u16,
+		ttl: u32,
+		data: DnsRecordData<'_>,
+	) -> Result<(), DnsMessageError> {
+		let section = section as usize + 1;
+		assert!(
+			self.section <= section,
+			"sections have to be written in order"
+		);
+		self.section = section;
+		self.entry(section, |writer| {
+			writer.name(name)?;
+			writer.bytes(&data.record_type().0.to_be_bytes())?;
+			writer.bytes(&class.to_be_bytes())?;
+			writer.bytes(&ttl.to_be_bytes())?;
+			writer.bytes(&[0, 0])?;
+			let start = writer.len;
+			match data {
+				DnsRecordData::A(addr) => writer.bytes(&addr.octets())?,
+				DnsRecordData::Aaaa(addr) => writer.bytes(&addr.octets())?,
+				DnsRecordData::Ptr(name) | DnsRecordData::Cname(name) => writer.name(name)?,
+				DnsRecordData::Srv {
+					priority,
+					weight,
+					port,
+					target,
+				} => {
+					writer.bytes(&priority.to_be_bytes())?;
+					writer.bytes(&weight.to_be_bytes())?;
+					writer.bytes(&port.to_be_bytes())?;
+					writer.name(target)?;
+				}
+				DnsRecordData::Txt(strings) => {
+					for string in strings {
+						let len =
+							u8::try_from(string.len()).map_err(|_| DnsMessageError::InvalidName)?;
+						writer.bytes(&[len])?;
+						writer.bytes(string)?;
+					}
+				}
+				DnsRecordData::Other(_, data) => writer.bytes(data)?,
+			}
+			let len =
+				u16::try_from(writer.len - start).map_err(|_| DnsMessageError::BufferTooSmall)?;
+			writer.buffer[start - 2..start].copy_from_slice(&len.to_be_bytes());
+			Ok(())
+		})
+	}
+
+	/// The length of the message written so far.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Whether nothing but the header was written so far.
+	pub fn is_empty(&self) -> bool {
+		self.len == HEADER_LEN
+	}
+
+	/// Complete the message, returning its length.
+	pub fn finish(self) -> usize {
+		self.len
+	}
+
+	/// Write an entry, counting it in `section` on success, and rolling it back otherwise.
+	fn entry(
+		&mut self,
+		section: usize,
+		write: impl FnOnce(&mut Self) -> Result<(), DnsMessageError>,
+	) -> Result<(), DnsMessageError> {
+		let start = self.len;
+		if let Err(e) = write(self) {
+			self.len = start;
+			return Err(e);
+		}
+		let count = &mut self.buffer[4 + 2 * section..6 + 2 * section];
+		let value = u16::from_be_bytes([count[0], count[1]]) + 1;
+		count.copy_from_slice(&value.to_be_bytes());
+		Ok(())
+	}
+
+	fn bytes(&mut self, data: &[u8]) -> Result<(), DnsMessageError> {
+		self.buffer
+			.get_mut(self.len..self.len + data.len())
+			.ok_or(DnsMessageError::BufferTooSmall)?
+			.copy_from_slice(data);
+		self.len += data.len();
+		Ok(())
+	}
+
+	fn name(&mut self, name: &str) -> Result<(), DnsMessageError> {
+		let start = self.len;
+		let mut rest = Some(name).filter(|name| !name.is_empty() && *name != ".");
+		while let Some(name) = rest {
+			let (label, tail) = split_label(name);
+			let len = unescape(label).count();
+			if len == 0 || len > 63 {
+				return Err(DnsMessageError::InvalidName);
+			}
+			self.bytes(&[len as u8])?;
+			for byte in unescape(label) {
+				self.bytes(&[byte])?;
+			}
+			rest = tail.filter(|tail| !tail.is_empty());
+		}
+		self.bytes(&[0])?;
+		match self.len - start > MAX_NAME_LEN {
+			true => Err(DnsMessageError::InvalidName),
+			false => Ok(()),
+		}
+	}
+}
+
+/// Write the name used for reverse lookups of `addr` (in `in-addr.arpa` or `ip6.arpa`) into
+/// `buffer`.
+pub(crate) fn reverse_name(addr: IpAddr, buffer: &mut [u8; REVERSE_NAME_LEN]) -> &str {
+	let mut len = 0;
+	let mut push = |bytes: &[u8]| {
+		buffer[len..len + bytes.len()].copy_from_slice(bytes);
+		len += bytes.len();
+	};
+	match addr {
+		IpAddr::V4(addr) => {
+			for octet in addr.octets().iter().rev() {
+				let digits = [octet / 100, octet / 10 % 10, octet % 10].map(|digit| b'0' + digit);
+				let skip = match octet {
+					0..=9 => 2,
+					10..=99 => 1,
+					_ => 0,
+				};
+				push(&digits[skip..]);
+				push(b".");
+			}
+			push(b"in-addr.arpa");
+		}
+		IpAddr::V6(addr) => {
+			for octet in addr.octets().iter().rev() {
+				for nibble in [octet & 0xf, octet >> 4] {
+					push(&[b"0123456789abcdef"[usize::from(nibble)], b'.']);
+				}
+			}
+			push(b"ip6.arpa");
+		}
+	}
+	core::str::from_utf8(&buffer[..len]).unwrap_or_default()
+}
@@ -0,0 +1,287 @@
+use crate::{nb, AddrType, DnsRecords, TcpClientStack};
+use core::net::SocketAddr;
+
+/// Most SRV records considered when ordering them by preference
+const MAX_SRV_RECORDS: usize = 16;
+
+/// Size of the buffer SRV records are resolved into by [`connect_srv`]
+const SRV_BUFFER_LEN: usize = 512;
+
+/// A service location, as found in an SRV record, see [RFC 2782].
+///
+/// [RFC 2782]: https://tools.ietf.org/html/rfc2782
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SrvRecord<'a> {
+	/// Priority of the target; lower values are preferred
+	pub priority: u16,
+	/// Relative weight among targets of equal priority
+	pub weight: u16,
+	/// The port the service is offered on
+	pub port: u16,
+	/// The host name providing the service, in dotted form; `.` if the service is not offered
+	pub target: &'a str,
+}
+
+/// The SRV records resolved through [`DnsRecords::get_srv_records`].
+///
+/// The records are stored one after the other, each as its priority, weight and port in network
+/// byte order, followed by the length of the target name in one byte and the target name in
+/// dotted form. Implementations of [`DnsRecords`] store records through
+/// [`SrvRecords::push`].
+#[derive(Copy, Clone, Debug)]
+pub struct SrvRecords<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> SrvRecords<'a> {
+	/// View the records stored at the beginning of a buffer.
+	pub fn new(data: &'a [u8]) -> Self {
+		SrvRecords { data }
+	}
+
+	/// Append a record to the `len` bytes of records in `buffer`, returning the new length, or
+	/// `None` if the record does not fit.
+	pub fn push(buffer: &mut [u8], len: usize, record: &SrvRecord<'_>) -> Option<usize> {
+		let target_len = u8::try_from(record.target.len()).ok()?;
+		let end = len + 7 + record.target.len();
+		let out = buffer.get_mut(len..end)?;
+		out[..2].copy_from_slice(&record.priority.to_be_bytes());
+		out[2..4].copy_from_slice(&record.weight.to_be_bytes());
+		out[4..6].copy_from_slice(&record.port.to_be_bytes());
+		out[6] = target_len;
+		out[7..].copy_from_slice(record.target.as_bytes());
+		Some(end)
+	}
+
+	/// The records, in the order they were stored.
+	pub fn iter(&self) -> impl Iterator<Item = SrvRecord<'a>> + 'a {
+		let mut data = self.data;
+		core::iter::from_fn(move || {
+			let field = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+			let len = usize::from(*data.get(6)?);
+			let target = core::str::from_utf8(data.get(7..7 + len)?).ok()?;
+			let record = SrvRecord {
+				priority: field(0),
+				weight: field(2),
+				port: field(4),
+				target,
+			};
+			data = &data[7 + len..];
+			Some(record)
+		})
+	}
+
+	/// Whether the records state that the service is not offered at all, by a single record
+	/// with the target `.`.
+	pub fn is_unavailable(&self) -> bool {
+		let mut records = self.iter();
+		matches!(
+			(records.next(), records.next()),
+			(Some(SrvRecord { target: ".", .. }), None)
+		)
+	}
+
+	/// The records in the order they are to be tried, following [RFC 2782]: by ascending
+	/// priority, and randomly weighted by their weights among records of the same priority.
+	///
+	/// `random` is called for a random number for each record of a priority with others left.
+	/// Only the first 16 records are considered.
+	///
+	/// ```
+	/// use embedded_nal::{SrvRecord, SrvRecords};
+	///
+	/// let mut buffer = [0; 128];
+	/// let mut len = 0;
+	/// for (priority, weight, target) in [(20, 0, "backup"), (10, 60, "a"), (10, 20, "b")] {
+	///     let record = SrvRecord { priority, weight, port: 5222, target };
+	///     len = SrvRecords::push(&mut buffer, len, &record).unwrap();
+	/// }
+	/// let records = SrvRecords::new(&buffer[..len]);
+	///
+	/// // The random number picks `b` within the running sum of weights 60 + 20.
+	/// let mut random = [70].into_iter();
+	/// let mut ordered = records.by_preference(move || random.next().unwrap());
+	/// assert_eq!(ordered.next().unwrap().target, "b");
+	/// assert_eq!(ordered.next().unwrap().target, "a");
+	/// assert_eq!(ordered.next().unwrap().target, "backup");
+	/// assert!(ordered.next().is_none());
+	/// ```
+	///
+	/// [RFC 2782]: https://tools.ietf.org/html/rfc2782
+	pub fn by_preference(
+		&self,
+		mut random: impl FnMut() -> u32 + 'a,
+	) -> impl Iterator<Item = SrvRecord<'a>> + 'a {
+		let records = *self;
+		let mut used = [false; MAX_SRV_RECORDS];
+		core::iter::from_fn(move || {
+			let remaining = || {
+				records
+					.iter()
+					.take(MAX_SRV_RECORDS)
+					.enumerate()
+					.filter(|(i, _)| !used[*i])
+			};
+			let priority = remaining().map(|(_, record)| record.priority).min()?;
+			let candidates = || remaining().filter(move |(_, record)| record.priority == priority);
+			let total: u32 = candidates()
+				.map(|(_, record)| u32::from(record.weight))
+				.sum();
+			let count = candidates().count();
+			let pick = match count {
+				1 => 0,
+				_ => random() % (total + 1),
+			};
+			// Records of weight 0 come first, so that they have a small chance of being picked.
+			let mut sum = 0;
+			let (i, record) = candidates()
+				.filter(|(_, record)| record.weight == 0)
+				.chain(candidates().filter(|(_, record)| record.weight > 0))
+				.find(|(_, record)| {
+					sum += u32::from(record.weight);
+					sum >= pick
+				})?;
+			used[i] = true;
+			Some(record)
+		})
+	}
+}
+
+/// A TXT record, a sequence of strings of up to 255 bytes each.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TxtRecord<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> TxtRecord<'a> {
+	/// View the data of a TXT record, the strings each preceded by their length in one byte.
+	pub fn new(data: &'a [u8]) -> Self {
+		TxtRecord { data }
+	}
+
+	/// The data of the record.
+	pub fn data(&self) -> &'a [u8] {
+		self.data
+	}
+
+	/// The strings of the record.
+	pub fn strings(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+		let mut data = self.data;
+		core::iter::from_fn(move || {
+			let (len, rest) = data.split_first()?;
+			let (string, rest) = rest.split_at(usize::from(*len).min(rest.len()));
+			data = rest;
+			Some(string)
+		})
+	}
+
+	/// The value of the `key=value` string with the given key, as used by DNS-SD (see
+	/// [RFC 6763, section 6.4]) and many other TXT based configurations.
+	///
+	/// Keys are compared case-insensitively. A key without `=` has an empty value.
+	///
+	/// [RFC 6763, section 6.4]: https://tools.ietf.org/html/rfc6763#section-6.4
+	pub fn value(&self, key: &str) -> Option<&'a [u8]> {
+		self.strings().find_map(|string| {
+			let (name, value) = match string.iter().position(|byte| *byte == b'=') {
+				Some(i) => (&string[..i], &string[i + 1..]),
+				None => (string, &string[string.len()..]),
+			};
+			name.eq_ignore_ascii_case(key.as_bytes()).then_some(value)
+		})
+	}
+}
+
+/// The TXT records resolved through [`DnsRecords::get_txt_records`].
+///
+/// The records are stored one after the other, each as the length of its data in two bytes in
+/// network byte order, followed by the data. Implementations of [`DnsRecords`] store records
+/// through [`TxtRecords::push`].
+#[derive(Copy, Clone, Debug)]
+pub struct TxtRecords<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> TxtRecords<'a> {
+	/// View the records stored at the beginning of a buffer.
+	pub fn new(data: &'a [u8]) -> Self {
+		TxtRecords { data }
+	}
+
+	/// Append a record with the given data to the `len` bytes of records in `buffer`, returning
+	/// the new length, or `None` if the record does not fit.
+	pub fn push(buffer: &mut [u8], len: usize, data: &[u8]) -> Option<usize> {
+		let data_len = u16::try_from(data.len()).ok()?;
+		let end = len + 2 + data.len();
+		let out = buffer.get_mut(len..end)?;
+		out[..2].copy_from_slice(&data_len.to_be_bytes());
+		out[2..].copy_from_slice(data);
+		Some(end)
+	}
+
+	/// The records, in the order they were stored.
+	pub fn iter(&self) -> impl Iterator<Item = TxtRecord<'a>> + 'a {
+		let mut data = self.data;
+		core::iter::from_fn(move || {
+			let len = usize::from(u16::from_be_bytes([*data.first()?, *data.get(1)?]));
+			let record = TxtRecord::new(data.get(2..2 + len)?);
+			data = &data[2 + len..];
+			Some(record)
+		})
+	}
+}
+
+/// Errors returned when connecting to a service located through SRV records.
+#[derive(Debug)]
+pub enum SrvConnectError<T, D> {
+	/// Connecting to the last target failed.
+	Transport(T),
+	/// Resolving the SRV records, or the address of the last target, failed.
+	Dns(D),
+	/// There are no SRV records for the service.
+	NotFound,
+	/// The SRV records state that the service is not offered.
+	Unavailable,
+}
+
+/// Connect to the service `name` (like `_xmpp-client._tcp.example.com`) through `stack`, trying
+/// the targets of its SRV records in the order of
+/// [`SrvRecords::by_preference`](SrvRecords::by_preference) until a connection succeeds.
+///
+/// Target names are resolved through `dns`, and `random` provides the random numbers for
+/// weighting the targets. If no target can be connected to, the error of the last one is
+/// returned. Each step blocks (by spinning on [`nb::Error::WouldBlock`]) until it completes.
+pub fn connect_srv<S: TcpClientStack, D: DnsRecords>(
+	stack: &mut S,
+	dns: &mut D,
+	name: &str,
+	random: impl FnMut() -> u32,
+) -> Result<S::TcpSocket, SrvConnectError<S::Error, D::Error>> {
+	let mut buffer = [0; SRV_BUFFER_LEN];
+	let len = nb::block!(dns.get_srv_records(name, &mut buffer)).map_err(SrvConnectError::Dns)?;
+	let records = SrvRecords::new(&buffer[..len]);
+	if records.is_unavailable() {
+		return Err(SrvConnectError::Unavailable);
+	}
+
+	let mut result = Err(SrvConnectError::NotFound);
+	for record in records.by_preference(random) {
+		result = nb::block!(dns.get_host_by_name(record.target, AddrType::Either))
+			.map_err(SrvConnectError::Dns)
+			.and_then(|addr| {
+				let mut socket = stack.socket().map_err(SrvConnectError::Transport)?;
+				let remote = SocketAddr::new(addr, record.port);
+				match nb::block!(stack.connect(&mut socket, remote)) {
+					Ok(()) => Ok(socket),
+					Err(e) => {
+						stack.close(socket).ok();
+						Err(SrvConnectError::Transport(e))
+					}
+				}
+			});
+		if result.is_ok() {
+			break;
+		}
+	}
+	result
+}
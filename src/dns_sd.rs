@@ -0,0 +1,435 @@
+use crate::mdns::{MAX_HOSTNAME_LEN, UNICAST_RESPONSE};
+use crate::{
+	DnsMessage, DnsMessageError, DnsMessageWriter, DnsName, DnsRecordData, DnsRecordType,
+	DnsSection, TxtRecord,
+};
+use core::time::Duration;
+
+/// Name of the meta-query enumerating the service types advertised on a network
+pub(crate) const SERVICE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+/// Time to live of service records, as recommended in RFC 6762, section 10
+pub(crate) const SERVICE_TTL: u32 = 4500;
+
+/// Longest service type with the `.local` suffix: `_` and 15 characters, `._tcp` and `.local`
+pub(crate) const SERVICE_TYPE_LEN: usize = 32;
+
+/// Longest TXT record data kept for a service instance
+pub(crate) const TXT_LEN: usize = 128;
+
+/// Longest interval between queries of a browser
+const MAX_QUERY_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A service instance advertised through
+/// [`MdnsResponder::add_service`](crate::MdnsResponder::add_service).
+#[derive(Copy, Clone, Debug)]
+pub struct DnsSdService<'a> {
+	/// The user-visible instance name, like `Kitchen Sensor`; a single label of up to 63 bytes,
+	/// which may contain spaces and dots
+	pub instance: &'a str,
+	/// The service type, like `_coap._udp` or `_http._tcp`
+	pub service: &'a str,
+	/// The port the service is offered on
+	pub port: u16,
+	/// The strings of the TXT record, usually `key=value` pairs, of up to 128 bytes in total
+	/// including a length byte per string
+	pub txt: &'a [&'a [u8]],
+}
+
+/// Copy a service type, with or without the `.local` suffix, to `buffer` in the form
+/// `_<service>._<protocol>.local`, checking its form.
+fn service_type(service: &str, buffer: &mut [u8; SERVICE_TYPE_LEN]) -> Option<usize> {
+	let service = service.strip_suffix('.').unwrap_or(service);
+	let service = service.strip_suffix(".local").unwrap_or(service);
+	let (name, protocol) = service.split_once('.')?;
+	let valid = name.len() > 1
+		&& name.len() <= 16
+		&& name.starts_with('_')
+		&& (protocol.eq_ignore_ascii_case("_tcp") || protocol.eq_ignore_ascii_case("_udp"));
+	if !valid {
+		return None;
+	}
+	let len = service.len() + ".local".len();
+	buffer[..service.len()].copy_from_slice(service.as_bytes());
+	buffer[service.len()..len].copy_from_slice(b".local");
+	Some(len)
+}
+
+/// Write the name of the instance with the label `instance` of the service type `service` to
+/// `buffer`, escaping dots and backslashes in the label.
+fn instance_name<'b>(instance: &[u8], service: &str, buffer: &'b mut [u8; 255]) -> Option<&'b str> {
+	let mut len = 0;
+	for &byte in instance {
+		if matches!(byte, b'.' | b'\\') {
+			*buffer.get_mut(len)? = b'\\';
+			len += 1;
+		}
+		*buffer.get_mut(len)? = byte;
+		len += 1;
+	}
+	*buffer.get_mut(len)? = b'.';
+	buffer
+		.get_mut(len + 1..len + 1 + service.len())?
+		.copy_from_slice(service.as_bytes());
+	core::str::from_utf8(&buffer[..len + 1 + service.len()]).ok()
+}
+
+/// A service instance in the table of a responder
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct AdvertisedService {
+	instance: [u8; 63],
+	instance_len: usize,
+	service: [u8; SERVICE_TYPE_LEN],
+	service_len: usize,
+	pub(crate) port: u16,
+	txt: [u8; TXT_LEN],
+	txt_len: usize,
+}
+
+impl AdvertisedService {
+	/// Check and copy a service, returning `None` if its names are invalid or the TXT record is
+	/// too long.
+	pub(crate) fn new(service: &DnsSdService<'_>) -> Option<Self> {
+		let mut advertised = AdvertisedService {
+			instance: [0; 63],
+			instance_len: service.instance.len(),
+			service: [0; SERVICE_TYPE_LEN],
+			service_len: 0,
+			port: service.port,
+			txt: [0; TXT_LEN],
+			txt_len: 0,
+		};
+		if service.instance.is_empty() {
+			return None;
+		}
+		advertised
+			.instance
+			.get_mut(..service.instance.len())?
+			.copy_from_slice(service.instance.as_bytes());
+		advertised.service_len = service_type(service.service, &mut advertised.service)?;
+		for string in service.txt {
+			let len = u8::try_from(string.len()).ok()?;
+			let out = advertised
+				.txt
+				.get_mut(advertised.txt_len..advertised.txt_len + 1 + string.len())?;
+			out[0] = len;
+			out[1..].copy_from_slice(string);
+			advertised.txt_len += out.len();
+		}
+		// A TXT record holds at least one (possibly empty) string.
+		advertised.txt_len = advertised.txt_len.max(1);
+		Some(advertised)
+	}
+
+	/// The service type with the `.local` suffix
+	pub(crate) fn service(&self) -> &str {
+		core::str::from_utf8(&self.service[..self.service_len]).unwrap_or_default()
+	}
+
+	/// The full name of the instance, written to `buffer`
+	pub(crate) fn name<'b>(&self, buffer: &'b mut [u8; 255]) -> &'b str {
+		instance_name(&self.instance[..self.instance_len], self.service(), buffer)
+			.unwrap_or_default()
+	}
+
+	/// Whether this is the instance `instance` of the service type `service`
+	pub(crate) fn is(&self, instance: &str, service: &str) -> bool {
+		let mut buffer = [0; SERVICE_TYPE_LEN];
+		let len = service_type(service, &mut buffer);
+		self.instance[..self.instance_len] == *instance.as_bytes()
+			&& len.is_some_and(|len| buffer[..len].eq_ignore_ascii_case(self.service().as_bytes()))
+	}
+
+	/// The data of the TXT record
+	pub(crate) fn txt(&self) -> DnsRecordData<'_> {
+		DnsRecordData::Other(DnsRecordType::TXT, &self.txt[..self.txt_len])
+	}
+}
+
+/// A service instance found by a [`DnsSdBrowser`].
+#[derive(Copy, Clone, Debug)]
+pub struct DnsSdInstance {
+	instance: [u8; 63],
+	instance_len: usize,
+	target: [u8; MAX_HOSTNAME_LEN],
+	target_len: usize,
+	port: u16,
+	txt: [u8; TXT_LEN],
+	txt_len: usize,
+	received: Duration,
+	ttl: u32,
+	refreshes: u8,
+}
+
+impl DnsSdInstance {
+	/// The user-visible instance name.
+	pub fn name(&self) -> &str {
+		// Only instances with UTF-8 names are kept.
+		core::str::from_utf8(&self.instance[..self.instance_len]).unwrap_or_default()
+	}
+
+	/// The host name and port the service is offered on, once its SRV record was received.
+	///
+	/// The host name is resolved to an address through [`Dns`](crate::Dns), for example with an
+	/// [`MdnsResolver`](crate::MdnsResolver).
+	pub fn target(&self) -> Option<(&str, u16)> {
+		let target = core::str::from_utf8(&self.target[..self.target_len]).ok()?;
+		(!target.is_empty()).then_some((target, self.port))
+	}
+
+	/// The strings of the TXT record, once it was received.
+	pub fn txt(&self) -> impl Iterator<Item = &[u8]> {
+		TxtRecord::new(&self.txt[..self.txt_len]).strings()
+	}
+
+	/// The value of the `key=value` pair with the given key in the TXT record, see
+	/// [RFC 6763, section 6.4].
+	///
+	/// Keys are compared case-insensitively. A key without `=` has an empty value.
+	///
+	/// [RFC 6763, section 6.4]: https://tools.ietf.org/html/rfc6763#section-6.4
+	pub fn txt_value(&self, key: &str) -> Option<&[u8]> {
+		TxtRecord::new(&self.txt[..self.txt_len]).value(key)
+	}
+
+	/// When the instance expires, unless refreshed
+	fn expires(&self) -> Duration {
+		self.received + Duration::from_secs(self.ttl.into())
+	}
+
+	/// When the next refresh query is due, at 80, 85, 90 and 95% of the time to live
+	fn refresh(&self) -> Duration {
+		match self.refreshes {
+			refreshes @ 0..=3 => {
+				let percent = 80 + 5 * u32::from(refreshes);
+				self.received + Duration::from_secs(self.ttl.into()) * percent / 100
+			}
+			_ => Duration::MAX,
+		}
+	}
+}
+
+/// A DNS-SD service browser (see [RFC 6763]) keeping track of the instances of a service type
+/// offered on the local network.
+///
+/// The browser does not own a socket, as it has to share port 5353 with the responder of the
+/// device; it is driven through
+/// [`MdnsResponder::poll_browser`](crate::MdnsResponder::poll_browser), or through
+/// [`.handle()`](DnsSdBrowser::handle) and [`.poll_query()`](DnsSdBrowser::poll_query) by other
+/// transports. It sends continuous queries at increasing intervals up to an hour, and refreshes
+/// instances when 80, 85, 90 and 95% of their time to live passed. Instances are removed when
+/// they expire, or when their records are withdrawn by a goodbye packet. Up to `N` instances are
+/// kept; further ones are ignored until others are removed.
+///
+/// ```
+/// use embedded_nal::{DnsMessage, DnsMessageWriter, DnsRecordData, DnsSdBrowser, DnsSection};
+/// use core::time::Duration;
+///
+/// let mut browser: DnsSdBrowser = DnsSdBrowser::new("_http._tcp")?;
+/// let mut query = [0; 512];
+/// assert!(browser.poll_query(Duration::ZERO, &mut query).is_some());
+///
+/// // A response as sent by the responder of a printer
+/// let mut response = [0; 512];
+/// let mut writer = DnsMessageWriter::new(&mut response, 0, DnsMessage::RESPONSE)?;
+/// let instance = "Printer\\.1._http._tcp.local";
+/// let ptr = DnsRecordData::Ptr(instance);
+/// writer.record(DnsSection::Answer, "_http._tcp.local", 1, 4500, ptr)?;
+/// let srv = DnsRecordData::Srv { priority: 0, weight: 0, port: 631, target: "printer.local" };
+/// writer.record(DnsSection::Additional, instance, 1, 120, srv)?;
+/// let txt = DnsRecordData::Txt(&[b"rp=ipp/print"]);
+/// writer.record(DnsSection::Additional, instance, 1, 4500, txt)?;
+/// let len = writer.finish();
+/// browser.handle(&DnsMessage::parse(&response[..len])?, Duration::from_secs(1));
+///
+/// let printer = browser.instances().next().unwrap();
+/// assert_eq!(printer.name(), "Printer.1");
+/// assert_eq!(printer.target(), Some(("printer.local", 631)));
+/// assert_eq!(printer.txt_value("rp"), Some(&b"ipp/print"[..]));
+/// # Ok::<(), embedded_nal::DnsMessageError>(())
+/// ```
+///
+/// [RFC 6763]: https://tools.ietf.org/html/rfc6763
+#[derive(Clone, Debug)]
+pub struct DnsSdBrowser<const N: usize = 4> {
+	service: [u8; SERVICE_TYPE_LEN],
+	service_len: usize,
+	instances: [Option<DnsSdInstance>; N],
+	interval: Duration,
+	next_query: Duration,
+}
+
+impl<const N: usize> DnsSdBrowser<N> {
+	/// Create a browser for a service type like `_coap._udp` or `_http._tcp`.
+	pub fn new(service: &str) -> Result<Self, DnsMessageError> {
+		let mut browser = DnsSdBrowser {
+			service: [0; SERVICE_TYPE_LEN],
+			service_len: 0,
+			instances: [None; N],
+			interval: Duration::ZERO,
+			next_query: Duration::ZERO,
+		};
+		browser.service_len =
+			service_type(service, &mut browser.service).ok_or(DnsMessageError::InvalidName)?;
+		Ok(browser)
+	}
+
+	/// The service type browsed for, with the `.local` suffix.
+	pub fn service(&self) -> &str {
+		core::str::from_utf8(&self.service[..self.service_len]).unwrap_or_default()
+	}
+
+	/// The instances currently known.
+	pub fn instances(&self) -> impl Iterator<Item = &DnsSdInstance> {
+		self.instances.iter().flatten()
+	}
+
+	/// Take note of the records in a message received at time `now`.
+	pub fn handle(&mut self, message: &DnsMessage<'_>, now: Duration) {
+		if !message.is_response() {
+			return;
+		}
+		for (_, record) in message.records() {
+			match record.record_type {
+				DnsRecordType::PTR if record.name.eq_str(self.service()) => {
+					let Some((label, rest)) = record.target().and_then(|name| name.split_first())
+					else {
+						continue;
+					};
+					if rest.eq_str(self.service()) && core::str::from_utf8(label).is_ok() {
+						self.update(label, now, record.ttl);
+					}
+				}
+				DnsRecordType::SRV | DnsRecordType::TXT => {
+					let Some(slot) = self.find(&record.name) else {
+						continue;
+					};
+					let Some(instance) = slot.as_mut().filter(|_| record.ttl > 0) else {
+						*slot = None;
+						continue;
+					};
+					if let Some((_, _, port, target)) = record.srv() {
+						let len = target.to_dotted(&mut instance.target).map_or(0, str::len);
+						instance.target_len = len;
+						instance.port = port;
+					} else if let Some(txt) = instance.txt.get_mut(..record.data.len()) {
+						txt.copy_from_slice(record.data);
+						instance.txt_len = record.data.len();
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// Write a query to `buffer` if one is due at time `now`, returning its length.
+	///
+	/// The query is to be sent to the mDNS group from port 5353. It lists the instances known
+	/// for more than half their time to live as known answers, so that their responders stay
+	/// silent.
+	pub fn poll_query(&mut self, now: Duration, buffer: &mut [u8]) -> Option<usize> {
+		for instance in &mut self.instances {
+			if instance
+				.as_ref()
+				.is_some_and(|instance| instance.expires() <= now)
+			{
+				*instance = None;
+			}
+		}
+		let mut due = now >= self.next_query;
+		for instance in self.instances.iter_mut().flatten() {
+			if instance.refresh() <= now {
+				instance.refreshes += 1;
+				due = true;
+			}
+		}
+		if !due {
+			return None;
+		}
+		if now >= self.next_query {
+			self.interval = (self.interval * 2).clamp(Duration::from_secs(1), MAX_QUERY_INTERVAL);
+			self.next_query = now + self.interval;
+		}
+
+		let mut writer = DnsMessageWriter::new(buffer, 0, 0).ok()?;
+		let class = match self.instances().next() {
+			// Ask for unicast responses to the first query, see RFC 6762, section 5.4
+			None if self.interval == Duration::from_secs(1) => {
+				DnsMessage::CLASS_IN | UNICAST_RESPONSE
+			}
+			_ => DnsMessage::CLASS_IN,
+		};
+		writer
+			.question(self.service(), DnsRecordType::PTR, class)
+			.ok()?;
+		for instance in self.instances() {
+			let remaining = instance.expires().saturating_sub(now).as_secs() as u32;
+			let mut name = [0; 255];
+			let name = instance_name(instance.name().as_bytes(), self.service(), &mut name);
+			if let (true, Some(name)) = (remaining > instance.ttl / 2, name) {
+				let ptr = DnsRecordData::Ptr(name);
+				let class = DnsMessage::CLASS_IN;
+				// Known answers that do not fit are left out.
+				writer
+					.record(DnsSection::Answer, self.service(), class, remaining, ptr)
+					.ok();
+			}
+		}
+		Some(writer.finish())
+	}
+
+	/// Add or refresh the instance labelled `label`, or remove it if `ttl` is 0.
+	fn update(&mut self, label: &[u8], now: Duration, ttl: u32) {
+		let known = self.instances.iter_mut().find(|instance| {
+			instance.as_ref().is_some_and(|instance| {
+				instance.instance[..instance.instance_len].eq_ignore_ascii_case(label)
+			})
+		});
+		let slot = match known {
+			Some(slot) if ttl == 0 => {
+				*slot = None;
+				return;
+			}
+			Some(slot) => slot,
+			None if ttl == 0 => return,
+			None => match self.instances.iter_mut().find(|slot| slot.is_none()) {
+				Some(slot) => {
+					let mut instance = DnsSdInstance {
+						instance: [0; 63],
+						instance_len: label.len(),
+						target: [0; MAX_HOSTNAME_LEN],
+						target_len: 0,
+						port: 0,
+						txt: [0; TXT_LEN],
+						txt_len: 0,
+						received: now,
+						ttl,
+						refreshes: 0,
+					};
+					instance.instance[..label.len()].copy_from_slice(label);
+					*slot = Some(instance);
+					return;
+				}
+				None => return,
+			},
+		};
+		if let Some(instance) = slot {
+			instance.received = now;
+			instance.ttl = ttl;
+			instance.refreshes = 0;
+		}
+	}
+
+	/// The slot of the known instance with the name `name`
+	fn find(&mut self, name: &DnsName<'_>) -> Option<&mut Option<DnsSdInstance>> {
+		let (label, rest) = name.split_first()?;
+		if !rest.eq_str(self.service()) {
+			return None;
+		}
+		self.instances.iter_mut().find(|instance| {
+			instance.as_ref().is_some_and(|instance| {
+				instance.instance[..instance.instance_len].eq_ignore_ascii_case(label)
+			})
+		})
+	}
+}
@@ -0,0 +1,282 @@
+use crate::{
+	nb, DnsMessage, DnsMessageWriter, DnsRecordData, DnsRecordType, DnsSection, UdpFullStack,
+};
+use core::net::IpAddr;
+
+/// The well-known DNS port
+pub const DNS_PORT: u16 = 53;
+
+/// Size of the buffers queries are received into and responses are written to, the largest
+/// message allowed over UDP without extensions
+pub const DNS_MESSAGE_LEN: usize = 512;
+
+/// The bits of the flags holding the operation code
+const OPCODE: u16 = 0x7800;
+
+/// The class matching records of any class
+const CLASS_ANY: u16 = 255;
+
+/// Time to live of answers unless configured otherwise
+const DEFAULT_TTL: u32 = 60;
+
+/// Longest name in dotted form, with room for escaped dots and backslashes
+const DOTTED_NAME_LEN: usize = 2 * 255;
+
+/// An entry in the zone table of a [`DnsZone`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DnsZoneEntry<'a> {
+	/// The name, in dotted form like `device.lan`
+	pub name: &'a str,
+	/// An address of the name, answered in an `A` or `AAAA` record
+	pub address: IpAddr,
+}
+
+/// The names answered by a DNS server, and how queries for other names are answered.
+///
+/// Names are looked up in a table of [`DnsZoneEntry`]s; a name may have several entries, for
+/// example one for its IPv4 and one for its IPv6 address. Queries for other names are answered
+///
+/// * in wildcard mode (see [`.set_wildcard()`](DnsZone::set_wildcard)), with the wildcard
+///   addresses, as used by captive portals to direct all traffic to the device,
+/// * otherwise, with `NXDOMAIN` if the name is in the [domain](DnsZone::set_domain) of the zone,
+/// * and with `REFUSED` if it is not, as the server does not resolve names recursively.
+///
+/// This type only turns queries into responses; [`DnsServer`] serves them on a port of a
+/// [`UdpFullStack`].
+///
+/// ```
+/// use embedded_nal::{DnsMessage, DnsMessageWriter, DnsRecordType, DnsZone, DnsZoneEntry};
+/// use core::net::{IpAddr, Ipv4Addr};
+///
+/// let portal = IpAddr::V4(Ipv4Addr::new(192, 168, 4, 1));
+/// let entries = [DnsZoneEntry { name: "setup.lan", address: portal }];
+/// let mut zone = DnsZone::new(&entries);
+/// zone.set_domain("lan");
+///
+/// let query = |name| {
+///     let mut query = [0; 64];
+///     let mut writer = DnsMessageWriter::new(&mut query, 7, DnsMessage::RECURSION_DESIRED)?;
+///     writer.question(name, DnsRecordType::A, DnsMessage::CLASS_IN)?;
+///     let len = writer.finish();
+///     Ok::<_, embedded_nal::DnsMessageError>((query, len))
+/// };
+/// let mut response = [0; 512];
+///
+/// let (setup, len) = query("Setup.lan")?;
+/// let len = zone.respond(&setup[..len], &mut response).unwrap();
+/// let (_, answer) = DnsMessage::parse(&response[..len])?.records().next().unwrap();
+/// assert_eq!(answer.ipv4(), Some(Ipv4Addr::new(192, 168, 4, 1)));
+///
+/// let (printer, len) = query("printer.lan")?;
+/// let len = zone.respond(&printer[..len], &mut response).unwrap();
+/// assert_eq!(DnsMessage::parse(&response[..len])?.response_code(), DnsMessage::NAME_ERROR);
+///
+/// let (example, example_len) = query("example.com")?;
+/// let len = zone.respond(&example[..example_len], &mut response).unwrap();
+/// assert_eq!(DnsMessage::parse(&response[..len])?.response_code(), DnsMessage::REFUSED);
+///
+/// // In wildcard mode, every name leads to the portal.
+/// let wildcard = [portal];
+/// zone.set_wildcard(&wildcard);
+/// let len = zone.respond(&example[..example_len], &mut response).unwrap();
+/// let (_, answer) = DnsMessage::parse(&response[..len])?.records().next().unwrap();
+/// assert_eq!(answer.ipv4(), Some(Ipv4Addr::new(192, 168, 4, 1)));
+/// # Ok::<(), embedded_nal::DnsMessageError>(())
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct DnsZone<'a> {
+	entries: &'a [DnsZoneEntry<'a>],
+	domain: Option<&'a str>,
+	wildcard: &'a [IpAddr],
+	ttl: u32,
+}
+
+impl<'a> DnsZone<'a> {
+	/// Create a zone answering the names in `entries`.
+	pub fn new(entries: &'a [DnsZoneEntry<'a>]) -> Self {
+		DnsZone {
+			entries,
+			domain: None,
+			wildcard: &[],
+			ttl: DEFAULT_TTL,
+		}
+	}
+
+	/// Set the domain of the zone, like `lan`; names in it that are not in the table are answered
+	/// with `NXDOMAIN`. Without a domain, the zone only consists of the names in its table.
+	pub fn set_domain(&mut self, domain: &'a str) {
+		self.domain = Some(domain);
+	}
+
+	/// Answer all names that are not in the table with `addresses`, or stop doing so if
+	/// `addresses` is empty.
+	pub fn set_wildcard(&mut self, addresses: &'a [IpAddr]) {
+		self.wildcard = addresses;
+	}
+
+	/// Set the time to live of answers in seconds; the default is 60.
+	pub fn set_ttl(&mut self, ttl: u32) {
+		self.ttl = ttl;
+	}
+
+	/// Write the response to `query` into `response`, returning its length.
+	///
+	/// Returns `None` if `query` is not to be answered at all, as it is no DNS query, or
+	/// `response` is too small for even an error response. Answers not fitting into
+	/// `response` are left out, and the response is marked as truncated.
+	pub fn respond(&self, query: &[u8], response: &mut [u8]) -> Option<usize> {
+		let header = query.get(..4)?;
+		let id = u16::from_be_bytes([header[0], header[1]]);
+		let flags = u16::from_be_bytes([header[2], header[3]]);
+		if flags & DnsMessage::RESPONSE != 0 {
+			return None;
+		}
+		let flags = DnsMessage::RESPONSE | flags & (OPCODE | DnsMessage::RECURSION_DESIRED);
+		match self.answer(query, response, id, flags) {
+			Ok(len) => Some(len),
+			Err(code) => DnsMessageWriter::new(response, id, flags | code)
+				.ok()
+				.map(DnsMessageWriter::finish),
+		}
+	}
+
+	/// Write the response to a query, or return the response code of an error response without
+	/// a question.
+	fn answer(&self, query: &[u8], response: &mut [u8], id: u16, flags: u16) -> Result<usize, u16> {
+		let message = DnsMessage::parse(query).map_err(|_| DnsMessage::FORMAT_ERROR)?;
+		if message.opcode() != 0 {
+			return Err(DnsMessage::NOT_IMPLEMENTED);
+		}
+		let mut questions = message.questions();
+		let (Some(question), None) = (questions.next(), questions.next()) else {
+			return Err(DnsMessage::FORMAT_ERROR);
+		};
+		let mut name = [0; DOTTED_NAME_LEN];
+		let name = question
+			.name
+			.to_dotted(&mut name)
+			.ok_or(DnsMessage::FORMAT_ERROR)?;
+
+		let in_table = self
+			.entries
+			.iter()
+			.any(|entry| question.name.eq_str(entry.name));
+		let code = match self.domain {
+			_ if !matches!(question.class, DnsMessage::CLASS_IN | CLASS_ANY) => DnsMessage::REFUSED,
+			_ if in_table || !self.wildcard.is_empty() => 0,
+			Some(domain) if question.name.ends_with(domain) => DnsMessage::NAME_ERROR,
+			_ => DnsMessage::REFUSED,
+		};
+		let flags = match code {
+			DnsMessage::REFUSED => flags | code,
+			_ => flags | DnsMessage::AUTHORITATIVE | code,
+		};
+
+		let mut writer =
+			DnsMessageWriter::new(response, id, flags).map_err(|_| DnsMessage::SERVER_FAILURE)?;
+		writer
+			.question(name, question.record_type, question.class)
+			.map_err(|_| DnsMessage::SERVER_FAILURE)?;
+		if code != 0 {
+			return Ok(writer.finish());
+		}
+
+		let table = self
+			.entries
+			.iter()
+			.filter(|entry| question.name.eq_str(entry.name))
+			.map(|entry| entry.address);
+		let wildcard = self.wildcard.iter().copied().filter(|_| !in_table);
+		let mut truncated = false;
+		for address in table.chain(wildcard) {
+			let data = match (question.record_type, address) {
+				(DnsRecordType::A | DnsRecordType::ANY, IpAddr::V4(addr)) => DnsRecordData::A(addr),
+				(DnsRecordType::AAAA | DnsRecordType::ANY, IpAddr::V6(addr)) => {
+					DnsRecordData::Aaaa(addr)
+				}
+				_ => continue,
+			};
+			truncated |= writer
+				.record(
+					DnsSection::Answer,
+					name,
+					DnsMessage::CLASS_IN,
+					self.ttl,
+					data,
+				)
+				.is_err();
+		}
+		let len = writer.finish();
+		if truncated {
+			response[2] |= (DnsMessage::TRUNCATED >> 8) as u8;
+		}
+		Ok(len)
+	}
+}
+
+/// A DNS server answering queries from a [`DnsZone`] on a port of any [`UdpFullStack`], for
+/// example to make the devices connected to an access point find the device, or to have them
+/// detect a captive portal.
+///
+/// The server does not block on incoming queries; it is driven by calling
+/// [`.poll()`](DnsServer::poll) repeatedly.
+///
+/// ```
+/// use embedded_nal::{DnsServer, DnsZone, UdpFullStack, DNS_PORT};
+/// use core::net::{IpAddr, Ipv4Addr};
+///
+/// fn serve_portal<S: UdpFullStack>(stack: S) -> Result<(), S::Error> {
+///     let portal = [IpAddr::V4(Ipv4Addr::new(192, 168, 4, 1))];
+///     let mut zone = DnsZone::new(&[]);
+///     zone.set_wildcard(&portal);
+///
+///     let mut server = DnsServer::new(stack, DNS_PORT)?;
+///     loop {
+///         server.poll(&zone)?;
+///         // ... serve the portal's web pages ...
+///     }
+/// }
+/// ```
+pub struct DnsServer<S: UdpFullStack> {
+	stack: S,
+	socket: S::UdpSocket,
+}
+
+impl<S: UdpFullStack> DnsServer<S> {
+	/// Create a server listening on `port` of `stack`, usually [`DNS_PORT`].
+	pub fn new(mut stack: S, port: u16) -> Result<Self, S::Error> {
+		let mut socket = stack.socket()?;
+		if let Err(e) = stack.bind(&mut socket, port) {
+			stack.close(socket).ok();
+			return Err(e);
+		}
+		Ok(DnsServer { stack, socket })
+	}
+
+	/// Answer the queries received since the last call from `zone`.
+	///
+	/// Sending a response blocks (by spinning on [`nb::Error::WouldBlock`]) until the stack
+	/// accepted it.
+	pub fn poll(&mut self, zone: &DnsZone<'_>) -> Result<(), S::Error> {
+		let mut query = [0; DNS_MESSAGE_LEN];
+		let mut response = [0; DNS_MESSAGE_LEN];
+		loop {
+			let (len, remote) = match self.stack.receive(&mut self.socket, &mut query) {
+				Ok(received) => received,
+				Err(nb::Error::WouldBlock) => return Ok(()),
+				Err(nb::Error::Other(e)) => return Err(e),
+			};
+			if let Some(len) = zone.respond(&query[..len], &mut response) {
+				nb::block!(self
+					.stack
+					.send_to(&mut self.socket, remote, &response[..len]))?;
+			}
+		}
+	}
+
+	/// Release the stack, closing the server's socket.
+	pub fn free(mut self) -> S {
+		self.stack.close(self.socket).ok();
+		self.stack
+	}
+}
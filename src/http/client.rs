@@ -0,0 +1,306 @@
+use super::{
+	HttpError, HttpMethod, HttpProtocolError, HttpRedirect, HttpRequestHead, HttpResponse,
+	HttpResponseReader, HttpUrl, HTTP_MAX_REDIRECTS,
+};
+use crate::{nb, AddrType, Dns, TcpClientStack, TcpError, TcpErrorKind};
+use core::net::{IpAddr, SocketAddr};
+
+/// A minimal HTTP/1.1 client on top of any [`TcpClientStack`] and [`Dns`] implementation.
+///
+/// Requests are sent and responses received into a buffer provided by the caller, which has to
+/// hold the request head and the complete response head and body; no allocation is performed.
+/// Bodies with a `Content-Length` as well as chunked bodies are supported. The connection is
+/// kept open after a response if the server allows it, and is reused for the next request to
+/// the same address; `GET` requests are sent again on a new connection if the server closed it
+/// in the meantime. Redirects to `http://` URLs are followed, up to 5 times by default.
+///
+/// Each request blocks (by spinning on [`nb::Error::WouldBlock`]) until the response is
+/// complete.
+///
+/// ```
+/// use embedded_nal::{HttpClient, TcpClientStack, nb};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// # struct Server { replies: Vec<&'static [u8]>, reply: &'static [u8], connections: usize }
+/// # #[derive(Debug)] struct Error;
+/// # impl embedded_nal::TcpError for Error {
+/// #   fn kind(&self) -> embedded_nal::TcpErrorKind { embedded_nal::TcpErrorKind::Other }
+/// # }
+/// # fn addr() -> SocketAddr { SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80) }
+/// # impl TcpClientStack for Server {
+/// #   type TcpSocket = ();
+/// #   type Error = Error;
+/// #   fn socket(&mut self) -> Result<(), Error> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Error> {
+/// #     self.connections += 1;
+/// #     Ok(())
+/// #   }
+/// #   // Replies to each request head with the next reply.
+/// #   fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Error> {
+/// #     if buffer.starts_with(b"GET ") {
+/// #       self.reply = self.replies.remove(0);
+/// #     }
+/// #     Ok(buffer.len())
+/// #   }
+/// #   // Hands out the reply 7 bytes at a time, then reports the connection as closed.
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<usize, Error> {
+/// #     let len = buffer.len().min(self.reply.len()).min(7);
+/// #     buffer[..len].copy_from_slice(&self.reply[..len]);
+/// #     self.reply = &self.reply[len..];
+/// #     Ok(len)
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), Error> { Ok(()) }
+/// #   fn local_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// #   fn peer_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// # }
+/// # struct Resolver;
+/// # impl embedded_nal::Dns for Resolver {
+/// #   type Error = ();
+/// #   fn get_host_by_name(&mut self, _: &str, _: embedded_nal::AddrType) -> nb::Result<IpAddr, ()> {
+/// #     Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+/// #   }
+/// #   fn get_host_by_address(&mut self, _: IpAddr, _: &mut [u8]) -> nb::Result<usize, ()> {
+/// #     Err(nb::Error::Other(()))
+/// #   }
+/// # }
+/// # let stack = Server {
+/// #   replies: vec![
+/// #     b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nHello \r\n6\r\nworld!\r\n0\r\n\r\n",
+/// #     b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nAlice",
+/// #     b"HTTP/1.1 200 OK\r\n\r\nHave a nice day",
+/// #     b"HTTP/1.1 301 Moved Permanently\r\nLocation: /old\r\nContent-Length: 0\r\n\r\n",
+/// #     b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n",
+/// #   ],
+/// #   reply: &[],
+/// #   connections: 0,
+/// # };
+/// # let dns = Resolver;
+/// let mut client = HttpClient::new(stack, dns);
+///
+/// let mut buffer = [0; 512];
+/// let response = client.get("http://example.com/greeting", &mut buffer)?;
+/// assert_eq!(response.status, 200);
+/// assert_eq!(response.body, b"Hello world!");
+///
+/// // The connection is kept open for the next requests, up to a response whose body is only
+/// // delimited by the server closing the connection.
+/// let response = client.get("http://example.com/name", &mut buffer)?;
+/// assert_eq!(response.body, b"Alice");
+/// let response = client.get("http://example.com/motd", &mut buffer)?;
+/// assert_eq!(response.body, b"Have a nice day");
+///
+/// // Once the limit is reached, the redirect itself is returned.
+/// client.set_max_redirects(1);
+/// let response = client.get("http://example.com/older", &mut buffer)?;
+/// assert_eq!(response.status, 301);
+/// assert_eq!(response.header("Location"), Some("/new"));
+///
+/// let (stack, _) = client.free();
+/// assert_eq!(stack.connections, 2);
+/// # Ok::<(), embedded_nal::HttpError<Error, ()>>(())
+/// ```
+pub struct HttpClient<S: TcpClientStack, D> {
+	stack: S,
+	dns: D,
+	connection: Option<(S::TcpSocket, SocketAddr)>,
+	max_redirects: u8,
+}
+
+impl<S: TcpClientStack, D: Dns> HttpClient<S, D> {
+	/// Create a client that connects through `stack`, resolving host names through `dns`.
+	pub fn new(stack: S, dns: D) -> Self {
+		HttpClient {
+			stack,
+			dns,
+			connection: None,
+			max_redirects: HTTP_MAX_REDIRECTS,
+		}
+	}
+
+	/// Set the number of redirects that are followed for a single request.
+	///
+	/// Once the limit is reached, the redirect response is returned. A limit of 0 disables
+	/// following redirects.
+	pub fn set_max_redirects(&mut self, max_redirects: u8) {
+		self.max_redirects = max_redirects;
+	}
+
+	/// Send a `GET` request for `url`, and receive the response into `buffer`.
+	pub fn get<'b>(
+		&mut self,
+		url: &str,
+		buffer: &'b mut [u8],
+	) -> Result<HttpResponse<'b>, HttpError<S::Error, D::Error>> {
+		self.request(HttpMethod::Get, url, &[], &[], buffer)
+	}
+
+	/// Send a request with the given header fields and body, and receive the response into
+	/// `buffer`.
+	///
+	/// A `Content-Length` header is added for `POST` and `PUT` requests, and for any request
+	/// with a non-empty body; `headers` must not contain one.
+	///
+	/// The header fields are only sent to the host and port of `url`, and left out when
+	/// following a redirect elsewhere.
+	pub fn request<'b>(
+		&mut self,
+		method: HttpMethod,
+		url: &str,
+		headers: &[(&str, &str)],
+		body: &[u8],
+		buffer: &'b mut [u8],
+	) -> Result<HttpResponse<'b>, HttpError<S::Error, D::Error>> {
+		let origin = HttpUrl::parse(url)?;
+		let mut method = method;
+		let mut body = body;
+		let mut location: Option<HttpRedirect> = None;
+		let mut redirects = 0;
+		loop {
+			let url = HttpUrl::parse(location.as_ref().map_or(url, HttpRedirect::location))?;
+			let head = HttpRequestHead {
+				method,
+				url,
+				// The header fields may hold credentials, which are not for other hosts.
+				headers: match url.same_origin(&origin) {
+					true => headers,
+					false => &[],
+				},
+				content_length: match method {
+					HttpMethod::Post | HttpMethod::Put => Some(body.len()),
+					_ => Some(body.len()).filter(|len| *len > 0),
+				},
+			};
+			let reader = self.exchange(&head, body, buffer)?;
+
+			let redirect = match redirects < self.max_redirects {
+				true => reader
+					.response(buffer)
+					.and_then(|response| response.redirect(method, &url)),
+				false => None,
+			};
+			match redirect {
+				Some(redirect) => {
+					if redirect.method != method {
+						body = &[];
+					}
+					method = redirect.method;
+					location = Some(redirect);
+					redirects += 1;
+				}
+				None => return Ok(reader.response(buffer).ok_or(HttpProtocolError::Closed)?),
+			}
+		}
+	}
+
+	/// Close the connection kept open for further requests, if any.
+	pub fn close(&mut self) -> Result<(), S::Error> {
+		match self.connection.take() {
+			Some((socket, _)) => self.stack.close(socket),
+			None => Ok(()),
+		}
+	}
+
+	/// Release the stack and resolver, closing any open connection.
+	pub fn free(mut self) -> (S, D) {
+		self.close().ok();
+		(self.stack, self.dns)
+	}
+
+	/// Send a request and receive the response, on a kept-open connection if possible.
+	fn exchange(
+		&mut self,
+		head: &HttpRequestHead<'_>,
+		body: &[u8],
+		buffer: &mut [u8],
+	) -> Result<HttpResponseReader, HttpError<S::Error, D::Error>> {
+		let remote = SocketAddr::new(self.resolve(&head.url)?, head.url.port);
+
+		let (mut socket, reused) = match self.connection.take() {
+			Some((socket, addr)) if addr == remote => (socket, true),
+			connection => {
+				if let Some((socket, _)) = connection {
+					self.stack.close(socket).ok();
+				}
+				(self.connect(remote)?, false)
+			}
+		};
+
+		let mut result = self.exchange_on(&mut socket, head, body, buffer);
+		// Other requests may have been acted on already, and must not be sent twice.
+		if reused
+			&& head.method == HttpMethod::Get
+			&& matches!(
+				result,
+				Err(HttpError::Transport(_) | HttpError::Protocol(HttpProtocolError::Closed))
+			) {
+			// The server may have closed the kept-open connection in the meantime.
+			self.stack.close(socket).ok();
+			socket = self.connect(remote)?;
+			result = self.exchange_on(&mut socket, head, body, buffer);
+		}
+
+		match &result {
+			Ok(reader) if reader.keep_alive() => self.connection = Some((socket, remote)),
+			_ => {
+				self.stack.close(socket).ok();
+			}
+		}
+		result
+	}
+
+	fn exchange_on(
+		&mut self,
+		socket: &mut S::TcpSocket,
+		head: &HttpRequestHead<'_>,
+		body: &[u8],
+		buffer: &mut [u8],
+	) -> Result<HttpResponseReader, HttpError<S::Error, D::Error>> {
+		let len = head.write(buffer)?;
+		self.send_all(socket, &buffer[..len])?;
+		self.send_all(socket, body)?;
+
+		let mut reader = HttpResponseReader::new();
+		loop {
+			let spare = reader.spare(buffer)?;
+			let len = match nb::block!(self.stack.receive(socket, spare)) {
+				Ok(len) => len,
+				Err(e) if e.kind() == TcpErrorKind::PipeClosed => 0,
+				Err(e) => return Err(HttpError::Transport(e)),
+			};
+			if reader.advance(buffer, len)? {
+				return Ok(reader);
+			}
+		}
+	}
+
+	fn send_all(
+		&mut self,
+		socket: &mut S::TcpSocket,
+		mut data: &[u8],
+	) -> Result<(), HttpError<S::Error, D::Error>> {
+		while !data.is_empty() {
+			let len = nb::block!(self.stack.send(socket, data)).map_err(HttpError::Transport)?;
+			data = &data[len..];
+		}
+		Ok(())
+	}
+
+	fn connect(
+		&mut self,
+		remote: SocketAddr,
+	) -> Result<S::TcpSocket, HttpError<S::Error, D::Error>> {
+		let mut socket = self.stack.socket().map_err(HttpError::Transport)?;
+		if let Err(e) = nb::block!(self.stack.connect(&mut socket, remote)) {
+			self.stack.close(socket).ok();
+			return Err(HttpError::Transport(e));
+		}
+		Ok(socket)
+	}
+
+	fn resolve(&mut self, url: &HttpUrl<'_>) -> Result<IpAddr, HttpError<S::Error, D::Error>> {
+		match url.hostname().parse() {
+			Ok(addr) => Ok(addr),
+			Err(_) => nb::block!(self.dns.get_host_by_name(url.hostname(), AddrType::Either))
+				.map_err(HttpError::Dns),
+		}
+	}
+}
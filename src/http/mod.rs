@@ -0,0 +1,708 @@
+use core::fmt::Write as _;
+
+mod client;
+mod server;
+
+pub use client::HttpClient;
+pub use server::{HttpHandler, HttpRequest, HttpResponder, HttpRoute, HttpServer};
+
+/// Number of redirects the HTTP clients follow for a single request unless configured otherwise
+pub const HTTP_MAX_REDIRECTS: u8 = 5;
+
+/// HTTP request methods supported by the HTTP client and server.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HttpMethod {
+	/// `GET`
+	Get,
+	/// `POST`
+	Post,
+	/// `PUT`
+	Put,
+	/// `DELETE`
+	Delete,
+}
+
+impl HttpMethod {
+	/// The method name as it appears in the request line.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			HttpMethod::Get => "GET",
+			HttpMethod::Post => "POST",
+			HttpMethod::Put => "PUT",
+			HttpMethod::Delete => "DELETE",
+		}
+	}
+
+	/// Look up a method by its name in the request line.
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"GET" => Some(HttpMethod::Get),
+			"POST" => Some(HttpMethod::Post),
+			"PUT" => Some(HttpMethod::Put),
+			"DELETE" => Some(HttpMethod::Delete),
+			_ => None,
+		}
+	}
+}
+
+/// Errors in HTTP messages or URLs, independent of the transport.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HttpProtocolError {
+	/// The URL is not an absolute `http://` URL.
+	InvalidUrl,
+	/// The peer sent a message that is not valid HTTP/1.1.
+	Malformed,
+	/// A message does not fit into the provided buffer.
+	BufferTooSmall,
+	/// The connection was closed before the message was complete.
+	Closed,
+}
+
+/// Errors returned by the HTTP client.
+#[derive(Debug)]
+pub enum HttpError<T, D> {
+	/// The underlying TCP connection failed.
+	Transport(T),
+	/// The host name could not be resolved.
+	Dns(D),
+	/// The URL or the exchanged messages are not valid.
+	Protocol(HttpProtocolError),
+}
+
+impl<T, D> From<HttpProtocolError> for HttpError<T, D> {
+	fn from(e: HttpProtocolError) -> Self {
+		HttpError::Protocol(e)
+	}
+}
+
+/// The parts of an absolute `http://` URL that are needed to make a request.
+///
+/// User information in the URL is not supported; fragments are dropped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HttpUrl<'a> {
+	/// Host name or IP address; IPv6 addresses are enclosed in brackets.
+	pub host: &'a str,
+	/// Port, 80 if not given in the URL
+	pub port: u16,
+	/// Path and query, without the leading slash
+	pub path: &'a str,
+}
+
+impl<'a> HttpUrl<'a> {
+	/// Split up an absolute `http://` URL.
+	pub fn parse(url: &'a str) -> Result<Self, HttpProtocolError> {
+		let rest = url
+			.get(..7)
+			.filter(|scheme| scheme.eq_ignore_ascii_case("http://"))
+			.map(|_| &url[7..])
+			.ok_or(HttpProtocolError::InvalidUrl)?;
+		let rest = rest.split('#').next().unwrap_or_default();
+
+		let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+		let (authority, path) = rest.split_at(authority_end);
+		let path = path.strip_prefix('/').unwrap_or(path);
+		if authority.contains('@') {
+			return Err(HttpProtocolError::InvalidUrl);
+		}
+
+		// The port separator is the last colon, unless that is part of an IPv6 address.
+		let (host, port) = match authority.rfind(':') {
+			Some(colon) if !authority[colon..].contains(']') => {
+				let port = authority[colon + 1..]
+					.parse()
+					.map_err(|_| HttpProtocolError::InvalidUrl)?;
+				(&authority[..colon], port)
+			}
+			_ => (authority, 80),
+		};
+		if host.is_empty() {
+			return Err(HttpProtocolError::InvalidUrl);
+		}
+
+		Ok(HttpUrl { host, port, path })
+	}
+
+	/// The host as used for name resolution, i.e. with brackets around IPv6 addresses removed.
+	pub fn hostname(&self) -> &'a str {
+		self.host
+			.strip_prefix('[')
+			.and_then(|host| host.strip_suffix(']'))
+			.unwrap_or(self.host)
+	}
+
+	/// Whether `other` has the same origin, i.e. host and port, so that credentials for one
+	/// apply to the other.
+	pub fn same_origin(&self, other: &HttpUrl<'_>) -> bool {
+		self.host.eq_ignore_ascii_case(other.host) && self.port == other.port
+	}
+}
+
+/// The head of an HTTP/1.1 request.
+#[derive(Copy, Clone, Debug)]
+pub struct HttpRequestHead<'a> {
+	/// The request method
+	pub method: HttpMethod,
+	/// The requested URL, which determines the request target and the `Host` header
+	pub url: HttpUrl<'a>,
+	/// Additional header fields, as name and value
+	pub headers: &'a [(&'a str, &'a str)],
+	/// Length of the request body, sent in the `Content-Length` header if present
+	pub content_length: Option<usize>,
+}
+
+impl HttpRequestHead<'_> {
+	/// Serialize the head, including the terminating empty line, into the beginning of `buffer`.
+	///
+	/// Returns the length of the head.
+	pub fn write(&self, buffer: &mut [u8]) -> Result<usize, HttpProtocolError> {
+		let mut out = HttpWriter::new(buffer);
+		let url = &self.url;
+		let result = (|| {
+			write!(out, "{} /{} HTTP/1.1\r\n", self.method.as_str(), url.path)?;
+			match url.port {
+				80 => write!(out, "Host: {}\r\n", url.host)?,
+				port => write!(out, "Host: {}:{}\r\n", url.host, port)?,
+			}
+			for (name, value) in self.headers {
+				write!(out, "{name}: {value}\r\n")?;
+			}
+			if let Some(len) = self.content_length {
+				write!(out, "Content-Length: {len}\r\n")?;
+			}
+			out.write_str("\r\n")
+		})();
+		result.map_err(|_| HttpProtocolError::BufferTooSmall)?;
+		Ok(out.len)
+	}
+}
+
+/// How the end of a message body is determined
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum HttpBodyLength {
+	/// The body has the given number of bytes.
+	Fixed(usize),
+	/// The body uses the chunked transfer coding, see [`ChunkedDecoder`].
+	Chunked,
+	/// The body extends until the connection is closed.
+	UntilClose,
+}
+
+/// The parsed head of an HTTP/1.1 response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct HttpResponseHead<'a> {
+	/// Length of the head in bytes, including the terminating empty line
+	pub len: usize,
+	/// The status code
+	pub status: u16,
+	/// The header fields, one per line, without the status line and the terminating empty line
+	pub headers: &'a str,
+	/// How the end of the body is determined
+	pub body_length: HttpBodyLength,
+	/// Whether the connection can be used for further requests after this response
+	pub keep_alive: bool,
+}
+
+impl<'a> HttpResponseHead<'a> {
+	/// Parse the head of a response from the beginning of `data`.
+	///
+	/// Returns `Ok(None)` if `data` does not contain the complete head yet.
+	pub fn parse(data: &'a [u8]) -> Result<Option<Self>, HttpProtocolError> {
+		let Some(len) = head_len(data) else {
+			return Ok(None);
+		};
+		let head =
+			core::str::from_utf8(&data[..len - 2]).map_err(|_| HttpProtocolError::Malformed)?;
+		let (status_line, headers) = head.split_once("\r\n").unwrap_or((head, ""));
+
+		let mut parts = status_line.splitn(3, ' ');
+		let version = parts.next().unwrap_or_default();
+		let status: u16 = parts
+			.next()
+			.filter(|status| status.len() == 3)
+			.and_then(|status| status.parse().ok())
+			.ok_or(HttpProtocolError::Malformed)?;
+		let http_10 = match version {
+			"HTTP/1.1" => false,
+			"HTTP/1.0" => true,
+			_ => return Err(HttpProtocolError::Malformed),
+		};
+
+		let framing = Framing::parse(headers)?;
+		let body_length = match status {
+			100..=199 | 204 | 304 => HttpBodyLength::Fixed(0),
+			_ => framing.body_length.unwrap_or(HttpBodyLength::UntilClose),
+		};
+		let keep_alive = match framing.connection {
+			Some(keep_alive) => keep_alive,
+			None => !http_10,
+		} && body_length != HttpBodyLength::UntilClose;
+
+		Ok(Some(HttpResponseHead {
+			len,
+			status,
+			headers,
+			body_length,
+			keep_alive,
+		}))
+	}
+}
+
+/// Find the value of the first header field named `name` in `headers`.
+pub(crate) fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+	headers
+		.split("\r\n")
+		.filter_map(|line| line.split_once(':'))
+		.find(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+		.map(|(_, value)| value.trim())
+}
+
+/// Find the length of an HTTP head, including the terminating empty line.
+pub(crate) fn head_len(data: &[u8]) -> Option<usize> {
+	data.windows(4)
+		.position(|window| window == b"\r\n\r\n")
+		.map(|position| position + 4)
+}
+
+/// Information on message framing and connection handling found in the header fields
+pub(crate) struct Framing {
+	/// The body length, if given explicitly
+	pub body_length: Option<HttpBodyLength>,
+	/// The explicit wish to keep the connection alive or close it, if given
+	pub connection: Option<bool>,
+}
+
+impl Framing {
+	pub fn parse(headers: &str) -> Result<Self, HttpProtocolError> {
+		let mut framing = Framing {
+			body_length: None,
+			connection: None,
+		};
+		for line in headers.split("\r\n").filter(|line| !line.is_empty()) {
+			let (name, value) = line.split_once(':').ok_or(HttpProtocolError::Malformed)?;
+			let value = value.trim();
+			if name.eq_ignore_ascii_case("Transfer-Encoding") {
+				let last = value.rsplit(',').next().unwrap_or_default().trim();
+				if !last.eq_ignore_ascii_case("chunked") {
+					return Err(HttpProtocolError::Malformed);
+				}
+				framing.body_length = Some(HttpBodyLength::Chunked);
+			} else if name.eq_ignore_ascii_case("Content-Length")
+				&& framing.body_length != Some(HttpBodyLength::Chunked)
+			{
+				let len = value.parse().map_err(|_| HttpProtocolError::Malformed)?;
+				framing.body_length = Some(HttpBodyLength::Fixed(len));
+			} else if name.eq_ignore_ascii_case("Connection") {
+				for option in value.split(',').map(str::trim) {
+					if option.eq_ignore_ascii_case("close") {
+						framing.connection = Some(false);
+					} else if option.eq_ignore_ascii_case("keep-alive") {
+						framing.connection.get_or_insert(true);
+					}
+				}
+			}
+		}
+		Ok(framing)
+	}
+}
+
+/// Position of a [`ChunkedDecoder`] within the chunked body
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ChunkState {
+	/// In the hexadecimal chunk size; the flag tells whether any digit was seen.
+	Size(bool),
+	/// In the chunk extensions after the size, up to the end of the line
+	Extension,
+	/// In the chunk data, with the given number of bytes remaining
+	Data(usize),
+	/// At the carriage return after the chunk data
+	DataCr,
+	/// At the line feed after the chunk data
+	DataLf,
+	/// In the trailer section; the flag tells whether the current line is empty so far.
+	Trailer(bool),
+	/// After the last chunk and trailer section
+	Done,
+}
+
+/// Decoder for bodies in the chunked transfer coding, working in place.
+///
+/// The decoder can be fed any amount of raw data at a time, and keeps its state in between.
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkedDecoder {
+	state: ChunkState,
+	size: usize,
+}
+
+impl Default for ChunkedDecoder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl ChunkedDecoder {
+	/// Create a decoder positioned at the beginning of a chunked body.
+	pub const fn new() -> Self {
+		ChunkedDecoder {
+			state: ChunkState::Size(false),
+			size: 0,
+		}
+	}
+
+	/// Whether the end of the body has been reached.
+	pub fn is_done(&self) -> bool {
+		self.state == ChunkState::Done
+	}
+
+	/// Decode the raw data in `buffer[*input..end]`, appending the decoded data in place at
+	/// `buffer[*output..]`.
+	///
+	/// `*output` must not be larger than `*input`. Both are advanced past the data that was
+	/// written and consumed, respectively; decoding stops at the end of the body, leaving any
+	/// data following it unconsumed. Returns whether the end of the body has been reached.
+	pub fn decode(
+		&mut self,
+		buffer: &mut [u8],
+		output: &mut usize,
+		input: &mut usize,
+		end: usize,
+	) -> Result<bool, HttpProtocolError> {
+		while *input < end && !self.is_done() {
+			if let ChunkState::Data(remaining) = self.state {
+				let len = remaining.min(end - *input);
+				buffer.copy_within(*input..*input + len, *output);
+				*input += len;
+				*output += len;
+				self.state = match remaining - len {
+					0 => ChunkState::DataCr,
+					remaining => ChunkState::Data(remaining),
+				};
+				continue;
+			}
+
+			let byte = buffer[*input];
+			*input += 1;
+			self.state = match (self.state, byte) {
+				(ChunkState::Size(_), b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') => {
+					let digit = (byte as char).to_digit(16).unwrap_or_default() as usize;
+					self.size = self
+						.size
+						.checked_mul(16)
+						.and_then(|size| size.checked_add(digit))
+						.ok_or(HttpProtocolError::Malformed)?;
+					ChunkState::Size(true)
+				}
+				(ChunkState::Size(true), b';' | b' ' | b'\t' | b'\r') => ChunkState::Extension,
+				(ChunkState::Size(true), b'\n') | (ChunkState::Extension, b'\n') => {
+					match core::mem::take(&mut self.size) {
+						0 => ChunkState::Trailer(true),
+						size => ChunkState::Data(size),
+					}
+				}
+				(ChunkState::Extension, _) => ChunkState::Extension,
+				(ChunkState::DataCr, b'\r') => ChunkState::DataLf,
+				(ChunkState::DataCr, b'\n') | (ChunkState::DataLf, b'\n') => {
+					ChunkState::Size(false)
+				}
+				(ChunkState::Trailer(empty), b'\n') => match empty {
+					true => ChunkState::Done,
+					false => ChunkState::Trailer(true),
+				},
+				(ChunkState::Trailer(empty), b'\r') => ChunkState::Trailer(empty),
+				(ChunkState::Trailer(_), _) => ChunkState::Trailer(false),
+				_ => return Err(HttpProtocolError::Malformed),
+			};
+		}
+		Ok(self.is_done())
+	}
+}
+
+/// A complete HTTP response, as returned by the HTTP client.
+///
+/// The head and body are stored in the buffer passed in with the request.
+#[derive(Copy, Clone, Debug)]
+pub struct HttpResponse<'b> {
+	/// The status code
+	pub status: u16,
+	/// The header fields, one per line
+	pub headers: &'b str,
+	/// The body, with any transfer coding removed
+	pub body: &'b [u8],
+}
+
+impl<'b> HttpResponse<'b> {
+	/// Look up the value of a header field by its case-insensitive name.
+	///
+	/// If the field occurs multiple times, the first value is returned.
+	pub fn header(&self, name: &str) -> Option<&'b str> {
+		header(self.headers, name)
+	}
+}
+
+impl<'b> HttpResponse<'b> {
+	/// Evaluate the response for a redirect, given the method and URL of the request.
+	///
+	/// Returns `None` if the response is not a redirect, or its location is neither an absolute
+	/// `http://` URL nor an absolute path, or is longer than 256 bytes. A `303 See Other`, and a
+	/// `301` or `302` in response to a `POST`, turn the method into `GET`.
+	pub fn redirect(&self, method: HttpMethod, url: &HttpUrl<'_>) -> Option<HttpRedirect> {
+		let method = match (self.status, method) {
+			(303, _) | (301 | 302, HttpMethod::Post) => HttpMethod::Get,
+			(301 | 302 | 307 | 308, method) => method,
+			_ => return None,
+		};
+		let location = self.header("Location")?;
+
+		let mut redirect = HttpRedirect {
+			method,
+			buffer: [0; 256],
+			len: 0,
+		};
+		let mut out = HttpWriter::new(&mut redirect.buffer);
+		if HttpUrl::parse(location).is_ok() {
+			out.write_str(location).ok()?;
+		} else if location.starts_with('/') {
+			write!(out, "http://{}:{}{}", url.host, url.port, location).ok()?;
+		} else {
+			return None;
+		}
+		redirect.len = out.len;
+		Some(redirect)
+	}
+}
+
+/// A redirect to be followed, see [`HttpResponse::redirect`].
+#[derive(Clone, Debug)]
+pub struct HttpRedirect {
+	/// The method to use for the redirected request
+	pub method: HttpMethod,
+	buffer: [u8; 256],
+	len: usize,
+}
+
+impl HttpRedirect {
+	/// The absolute URL to request next.
+	pub fn location(&self) -> &str {
+		// Only ever filled from `&str`s.
+		core::str::from_utf8(&self.buffer[..self.len]).unwrap_or_default()
+	}
+}
+
+/// Information kept by an [`HttpResponseReader`] once the head is parsed
+#[derive(Copy, Clone, Debug)]
+struct ReceivedHead {
+	status: u16,
+	len: usize,
+	headers_start: usize,
+	body_length: HttpBodyLength,
+	keep_alive: bool,
+}
+
+/// Receives an HTTP/1.1 response into a fixed buffer, independent of the transport.
+///
+/// The caller alternates between reading from the connection into
+/// [`.spare()`](HttpResponseReader::spare) and reporting the number of bytes read to
+/// [`.advance()`](HttpResponseReader::advance), always with the same buffer, until the response
+/// is complete. Informational (`1xx`) responses are skipped, and chunked bodies are decoded in
+/// place, so the complete head and decoded body have to fit into the buffer.
+///
+/// ```
+/// use embedded_nal::{HttpProtocolError, HttpResponseReader};
+///
+/// let mut buffer = [0; 64];
+/// let mut reader = HttpResponseReader::new();
+/// for part in [&b"HTTP/1.1 200 OK\r\nContent-"[..], b"Length: 2\r\n\r\nok"] {
+///     let spare = reader.spare(&mut buffer)?;
+///     spare[..part.len()].copy_from_slice(part);
+///     reader.advance(&mut buffer, part.len())?;
+/// }
+/// assert_eq!(reader.response(&buffer).unwrap().body, b"ok");
+///
+/// // A length no buffer could hold
+/// let head = b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\n";
+/// let mut reader = HttpResponseReader::new();
+/// reader.spare(&mut buffer)?[..head.len()].copy_from_slice(head);
+/// let result = reader.advance(&mut buffer, head.len());
+/// assert_eq!(result, Err(HttpProtocolError::Malformed));
+/// # Ok::<(), HttpProtocolError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponseReader {
+	head: Option<ReceivedHead>,
+	filled: usize,
+	decoder: ChunkedDecoder,
+	output: usize,
+	input: usize,
+	body_end: Option<usize>,
+}
+
+impl HttpResponseReader {
+	/// Create a reader for a new response.
+	pub const fn new() -> Self {
+		HttpResponseReader {
+			head: None,
+			filled: 0,
+			decoder: ChunkedDecoder::new(),
+			output: 0,
+			input: 0,
+			body_end: None,
+		}
+	}
+
+	/// Whether the response is complete.
+	pub fn is_complete(&self) -> bool {
+		self.body_end.is_some()
+	}
+
+	/// Whether the connection can be used for further requests once the response is complete.
+	pub fn keep_alive(&self) -> bool {
+		self.head.is_some_and(|head| head.keep_alive)
+	}
+
+	/// The part of `buffer` that data read from the connection is to be stored in next.
+	///
+	/// This never extends past the end of the response if its length is known in advance, and
+	/// is empty once the response is complete.
+	pub fn spare<'a>(&mut self, buffer: &'a mut [u8]) -> Result<&'a mut [u8], HttpProtocolError> {
+		if self.is_complete() {
+			return Ok(&mut []);
+		}
+		let end = match self.head.map(|head| (head.len, head.body_length)) {
+			Some((len, HttpBodyLength::Fixed(body_len))) => len
+				.checked_add(body_len)
+				.ok_or(HttpProtocolError::Malformed)?,
+			Some((_, HttpBodyLength::Chunked)) => {
+				// Reclaim the space of the chunk framing that was already decoded.
+				buffer.copy_within(self.input..self.filled, self.output);
+				self.filled -= self.input - self.output;
+				self.input = self.output;
+				buffer.len()
+			}
+			_ => buffer.len(),
+		};
+		match buffer.get_mut(self.filled..end) {
+			Some(spare) if !spare.is_empty() => Ok(spare),
+			_ => Err(HttpProtocolError::BufferTooSmall),
+		}
+	}
+
+	/// Account for `len` bytes that were stored in [`.spare()`](HttpResponseReader::spare).
+	///
+	/// A `len` of 0 indicates that the connection was closed. Returns whether the response is
+	/// complete.
+	pub fn advance(&mut self, buffer: &mut [u8], len: usize) -> Result<bool, HttpProtocolError> {
+		let closed = len == 0;
+		self.filled += len;
+
+		let head = match self.head {
+			Some(head) => head,
+			None => loop {
+				let Some(head) = HttpResponseHead::parse(&buffer[..self.filled])? else {
+					return match closed {
+						true => Err(HttpProtocolError::Closed),
+						false => Ok(false),
+					};
+				};
+				let head = ReceivedHead {
+					status: head.status,
+					len: head.len,
+					headers_start: head.len - 2 - head.headers.len(),
+					body_length: head.body_length,
+					keep_alive: head.keep_alive,
+				};
+				if head.status < 200 {
+					buffer.copy_within(head.len..self.filled, 0);
+					self.filled -= head.len;
+					continue;
+				}
+				self.head = Some(head);
+				self.output = head.len;
+				self.input = head.len;
+				break head;
+			},
+		};
+
+		self.body_end = match head.body_length {
+			HttpBodyLength::Fixed(body_len) => {
+				let end = head
+					.len
+					.checked_add(body_len)
+					.ok_or(HttpProtocolError::Malformed)?;
+				(self.filled >= end).then_some(end)
+			}
+			HttpBodyLength::UntilClose if closed => Some(self.filled),
+			HttpBodyLength::Chunked => {
+				let done =
+					self.decoder
+						.decode(buffer, &mut self.output, &mut self.input, self.filled)?;
+				done.then_some(self.output)
+			}
+			_ => None,
+		};
+		match (self.body_end, closed) {
+			(None, true) => Err(HttpProtocolError::Closed),
+			(body_end, _) => Ok(body_end.is_some()),
+		}
+	}
+
+	/// The complete response, stored in `buffer`.
+	///
+	/// Returns `None` if the response is not complete yet.
+	pub fn response<'b>(&self, buffer: &'b [u8]) -> Option<HttpResponse<'b>> {
+		let (head, body_end) = self.head.zip(self.body_end)?;
+		Some(HttpResponse {
+			status: head.status,
+			// Validated while parsing the head.
+			headers: core::str::from_utf8(&buffer[head.headers_start..head.len - 2])
+				.unwrap_or_default(),
+			body: &buffer[head.len..body_end],
+		})
+	}
+}
+
+/// Writes formatted text into a byte buffer, as used for composing HTTP messages without
+/// allocation.
+///
+/// Writing fails once the text does not fit, leaving out the part that did not fit.
+///
+/// ```
+/// use embedded_nal::HttpWriter;
+/// use core::fmt::Write;
+///
+/// let mut buffer = [0; 16];
+/// let mut authority = HttpWriter::new(&mut buffer);
+/// write!(authority, "{}:{}", "example.com", 8080)?;
+/// assert_eq!(authority.as_str(), "example.com:8080");
+/// assert!(authority.write_str("/").is_err());
+/// # Ok::<(), core::fmt::Error>(())
+/// ```
+pub struct HttpWriter<'a> {
+	buffer: &'a mut [u8],
+	len: usize,
+}
+
+impl<'a> HttpWriter<'a> {
+	/// Create a writer filling `buffer` from its beginning.
+	pub fn new(buffer: &'a mut [u8]) -> Self {
+		HttpWriter { buffer, len: 0 }
+	}
+
+	/// The text written so far.
+	pub fn as_str(&self) -> &str {
+		// Only ever filled from whole `&str`s.
+		core::str::from_utf8(&self.buffer[..self.len]).unwrap_or_default()
+	}
+}
+
+impl core::fmt::Write for HttpWriter<'_> {
+	fn write_str(&mut self, s: &str) -> core::fmt::Result {
+		let end = self.len + s.len();
+		self.buffer
+			.get_mut(self.len..end)
+			.ok_or(core::fmt::Error)?
+			.copy_from_slice(s.as_bytes());
+		self.len = end;
+		Ok(())
+	}
+}
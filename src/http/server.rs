@@ -0,0 +1,671 @@
+use super::{head_len, header, ChunkedDecoder, Framing, HttpBodyLength, HttpMethod, HttpWriter};
+use crate::{nb, TcpClientStack, TcpFullStack};
+use core::fmt::Write as _;
+use core::ops::Range;
+
+/// A request received by an [`HttpServer`], stored in the buffer of its connection.
+#[derive(Copy, Clone, Debug)]
+pub struct HttpRequest<'b> {
+	/// The request method
+	pub method: HttpMethod,
+	/// The path of the request target, starting with a slash
+	pub path: &'b str,
+	/// The query of the request target, without the question mark
+	pub query: Option<&'b str>,
+	/// The header fields, one per line
+	pub headers: &'b str,
+	/// The body, with any transfer coding removed
+	pub body: &'b [u8],
+}
+
+impl<'b> HttpRequest<'b> {
+	/// Look up the value of a header field by its case-insensitive name.
+	///
+	/// If the field occurs multiple times, the first value is returned.
+	pub fn header(&self, name: &str) -> Option<&'b str> {
+		header(self.headers, name)
+	}
+}
+
+/// A function handling requests routed to it by an [`HttpServer`].
+///
+/// It is passed the server's context, the request and the responder for sending the response.
+pub type HttpHandler<C, S> = fn(
+	&mut C,
+	&HttpRequest<'_>,
+	&mut HttpResponder<'_, S>,
+) -> Result<(), <S as TcpClientStack>::Error>;
+
+/// An entry in the routing table of an [`HttpServer`].
+///
+/// `C` is the context passed to the handlers, `S` the TCP stack of the server.
+pub struct HttpRoute<C, S: TcpClientStack> {
+	/// The method of matching requests
+	pub method: HttpMethod,
+	/// The path of matching requests.
+	///
+	/// A path ending in `*` matches all paths starting with the part before it.
+	pub path: &'static str,
+	/// The function handling matching requests.
+	///
+	/// If it returns without sending a response, a `500 Internal Server Error` is sent in its
+	/// place.
+	pub handler: HttpHandler<C, S>,
+}
+
+impl<C, S: TcpClientStack> HttpRoute<C, S> {
+	fn matches(&self, path: &str) -> bool {
+		match self.path.strip_suffix('*') {
+			Some(prefix) => path.starts_with(prefix),
+			None => path == self.path,
+		}
+	}
+}
+
+/// Progress of an [`HttpResponder`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ResponseState {
+	/// Nothing has been sent yet.
+	Pending,
+	/// The head of a chunked response has been sent.
+	Chunked,
+	/// The response has been sent completely.
+	Complete,
+}
+
+/// Sends the response to a request received by an [`HttpServer`].
+///
+/// Responses are either sent at once with a `Content-Length` through
+/// [`.respond()`](HttpResponder::respond), or piecewise with the chunked transfer coding through
+/// [`.start_chunked()`](HttpResponder::start_chunked) and
+/// [`.write_chunk()`](HttpResponder::write_chunk). Sending blocks (by spinning on
+/// [`nb::Error::WouldBlock`]) until the stack accepted all data.
+pub struct HttpResponder<'r, S: TcpClientStack> {
+	stack: &'r mut S,
+	socket: &'r mut S::TcpSocket,
+	state: ResponseState,
+	keep_alive: bool,
+	/// Whether the request was made with HTTP/1.0, which has no chunked transfer coding
+	http_10: bool,
+}
+
+impl<S: TcpClientStack> HttpResponder<'_, S> {
+	/// Send a complete response with the given status, header fields and body.
+	///
+	/// A `Content-Length` header is added; `headers` must not contain one.
+	///
+	/// # Panics
+	///
+	/// Panics if a response was sent or started before.
+	pub fn respond(
+		&mut self,
+		status: u16,
+		headers: &[(&str, &str)],
+		body: &[u8],
+	) -> Result<(), S::Error> {
+		self.write_head(status, headers, Some(body.len()))?;
+		send_all(self.stack, self.socket, body)?;
+		self.state = ResponseState::Complete;
+		Ok(())
+	}
+
+	/// Send the head of a response with the given status and header fields, with a body in the
+	/// chunked transfer coding that is sent through [`.write_chunk()`](HttpResponder::write_chunk).
+	///
+	/// The body is terminated once the handler returns. HTTP/1.0 clients do not support the
+	/// chunked transfer coding; they are sent the body as is, and the connection is closed
+	/// after it.
+	///
+	/// # Panics
+	///
+	/// Panics if a response was sent or started before.
+	pub fn start_chunked(&mut self, status: u16, headers: &[(&str, &str)]) -> Result<(), S::Error> {
+		// Without chunks, only closing the connection tells where the body ends.
+		self.keep_alive &= !self.http_10;
+		self.write_head(status, headers, None)?;
+		self.state = ResponseState::Chunked;
+		Ok(())
+	}
+
+	/// Send a piece of the body of a response started through
+	/// [`.start_chunked()`](HttpResponder::start_chunked).
+	///
+	/// # Panics
+	///
+	/// Panics if no chunked response was started.
+	pub fn write_chunk(&mut self, data: &[u8]) -> Result<(), S::Error> {
+		assert!(
+			self.state == ResponseState::Chunked,
+			"no chunked response was started"
+		);
+		if self.http_10 {
+			return send_all(self.stack, self.socket, data);
+		}
+		if data.is_empty() {
+			// An empty chunk would terminate the body.
+			return Ok(());
+		}
+		let mut size = [0; 2 * core::mem::size_of::<usize>() + 2];
+		let mut out = HttpWriter::new(&mut size);
+		write!(out, "{:x}\r\n", data.len()).ok();
+		let len = out.len;
+		send_all(self.stack, self.socket, &size[..len])?;
+		send_all(self.stack, self.socket, data)?;
+		send_all(self.stack, self.socket, b"\r\n")
+	}
+
+	/// Complete the response after the handler returned.
+	fn finish(&mut self) -> Result<(), S::Error> {
+		match self.state {
+			ResponseState::Pending => self.respond(500, &[], &[]),
+			ResponseState::Chunked => {
+				if !self.http_10 {
+					send_all(self.stack, self.socket, b"0\r\n\r\n")?;
+				}
+				self.state = ResponseState::Complete;
+				Ok(())
+			}
+			ResponseState::Complete => Ok(()),
+		}
+	}
+
+	fn write_head(
+		&mut self,
+		status: u16,
+		headers: &[(&str, &str)],
+		content_length: Option<usize>,
+	) -> Result<(), S::Error> {
+		assert!(
+			self.state == ResponseState::Pending,
+			"a response was already sent"
+		);
+		let mut out = Sender {
+			stack: &mut *self.stack,
+			socket: &mut *self.socket,
+			buffer: [0; 128],
+			len: 0,
+			error: None,
+		};
+		let result = (|| {
+			write!(out, "HTTP/1.1 {} {}\r\n", status, reason(status))?;
+			for (name, value) in headers {
+				write!(out, "{name}: {value}\r\n")?;
+			}
+			match content_length {
+				_ if matches!(status, 100..=199 | 204 | 304) => (),
+				Some(len) => write!(out, "Content-Length: {len}\r\n")?,
+				None if self.http_10 => (),
+				None => out.write_str("Transfer-Encoding: chunked\r\n")?,
+			}
+			if !self.keep_alive {
+				out.write_str("Connection: close\r\n")?;
+			}
+			out.write_str("\r\n")?;
+			out.flush()
+		})();
+		match (result, out.error) {
+			(Err(_), Some(e)) => Err(e),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Formatted writes to a socket, staged in a small buffer
+struct Sender<'a, S: TcpClientStack> {
+	stack: &'a mut S,
+	socket: &'a mut S::TcpSocket,
+	buffer: [u8; 128],
+	len: usize,
+	error: Option<S::Error>,
+}
+
+impl<S: TcpClientStack> Sender<'_, S> {
+	fn flush(&mut self) -> core::fmt::Result {
+		let result = send_all(self.stack, self.socket, &self.buffer[..self.len]);
+		self.len = 0;
+		result.map_err(|e| {
+			self.error = Some(e);
+			core::fmt::Error
+		})
+	}
+}
+
+impl<S: TcpClientStack> core::fmt::Write for Sender<'_, S> {
+	fn write_str(&mut self, mut s: &str) -> core::fmt::Result {
+		while !s.is_empty() {
+			if self.len == self.buffer.len() {
+				self.flush()?;
+			}
+			let len = s.len().min(self.buffer.len() - self.len);
+			self.buffer[self.len..][..len].copy_from_slice(&s.as_bytes()[..len]);
+			self.len += len;
+			s = &s[len..];
+		}
+		Ok(())
+	}
+}
+
+fn send_all<S: TcpClientStack>(
+	stack: &mut S,
+	socket: &mut S::TcpSocket,
+	mut data: &[u8],
+) -> Result<(), S::Error> {
+	while !data.is_empty() {
+		let len = nb::block!(stack.send(socket, data))?;
+		data = &data[len..];
+	}
+	Ok(())
+}
+
+/// The reason phrase for common status codes
+fn reason(status: u16) -> &'static str {
+	match status {
+		100 => "Continue",
+		200 => "OK",
+		201 => "Created",
+		202 => "Accepted",
+		204 => "No Content",
+		301 => "Moved Permanently",
+		302 => "Found",
+		303 => "See Other",
+		304 => "Not Modified",
+		307 => "Temporary Redirect",
+		308 => "Permanent Redirect",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		403 => "Forbidden",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		409 => "Conflict",
+		413 => "Content Too Large",
+		415 => "Unsupported Media Type",
+		431 => "Request Header Fields Too Large",
+		500 => "Internal Server Error",
+		501 => "Not Implemented",
+		503 => "Service Unavailable",
+		_ => "",
+	}
+}
+
+/// The parsed head of a request, as positions in the connection's buffer
+#[derive(Copy, Clone, Debug)]
+struct RequestHead {
+	/// The method, `None` if not supported
+	method: Option<HttpMethod>,
+	target: (usize, usize),
+	headers: (usize, usize),
+	len: usize,
+	body_length: HttpBodyLength,
+	keep_alive: bool,
+	http_10: bool,
+}
+
+impl RequestHead {
+	/// Parse the head of a request from the beginning of `data`, returning `Ok(None)` if it is
+	/// not complete yet.
+	fn parse(data: &[u8]) -> Result<Option<Self>, ()> {
+		let Some(len) = head_len(data) else {
+			return Ok(None);
+		};
+		let head = core::str::from_utf8(&data[..len - 2]).map_err(|_| ())?;
+		let (request_line, headers) = head.split_once("\r\n").unwrap_or((head, ""));
+
+		let mut parts = request_line.split(' ');
+		let (Some(method), Some(target), Some(version), None) =
+			(parts.next(), parts.next(), parts.next(), parts.next())
+		else {
+			return Err(());
+		};
+		let http_10 = match version {
+			"HTTP/1.1" => false,
+			"HTTP/1.0" => true,
+			_ => return Err(()),
+		};
+		if !target.starts_with('/') {
+			return Err(());
+		}
+
+		let framing = Framing::parse(headers).map_err(|_| ())?;
+		let target_start = method.len() + 1;
+		Ok(Some(RequestHead {
+			method: HttpMethod::from_name(method),
+			target: (target_start, target_start + target.len()),
+			headers: (len - 2 - headers.len(), len - 2),
+			len,
+			body_length: framing.body_length.unwrap_or(HttpBodyLength::Fixed(0)),
+			keep_alive: framing.connection.unwrap_or(!http_10),
+			http_10,
+		}))
+	}
+}
+
+/// A request whose head was received, and whose body is being received
+#[derive(Clone, Debug)]
+struct PendingRequest {
+	head: RequestHead,
+	decoder: ChunkedDecoder,
+	output: usize,
+	input: usize,
+}
+
+/// An accepted connection of an [`HttpServer`]
+struct Connection<T, const B: usize> {
+	socket: T,
+	buffer: [u8; B],
+	filled: usize,
+	request: Option<PendingRequest>,
+}
+
+impl<T, const B: usize> Connection<T, B> {
+	/// Check whether a complete request was received.
+	///
+	/// Returns the location of the body and the number of bytes taken up by the request, or the
+	/// status code of an error response if the request can not be handled.
+	fn poll_request(&mut self) -> Result<Option<(Range<usize>, usize)>, u16> {
+		let request = match &mut self.request {
+			Some(request) => request,
+			None => {
+				let head = match RequestHead::parse(&self.buffer[..self.filled]) {
+					Ok(Some(head)) => head,
+					Ok(None) if self.filled == B => return Err(431),
+					Ok(None) => return Ok(None),
+					Err(()) => return Err(400),
+				};
+				if matches!(head.body_length, HttpBodyLength::Fixed(len)
+					if head.len.checked_add(len).filter(|end| *end <= B).is_none())
+				{
+					return Err(413);
+				}
+				self.request.insert(PendingRequest {
+					head,
+					decoder: ChunkedDecoder::new(),
+					output: head.len,
+					input: head.len,
+				})
+			}
+		};
+
+		let start = request.head.len;
+		match request.head.body_length {
+			HttpBodyLength::Fixed(len) => {
+				// Checked to fit into the buffer when the head was received.
+				let end = start + len;
+				Ok((self.filled >= end).then_some((start..end, end)))
+			}
+			HttpBodyLength::Chunked => {
+				let done = request
+					.decoder
+					.decode(
+						&mut self.buffer,
+						&mut request.output,
+						&mut request.input,
+						self.filled,
+					)
+					.map_err(|_| 400_u16)?;
+				if done {
+					return Ok(Some((start..request.output, request.input)));
+				}
+				// Reclaim the space of the chunk framing that was already decoded.
+				self.buffer
+					.copy_within(request.input..self.filled, request.output);
+				self.filled -= request.input - request.output;
+				request.input = request.output;
+				if self.filled == B {
+					Err(413)
+				} else {
+					Ok(None)
+				}
+			}
+			_ => Ok(None),
+		}
+	}
+}
+
+/// A minimal HTTP/1.1 server on top of any [`TcpFullStack`].
+///
+/// The server listens on a port, and serves up to `N` accepted connections at a time, each with
+/// a buffer of `B` bytes that has to hold the complete head and body of a request. Requests are
+/// routed by method and path to the handler functions given in a table of [`HttpRoute`]s;
+/// requests for paths that are not in the table are answered with `404 Not Found`, and with
+/// `405 Method Not Allowed` if only the method does not match. Connections are kept open if the
+/// client allows it.
+///
+/// The server does not block on incoming data; it is driven by calling
+/// [`.poll()`](HttpServer::poll) repeatedly.
+///
+/// ```
+/// use embedded_nal::{HttpResponder, HttpRequest, HttpRoute, HttpServer, HttpMethod};
+/// use embedded_nal::{TcpClientStack, TcpFullStack, nb};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// # struct Stack { accepted: bool, request: &'static [u8], sent: [u8; 256], sent_len: usize }
+/// # #[derive(Debug)] struct Error;
+/// # impl embedded_nal::TcpError for Error {
+/// #   fn kind(&self) -> embedded_nal::TcpErrorKind { embedded_nal::TcpErrorKind::Other }
+/// # }
+/// # fn addr() -> SocketAddr { SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80) }
+/// # impl TcpClientStack for Stack {
+/// #   type TcpSocket = ();
+/// #   type Error = Error;
+/// #   fn socket(&mut self) -> Result<(), Error> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Error> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Error> {
+/// #     self.sent[self.sent_len..][..buffer.len()].copy_from_slice(buffer);
+/// #     self.sent_len += buffer.len();
+/// #     Ok(buffer.len())
+/// #   }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<usize, Error> {
+/// #     let len = buffer.len().min(self.request.len());
+/// #     buffer[..len].copy_from_slice(&self.request[..len]);
+/// #     self.request = &self.request[len..];
+/// #     match len { 0 => Err(nb::Error::WouldBlock), len => Ok(len) }
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), Error> { Ok(()) }
+/// #   fn local_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// #   fn peer_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// # }
+/// # impl TcpFullStack for Stack {
+/// #   fn bind(&mut self, _: &mut (), _: u16) -> Result<(), Error> { Ok(()) }
+/// #   fn listen(&mut self, _: &mut ()) -> Result<(), Error> { Ok(()) }
+/// #   fn accept(&mut self, _: &mut ()) -> nb::Result<((), SocketAddr), Error> {
+/// #     match core::mem::replace(&mut self.accepted, true) {
+/// #       false => Ok(((), addr())),
+/// #       true => Err(nb::Error::WouldBlock),
+/// #     }
+/// #   }
+/// # }
+/// # let stack = Stack {
+/// #   accepted: false,
+/// #   request: b"PUT /led HTTP/1.1\r\nHost: device\r\nContent-Length: 2\r\n\r\non",
+/// #   sent: [0; 256],
+/// #   sent_len: 0,
+/// # };
+/// struct Device {
+///     led: bool,
+/// }
+///
+/// fn set_led<S: TcpClientStack>(
+///     device: &mut Device,
+///     request: &HttpRequest<'_>,
+///     responder: &mut HttpResponder<'_, S>,
+/// ) -> Result<(), S::Error> {
+///     match request.body {
+///         b"on" | b"off" => {
+///             device.led = request.body == b"on";
+///             responder.respond(204, &[], &[])
+///         }
+///         _ => responder.respond(400, &[("Content-Type", "text/plain")], b"on or off"),
+///     }
+/// }
+///
+/// let routes = [HttpRoute { method: HttpMethod::Put, path: "/led", handler: set_led }];
+/// let mut device = Device { led: false };
+///
+/// let mut server: HttpServer<_> = HttpServer::new(stack, 80)?;
+/// server.poll(&routes, &mut device)?;
+/// assert!(device.led);
+/// # let stack = server.free();
+/// # assert_eq!(&stack.sent[..stack.sent_len], b"HTTP/1.1 204 No Content\r\n\r\n");
+///
+/// // Bodies that do not fit into the buffer of the connection are refused.
+/// # let stack = Stack {
+/// #   accepted: false,
+/// #   request: b"PUT /led HTTP/1.1\r\nContent-Length: 18446744073709551615\r\n\r\non",
+/// #   sent: [0; 256],
+/// #   sent_len: 0,
+/// # };
+/// let mut server: HttpServer<_> = HttpServer::new(stack, 80)?;
+/// server.poll(&routes, &mut device)?;
+/// # let stack = server.free();
+/// # assert!(stack.sent[..stack.sent_len].starts_with(b"HTTP/1.1 413 Content Too Large\r\n"));
+/// # Ok::<(), Error>(())
+/// ```
+pub struct HttpServer<S: TcpFullStack, const N: usize = 4, const B: usize = 1024> {
+	stack: S,
+	listener: S::TcpSocket,
+	connections: [Option<Connection<S::TcpSocket, B>>; N],
+}
+
+impl<S: TcpFullStack, const N: usize, const B: usize> HttpServer<S, N, B> {
+	/// Create a server listening on `port` of `stack`.
+	pub fn new(mut stack: S, port: u16) -> Result<Self, S::Error> {
+		let mut listener = stack.socket()?;
+		if let Err(e) = stack
+			.bind(&mut listener, port)
+			.and_then(|()| stack.listen(&mut listener))
+		{
+			stack.close(listener).ok();
+			return Err(e);
+		}
+		Ok(HttpServer {
+			stack,
+			listener,
+			connections: core::array::from_fn(|_| None),
+		})
+	}
+
+	/// Accept pending connections while there is room for them, and handle the requests that
+	/// were received completely since the last call.
+	///
+	/// Connections failing or closed by the client are closed and dropped silently; only errors
+	/// of the listening socket are returned.
+	pub fn poll<C>(&mut self, routes: &[HttpRoute<C, S>], context: &mut C) -> Result<(), S::Error> {
+		while let Some(slot) = self.connections.iter_mut().find(|slot| slot.is_none()) {
+			match self.stack.accept(&mut self.listener) {
+				Ok((socket, _)) => {
+					*slot = Some(Connection {
+						socket,
+						buffer: [0; B],
+						filled: 0,
+						request: None,
+					})
+				}
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => return Err(e),
+			}
+		}
+
+		for slot in self.connections.iter_mut() {
+			let keep = match slot {
+				Some(connection) => service(&mut self.stack, connection, routes, context),
+				None => continue,
+			};
+			if !keep {
+				if let Some(connection) = slot.take() {
+					self.stack.close(connection.socket).ok();
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Release the stack, closing the listening socket and all connections.
+	pub fn free(mut self) -> S {
+		for connection in self.connections.iter_mut().filter_map(Option::take) {
+			self.stack.close(connection.socket).ok();
+		}
+		self.stack.close(self.listener).ok();
+		self.stack
+	}
+}
+
+/// Receive on a connection, and handle all requests that are complete.
+///
+/// Returns whether the connection is to be kept open.
+fn service<S: TcpFullStack, C, const B: usize>(
+	stack: &mut S,
+	connection: &mut Connection<S::TcpSocket, B>,
+	routes: &[HttpRoute<C, S>],
+	context: &mut C,
+) -> bool {
+	if connection.filled < B {
+		match stack.receive(
+			&mut connection.socket,
+			&mut connection.buffer[connection.filled..],
+		) {
+			Ok(0) | Err(nb::Error::Other(_)) => return false,
+			Ok(len) => connection.filled += len,
+			Err(nb::Error::WouldBlock) => (),
+		}
+	}
+
+	loop {
+		let (body, len) = match connection.poll_request() {
+			Ok(Some(request)) => request,
+			Ok(None) => return true,
+			Err(status) => {
+				let mut responder = HttpResponder {
+					stack: &mut *stack,
+					socket: &mut connection.socket,
+					state: ResponseState::Pending,
+					keep_alive: false,
+					http_10: false,
+				};
+				responder.respond(status, &[], &[]).ok();
+				return false;
+			}
+		};
+		let Some(request) = connection.request.take() else {
+			return false;
+		};
+		let head = request.head;
+
+		// Validated while parsing the head.
+		let text = core::str::from_utf8(&connection.buffer[..head.len]).unwrap_or_default();
+		let target = &text[head.target.0..head.target.1];
+		let (path, query) = match target.split_once('?') {
+			Some((path, query)) => (path, Some(query)),
+			None => (target, None),
+		};
+		let mut responder = HttpResponder {
+			stack: &mut *stack,
+			socket: &mut connection.socket,
+			state: ResponseState::Pending,
+			keep_alive: head.keep_alive,
+			http_10: head.http_10,
+		};
+		let result = match head.method {
+			Some(method) => {
+				let request = HttpRequest {
+					method,
+					path,
+					query,
+					headers: &text[head.headers.0..head.headers.1],
+					body: &connection.buffer[body],
+				};
+				let mut matching = routes.iter().filter(|route| route.matches(path));
+				match matching.clone().find(|route| route.method == method) {
+					Some(route) => (route.handler)(context, &request, &mut responder),
+					None if matching.next().is_some() => responder.respond(405, &[], &[]),
+					None => responder.respond(404, &[], &[]),
+				}
+			}
+			None => responder.respond(501, &[], &[]),
+		};
+		if result.and_then(|()| responder.finish()).is_err() || !responder.keep_alive {
+			return false;
+		}
+
+		connection.buffer.copy_within(len..connection.filled, 0);
+		connection.filled -= len;
+	}
+}
@@ -0,0 +1,179 @@
+use core::net::IpAddr;
+use core::time::Duration;
+
+/// An ICMP echo reply, as returned by [`IcmpEcho::echo`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EchoReply {
+	/// Length of the payload of the reply
+	///
+	/// If it exceeds the length of the buffer passed in, the remaining bytes were discarded.
+	pub len: usize,
+	/// Time between sending the request and receiving the reply
+	pub rtt: Duration,
+	/// IPv4 time to live or IPv6 hop limit of the reply, if the stack can report it
+	pub hop_limit: Option<u8>,
+}
+
+/// This trait is implemented by network stacks that can send ICMP (IPv4) and ICMPv6 echo requests
+/// and receive the corresponding replies, as used by the `ping` utility.
+pub trait IcmpEcho {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Send an echo request to `remote` and wait for the matching reply.
+	///
+	/// The request carries the given `identifier`, `sequence` number and `payload`; the ICMP or
+	/// ICMPv6 variant is picked from the address family of `remote`. The payload of the reply is
+	/// stored at the beginning of `buffer`.
+	///
+	/// The first call sends the request. If the reply could not be received immediately, this
+	/// function should return [`nb::Error::WouldBlock`], and be called again with the same
+	/// arguments until the reply arrives. Once `timeout` has passed since the request was sent,
+	/// `Ok(None)` is returned. Calling it with a different identifier or sequence number abandons
+	/// any previous request.
+	fn echo(
+		&mut self,
+		remote: IpAddr,
+		identifier: u16,
+		sequence: u16,
+		payload: &[u8],
+		timeout: Duration,
+		buffer: &mut [u8],
+	) -> nb::Result<Option<EchoReply>, Self::Error>;
+}
+
+impl<T: IcmpEcho> IcmpEcho for &mut T {
+	type Error = T::Error;
+
+	fn echo(
+		&mut self,
+		remote: IpAddr,
+		identifier: u16,
+		sequence: u16,
+		payload: &[u8],
+		timeout: Duration,
+		buffer: &mut [u8],
+	) -> nb::Result<Option<EchoReply>, Self::Error> {
+		T::echo(self, remote, identifier, sequence, payload, timeout, buffer)
+	}
+}
+
+/// Statistics over the echo requests of a ping session.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PingStatistics {
+	/// Number of echo requests sent
+	pub transmitted: u32,
+	/// Number of echo replies received in time
+	pub received: u32,
+	/// Smallest round-trip time of any reply
+	pub min: Option<Duration>,
+	/// Largest round-trip time of any reply
+	pub max: Option<Duration>,
+	/// Sum of the round-trip times of all replies
+	pub total: Duration,
+}
+
+impl PingStatistics {
+	/// Account for a request that was answered after `rtt`.
+	pub fn record_reply(&mut self, rtt: Duration) {
+		self.transmitted += 1;
+		self.received += 1;
+		self.min = Some(self.min.map_or(rtt, |min| min.min(rtt)));
+		self.max = Some(self.max.map_or(rtt, |max| max.max(rtt)));
+		self.total += rtt;
+	}
+
+	/// Account for a request that timed out.
+	pub fn record_loss(&mut self) {
+		self.transmitted += 1;
+	}
+
+	/// Average round-trip time of all replies, if any were received.
+	pub fn avg(&self) -> Option<Duration> {
+		match self.received {
+			0 => None,
+			received => Some(self.total / received),
+		}
+	}
+
+	/// Percentage of requests that were not answered in time.
+	pub fn loss_percent(&self) -> u8 {
+		match self.transmitted {
+			0 => 0,
+			transmitted => (100 - u64::from(self.received) * 100 / u64::from(transmitted)) as u8,
+		}
+	}
+}
+
+/// A series of echo requests to a single host, collecting [`PingStatistics`].
+///
+/// ```
+/// use embedded_nal::{nb, IcmpEcho, Loopback, PingSession};
+/// use core::net::{IpAddr, Ipv4Addr};
+/// use core::time::Duration;
+///
+/// let mut network = Loopback::<1>::new();
+/// let mut session = PingSession::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0x1234, Duration::from_secs(1));
+/// let mut buffer = [0; 32];
+/// for _ in 0..4 {
+///     nb::block!(session.ping(&mut network, b"abcdefgh", &mut buffer))?;
+/// }
+///
+/// let statistics = session.statistics();
+/// assert_eq!(statistics.received, 4);
+/// assert_eq!(statistics.loss_percent(), 0);
+/// # Ok::<(), embedded_nal::LoopbackError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct PingSession {
+	remote: IpAddr,
+	identifier: u16,
+	sequence: u16,
+	timeout: Duration,
+	statistics: PingStatistics,
+}
+
+impl PingSession {
+	/// Start a session pinging `remote`, with requests carrying the given `identifier` and timing
+	/// out after `timeout`.
+	pub fn new(remote: IpAddr, identifier: u16, timeout: Duration) -> Self {
+		PingSession {
+			remote,
+			identifier,
+			sequence: 0,
+			timeout,
+			statistics: PingStatistics::default(),
+		}
+	}
+
+	/// Send the next echo request, and wait for its reply.
+	///
+	/// This follows the calling convention of [`IcmpEcho::echo`]; once it returns `Ok`, the result
+	/// is accounted for in the statistics, and the next call sends a new request.
+	pub fn ping<S: IcmpEcho + ?Sized>(
+		&mut self,
+		stack: &mut S,
+		payload: &[u8],
+		buffer: &mut [u8],
+	) -> nb::Result<Option<EchoReply>, S::Error> {
+		let reply = stack.echo(
+			self.remote,
+			self.identifier,
+			self.sequence,
+			payload,
+			self.timeout,
+			buffer,
+		)?;
+		self.sequence = self.sequence.wrapping_add(1);
+		match reply {
+			Some(reply) => self.statistics.record_reply(reply.rtt),
+			None => self.statistics.record_loss(),
+		}
+		Ok(reply)
+	}
+
+	/// The statistics of all requests completed so far.
+	pub fn statistics(&self) -> &PingStatistics {
+		&self.statistics
+	}
+}
@@ -0,0 +1,77 @@
+use core::net::IpAddr;
+
+/// Properties of a network interface, as reported by [`NetworkInterfaces::interface`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceInfo {
+	/// Length of the interface name stored at the beginning of the provided buffer
+	pub name_len: usize,
+	/// Maximum transmission unit of the link, in bytes
+	pub mtu: usize,
+	/// Whether the link is currently up
+	pub link_up: bool,
+}
+
+/// An IP address assigned to a network interface, along with the prefix length of its subnet.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceAddress {
+	/// The assigned address
+	pub addr: IpAddr,
+	/// Number of leading bits of `addr` that identify the subnet
+	pub prefix_len: u8,
+}
+
+/// This trait is implemented by network stacks that can enumerate their local interfaces and the
+/// addresses assigned to them.
+///
+/// Interfaces are identified by their index, which uses the same numbering as the zone identifier
+/// of IPv6 addresses. Together, this allows picking a suitable source address when responding to
+/// a request, or advertising all addresses under which the device is reachable.
+pub trait NetworkInterfaces {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// List the indices of all network interfaces.
+	///
+	/// The indices are stored at the beginning of `result`. The total number of interfaces is
+	/// returned; if it exceeds the length of `result`, the remaining indices are not stored.
+	fn interfaces(&mut self, result: &mut [u32]) -> Result<usize, Self::Error>;
+
+	/// Query the properties of the network interface with the given index.
+	///
+	/// The interface name is stored at the beginning of `name`, its length is returned as part of
+	/// the result.
+	///
+	/// If the buffer is too small to hold the name, or there is no interface with that index, an
+	/// error should be returned.
+	fn interface(&mut self, index: u32, name: &mut [u8]) -> Result<InterfaceInfo, Self::Error>;
+
+	/// List the IPv4 and IPv6 addresses assigned to the network interface with the given index.
+	///
+	/// The addresses are stored at the beginning of `result`. The total number of addresses is
+	/// returned; if it exceeds the length of `result`, the remaining addresses are not stored.
+	fn addresses(
+		&mut self,
+		index: u32,
+		result: &mut [InterfaceAddress],
+	) -> Result<usize, Self::Error>;
+}
+
+impl<T: NetworkInterfaces> NetworkInterfaces for &mut T {
+	type Error = T::Error;
+
+	fn interfaces(&mut self, result: &mut [u32]) -> Result<usize, Self::Error> {
+		T::interfaces(self, result)
+	}
+
+	fn interface(&mut self, index: u32, name: &mut [u8]) -> Result<InterfaceInfo, Self::Error> {
+		T::interface(self, index, name)
+	}
+
+	fn addresses(
+		&mut self,
+		index: u32,
+		result: &mut [InterfaceAddress],
+	) -> Result<usize, Self::Error> {
+		T::addresses(self, index, result)
+	}
+}
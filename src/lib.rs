@@ -3,13 +3,77 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
+mod clock;
+mod config;
+mod dhcp;
 mod dns;
+mod dns_message;
+mod dns_records;
+mod dns_sd;
+mod dns_server;
+mod http;
+mod icmp;
+mod interface;
+mod loopback;
+mod mdns;
+mod sntp;
+mod socks5;
 mod stack;
+mod status;
+mod syslog;
+mod tftp;
+#[cfg(feature = "tls")]
+mod tls;
 
 pub use nb;
 
-pub use dns::{AddrType, Dns};
+pub use clock::Clock;
+pub use config::NetworkConfig;
+pub use dhcp::{
+	DhcpClient, DhcpEvent, DhcpLease, DhcpLeases, DhcpMessage, DhcpMessageError, DhcpMessageType,
+	DhcpMessageWriter, DhcpOption, DhcpServer, DhcpServerConfig, DhcpServerLease, DHCP_CLIENT_PORT,
+	DHCP_MESSAGE_LEN, DHCP_SERVER_PORT,
+};
+pub use dns::{AddrType, Dns, DnsRecords};
+pub use dns_message::{
+	DnsMessage, DnsMessageError, DnsMessageWriter, DnsName, DnsQuestion, DnsRecord, DnsRecordData,
+	DnsRecordType, DnsSection,
+};
+pub use dns_records::{connect_srv, SrvConnectError, SrvRecord, SrvRecords, TxtRecord, TxtRecords};
+pub use dns_sd::{DnsSdBrowser, DnsSdInstance, DnsSdService};
+pub use dns_server::{DnsServer, DnsZone, DnsZoneEntry, DNS_MESSAGE_LEN, DNS_PORT};
+pub use http::{
+	HttpClient, HttpError, HttpHandler, HttpMethod, HttpProtocolError, HttpRedirect, HttpRequest,
+	HttpRequestHead, HttpResponder, HttpResponse, HttpResponseReader, HttpRoute, HttpServer,
+	HttpUrl, HttpWriter, HTTP_MAX_REDIRECTS,
+};
+pub use icmp::{EchoReply, IcmpEcho, PingSession, PingStatistics};
+pub use interface::{InterfaceAddress, InterfaceInfo, NetworkInterfaces};
+pub use loopback::{Loopback, LoopbackError};
+pub use mdns::{
+	MdnsError, MdnsResolver, MdnsResponder, MDNS_IPV4_GROUP, MDNS_IPV6_GROUP, MDNS_PORT,
+};
+pub use sntp::{
+	SntpClient, SntpClock, SntpError, SntpLeap, SntpRequest, SntpResponse, SntpTimestamp,
+	SNTP_MESSAGE_LEN, SNTP_PORT,
+};
+pub use socks5::{Socks5Credentials, Socks5Error, Socks5Socket, Socks5Stack};
 pub use stack::{
-	SharableStack, SharedStack, TcpClientStack, TcpError, TcpErrorKind, TcpFullStack,
-	UdpClientStack, UdpFullStack,
+	gather_datagram, RawIpStack, SharableStack, SharedStack, TcpClientStack, TcpError,
+	TcpErrorKind, TcpFullStack, UdpClientStack, UdpFullStack, UdpMulticastStack, UdpTimestampStack,
+	UdpVectoredError, UDP_VECTORED_SEND_LEN,
+};
+pub use status::{IpConfig, NetworkStatus};
+#[cfg(feature = "log")]
+pub use syslog::SyslogLogger;
+pub use syslog::{
+	SyslogClient, SyslogFacility, SyslogMessage, SyslogQueue, SyslogSeverity, SyslogStructuredData,
+	SyslogTcpClient, SYSLOG_MESSAGE_LEN, SYSLOG_OCTET_COUNT_LEN, SYSLOG_PORT, SYSLOG_TCP_PORT,
+};
+pub use tftp::{
+	TftpClient, TftpError, TftpErrorCode, TftpFiles, TftpServer, TftpSink, TftpSource, TFTP_PORT,
+};
+#[cfg(feature = "tls")]
+pub use tls::{
+	ClientCertificate, Psk, TlsClient, TlsConfig, TlsError, TlsSession, TlsSocket, TlsStack,
 };
@@ -0,0 +1,191 @@
+use crate::{nb, EchoReply, IcmpEcho, IpConfig, NetworkConfig, NetworkStatus};
+use core::net::IpAddr;
+use core::time::Duration;
+
+/// Errors returned by [`Loopback`].
+#[non_exhaustive]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LoopbackError {
+	/// More entries were given than the loopback network has room for.
+	Capacity,
+	/// The operation is not available on a loopback network.
+	Unsupported,
+}
+
+/// An in-memory loopback network.
+///
+/// It holds up to `N` IP configurations and `N` DNS servers, and is mainly useful for testing
+/// code written against the configuration traits without any hardware. Its link is always up,
+/// and it is configured as soon as a static configuration is set, which is reported as a
+/// configuration change along with setting DNS servers. As there is no DHCP server on a loopback
+/// network, DHCP can not be enabled.
+///
+/// Echo requests to loopback addresses and to configured addresses are answered immediately;
+/// requests to any other address time out immediately, as nothing could ever answer them.
+///
+/// ```
+/// use embedded_nal::{InterfaceAddress, IpConfig, Loopback, NetworkConfig, NetworkStatus};
+/// use core::net::{IpAddr, Ipv4Addr};
+///
+/// let mut network = Loopback::<2>::new();
+/// assert!(network.wait_config_up().is_err());
+///
+/// let address = InterfaceAddress {
+///     addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+///     prefix_len: 24,
+/// };
+/// let gateway = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)));
+/// network.set_static_config(&[IpConfig { address, gateway }])?;
+/// network.wait_config_up()?;
+/// network.wait_config_change()?;
+/// assert!(network.wait_config_change().is_err());
+///
+/// let mut configs = [IpConfig { address, gateway: None }; 2];
+/// assert_eq!(network.ip_config(&mut configs)?, 1);
+/// assert_eq!(configs[0].gateway, gateway);
+///
+/// network.set_dns_servers(&[IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))])?;
+/// network.wait_config_change()?;
+/// # Ok::<(), embedded_nal::nb::Error<embedded_nal::LoopbackError>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Loopback<const N: usize> {
+	configs: [Option<IpConfig>; N],
+	dns_servers: [Option<IpAddr>; N],
+	changed: bool,
+}
+
+impl<const N: usize> Loopback<N> {
+	/// Create a loopback network without any configuration.
+	pub const fn new() -> Self {
+		Loopback {
+			configs: [None; N],
+			dns_servers: [None; N],
+			changed: false,
+		}
+	}
+
+	/// Whether any IP configuration is applied.
+	pub fn is_configured(&self) -> bool {
+		self.configs.iter().any(Option::is_some)
+	}
+
+	/// Whether `addr` is a loopback address or one of the configured addresses.
+	pub fn is_local(&self, addr: IpAddr) -> bool {
+		addr.is_loopback()
+			|| self
+				.configs
+				.iter()
+				.flatten()
+				.any(|config| config.address.addr == addr)
+	}
+}
+
+impl<const N: usize> Default for Loopback<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> NetworkConfig for Loopback<N> {
+	type Error = LoopbackError;
+
+	fn set_static_config(&mut self, configs: &[IpConfig]) -> Result<(), Self::Error> {
+		store(&mut self.configs, configs)?;
+		self.changed = true;
+		Ok(())
+	}
+
+	fn set_dhcp(&mut self, enabled: bool) -> Result<(), Self::Error> {
+		match enabled {
+			true => Err(LoopbackError::Unsupported),
+			false => Ok(()),
+		}
+	}
+
+	fn set_dns_servers(&mut self, servers: &[IpAddr]) -> Result<(), Self::Error> {
+		store(&mut self.dns_servers, servers)?;
+		self.changed = true;
+		Ok(())
+	}
+}
+
+impl<const N: usize> NetworkStatus for Loopback<N> {
+	type Error = LoopbackError;
+
+	fn wait_config_up(&mut self) -> nb::Result<(), Self::Error> {
+		match self.is_configured() {
+			true => Ok(()),
+			false => Err(nb::Error::WouldBlock),
+		}
+	}
+
+	fn wait_link_down(&mut self) -> nb::Result<(), Self::Error> {
+		Err(nb::Error::WouldBlock)
+	}
+
+	fn wait_config_change(&mut self) -> nb::Result<(), Self::Error> {
+		match core::mem::take(&mut self.changed) {
+			true => Ok(()),
+			false => Err(nb::Error::WouldBlock),
+		}
+	}
+
+	fn ip_config(&mut self, result: &mut [IpConfig]) -> Result<usize, Self::Error> {
+		Ok(load(&self.configs, result))
+	}
+
+	fn dns_servers(&mut self, result: &mut [IpAddr]) -> Result<usize, Self::Error> {
+		Ok(load(&self.dns_servers, result))
+	}
+}
+
+impl<const N: usize> IcmpEcho for Loopback<N> {
+	type Error = LoopbackError;
+
+	fn echo(
+		&mut self,
+		remote: IpAddr,
+		_identifier: u16,
+		_sequence: u16,
+		payload: &[u8],
+		_timeout: Duration,
+		buffer: &mut [u8],
+	) -> nb::Result<Option<EchoReply>, Self::Error> {
+		if !self.is_local(remote) {
+			return Ok(None);
+		}
+
+		let copied = payload.len().min(buffer.len());
+		buffer[..copied].copy_from_slice(&payload[..copied]);
+		Ok(Some(EchoReply {
+			len: payload.len(),
+			rtt: Duration::ZERO,
+			hop_limit: None,
+		}))
+	}
+}
+
+/// Replace the entries of `slots` with `items`, leaving the rest empty.
+fn store<T: Copy>(slots: &mut [Option<T>], items: &[T]) -> Result<(), LoopbackError> {
+	if items.len() > slots.len() {
+		return Err(LoopbackError::Capacity);
+	}
+
+	for (i, slot) in slots.iter_mut().enumerate() {
+		*slot = items.get(i).copied();
+	}
+	Ok(())
+}
+
+/// Copy the entries of `slots` to the beginning of `result`, returning their total number.
+fn load<T: Copy>(slots: &[Option<T>], result: &mut [T]) -> usize {
+	let mut count = 0;
+	for item in slots.iter().flatten() {
+		if let Some(out) = result.get_mut(count) {
+			*out = *item;
+		}
+		count += 1;
+	}
+	count
+}
@@ -0,0 +1,1242 @@
+use crate::dns_message::{reverse_name, REVERSE_NAME_LEN};
+use crate::dns_sd::{AdvertisedService, SERVICE_ENUMERATION, SERVICE_TTL};
+use crate::{
+	nb, AddrType, Clock, Dns, DnsMessage, DnsMessageWriter, DnsRecord, DnsRecordData,
+	DnsRecordType, DnsRecords, DnsSdBrowser, DnsSdService, DnsSection, SrvRecord, SrvRecords,
+	TxtRecords, UdpFullStack, UdpMulticastStack,
+};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use core::time::Duration;
+
+/// The well-known mDNS port
+pub const MDNS_PORT: u16 = 5353;
+
+/// The IPv4 multicast group of mDNS
+pub const MDNS_IPV4_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// The IPv6 multicast group of mDNS
+pub const MDNS_IPV6_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+
+/// Size of the buffers messages are sent from and received into
+pub(crate) const MESSAGE_LEN: usize = 512;
+
+/// Bit in the class of a question asking for a unicast response
+pub(crate) const UNICAST_RESPONSE: u16 = 0x8000;
+
+/// Bit in the class of a record telling caches to drop other records of the same name and type
+pub(crate) const CACHE_FLUSH: u16 = 0x8000;
+
+/// Time to live of address records, as recommended in RFC 6762, section 10
+const HOST_TTL: u32 = 120;
+
+/// Longest time to live in responses to legacy unicast queries
+const LEGACY_TTL: u32 = 10;
+
+/// Longest hostname label, with room for the `.local` suffix
+pub(crate) const MAX_HOSTNAME_LEN: usize = 63 + ".local".len();
+
+/// Number of probes sent before claiming a name
+const PROBES: u8 = 3;
+
+/// Number of unsolicited announcements after claiming a name
+const ANNOUNCEMENTS: u8 = 2;
+
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+const RETRANSMIT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Errors returned by [`MdnsResolver`] and [`MdnsResponder`].
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum MdnsError<E> {
+	/// The UDP socket failed.
+	Transport(E),
+	/// The name to resolve is not in the `.local` domain.
+	NotLocal,
+	/// The hostname is empty, too long, or contains dots, or a service has an invalid name or
+	/// too long a TXT record.
+	InvalidName,
+	/// No response arrived in time.
+	Timeout,
+	/// The resolved name does not fit into the buffer.
+	BufferTooSmall,
+	/// The table of advertised services is full.
+	TableFull,
+}
+
+/// Whether `name` is in the `.local` domain, ignoring a trailing dot.
+fn is_local(name: &str) -> bool {
+	let name = name.strip_suffix('.').unwrap_or(name);
+	name.len() > ".local".len()
+		&& name.as_bytes()[name.len() - ".local".len()..].eq_ignore_ascii_case(b".local")
+}
+
+/// A query in progress
+struct Pending<T> {
+	socket: T,
+	name: [u8; 255],
+	name_len: usize,
+	record_type: DnsRecordType,
+	id: u16,
+	started: Duration,
+	sent: Duration,
+}
+
+/// A [`Dns`] implementation resolving names in the `.local` domain through multicast DNS (see
+/// [RFC 6762]), on top of any [`UdpFullStack`] implementation.
+///
+/// Queries are sent as one-shot queries to the IPv4 mDNS group from a socket bound to an
+/// ephemeral port, so that responders answer by unicast and no multicast group needs to be
+/// joined. Queries are repeated every second until an answer arrives, or the timeout measured by
+/// the resolver's [`Clock`] expires.
+///
+/// Following the conventions of [`nb`], a query is sent on the first call and
+/// [`nb::Error::WouldBlock`] is returned until it completes; a call for a different name abandons
+/// the pending query. Reverse lookups of addresses through `in-addr.arpa` and `ip6.arpa` names
+/// are sent over mDNS as well.
+///
+/// ```
+/// use embedded_nal::{nb, AddrType, Dns, MdnsResolver};
+/// use embedded_nal::{DnsMessage, DnsMessageWriter, DnsRecordData, DnsSection};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// use core::time::Duration;
+/// # struct Network { response: Option<([u8; 512], usize)> }
+/// # impl embedded_nal::UdpClientStack for Network {
+/// #   type UdpSocket = ();
+/// #   type Error = ();
+/// #   fn socket(&mut self) -> Result<(), ()> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), _: &[u8]) -> nb::Result<(), ()> { Ok(()) }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+/// #     let (response, len) = self.response.take().ok_or(nb::Error::WouldBlock)?;
+/// #     buffer[..len].copy_from_slice(&response[..len]);
+/// #     Ok((len, "192.168.1.20:5353".parse().unwrap()))
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # impl embedded_nal::UdpFullStack for Network {
+/// #   fn bind(&mut self, _: &mut (), _: u16) -> Result<(), ()> { Ok(()) }
+/// #   fn send_to(&mut self, _: &mut (), _: SocketAddr, query: &[u8]) -> nb::Result<(), ()> {
+/// #     let query = DnsMessage::parse(query).unwrap();
+/// #     let mut response = [0; 512];
+/// #     let mut writer = DnsMessageWriter::new(&mut response, query.id(), DnsMessage::RESPONSE).unwrap();
+/// #     let address = DnsRecordData::A(Ipv4Addr::new(192, 168, 1, 20));
+/// #     writer.record(DnsSection::Answer, "device-1234.local", 1, 10, address).unwrap();
+/// #     let len = writer.finish();
+/// #     self.response = Some((response, len));
+/// #     Ok(())
+/// #   }
+/// # }
+/// # let stack = Network { response: None };
+/// # let mut ticks = 0;
+/// # let clock = move || { ticks += 1; Duration::from_millis(ticks) };
+/// let mut resolver = MdnsResolver::new(stack, clock);
+///
+/// let addr = nb::block!(resolver.get_host_by_name("device-1234.local", AddrType::IPv4))?;
+/// assert_eq!(addr, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)));
+/// # Ok::<(), embedded_nal::MdnsError<()>>(())
+/// ```
+///
+/// [RFC 6762]: https://tools.ietf.org/html/rfc6762
+pub struct MdnsResolver<S: UdpFullStack, C> {
+	stack: S,
+	clock: C,
+	local_port: u16,
+	timeout: Duration,
+	next_id: u16,
+	pending: Option<Pending<S::UdpSocket>>,
+}
+
+impl<S: UdpFullStack, C: Clock> MdnsResolver<S, C> {
+	/// Create a resolver that sends queries through `stack`, timing them with `clock`.
+	pub fn new(stack: S, clock: C) -> Self {
+		MdnsResolver {
+			stack,
+			clock,
+			local_port: 0,
+			timeout: DEFAULT_TIMEOUT,
+			next_id: 1,
+			pending: None,
+		}
+	}
+
+	/// Set the local port queries are sent from.
+	///
+	/// The default of 0 lets stacks that support it pick an ephemeral port. The port must not be
+	/// [`MDNS_PORT`], as responses would then be sent to the multicast group.
+	pub fn set_local_port(&mut self, local_port: u16) {
+		self.local_port = local_port;
+	}
+
+	/// Set the time to wait for an answer; the default is 3 seconds.
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
+	/// Abandon the pending query, if any.
+	pub fn cancel(&mut self) {
+		if let Some(pending) = self.pending.take() {
+			self.stack.close(pending.socket).ok();
+		}
+	}
+
+	/// Release the stack and clock, abandoning the pending query.
+	pub fn free(mut self) -> (S, C) {
+		self.cancel();
+		(self.stack, self.clock)
+	}
+
+	/// Query `name`, returning what `accept` makes of the records of `name` in the first response
+	/// it accepts.
+	fn poll_query<R>(
+		&mut self,
+		name: &str,
+		record_type: DnsRecordType,
+		mut accept: impl FnMut(&mut dyn Iterator<Item = DnsRecord<'_>>) -> Option<R>,
+	) -> nb::Result<R, MdnsError<S::Error>> {
+		let now = self.clock.now();
+		let mut pending = match self.pending.take() {
+			Some(pending)
+				if pending.name[..pending.name_len] == *name.as_bytes()
+					&& pending.record_type == record_type =>
+			{
+				pending
+			}
+			other => {
+				self.pending = other;
+				self.cancel();
+				self.start(name, record_type, now)?
+			}
+		};
+
+		let mut buffer = [0; MESSAGE_LEN];
+		loop {
+			let len = match self.stack.receive(&mut pending.socket, &mut buffer) {
+				Ok((len, _)) => len,
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => {
+					self.stack.close(pending.socket).ok();
+					return Err(nb::Error::Other(MdnsError::Transport(e)));
+				}
+			};
+			let Ok(message) = DnsMessage::parse(&buffer[..len]) else {
+				continue;
+			};
+			// Responses to one-shot queries may or may not echo the ID.
+			if !message.is_response() || message.id() != 0 && message.id() != pending.id {
+				continue;
+			}
+			let answer = accept(
+				&mut message
+					.records()
+					.map(|(_, record)| record)
+					.filter(|record| record.name.eq_str(name)),
+			);
+			if let Some(answer) = answer {
+				self.stack.close(pending.socket).ok();
+				return Ok(answer);
+			}
+		}
+
+		if now.saturating_sub(pending.started) >= self.timeout {
+			self.stack.close(pending.socket).ok();
+			return Err(nb::Error::Other(MdnsError::Timeout));
+		}
+		if now.saturating_sub(pending.sent) >= RETRANSMIT_INTERVAL {
+			if let Err(e) = self.send_query(&mut pending, name) {
+				self.stack.close(pending.socket).ok();
+				return Err(nb::Error::Other(e));
+			}
+			pending.sent = now;
+		}
+		self.pending = Some(pending);
+		Err(nb::Error::WouldBlock)
+	}
+
+	fn start(
+		&mut self,
+		name: &str,
+		record_type: DnsRecordType,
+		now: Duration,
+	) -> Result<Pending<S::UdpSocket>, MdnsError<S::Error>> {
+		let mut pending = Pending {
+			socket: self.stack.socket().map_err(MdnsError::Transport)?,
+			name: [0; 255],
+			name_len: name.len(),
+			record_type,
+			id: self.next_id,
+			started: now,
+			sent: now,
+		};
+		self.next_id = self.next_id.wrapping_add(1).max(1);
+		let result = match pending.name.get_mut(..name.len()) {
+			Some(buffer) => {
+				buffer.copy_from_slice(name.as_bytes());
+				self.stack
+					.bind(&mut pending.socket, self.local_port)
+					.map_err(MdnsError::Transport)
+					.and_then(|_| self.send_query(&mut pending, name))
+			}
+			None => Err(MdnsError::InvalidName),
+		};
+		match result {
+			Ok(()) => Ok(pending),
+			Err(e) => {
+				self.stack.close(pending.socket).ok();
+				Err(e)
+			}
+		}
+	}
+
+	fn send_query(
+		&mut self,
+		pending: &mut Pending<S::UdpSocket>,
+		name: &str,
+	) -> Result<(), MdnsError<S::Error>> {
+		let mut buffer = [0; MESSAGE_LEN];
+		let mut writer = DnsMessageWriter::new(&mut buffer, pending.id, 0)
+			.map_err(|_| MdnsError::BufferTooSmall)?;
+		writer
+			.question(name, pending.record_type, DnsMessage::CLASS_IN)
+			.map_err(|_| MdnsError::InvalidName)?;
+		let len = writer.finish();
+		let group = SocketAddr::new(IpAddr::V4(MDNS_IPV4_GROUP), MDNS_PORT);
+		nb::block!(self
+			.stack
+			.send_to(&mut pending.socket, group, &buffer[..len]))
+		.map_err(MdnsError::Transport)
+	}
+}
+
+impl<S: UdpFullStack, C: Clock> Dns for MdnsResolver<S, C> {
+	type Error = MdnsError<S::Error>;
+
+	fn get_host_by_name(
+		&mut self,
+		hostname: &str,
+		addr_type: AddrType,
+	) -> nb::Result<IpAddr, Self::Error> {
+		if !is_local(hostname) {
+			return Err(nb::Error::Other(MdnsError::NotLocal));
+		}
+		let record_type = match addr_type {
+			AddrType::IPv4 => DnsRecordType::A,
+			AddrType::IPv6 => DnsRecordType::AAAA,
+			AddrType::Either => DnsRecordType::ANY,
+		};
+		self.poll_query(hostname, record_type, |records| {
+			records
+				.filter_map(|record| match addr_type {
+					AddrType::IPv4 => record.ipv4().map(IpAddr::V4),
+					AddrType::IPv6 => record.ipv6().map(IpAddr::V6),
+					AddrType::Either => record
+						.ipv4()
+						.map(IpAddr::V4)
+						.or(record.ipv6().map(IpAddr::V6)),
+				})
+				.next()
+		})
+	}
+
+	fn get_host_by_address(
+		&mut self,
+		addr: IpAddr,
+		result: &mut [u8],
+	) -> nb::Result<usize, Self::Error> {
+		let mut name = [0; REVERSE_NAME_LEN];
+		let name = reverse_name(addr, &mut name);
+		self.poll_query(name, DnsRecordType::PTR, |records| {
+			records
+				.filter_map(|record| record.target())
+				.next()
+				.map(|target| target.to_dotted(result).map(str::len))
+		})?
+		.ok_or(nb::Error::Other(MdnsError::BufferTooSmall))
+	}
+}
+
+/// Names without records of the queried type go unanswered in mDNS, so querying them fails with
+/// [`MdnsError::Timeout`] rather than returning no records.
+impl<S: UdpFullStack, C: Clock> DnsRecords for MdnsResolver<S, C> {
+	fn get_srv_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error> {
+		if !is_local(name) {
+			return Err(nb::Error::Other(MdnsError::NotLocal));
+		}
+		self.poll_query(name, DnsRecordType::SRV, |records| {
+			let mut len = Some(0);
+			let mut found = false;
+			for (priority, weight, port, target) in records.filter_map(|record| record.srv()) {
+				found = true;
+				let mut buffer = [0; 255];
+				len = len.and_then(|len| {
+					let record = SrvRecord {
+						priority,
+						weight,
+						port,
+						target: target.to_dotted(&mut buffer)?,
+					};
+					SrvRecords::push(result, len, &record)
+				});
+			}
+			found.then_some(len)
+		})?
+		.ok_or(nb::Error::Other(MdnsError::BufferTooSmall))
+	}
+
+	fn get_txt_records(&mut self, name: &str, result: &mut [u8]) -> nb::Result<usize, Self::Error> {
+		if !is_local(name) {
+			return Err(nb::Error::Other(MdnsError::NotLocal));
+		}
+		self.poll_query(name, DnsRecordType::TXT, |records| {
+			let mut len = Some(0);
+			let mut found = false;
+			for record in records.filter(|record| record.record_type == DnsRecordType::TXT) {
+				found = true;
+				len = len.and_then(|len| TxtRecords::push(result, len, record.data));
+			}
+			found.then_some(len)
+		})?
+		.ok_or(nb::Error::Other(MdnsError::BufferTooSmall))
+	}
+
+	fn get_canonical_name(
+		&mut self,
+		name: &str,
+		result: &mut [u8],
+	) -> nb::Result<usize, Self::Error> {
+		if !is_local(name) {
+			return Err(nb::Error::Other(MdnsError::NotLocal));
+		}
+		self.poll_query(name, DnsRecordType::CNAME, |records| {
+			records
+				.filter(|record| record.record_type == DnsRecordType::CNAME)
+				.filter_map(|record| record.target())
+				.next()
+				.map(|target| target.to_dotted(result).map(str::len))
+		})?
+		.ok_or(nb::Error::Other(MdnsError::BufferTooSmall))
+	}
+}
+
+/// The steps of claiming a hostname, see RFC 6762, section 8
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+	/// The given number of probes were sent.
+	Probing(u8),
+	/// The given number of announcements were sent.
+	Announcing(u8),
+	/// The hostname is claimed.
+	Established,
+}
+
+/// Selection of the records owned by a responder
+#[derive(Copy, Clone, Debug)]
+struct Records<const N: usize> {
+	a: bool,
+	aaaa: bool,
+	ipv4_ptr: bool,
+	ipv6_ptr: bool,
+	services: [ServiceRecords; N],
+}
+
+/// Selection of the records of an advertised service
+#[derive(Copy, Clone, Debug, Default)]
+struct ServiceRecords {
+	/// The `PTR` record of the service type enumeration
+	enumeration: bool,
+	ptr: bool,
+	srv: bool,
+	txt: bool,
+}
+
+impl<const N: usize> Records<N> {
+	fn none() -> Self {
+		Records {
+			a: false,
+			aaaa: false,
+			ipv4_ptr: false,
+			ipv6_ptr: false,
+			services: [ServiceRecords::default(); N],
+		}
+	}
+
+	fn add(&mut self, other: &Records<N>) {
+		self.a |= other.a;
+		self.aaaa |= other.aaaa;
+		self.ipv4_ptr |= other.ipv4_ptr;
+		self.ipv6_ptr |= other.ipv6_ptr;
+		for (service, other) in self.services.iter_mut().zip(&other.services) {
+			service.enumeration |= other.enumeration;
+			service.ptr |= other.ptr;
+			service.srv |= other.srv;
+			service.txt |= other.txt;
+		}
+	}
+
+	fn any(&self) -> bool {
+		self.a
+			|| self.aaaa
+			|| self.ipv4_ptr
+			|| self.ipv6_ptr
+			|| self
+				.services
+				.iter()
+				.any(|service| service.enumeration || service.ptr || service.srv || service.txt)
+	}
+}
+
+/// How records are written
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+	/// In responses and announcements
+	Response,
+	/// In responses to legacy unicast queries, see RFC 6762, section 6.7
+	Legacy,
+	/// In the authority section of probes
+	Probe,
+	/// In goodbye packets withdrawing the records
+	Goodbye,
+}
+
+impl Mode {
+	/// The class and time to live for a record, which is unique to this host unless `shared`.
+	fn class_ttl(self, ttl: u32, shared: bool) -> (u16, u32) {
+		let flush = match shared {
+			true => DnsMessage::CLASS_IN,
+			false => DnsMessage::CLASS_IN | CACHE_FLUSH,
+		};
+		match self {
+			Mode::Response => (flush, ttl),
+			Mode::Legacy => (DnsMessage::CLASS_IN, ttl.min(LEGACY_TTL)),
+			Mode::Probe => (DnsMessage::CLASS_IN, ttl),
+			Mode::Goodbye => (flush, 0),
+		}
+	}
+}
+
+/// A multicast DNS responder (see [RFC 6762]) making the device reachable as `<hostname>.local`,
+/// and advertising up to `N` services through DNS-SD (see [RFC 6763]), on top of any
+/// [`UdpMulticastStack`] implementation.
+///
+/// The responder binds [`MDNS_PORT`], joins the IPv4 mDNS group, and claims its hostname by
+/// sending three probes 250 ms apart, followed by two announcements one second apart. If another
+/// host already uses the name, the responder renames itself to `<hostname>-2`, `<hostname>-3` and
+/// so on, and probes again; [`.hostname()`](MdnsResponder::hostname) returns the name currently in
+/// use. Once claimed, `A`, `AAAA` and `ANY` queries for the name and `PTR` queries for the reverse
+/// names of its addresses are answered, by unicast if requested or for legacy resolvers querying
+/// from another port than [`MDNS_PORT`], and to the group otherwise.
+///
+/// Services added through [`.add_service()`](MdnsResponder::add_service) are announced along with
+/// the hostname, and withdrawn with goodbye packets when removed. Queries for their service type,
+/// instance names and the service type enumeration are answered, with the `SRV`, `TXT` and
+/// address records a browser needs next included as additional records. Instance names are not
+/// probed for, and should be unique, for example by including a serial number.
+///
+/// The responder does its work in [`.poll()`](MdnsResponder::poll), or
+/// [`.poll_browser()`](MdnsResponder::poll_browser) when browsing for services, which have to be
+/// called regularly; they never block except for sending responses.
+///
+/// ```
+/// use embedded_nal::{DnsMessage, DnsRecordType, DnsSdService, MdnsResponder, MDNS_IPV4_GROUP};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// use core::time::Duration;
+/// # use embedded_nal::{nb, DnsMessageWriter, DnsRecordData, DnsSection, MdnsError};
+/// # use std::{cell::{Cell, RefCell}, collections::VecDeque, rc::Rc};
+/// # /// Datagrams on their way to the responder, and the datagrams it sent
+/// # #[derive(Default)]
+/// # struct Wire { incoming: VecDeque<(SocketAddr, Vec<u8>)>, sent: Vec<(SocketAddr, Vec<u8>)> }
+/// # #[derive(Clone, Default)]
+/// # struct Network(Rc<RefCell<Wire>>);
+/// # impl Network {
+/// #   fn deliver(&self, from: SocketAddr, message: Vec<u8>) {
+/// #     self.0.borrow_mut().incoming.push_back((from, message));
+/// #   }
+/// #   fn sent(&self) -> Vec<(SocketAddr, Vec<u8>)> { core::mem::take(&mut self.0.borrow_mut().sent) }
+/// # }
+/// # impl embedded_nal::UdpClientStack for Network {
+/// #   type UdpSocket = ();
+/// #   type Error = ();
+/// #   fn socket(&mut self) -> Result<(), ()> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), _: &[u8]) -> nb::Result<(), ()> { Ok(()) }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+/// #     let (from, message) = self.0.borrow_mut().incoming.pop_front().ok_or(nb::Error::WouldBlock)?;
+/// #     buffer[..message.len()].copy_from_slice(&message);
+/// #     Ok((message.len(), from))
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # impl embedded_nal::UdpFullStack for Network {
+/// #   fn bind(&mut self, _: &mut (), _: u16) -> Result<(), ()> { Ok(()) }
+/// #   fn send_to(&mut self, _: &mut (), remote: SocketAddr, message: &[u8]) -> nb::Result<(), ()> {
+/// #     self.0.borrow_mut().sent.push((remote, message.to_vec()));
+/// #     Ok(())
+/// #   }
+/// # }
+/// # impl embedded_nal::UdpMulticastStack for Network {
+/// #   fn join_multicast_group(&mut self, _: &mut (), _: IpAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn leave_multicast_group(&mut self, _: &mut (), _: IpAddr) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # /// A query for the address of `name`, listing `known` as a known answer
+/// # fn query(name: &str, known: Option<Ipv4Addr>) -> Vec<u8> {
+/// #   let mut buffer = [0; 512];
+/// #   let mut writer = DnsMessageWriter::new(&mut buffer, 0, 0).unwrap();
+/// #   writer.question(name, DnsRecordType::A, DnsMessage::CLASS_IN).unwrap();
+/// #   if let Some(addr) = known {
+/// #     writer.record(DnsSection::Answer, name, DnsMessage::CLASS_IN, 120, DnsRecordData::A(addr)).unwrap();
+/// #   }
+/// #   let len = writer.finish();
+/// #   buffer[..len].to_vec()
+/// # }
+/// # /// A response of another host giving the address of `name`
+/// # fn response(name: &str, addr: Ipv4Addr) -> Vec<u8> {
+/// #   let mut buffer = [0; 512];
+/// #   let flags = DnsMessage::RESPONSE | DnsMessage::AUTHORITATIVE;
+/// #   let mut writer = DnsMessageWriter::new(&mut buffer, 0, flags).unwrap();
+/// #   writer.record(DnsSection::Answer, name, DnsMessage::CLASS_IN, 120, DnsRecordData::A(addr)).unwrap();
+/// #   let len = writer.finish();
+/// #   buffer[..len].to_vec()
+/// # }
+/// # let network = Network::default();
+/// # let stack = network.clone();
+/// # let time = Rc::new(Cell::new(Duration::ZERO));
+/// # let clock = { let time = time.clone(); move || time.get() };
+/// # let peer: SocketAddr = "192.168.1.99:5353".parse().unwrap();
+/// # let group = SocketAddr::new(IpAddr::V4(MDNS_IPV4_GROUP), 5353);
+/// let mut responder: MdnsResponder<_, _> = MdnsResponder::new(stack, clock, "device.")?;
+/// let address = Ipv4Addr::new(192, 168, 1, 20);
+/// responder.set_addresses(Some(address), None);
+/// responder.add_service(&DnsSdService {
+///     instance: "Kitchen Sensor",
+///     service: "_coap._udp",
+///     port: 5683,
+///     txt: &[b"rt=temperature"],
+/// })?;
+///
+/// // The hostname is probed for, but another host already uses it.
+/// responder.poll()?;
+/// let [(to, probe)] = &network.sent()[..] else { panic!() };
+/// let probe = DnsMessage::parse(probe).unwrap();
+/// assert!(*to == group && !probe.is_response());
+/// assert!(probe.questions().next().unwrap().name.eq_str("device.local"));
+/// network.deliver(peer, response("device.local", Ipv4Addr::new(192, 168, 1, 99)));
+/// responder.poll()?;
+/// assert_eq!(responder.hostname(), "device-2.local");
+///
+/// // The new name is claimed after three probes, and announced.
+/// for ms in [250, 500, 750] {
+///     time.set(Duration::from_millis(ms));
+///     responder.poll()?;
+/// }
+/// assert!(responder.is_claimed());
+/// let announced = network.sent().iter().any(|(to, message)| {
+///     let message = DnsMessage::parse(message).unwrap();
+///     *to == group
+///         && message.is_response()
+///         && message.records().any(|(_, record)| record.ipv4() == Some(address))
+/// });
+/// assert!(announced);
+///
+/// // Queries are answered to the group, unless the querier listed the answer as known.
+/// network.deliver(peer, query("device-2.local", None));
+/// responder.poll()?;
+/// let [(to, answer)] = &network.sent()[..] else { panic!() };
+/// let answer = DnsMessage::parse(answer).unwrap();
+/// assert_eq!(*to, group);
+/// assert!(answer.records().any(|(_, record)| record.ipv4() == Some(address)));
+/// network.deliver(peer, query("device-2.local", Some(address)));
+/// responder.poll()?;
+/// assert!(network.sent().is_empty());
+/// # Ok::<(), MdnsError<()>>(())
+/// ```
+///
+/// [RFC 6762]: https://tools.ietf.org/html/rfc6762
+/// [RFC 6763]: https://tools.ietf.org/html/rfc6763
+pub struct MdnsResponder<S: UdpMulticastStack, C, const N: usize = 4> {
+	stack: S,
+	clock: C,
+	socket: S::UdpSocket,
+	name: [u8; MAX_HOSTNAME_LEN],
+	name_len: usize,
+	label_len: usize,
+	renames: u32,
+	ipv4: Option<Ipv4Addr>,
+	ipv6: Option<Ipv6Addr>,
+	services: [Option<AdvertisedService>; N],
+	state: State,
+	next: Duration,
+}
+
+impl<S: UdpMulticastStack, C: Clock, const N: usize> MdnsResponder<S, C, N> {
+	/// Create a responder claiming `hostname`, given as a single label with or without the
+	/// `.local` suffix, on the network of `stack`, timing probes and announcements with `clock`.
+	///
+	/// No addresses are announced until they are set through
+	/// [`.set_addresses()`](MdnsResponder::set_addresses).
+	pub fn new(mut stack: S, clock: C, hostname: &str) -> Result<Self, MdnsError<S::Error>> {
+		let name = hostname.strip_suffix('.').unwrap_or(hostname);
+		let label = name.strip_suffix(".local").unwrap_or(name);
+		if label.is_empty() || label.len() > 63 || label.contains('.') {
+			return Err(MdnsError::InvalidName);
+		}
+
+		let mut socket = stack.socket().map_err(MdnsError::Transport)?;
+		let result = stack
+			.bind(&mut socket, MDNS_PORT)
+			.and_then(|_| stack.join_multicast_group(&mut socket, IpAddr::V4(MDNS_IPV4_GROUP)));
+		if let Err(e) = result {
+			stack.close(socket).ok();
+			return Err(MdnsError::Transport(e));
+		}
+
+		let mut responder = MdnsResponder {
+			stack,
+			clock,
+			socket,
+			name: [0; MAX_HOSTNAME_LEN],
+			name_len: 0,
+			label_len: label.len(),
+			renames: 0,
+			ipv4: None,
+			ipv6: None,
+			services: [None; N],
+			state: State::Probing(0),
+			next: Duration::ZERO,
+		};
+		responder.name[..label.len()].copy_from_slice(label.as_bytes());
+		responder.set_suffix();
+		Ok(responder)
+	}
+
+	/// The full hostname currently claimed or being probed for, including the `.local` suffix.
+	pub fn hostname(&self) -> &str {
+		core::str::from_utf8(&self.name[..self.name_len]).unwrap_or_default()
+	}
+
+	/// Whether probing completed, and the hostname is claimed.
+	pub fn is_claimed(&self) -> bool {
+		!matches!(self.state, State::Probing(_))
+	}
+
+	/// Set the addresses to answer with, announcing them if the hostname is already claimed.
+	pub fn set_addresses(&mut self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) {
+		self.ipv4 = ipv4;
+		self.ipv6 = ipv6;
+		self.announce();
+	}
+
+	/// Advertise a service, replacing the one with the same instance name and service type if
+	/// there is one.
+	///
+	/// The service is announced once the hostname is claimed.
+	pub fn add_service(&mut self, service: &DnsSdService<'_>) -> Result<(), MdnsError<S::Error>> {
+		let advertised = AdvertisedService::new(service).ok_or(MdnsError::InvalidName)?;
+		let slot =
+			match self.services.iter().position(|slot| {
+				slot.is_some_and(|slot| slot.is(service.instance, service.service))
+			}) {
+				Some(i) => &mut self.services[i],
+				None => self
+					.services
+					.iter_mut()
+					.find(|slot| slot.is_none())
+					.ok_or(MdnsError::TableFull)?,
+			};
+		*slot = Some(advertised);
+		self.announce();
+		Ok(())
+	}
+
+	/// Stop advertising a service, sending a goodbye packet for it.
+	///
+	/// Returns whether the service was advertised.
+	pub fn remove_service(
+		&mut self,
+		instance: &str,
+		service: &str,
+	) -> Result<bool, MdnsError<S::Error>> {
+		let Some(i) = self
+			.services
+			.iter()
+			.position(|slot| slot.is_some_and(|slot| slot.is(instance, service)))
+		else {
+			return Ok(false);
+		};
+		if self.is_claimed() {
+			self.send_service(i, Mode::Goodbye)?;
+		}
+		self.services[i] = None;
+		Ok(true)
+	}
+
+	/// Answer queries, and send probes and announcements when they are due.
+	pub fn poll(&mut self) -> Result<(), MdnsError<S::Error>> {
+		let now = self.clock.now();
+		self.receive(now, |_| ())?;
+		self.send_due(now)
+	}
+
+	/// Answer queries, and send probes and announcements when they are due, like
+	/// [`.poll()`](MdnsResponder::poll), and pass the responses received to `browser`, sending
+	/// its queries when they are due.
+	pub fn poll_browser<const M: usize>(
+		&mut self,
+		browser: &mut DnsSdBrowser<M>,
+	) -> Result<(), MdnsError<S::Error>> {
+		let now = self.clock.now();
+		self.receive(now, |message| browser.handle(message, now))?;
+		self.send_due(now)?;
+		let mut buffer = [0; MESSAGE_LEN];
+		match browser.poll_query(now, &mut buffer) {
+			Some(len) => self.send(self.group(), &buffer[..len]),
+			None => Ok(()),
+		}
+	}
+
+	/// Withdraw the records by sending goodbye packets, and release the stack and clock.
+	pub fn free(mut self) -> (S, C) {
+		if self.is_claimed() {
+			self.send_announcement(Mode::Goodbye).ok();
+		}
+		self.stack
+			.leave_multicast_group(&mut self.socket, IpAddr::V4(MDNS_IPV4_GROUP))
+			.ok();
+		self.stack.close(self.socket).ok();
+		(self.stack, self.clock)
+	}
+
+	/// Handle the messages received, passing them to `inspect` as well.
+	fn receive(
+		&mut self,
+		now: Duration,
+		mut inspect: impl FnMut(&DnsMessage<'_>),
+	) -> Result<(), MdnsError<S::Error>> {
+		let mut buffer = [0; MESSAGE_LEN];
+		loop {
+			match self.stack.receive(&mut self.socket, &mut buffer) {
+				Ok((len, remote)) => {
+					if let Ok(message) = DnsMessage::parse(&buffer[..len]) {
+						inspect(&message);
+						self.handle(&message, remote, now)?;
+					}
+				}
+				Err(nb::Error::WouldBlock) => return Ok(()),
+				Err(nb::Error::Other(e)) => return Err(MdnsError::Transport(e)),
+			}
+		}
+	}
+
+	/// Send the probes and announcements due at time `now`.
+	fn send_due(&mut self, now: Duration) -> Result<(), MdnsError<S::Error>> {
+		while now >= self.next {
+			match self.state {
+				State::Probing(probes) if probes < PROBES => {
+					self.send_probe()?;
+					self.state = State::Probing(probes + 1);
+					self.next = now + PROBE_INTERVAL;
+				}
+				State::Probing(_) => self.state = State::Announcing(0),
+				State::Announcing(announcements) if announcements < ANNOUNCEMENTS => {
+					self.send_announcement(Mode::Response)?;
+					self.state = State::Announcing(announcements + 1);
+					self.next = now + ANNOUNCE_INTERVAL;
+				}
+				State::Announcing(_) | State::Established => {
+					self.state = State::Established;
+					self.next = Duration::MAX;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Announce the records again, if the hostname is already claimed.
+	fn announce(&mut self) {
+		if self.is_claimed() {
+			self.state = State::Announcing(0);
+			self.next = Duration::ZERO;
+		}
+	}
+
+	fn handle(
+		&mut self,
+		message: &DnsMessage<'_>,
+		remote: SocketAddr,
+		now: Duration,
+	) -> Result<(), MdnsError<S::Error>> {
+		if message.opcode() != 0 {
+			return Ok(());
+		}
+		if message.is_response() {
+			if message.records().any(|(_, record)| self.conflicts(&record)) {
+				self.rename(now);
+			}
+			return Ok(());
+		}
+		match self.state {
+			State::Probing(_) => {
+				// Another host probing for the same name at the same time
+				if self.loses_tie_break(message) {
+					self.state = State::Probing(0);
+					self.next = now + Duration::from_secs(1);
+				}
+				Ok(())
+			}
+			_ => self.answer(message, remote),
+		}
+	}
+
+	/// Whether a record received in a response conflicts with the records of this host.
+	fn conflicts(&self, record: &DnsRecord<'_>) -> bool {
+		if record.ttl == 0 || !record.name.eq_str(self.hostname()) {
+			return false;
+		}
+		match record.record_type {
+			DnsRecordType::A => record.ipv4() != self.ipv4,
+			DnsRecordType::AAAA => record.ipv6() != self.ipv6,
+			// While probing, any record for the name means it is taken.
+			_ => !self.is_claimed(),
+		}
+	}
+
+	/// Whether the records proposed in a probe of another host win over the records of this
+	/// host, see RFC 6762, section 8.2.
+	///
+	/// Only the lexicographically first records of both sides are compared, which decides all
+	/// cases but those of hosts sharing their first address.
+	fn loses_tie_break(&self, message: &DnsMessage<'_>) -> bool {
+		let ipv4 = self.ipv4.map(|addr| addr.octets());
+		let ipv6 = self.ipv6.map(|addr| addr.octets());
+		let ours = match (&ipv4, &ipv6) {
+			(Some(ipv4), _) => Some((DnsRecordType::A.0, &ipv4[..])),
+			(None, Some(ipv6)) => Some((DnsRecordType::AAAA.0, &ipv6[..])),
+			(None, None) => None,
+		};
+		let theirs = message
+			.records()
+			.filter(|(section, record)| {
+				*section == DnsSection::Authority && record.name.eq_str(self.hostname())
+			})
+			.map(|(_, record)| (record.record_type.0, record.data))
+			.min();
+		theirs.is_some() && theirs > ours
+	}
+
+	fn rename(&mut self, now: Duration) {
+		self.renames += 1;
+		self.set_suffix();
+		self.state = State::Probing(0);
+		self.next = now;
+	}
+
+	/// Write the `.local` suffix, preceded by the number of renames plus one if renamed.
+	fn set_suffix(&mut self) {
+		let mut number = [0; 10];
+		let mut digits = 0;
+		let mut value = self.renames + 1;
+		while self.renames > 0 && value > 0 {
+			number[number.len() - 1 - digits] = b'0' + (value % 10) as u8;
+			value /= 10;
+			digits += 1;
+		}
+		let number = &number[number.len() - digits..];
+
+		let suffix_len = number.len() + usize::from(!number.is_empty());
+		let mut len = self.label_len.min(63 - suffix_len);
+		while core::str::from_utf8(&self.name[..len]).is_err() {
+			len -= 1;
+		}
+		// The label is truncated for good, and thus kept short enough for further renames.
+		self.label_len = len;
+		if !number.is_empty() {
+			self.name[len] = b'-';
+			self.name[len + 1..len + 1 + number.len()].copy_from_slice(number);
+			len += suffix_len;
+		}
+		self.name[len..len + ".local".len()].copy_from_slice(b".local");
+		self.name_len = len + ".local".len();
+	}
+
+	fn answer(
+		&mut self,
+		message: &DnsMessage<'_>,
+		remote: SocketAddr,
+	) -> Result<(), MdnsError<S::Error>> {
+		let legacy = remote.port() != MDNS_PORT;
+		let mut ipv4_name = [0; REVERSE_NAME_LEN];
+		let ipv4_name = self
+			.ipv4
+			.map(|addr| reverse_name(addr.into(), &mut ipv4_name));
+		let mut ipv6_name = [0; REVERSE_NAME_LEN];
+		let ipv6_name = self
+			.ipv6
+			.map(|addr| reverse_name(addr.into(), &mut ipv6_name));
+
+		let mut records = Records::none();
+		let mut unicast = true;
+		let mut buffer = [0; MESSAGE_LEN];
+		let id = if legacy { message.id() } else { 0 };
+		let flags = DnsMessage::RESPONSE | DnsMessage::AUTHORITATIVE;
+		let mut writer =
+			DnsMessageWriter::new(&mut buffer, id, flags).map_err(|_| MdnsError::BufferTooSmall)?;
+		for question in message.questions() {
+			let asks = |record_type| {
+				question.record_type == record_type || question.record_type == DnsRecordType::ANY
+			};
+			let is = |name: Option<&str>| name.is_some_and(|name| question.name.eq_str(name));
+			let host = question.name.eq_str(self.hostname());
+			let mut matched = Records {
+				a: host && asks(DnsRecordType::A),
+				aaaa: host && asks(DnsRecordType::AAAA),
+				ipv4_ptr: is(ipv4_name) && asks(DnsRecordType::PTR),
+				ipv6_ptr: is(ipv6_name) && asks(DnsRecordType::PTR),
+				..Records::none()
+			};
+			let enumeration = question.name.eq_str(SERVICE_ENUMERATION) && asks(DnsRecordType::PTR);
+			for (i, service) in self.services.iter().enumerate() {
+				let Some(service) = service else {
+					continue;
+				};
+				let mut name = [0; 255];
+				let instance = question.name.eq_str(service.name(&mut name));
+				matched.services[i] = ServiceRecords {
+					// Each service type is enumerated once.
+					enumeration: enumeration && self.first_of_type(i),
+					ptr: question.name.eq_str(service.service()) && asks(DnsRecordType::PTR),
+					srv: instance && asks(DnsRecordType::SRV),
+					txt: instance && asks(DnsRecordType::TXT),
+				};
+			}
+			if !matched.any() {
+				continue;
+			}
+			records.add(&matched);
+			unicast &= question.class & UNICAST_RESPONSE != 0;
+			if legacy {
+				let mut name = [0; 255];
+				if let Some(name) = question.name.to_dotted(&mut name) {
+					writer
+						.question(
+							name,
+							question.record_type,
+							question.class & !UNICAST_RESPONSE,
+						)
+						.ok();
+				}
+			}
+		}
+
+		// Known-answer suppression, see RFC 6762, section 7.1
+		for (_, known) in message.records() {
+			if known.ttl >= HOST_TTL / 2 && known.name.eq_str(self.hostname()) {
+				records.a &= !(known.ipv4().is_some() && known.ipv4() == self.ipv4);
+				records.aaaa &= !(known.ipv6().is_some() && known.ipv6() == self.ipv6);
+			}
+			if known.ttl < SERVICE_TTL / 2 {
+				continue;
+			}
+			for (i, service) in self.services.iter().enumerate() {
+				let Some(service) = service else {
+					continue;
+				};
+				let mut name = [0; 255];
+				let name = service.name(&mut name);
+				if known.name.eq_str(service.service())
+					&& known.target().is_some_and(|target| target.eq_str(name))
+				{
+					records.services[i].ptr = false;
+				}
+			}
+		}
+		if !records.any() {
+			return Ok(());
+		}
+
+		// The records a browser asks for next, see RFC 6763, section 12
+		let mut additional = Records::none();
+		for (selected, answered) in additional.services.iter_mut().zip(&records.services) {
+			if answered.ptr {
+				selected.srv = !answered.srv;
+				selected.txt = !answered.txt;
+			}
+		}
+		let targets = (records.services.iter())
+			.chain(&additional.services)
+			.any(|service| service.srv);
+		additional.a = targets && !records.a;
+		additional.aaaa = targets && !records.aaaa;
+
+		let mode = if legacy { Mode::Legacy } else { Mode::Response };
+		self.write_records(&mut writer, DnsSection::Answer, mode, &records);
+		self.write_records(&mut writer, DnsSection::Additional, mode, &additional);
+		let len = writer.finish();
+		let destination = match legacy || unicast {
+			true => remote,
+			false => self.group(),
+		};
+		self.send(destination, &buffer[..len])
+	}
+
+	/// Whether no service before slot `i` has the same service type as the one in it.
+	fn first_of_type(&self, i: usize) -> bool {
+		let Some(service) = &self.services[i] else {
+			return false;
+		};
+		!self.services[..i]
+			.iter()
+			.flatten()
+			.any(|other| other.service().eq_ignore_ascii_case(service.service()))
+	}
+
+	fn send_probe(&mut self) -> Result<(), MdnsError<S::Error>> {
+		let mut buffer = [0; MESSAGE_LEN];
+		let mut writer =
+			DnsMessageWriter::new(&mut buffer, 0, 0).map_err(|_| MdnsError::BufferTooSmall)?;
+		writer
+			.question(
+				self.hostname(),
+				DnsRecordType::ANY,
+				DnsMessage::CLASS_IN | UNICAST_RESPONSE,
+			)
+			.map_err(|_| MdnsError::BufferTooSmall)?;
+		let records = Records {
+			a: true,
+			aaaa: true,
+			..Records::none()
+		};
+		self.write_records(&mut writer, DnsSection::Authority, Mode::Probe, &records);
+		let len = writer.finish();
+		self.send(self.group(), &buffer[..len])
+	}
+
+	/// Send the host records, and those of each service in a message of its own.
+	fn send_announcement(&mut self, mode: Mode) -> Result<(), MdnsError<S::Error>> {
+		let mut buffer = [0; MESSAGE_LEN];
+		let flags = DnsMessage::RESPONSE | DnsMessage::AUTHORITATIVE;
+		let mut writer =
+			DnsMessageWriter::new(&mut buffer, 0, flags).map_err(|_| MdnsError::BufferTooSmall)?;
+		let records = Records {
+			a: true,
+			aaaa: true,
+			ipv4_ptr: true,
+			ipv6_ptr: true,
+			..Records::none()
+		};
+		self.write_records(&mut writer, DnsSection::Answer, mode, &records);
+		if !writer.is_empty() {
+			let len = writer.finish();
+			self.send(self.group(), &buffer[..len])?;
+		}
+		for i in 0..N {
+			if self.services[i].is_some() {
+				self.send_service(i, mode)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Send the records of the service in slot `i`.
+	fn send_service(&mut self, i: usize, mode: Mode) -> Result<(), MdnsError<S::Error>> {
+		let mut buffer = [0; MESSAGE_LEN];
+		let flags = DnsMessage::RESPONSE | DnsMessage::AUTHORITATIVE;
+		let mut writer =
+			DnsMessageWriter::new(&mut buffer, 0, flags).map_err(|_| MdnsError::BufferTooSmall)?;
+		let mut records = Records::none();
+		records.services[i] = ServiceRecords {
+			// The service type stays enumerated as long as there are services of the type.
+			enumeration: mode != Mode::Goodbye || self.only_of_type(i),
+			ptr: true,
+			srv: true,
+			txt: true,
+		};
+		self.write_records(&mut writer, DnsSection::Answer, mode, &records);
+		let len = writer.finish();
+		self.send(self.group(), &buffer[..len])
+	}
+
+	/// Whether no other service has the same service type as the one in slot `i`.
+	fn only_of_type(&self, i: usize) -> bool {
+		let Some(service) = &self.services[i] else {
+			return false;
+		};
+		let same =
+			|other: &AdvertisedService| other.service().eq_ignore_ascii_case(service.service());
+		self.services
+			.iter()
+			.flatten()
+			.filter(|other| same(other))
+			.count() == 1
+	}
+
+	/// Write the selected records, skipping those that do not fit.
+	fn write_records(
+		&self,
+		writer: &mut DnsMessageWriter<'_>,
+		section: DnsSection,
+		mode: Mode,
+		records: &Records<N>,
+	) {
+		let hostname = self.hostname();
+		let mut write = |name: &str, ttl: u32, shared: bool, data: DnsRecordData<'_>| {
+			let (class, ttl) = mode.class_ttl(ttl, shared);
+			writer.record(section, name, class, ttl, data).ok();
+		};
+		if let (true, Some(addr)) = (records.a, self.ipv4) {
+			write(hostname, HOST_TTL, false, DnsRecordData::A(addr));
+		}
+		if let (true, Some(addr)) = (records.aaaa, self.ipv6) {
+			write(hostname, HOST_TTL, false, DnsRecordData::Aaaa(addr));
+		}
+		let mut name = [0; REVERSE_NAME_LEN];
+		if let (true, Some(addr)) = (records.ipv4_ptr, self.ipv4) {
+			let name = reverse_name(addr.into(), &mut name);
+			write(name, HOST_TTL, false, DnsRecordData::Ptr(hostname));
+		}
+		if let (true, Some(addr)) = (records.ipv6_ptr, self.ipv6) {
+			let name = reverse_name(addr.into(), &mut name);
+			write(name, HOST_TTL, false, DnsRecordData::Ptr(hostname));
+		}
+
+		for (service, selected) in self.services.iter().zip(&records.services) {
+			let Some(service) = service else {
+				continue;
+			};
+			let mut name = [0; 255];
+			let name = service.name(&mut name);
+			if selected.enumeration {
+				let data = DnsRecordData::Ptr(service.service());
+				write(SERVICE_ENUMERATION, SERVICE_TTL, true, data);
+			}
+			if selected.ptr {
+				write(
+					service.service(),
+					SERVICE_TTL,
+					true,
+					DnsRecordData::Ptr(name),
+				);
+			}
+			if selected.srv {
+				let data = DnsRecordData::Srv {
+					priority: 0,
+					weight: 0,
+					port: service.port,
+					target: hostname,
+				};
+				write(name, HOST_TTL, false, data);
+			}
+			if selected.txt {
+				write(name, SERVICE_TTL, false, service.txt());
+			}
+		}
+	}
+
+	fn group(&self) -> SocketAddr {
+		SocketAddr::new(IpAddr::V4(MDNS_IPV4_GROUP), MDNS_PORT)
+	}
+
+	fn send(&mut self, destination: SocketAddr, message: &[u8]) -> Result<(), MdnsError<S::Error>> {
+		nb::block!(self.stack.send_to(&mut self.socket, destination, message))
+			.map_err(MdnsError::Transport)
+	}
+}
@@ -0,0 +1,316 @@
+use crate::{nb, AddrType, Dns, UdpClientStack};
+use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
+
+/// The well-known NTP port
+pub const SNTP_PORT: u16 = 123;
+
+/// Length of an SNTP message without authentication
+pub const SNTP_MESSAGE_LEN: usize = 48;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Default time to wait for a response from a single server
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A point in time in the NTP timestamp format: seconds since 1900 in the upper 32 bits, and the
+/// fraction of a second in the lower 32 bits.
+///
+/// Timestamps wrap around every 136 years; following [RFC 4330, section 3], timestamps with the
+/// most significant bit cleared are taken to be after 2036 when converting to Unix time.
+///
+/// ```
+/// use embedded_nal::SntpTimestamp;
+/// use core::time::Duration;
+///
+/// let time = SntpTimestamp::from_unix(Duration::from_secs(1_700_000_000));
+/// assert_eq!(time.to_unix(), Duration::from_secs(1_700_000_000));
+/// assert_eq!(SntpTimestamp(0x8000_0000 << 32).to_unix(), Duration::ZERO);
+/// ```
+///
+/// [RFC 4330, section 3]: https://tools.ietf.org/html/rfc4330#section-3
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SntpTimestamp(pub u64);
+
+impl SntpTimestamp {
+	/// Convert the time passed since the Unix epoch.
+	pub fn from_unix(since_epoch: Duration) -> Self {
+		let seconds = since_epoch.as_secs().wrapping_add(UNIX_OFFSET) as u32;
+		let fraction = (u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000;
+		SntpTimestamp((u64::from(seconds) << 32) | fraction)
+	}
+
+	/// The time passed since the Unix epoch.
+	///
+	/// Times before the Unix epoch are taken to be at it.
+	pub fn to_unix(self) -> Duration {
+		let mut seconds = self.0 >> 32;
+		if seconds & 0x8000_0000 == 0 {
+			seconds += 1 << 32;
+		}
+		let nanos = ((self.0 & 0xffff_ffff) * 1_000_000_000) >> 32;
+		match seconds.checked_sub(UNIX_OFFSET) {
+			Some(seconds) => Duration::new(seconds, nanos as u32),
+			None => Duration::ZERO,
+		}
+	}
+
+	/// The signed difference `self - earlier` in nanoseconds, assuming both are less than 68
+	/// years apart.
+	fn nanos_since(self, earlier: SntpTimestamp) -> i64 {
+		fixed_to_nanos(i128::from(self.0.wrapping_sub(earlier.0) as i64))
+	}
+}
+
+/// Convert a signed 32.32 fixed point number of seconds to nanoseconds.
+fn fixed_to_nanos(fixed: i128) -> i64 {
+	((fixed * 1_000_000_000) >> 32) as i64
+}
+
+/// A source of the local time for an SNTP client.
+///
+/// The time does not need to be correct (finding its offset is the purpose of SNTP), but it
+/// needs to advance steadily while a request is pending. It is implemented for closures
+/// returning the time.
+pub trait SntpClock {
+	/// The current local time.
+	fn now(&mut self) -> SntpTimestamp;
+}
+
+impl<F: FnMut() -> SntpTimestamp> SntpClock for F {
+	fn now(&mut self) -> SntpTimestamp {
+		self()
+	}
+}
+
+/// Warning of an impending leap second, sent along with the time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SntpLeap {
+	/// No leap second is scheduled.
+	None,
+	/// The last minute of the current day has 61 seconds.
+	Insert,
+	/// The last minute of the current day has 59 seconds.
+	Delete,
+}
+
+/// Errors returned by the SNTP clients.
+#[derive(Debug)]
+pub enum SntpError<E, D> {
+	/// The UDP socket failed.
+	Transport(E),
+	/// The server name could not be resolved.
+	Dns(D),
+	/// No response arrived in time.
+	Timeout,
+	/// The response is not a valid SNTP server response to the request.
+	InvalidResponse,
+	/// The server is not synchronized to a time source.
+	Unsynchronized,
+	/// The server sent a kiss-o'-death message with the given code (like `DENY` or `RATE`),
+	/// asking the client to stop sending requests to it or reduce their rate.
+	KissOfDeath([u8; 4]),
+	/// No servers were given.
+	NoServers,
+}
+
+/// A valid time returned by an SNTP server.
+///
+/// Offset and delay are computed as in [RFC 4330, section 5], from the local times of sending
+/// the request and receiving the response, and the server times of receiving the request and
+/// sending the response.
+///
+/// [RFC 4330, section 5]: https://tools.ietf.org/html/rfc4330#section-5
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SntpResponse {
+	/// The server that sent the response
+	pub server: SocketAddr,
+	/// The stratum of the server, 1 for primary servers
+	pub stratum: u8,
+	/// A warning of an impending leap second
+	pub leap: SntpLeap,
+	/// The offset in nanoseconds to add to the local time to get the server's time
+	pub offset: i64,
+	/// The round-trip delay of the request
+	pub delay: Duration,
+	/// The server's time at the moment the response was received
+	pub time: SntpTimestamp,
+}
+
+/// A request to an SNTP server, independent of the transport.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SntpRequest {
+	/// The local time the request is sent at
+	pub transmit: SntpTimestamp,
+}
+
+impl SntpRequest {
+	/// The message to send to the server.
+	pub fn message(&self) -> [u8; SNTP_MESSAGE_LEN] {
+		let mut message = [0; SNTP_MESSAGE_LEN];
+		// No leap warning, version 4, client mode
+		message[0] = (4 << 3) | 3;
+		message[40..].copy_from_slice(&self.transmit.0.to_be_bytes());
+		message
+	}
+
+	/// Evaluate a message received from `server` at local time `receive`.
+	///
+	/// Returns `None` if the message is not a response to this request, and should be ignored.
+	pub fn evaluate<E, D>(
+		&self,
+		message: &[u8],
+		server: SocketAddr,
+		receive: SntpTimestamp,
+	) -> Option<Result<SntpResponse, SntpError<E, D>>> {
+		let timestamp = |offset: usize| {
+			let mut bytes = [0; 8];
+			bytes.copy_from_slice(&message[offset..offset + 8]);
+			SntpTimestamp(u64::from_be_bytes(bytes))
+		};
+		let transmit = self.transmit;
+		if message.len() < SNTP_MESSAGE_LEN || timestamp(24) != transmit {
+			return None;
+		}
+
+		let leap = message[0] >> 6;
+		let version = (message[0] >> 3) & 0x7;
+		let mode = message[0] & 0x7;
+		let stratum = message[1];
+		let server_receive = timestamp(32);
+		let server_transmit = timestamp(40);
+		if !matches!(version, 3 | 4) || mode != 4 || server_transmit.0 == 0 {
+			return Some(Err(SntpError::InvalidResponse));
+		}
+		if stratum == 0 {
+			let mut code = [0; 4];
+			code.copy_from_slice(&message[12..16]);
+			return Some(Err(SntpError::KissOfDeath(code)));
+		}
+		if leap == 3 || stratum > 15 {
+			return Some(Err(SntpError::Unsynchronized));
+		}
+
+		let offset = fixed_to_nanos(
+			(i128::from(server_receive.0.wrapping_sub(transmit.0) as i64)
+				+ i128::from(server_transmit.0.wrapping_sub(receive.0) as i64))
+				/ 2,
+		);
+		let delay = receive.nanos_since(transmit) - server_transmit.nanos_since(server_receive);
+		let time = receive
+			.0
+			.wrapping_add_signed(((i128::from(offset) << 32) / 1_000_000_000) as i64);
+		Some(Ok(SntpResponse {
+			server,
+			stratum,
+			leap: match leap {
+				1 => SntpLeap::Insert,
+				2 => SntpLeap::Delete,
+				_ => SntpLeap::None,
+			},
+			offset,
+			delay: Duration::from_nanos(delay.max(0) as u64),
+			time: SntpTimestamp(time),
+		}))
+	}
+}
+
+/// An SNTP client (see [RFC 4330]) on top of any [`UdpClientStack`] and [`Dns`] implementation.
+///
+/// Servers are queried in turn until one of them returns a valid time. Each query blocks (by
+/// spinning on [`nb::Error::WouldBlock`]) until a response arrives, or the timeout measured by
+/// the client's [`SntpClock`] expires.
+///
+/// [RFC 4330]: https://tools.ietf.org/html/rfc4330
+pub struct SntpClient<S, D, C> {
+	stack: S,
+	dns: D,
+	clock: C,
+	timeout: Duration,
+}
+
+impl<S: UdpClientStack, D: Dns, C: SntpClock> SntpClient<S, D, C> {
+	/// Create a client that sends requests through `stack`, resolves server names through `dns`,
+	/// and takes the local time from `clock`.
+	pub fn new(stack: S, dns: D, clock: C) -> Self {
+		SntpClient {
+			stack,
+			dns,
+			clock,
+			timeout: DEFAULT_TIMEOUT,
+		}
+	}
+
+	/// Set the time to wait for a response from a single server; the default is 5 seconds.
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
+	/// Query the given servers, given by name or IP address, in turn until one of them returns a
+	/// valid time.
+	///
+	/// If none does, the error of the last one is returned.
+	pub fn query(
+		&mut self,
+		servers: &[&str],
+	) -> Result<SntpResponse, SntpError<S::Error, D::Error>> {
+		let mut result = Err(SntpError::NoServers);
+		for server in servers {
+			result = match server.parse::<IpAddr>() {
+				Ok(addr) => Ok(addr),
+				Err(_) => nb::block!(self.dns.get_host_by_name(server, AddrType::Either))
+					.map_err(SntpError::Dns),
+			}
+			.and_then(|addr| self.query_addr(SocketAddr::new(addr, SNTP_PORT)));
+			if result.is_ok() {
+				break;
+			}
+		}
+		result
+	}
+
+	/// Query a single server.
+	pub fn query_addr(
+		&mut self,
+		server: SocketAddr,
+	) -> Result<SntpResponse, SntpError<S::Error, D::Error>> {
+		let mut socket = self.stack.socket().map_err(SntpError::Transport)?;
+		let result = self.exchange(&mut socket, server);
+		self.stack.close(socket).ok();
+		result
+	}
+
+	fn exchange(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		server: SocketAddr,
+	) -> Result<SntpResponse, SntpError<S::Error, D::Error>> {
+		self.stack
+			.connect(socket, server)
+			.map_err(SntpError::Transport)?;
+		let request = SntpRequest {
+			transmit: self.clock.now(),
+		};
+		nb::block!(self.stack.send(socket, &request.message())).map_err(SntpError::Transport)?;
+
+		let mut buffer = [0; SNTP_MESSAGE_LEN];
+		loop {
+			let result = self.stack.receive(socket, &mut buffer);
+			let receive = self.clock.now();
+			match result {
+				Ok((len, remote)) if remote == server => {
+					if let Some(result) = request.evaluate(&buffer[..len], server, receive) {
+						return result;
+					}
+				}
+				Ok(_) => (),
+				Err(nb::Error::WouldBlock) => (),
+				Err(nb::Error::Other(e)) => return Err(SntpError::Transport(e)),
+			}
+			if receive.nanos_since(request.transmit) as i128 > self.timeout.as_nanos() as i128 {
+				return Err(SntpError::Timeout);
+			}
+		}
+	}
+}
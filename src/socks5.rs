@@ -0,0 +1,433 @@
+use crate::{nb, TcpClientStack, TcpError, TcpErrorKind};
+use core::net::{IpAddr, SocketAddr};
+
+/// Username and password for authenticating with a SOCKS5 proxy (see [RFC 1929]).
+///
+/// [RFC 1929]: https://tools.ietf.org/html/rfc1929
+#[derive(Copy, Clone, Debug)]
+pub struct Socks5Credentials<'a> {
+	/// The username, at most 255 bytes long
+	pub username: &'a str,
+	/// The password, at most 255 bytes long
+	pub password: &'a str,
+}
+
+/// Errors returned by [`Socks5Stack`].
+#[derive(Debug)]
+pub enum Socks5Error<E> {
+	/// The underlying TCP socket failed.
+	Transport(E),
+	/// The proxy sent a message that does not follow the SOCKS5 protocol.
+	Protocol,
+	/// The proxy does not accept any of the offered authentication methods.
+	NoAcceptableMethod,
+	/// The proxy rejected the credentials.
+	AuthenticationFailed,
+	/// The proxy could not establish the connection; the SOCKS5 reply code is given.
+	Rejected(u8),
+	/// The host name or credentials are longer than 255 bytes.
+	InvalidInput,
+	/// The socket is not connected, or the proxy closed the connection during the handshake.
+	Closed,
+}
+
+impl<E: TcpError> TcpError for Socks5Error<E> {
+	fn kind(&self) -> TcpErrorKind {
+		match self {
+			Socks5Error::Transport(e) => e.kind(),
+			Socks5Error::Closed => TcpErrorKind::PipeClosed,
+			_ => TcpErrorKind::Other,
+		}
+	}
+}
+
+/// Progress of a [`Socks5Socket`] through the SOCKS5 handshake
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+	/// The connection to the proxy is not established yet.
+	Idle,
+	/// Waiting for the proxy's choice of authentication method
+	Greeting,
+	/// Waiting for the result of username/password authentication
+	Authentication,
+	/// Waiting for the fixed part of the reply to the CONNECT request
+	Request,
+	/// Waiting for the rest of the reply to the CONNECT request
+	Reply,
+	/// The tunnel is established.
+	Connected,
+}
+
+/// Destination of a CONNECT request
+#[derive(Copy, Clone)]
+enum Target<'a> {
+	Addr(SocketAddr),
+	Host(&'a str, u16),
+}
+
+/// Size of the buffer holding SOCKS5 messages; the largest is a username/password request.
+const MESSAGE_LEN: usize = 3 + 255 + 255;
+
+/// A SOCKS5 proxy client (see [RFC 1928]) on top of any [`TcpClientStack`].
+///
+/// Sockets connect to the proxy, which in turn connects to the actual remote host; once
+/// [`.connect()`](TcpClientStack::connect) returned `Ok`, data is tunnelled unchanged. Remote
+/// hosts can also be given by name through [`.connect_to_host()`](Socks5Stack::connect_to_host),
+/// leaving name resolution to the proxy.
+///
+/// ```
+/// use embedded_nal::{Socks5Error, Socks5Stack, TcpClientStack, nb};
+/// use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+/// # struct Proxy { replies: &'static [&'static [u8]], pending: &'static [u8], hang_up: bool }
+/// # #[derive(Debug)] struct Error;
+/// # impl embedded_nal::TcpError for Error {
+/// #   fn kind(&self) -> embedded_nal::TcpErrorKind { embedded_nal::TcpErrorKind::Other }
+/// # }
+/// # fn addr() -> SocketAddr { SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 1080)) }
+/// # impl TcpClientStack for Proxy {
+/// #   type TcpSocket = ();
+/// #   type Error = Error;
+/// #   fn socket(&mut self) -> Result<(), Error> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Error> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Error> {
+/// #     if let Some((reply, rest)) = self.replies.split_first() {
+/// #       self.pending = reply;
+/// #       self.replies = rest;
+/// #     }
+/// #     Ok(buffer.len())
+/// #   }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<usize, Error> {
+/// #     let len = buffer.len().min(self.pending.len());
+/// #     buffer[..len].copy_from_slice(&self.pending[..len]);
+/// #     self.pending = &self.pending[len..];
+/// #     match len { 0 if !self.hang_up => Err(nb::Error::WouldBlock), len => Ok(len) }
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), Error> { Ok(()) }
+/// #   fn local_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// #   fn peer_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// # }
+/// # let driver = Proxy {
+/// #   replies: &[&[5, 0], &[5, 0, 0, 1, 10, 0, 0, 1, 0, 80]],
+/// #   pending: &[],
+/// #   hang_up: false,
+/// # };
+/// let proxy = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 1080));
+/// let mut stack = Socks5Stack::new(driver, proxy, None);
+///
+/// let mut socket = stack.socket()?;
+/// nb::block!(stack.connect_to_host(&mut socket, "example.com", 80))?;
+/// stack.send(&mut socket, b"GET / HTTP/1.0\r\n\r\n")?;
+///
+/// // A proxy that hangs up during the handshake fails the connection.
+/// # let driver = Proxy { replies: &[&[5]], pending: &[], hang_up: true };
+/// let mut stack = Socks5Stack::new(driver, proxy, None);
+/// let mut socket = stack.socket()?;
+/// let result = nb::block!(stack.connect_to_host(&mut socket, "example.com", 80));
+/// assert!(matches!(result, Err(Socks5Error::Closed)));
+/// # Ok::<(), nb::Error<embedded_nal::Socks5Error<Error>>>(())
+/// ```
+///
+/// [RFC 1928]: https://tools.ietf.org/html/rfc1928
+pub struct Socks5Stack<'c, T> {
+	stack: T,
+	proxy: SocketAddr,
+	credentials: Option<Socks5Credentials<'c>>,
+}
+
+/// A socket of a [`Socks5Stack`].
+pub struct Socks5Socket<S> {
+	socket: S,
+	state: State,
+	target: Option<SocketAddr>,
+	buffer: [u8; MESSAGE_LEN],
+	sent: usize,
+	message_len: usize,
+	received: usize,
+	expected: usize,
+}
+
+impl<S> Socks5Socket<S> {
+	/// Send `message_len` bytes from the buffer, then wait for `expected` bytes in `state`.
+	fn start(&mut self, state: State, message_len: usize, expected: usize) {
+		self.state = state;
+		self.sent = 0;
+		self.message_len = message_len;
+		self.received = 0;
+		self.expected = expected;
+	}
+}
+
+impl<'c, T: TcpClientStack> Socks5Stack<'c, T> {
+	/// Create a SOCKS5 client that connects to `proxy` through `stack`.
+	///
+	/// If `credentials` are given, username/password authentication is offered to the proxy in
+	/// addition to no authentication.
+	pub fn new(stack: T, proxy: SocketAddr, credentials: Option<Socks5Credentials<'c>>) -> Self {
+		Socks5Stack {
+			stack,
+			proxy,
+			credentials,
+		}
+	}
+
+	/// Connect to the given remote host name and port through the proxy.
+	///
+	/// The name is resolved by the proxy. Like [`.connect()`](TcpClientStack::connect), this
+	/// returns [`nb::Error::WouldBlock`] until the tunnel is established.
+	pub fn connect_to_host(
+		&mut self,
+		socket: &mut Socks5Socket<T::TcpSocket>,
+		host: &str,
+		port: u16,
+	) -> nb::Result<(), Socks5Error<T::Error>> {
+		self.drive(socket, Target::Host(host, port))
+	}
+
+	fn drive(
+		&mut self,
+		socket: &mut Socks5Socket<T::TcpSocket>,
+		target: Target<'_>,
+	) -> nb::Result<(), Socks5Error<T::Error>> {
+		loop {
+			match socket.state {
+				State::Connected => return Ok(()),
+				State::Idle => {
+					self.stack
+						.connect(&mut socket.socket, self.proxy)
+						.map_err(|e| e.map(Socks5Error::Transport))?;
+					let len = encode_greeting(&mut socket.buffer, self.credentials.is_some());
+					socket.start(State::Greeting, len, 2);
+				}
+				_ => {
+					while socket.sent < socket.message_len {
+						let len = self
+							.stack
+							.send(
+								&mut socket.socket,
+								&socket.buffer[socket.sent..socket.message_len],
+							)
+							.map_err(|e| e.map(Socks5Error::Transport))?;
+						if len == 0 {
+							return Err(nb::Error::WouldBlock);
+						}
+						socket.sent += len;
+					}
+					while socket.received < socket.expected {
+						let len = self
+							.stack
+							.receive(
+								&mut socket.socket,
+								&mut socket.buffer[socket.received..socket.expected],
+							)
+							.map_err(|e| e.map(Socks5Error::Transport))?;
+						if len == 0 {
+							return Err(nb::Error::Other(Socks5Error::Closed));
+						}
+						socket.received += len;
+					}
+					self.advance(socket, target)?;
+				}
+			}
+		}
+	}
+
+	/// Evaluate a complete message from the proxy, and start the next step of the handshake.
+	fn advance(
+		&mut self,
+		socket: &mut Socks5Socket<T::TcpSocket>,
+		target: Target<'_>,
+	) -> Result<(), Socks5Error<T::Error>> {
+		let reply = &socket.buffer[..socket.received];
+		match socket.state {
+			State::Greeting => match (reply, self.credentials) {
+				([5, 0], _) => self.request(socket, target),
+				([5, 2], Some(credentials)) => {
+					let len = encode_credentials(&mut socket.buffer, &credentials)?;
+					socket.start(State::Authentication, len, 2);
+					Ok(())
+				}
+				([5, 0xff], _) => Err(Socks5Error::NoAcceptableMethod),
+				_ => Err(Socks5Error::Protocol),
+			},
+			State::Authentication => match reply {
+				[1, 0] => self.request(socket, target),
+				_ => Err(Socks5Error::AuthenticationFailed),
+			},
+			State::Request => {
+				let expected = reply_len(reply)?;
+				socket.state = State::Reply;
+				socket.expected = expected;
+				Ok(())
+			}
+			State::Reply => {
+				socket.state = State::Connected;
+				socket.target = match target {
+					Target::Addr(remote) => Some(remote),
+					Target::Host(..) => None,
+				};
+				Ok(())
+			}
+			State::Idle | State::Connected => Err(Socks5Error::Protocol),
+		}
+	}
+
+	fn request(
+		&mut self,
+		socket: &mut Socks5Socket<T::TcpSocket>,
+		target: Target<'_>,
+	) -> Result<(), Socks5Error<T::Error>> {
+		let len = encode_request(&mut socket.buffer, target)?;
+		// The fixed part of the reply, plus the first byte of the address
+		socket.start(State::Request, len, 5);
+		Ok(())
+	}
+}
+
+impl<'c, T: TcpClientStack> TcpClientStack for Socks5Stack<'c, T> {
+	type TcpSocket = Socks5Socket<T::TcpSocket>;
+	type Error = Socks5Error<T::Error>;
+
+	fn socket(&mut self) -> Result<Self::TcpSocket, Self::Error> {
+		Ok(Socks5Socket {
+			socket: self.stack.socket().map_err(Socks5Error::Transport)?,
+			state: State::Idle,
+			target: None,
+			buffer: [0; MESSAGE_LEN],
+			sent: 0,
+			message_len: 0,
+			received: 0,
+			expected: 0,
+		})
+	}
+
+	fn connect(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		remote: SocketAddr,
+	) -> nb::Result<(), Self::Error> {
+		self.drive(socket, Target::Addr(remote))
+	}
+
+	fn send(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffer: &[u8],
+	) -> nb::Result<usize, Self::Error> {
+		if socket.state != State::Connected {
+			return Err(nb::Error::Other(Socks5Error::Closed));
+		}
+		self.stack
+			.send(&mut socket.socket, buffer)
+			.map_err(|e| e.map(Socks5Error::Transport))
+	}
+
+	fn receive(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<usize, Self::Error> {
+		if socket.state != State::Connected {
+			return Err(nb::Error::Other(Socks5Error::Closed));
+		}
+		self.stack
+			.receive(&mut socket.socket, buffer)
+			.map_err(|e| e.map(Socks5Error::Transport))
+	}
+
+	fn close(&mut self, socket: Self::TcpSocket) -> Result<(), Self::Error> {
+		self.stack
+			.close(socket.socket)
+			.map_err(Socks5Error::Transport)
+	}
+
+	fn local_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		self.stack
+			.local_addr(&socket.socket)
+			.map_err(Socks5Error::Transport)
+	}
+
+	/// The remote address is the one passed to [`.connect()`](TcpClientStack::connect); for
+	/// connections established by host name, it is the proxy's address.
+	fn peer_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		match socket.target {
+			Some(remote) => Ok(remote),
+			None => self
+				.stack
+				.peer_addr(&socket.socket)
+				.map_err(Socks5Error::Transport),
+		}
+	}
+}
+
+/// Write the method selection message, returning its length.
+fn encode_greeting(buffer: &mut [u8], offer_credentials: bool) -> usize {
+	match offer_credentials {
+		true => {
+			buffer[..4].copy_from_slice(&[5, 2, 0, 2]);
+			4
+		}
+		false => {
+			buffer[..3].copy_from_slice(&[5, 1, 0]);
+			3
+		}
+	}
+}
+
+/// Write the username/password request, returning its length.
+fn encode_credentials<E>(
+	buffer: &mut [u8],
+	credentials: &Socks5Credentials<'_>,
+) -> Result<usize, Socks5Error<E>> {
+	let username = credentials.username.as_bytes();
+	let password = credentials.password.as_bytes();
+	let username_len = u8::try_from(username.len()).map_err(|_| Socks5Error::InvalidInput)?;
+	let password_len = u8::try_from(password.len()).map_err(|_| Socks5Error::InvalidInput)?;
+
+	buffer[0] = 1;
+	buffer[1] = username_len;
+	buffer[2..][..username.len()].copy_from_slice(username);
+	let rest = &mut buffer[2 + username.len()..];
+	rest[0] = password_len;
+	rest[1..][..password.len()].copy_from_slice(password);
+	Ok(3 + username.len() + password.len())
+}
+
+/// Write the CONNECT request, returning its length.
+fn encode_request<E>(buffer: &mut [u8], target: Target<'_>) -> Result<usize, Socks5Error<E>> {
+	buffer[..3].copy_from_slice(&[5, 1, 0]);
+	let (address_len, port) = match target {
+		Target::Addr(remote) => {
+			match remote.ip() {
+				IpAddr::V4(ip) => {
+					buffer[3] = 1;
+					buffer[4..8].copy_from_slice(&ip.octets());
+				}
+				IpAddr::V6(ip) => {
+					buffer[3] = 4;
+					buffer[4..20].copy_from_slice(&ip.octets());
+				}
+			}
+			(if remote.is_ipv4() { 4 } else { 16 }, remote.port())
+		}
+		Target::Host(host, port) => {
+			let host = host.as_bytes();
+			buffer[3] = 3;
+			buffer[4] = u8::try_from(host.len()).map_err(|_| Socks5Error::InvalidInput)?;
+			buffer[5..][..host.len()].copy_from_slice(host);
+			(1 + host.len(), port)
+		}
+	};
+	buffer[4 + address_len..][..2].copy_from_slice(&port.to_be_bytes());
+	Ok(6 + address_len)
+}
+
+/// Check the first five bytes of a reply to the CONNECT request, returning its total length.
+fn reply_len<E>(reply: &[u8]) -> Result<usize, Socks5Error<E>> {
+	match *reply {
+		[5, 0, 0, 1, _] => Ok(4 + 4 + 2),
+		[5, 0, 0, 4, _] => Ok(4 + 16 + 2),
+		[5, 0, 0, 3, len] => Ok(4 + 1 + usize::from(len) + 2),
+		[5, code, ..] if code != 0 => Err(Socks5Error::Rejected(code)),
+		_ => Err(Socks5Error::Protocol),
+	}
+}
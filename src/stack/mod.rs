@@ -1,7 +1,12 @@
+mod raw;
 mod share;
 mod tcp;
 mod udp;
 
+pub use raw::RawIpStack;
 pub use share::{SharableStack, SharedStack};
 pub use tcp::{TcpClientStack, TcpError, TcpErrorKind, TcpFullStack};
-pub use udp::{UdpClientStack, UdpFullStack};
+pub use udp::{
+	gather_datagram, UdpClientStack, UdpFullStack, UdpMulticastStack, UdpTimestampStack,
+	UdpVectoredError, UDP_VECTORED_SEND_LEN,
+};
@@ -0,0 +1,79 @@
+use core::net::IpAddr;
+
+/// This trait is implemented by IP stacks that allow sending and receiving raw IP payloads. It can
+/// be used to implement protocols other than TCP and UDP, such as IGMP, directly on top of IP.
+///
+/// Sockets are opened for a single IP protocol number (or IPv6 next header value), and exchange
+/// the payloads of IP packets carrying that protocol; the IP header itself is built and parsed by
+/// the stack.
+pub trait RawIpStack {
+	/// The type returned when we create a new raw socket
+	type RawSocket;
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Open a socket for the given IP protocol number.
+	fn socket(&mut self, protocol: u8) -> Result<Self::RawSocket, Self::Error>;
+
+	/// Send an IP payload to a remote host.
+	///
+	/// The local address can be left unspecified (`0.0.0.0` / `[::]`), in which case the stack
+	/// picks a suitable one.
+	fn send_to(
+		&mut self,
+		socket: &mut Self::RawSocket,
+		local: IpAddr,
+		remote: IpAddr,
+		buffer: &[u8],
+	) -> nb::Result<(), Self::Error>;
+
+	/// Receive the payload of an IP packet carrying the socket's protocol.
+	///
+	/// If the received payload exceeds the buffer's length, it is received regardless, and the
+	/// remaining bytes are discarded. The full payload size is still indicated in the result,
+	/// allowing the recipient to detect that truncation.
+	///
+	/// The local and remote address are given, in that order, in the result along with the number
+	/// of bytes. If a packet has not been received when called, then [`nb::Error::WouldBlock`]
+	/// should be returned.
+	fn receive(
+		&mut self,
+		socket: &mut Self::RawSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<(usize, IpAddr, IpAddr), Self::Error>;
+
+	/// Close an existing raw socket.
+	fn close(&mut self, socket: Self::RawSocket) -> Result<(), Self::Error>;
+}
+
+impl<T: RawIpStack> RawIpStack for &mut T {
+	type Error = T::Error;
+
+	type RawSocket = T::RawSocket;
+
+	fn socket(&mut self, protocol: u8) -> Result<Self::RawSocket, Self::Error> {
+		T::socket(self, protocol)
+	}
+
+	fn send_to(
+		&mut self,
+		socket: &mut Self::RawSocket,
+		local: IpAddr,
+		remote: IpAddr,
+		buffer: &[u8],
+	) -> nb::Result<(), Self::Error> {
+		T::send_to(self, socket, local, remote, buffer)
+	}
+
+	fn receive(
+		&mut self,
+		socket: &mut Self::RawSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<(usize, IpAddr, IpAddr), Self::Error> {
+		T::receive(self, socket, buffer)
+	}
+
+	fn close(&mut self, socket: Self::RawSocket) -> Result<(), Self::Error> {
+		T::close(self, socket)
+	}
+}
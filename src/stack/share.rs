@@ -1,6 +1,9 @@
-use crate::{nb, TcpClientStack, TcpFullStack, UdpClientStack, UdpFullStack};
+use crate::{
+	nb, RawIpStack, TcpClientStack, TcpFullStack, UdpClientStack, UdpFullStack, UdpMulticastStack,
+	UdpTimestampStack, UdpVectoredError,
+};
 use core::cell::RefCell;
-use core::net::SocketAddr;
+use core::net::{IpAddr, SocketAddr};
 
 /// Sharable wrapper for a network stack implementation.
 ///
@@ -77,7 +80,7 @@ impl<T> SharableStack<T> {
 	}
 
 	/// Returns a shared reference to the driver that can be used as a first-class implementation.
-	pub fn acquire(&self) -> SharedStack<T> {
+	pub fn acquire(&self) -> SharedStack<'_, T> {
 		SharedStack { stack: &self.stack }
 	}
 }
@@ -107,6 +110,7 @@ where
 	forward! {socket() -> Result<Self::UdpSocket, Self::Error>}
 	forward! {connect(socket: &mut Self::UdpSocket, address: SocketAddr) -> Result<(), Self::Error>}
 	forward! {send(socket: &mut Self::UdpSocket, data: &[u8]) -> Result<(), nb::Error<<T as UdpClientStack>::Error>>}
+	forward! {send_vectored(socket: &mut Self::UdpSocket, data: &[&[u8]]) -> Result<(), nb::Error<UdpVectoredError<<T as UdpClientStack>::Error>>>}
 	forward! {receive(socket: &mut Self::UdpSocket, data: &mut [u8]) -> Result<(usize, SocketAddr), nb::Error<<T as UdpClientStack>::Error>>}
 	forward! {close(socket: Self::UdpSocket) -> Result<(), Self::Error>}
 }
@@ -119,6 +123,24 @@ where
 	forward! {send_to(socket: &mut Self::UdpSocket, remote: SocketAddr, buffer: &[u8]) -> Result<(), nb::Error<<T as UdpClientStack>::Error>>}
 }
 
+impl<'a, T> UdpMulticastStack for SharedStack<'a, T>
+where
+	T: UdpMulticastStack,
+{
+	forward! {join_multicast_group(socket: &mut Self::UdpSocket, group: IpAddr) -> Result<(), <T as UdpClientStack>::Error>}
+	forward! {leave_multicast_group(socket: &mut Self::UdpSocket, group: IpAddr) -> Result<(), <T as UdpClientStack>::Error>}
+}
+
+impl<'a, T> UdpTimestampStack for SharedStack<'a, T>
+where
+	T: UdpTimestampStack,
+{
+	type Instant = T::Instant;
+
+	forward! {now() -> Self::Instant}
+	forward! {receive_timestamped(socket: &mut Self::UdpSocket, data: &mut [u8]) -> Result<(usize, SocketAddr, Self::Instant), nb::Error<<T as UdpClientStack>::Error>>}
+}
+
 impl<'a, T> TcpClientStack for SharedStack<'a, T>
 where
 	T: TcpClientStack,
@@ -129,8 +151,11 @@ where
 	forward! {socket() -> Result<Self::TcpSocket, Self::Error>}
 	forward! {connect(socket: &mut Self::TcpSocket, address: SocketAddr) -> Result<(), nb::Error<<T as TcpClientStack>::Error>>}
 	forward! {send(socket: &mut Self::TcpSocket, data: &[u8]) -> Result<usize, nb::Error<<T as TcpClientStack>::Error>>}
+	forward! {send_vectored(socket: &mut Self::TcpSocket, data: &[&[u8]]) -> Result<usize, nb::Error<<T as TcpClientStack>::Error>>}
 	forward! {receive(socket: &mut Self::TcpSocket, data: &mut [u8]) -> Result<usize, nb::Error<<T as TcpClientStack>::Error>>}
 	forward! {close(socket: Self::TcpSocket) -> Result<(), Self::Error>}
+	forward! {local_addr(socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error>}
+	forward! {peer_addr(socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error>}
 }
 
 impl<'a, T> TcpFullStack for SharedStack<'a, T>
@@ -141,3 +166,16 @@ where
 	forward! {listen(socket: &mut Self::TcpSocket) -> Result<(), <T as TcpClientStack>::Error>}
 	forward! {accept(socket: &mut Self::TcpSocket) -> Result<(<T as TcpClientStack>::TcpSocket, SocketAddr), nb::Error<<T as TcpClientStack>::Error>>}
 }
+
+impl<'a, T> RawIpStack for SharedStack<'a, T>
+where
+	T: RawIpStack,
+{
+	type RawSocket = T::RawSocket;
+	type Error = T::Error;
+
+	forward! {socket(protocol: u8) -> Result<Self::RawSocket, Self::Error>}
+	forward! {send_to(socket: &mut Self::RawSocket, local: IpAddr, remote: IpAddr, buffer: &[u8]) -> Result<(), nb::Error<<T as RawIpStack>::Error>>}
+	forward! {receive(socket: &mut Self::RawSocket, buffer: &mut [u8]) -> Result<(usize, IpAddr, IpAddr), nb::Error<<T as RawIpStack>::Error>>}
+	forward! {close(socket: Self::RawSocket) -> Result<(), <T as RawIpStack>::Error>}
+}
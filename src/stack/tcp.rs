@@ -1,5 +1,8 @@
 use core::net::SocketAddr;
 
+/// Size of the buffer the provided vectored send implementation gathers small buffers into.
+const VECTORED_SEND_BUFFER_LEN: usize = 512;
+
 /// Represents specific errors encountered during TCP operations.
 #[non_exhaustive]
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -7,6 +10,9 @@ pub enum TcpErrorKind {
 	/// The socket has been closed in the direction in which the failing operation was attempted.
 	PipeClosed,
 
+	/// The TLS session on top of the connection failed, e.g. because the handshake was rejected.
+	Tls,
+
 	/// Some other error has occurred.
 	Other,
 }
@@ -53,6 +59,37 @@ pub trait TcpClientStack {
 		buffer: &[u8],
 	) -> nb::Result<usize, Self::Error>;
 
+	/// Write the concatenation of `buffers` to the stream.
+	///
+	/// Returns the number of bytes written (which may be less than the total length of `buffers`)
+	/// or an error.
+	///
+	/// There is a provided implementation that copies leading buffers into a bounded buffer on
+	/// the stack (or passes on the first non-empty buffer directly if it is large enough) and
+	/// writes that through [`TcpClientStack::send`], but may be provided more efficiently by
+	/// implementers that support scatter/gather natively.
+	fn send_vectored(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffers: &[&[u8]],
+	) -> nb::Result<usize, Self::Error> {
+		let mut scratch = [0; VECTORED_SEND_BUFFER_LEN];
+		let mut len = 0;
+		for buffer in buffers.iter().filter(|buffer| !buffer.is_empty()) {
+			if len == 0 && buffer.len() >= scratch.len() {
+				return self.send(socket, buffer);
+			}
+
+			let chunk = buffer.len().min(scratch.len() - len);
+			scratch[len..][..chunk].copy_from_slice(&buffer[..chunk]);
+			len += chunk;
+			if len == scratch.len() {
+				break;
+			}
+		}
+		self.send(socket, &scratch[..len])
+	}
+
 	/// Receive data from the stream.
 	///
 	/// Returns `Ok(n)`, which means `n` bytes of data have been received and
@@ -67,6 +104,23 @@ pub trait TcpClientStack {
 
 	/// Close an existing TCP socket.
 	fn close(&mut self, socket: Self::TcpSocket) -> Result<(), Self::Error>;
+
+	/// Query the local address of a socket.
+	///
+	/// This is the address and port the stack picked for a connected socket, or the address a
+	/// listening socket or accepted connection is bound to. Stacks that can not obtain the local
+	/// address may present an invalid one, as described for the UDP traits.
+	///
+	/// Returns an error if the socket is neither connected nor bound.
+	fn local_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error>;
+
+	/// Query the remote address of a connected socket.
+	///
+	/// For sockets obtained through [`TcpFullStack::accept`], this is the same address that was
+	/// returned along with the socket.
+	///
+	/// Returns an error if the socket is not connected.
+	fn peer_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error>;
 }
 
 /// This trait is implemented by TCP/IP stacks that expose TCP server functionality. TCP servers
@@ -120,6 +174,14 @@ impl<T: TcpClientStack> TcpClientStack for &mut T {
 		T::send(self, socket, buffer)
 	}
 
+	fn send_vectored(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffers: &[&[u8]],
+	) -> nb::Result<usize, Self::Error> {
+		T::send_vectored(self, socket, buffers)
+	}
+
 	fn receive(
 		&mut self,
 		socket: &mut Self::TcpSocket,
@@ -131,4 +193,12 @@ impl<T: TcpClientStack> TcpClientStack for &mut T {
 	fn close(&mut self, socket: Self::TcpSocket) -> Result<(), Self::Error> {
 		T::close(self, socket)
 	}
+
+	fn local_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		T::local_addr(self, socket)
+	}
+
+	fn peer_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		T::peer_addr(self, socket)
+	}
 }
@@ -1,4 +1,18 @@
-use core::net::SocketAddr;
+use core::net::{IpAddr, SocketAddr};
+
+/// Size of the buffer the provided vectored send implementations gather datagrams into.
+///
+/// This is the largest UDP payload that fits into an unfragmented IPv4 packet on an Ethernet link.
+pub const UDP_VECTORED_SEND_LEN: usize = 1472;
+
+/// Error returned by the vectored sends of the UDP traits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpVectoredError<E> {
+	/// The datagram is longer than the implementation can gather, and was not sent.
+	TooLong,
+	/// Sending the datagram failed.
+	Send(E),
+}
 
 /// This trait is implemented by UDP/IP stacks. You could, for example, have
 /// an implementation which knows how to send AT commands to an ESP8266 WiFi
@@ -29,6 +43,64 @@ pub trait UdpClientStack {
 	/// or the last one used in `UdpServerStack::write_to`.
 	fn send(&mut self, socket: &mut Self::UdpSocket, buffer: &[u8]) -> nb::Result<(), Self::Error>;
 
+	/// Send a datagram, gathered from the concatenation of `buffers`, to the remote host.
+	///
+	/// The remote host used is the same as in [`UdpClientStack::send`].
+	///
+	/// There is a provided implementation that copies the buffers into a bounded buffer on the
+	/// stack and sends that, but may be provided more efficiently by implementers that support
+	/// scatter/gather natively.
+	///
+	/// The provided implementation gathers datagrams of up to [`UDP_VECTORED_SEND_LEN`] bytes,
+	/// and fails with [`UdpVectoredError::TooLong`] without sending anything for longer ones,
+	/// unless they are held in a single buffer. Implementers used with larger datagrams should
+	/// provide their own.
+	///
+	/// ```
+	/// use embedded_nal::{nb, UdpClientStack, UdpVectoredError};
+	/// use core::net::SocketAddr;
+	///
+	/// struct Recorder(Vec<usize>);
+	///
+	/// impl UdpClientStack for Recorder {
+	///     type UdpSocket = ();
+	///     type Error = ();
+	///     fn socket(&mut self) -> Result<(), ()> { Ok(()) }
+	///     fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), ()> { Ok(()) }
+	///     fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<(), ()> {
+	///         self.0.push(buffer.len());
+	///         Ok(())
+	///     }
+	///     fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+	///         Err(nb::Error::WouldBlock)
+	///     }
+	///     fn close(&mut self, _: ()) -> Result<(), ()> { Ok(()) }
+	/// }
+	///
+	/// let mut stack = Recorder(Vec::new());
+	/// let (header, payload) = ([0; 8], [0; 1400]);
+	/// stack.send_vectored(&mut (), &[&header, &payload]).unwrap();
+	/// stack.send_vectored(&mut (), &[&[], &[0; 2000], &[]]).unwrap();
+	/// assert_eq!(stack.0, [1408, 2000]);
+	///
+	/// // A longer datagram is not cut off.
+	/// let result = stack.send_vectored(&mut (), &[&header, &[0; 2000]]);
+	/// assert_eq!(result, Err(nb::Error::Other(UdpVectoredError::TooLong)));
+	/// let result = stack.send_vectored(&mut (), &[&[0; 2000], &header]);
+	/// assert_eq!(result, Err(nb::Error::Other(UdpVectoredError::TooLong)));
+	/// assert_eq!(stack.0.len(), 2);
+	/// ```
+	fn send_vectored(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		buffers: &[&[u8]],
+	) -> nb::Result<(), UdpVectoredError<Self::Error>> {
+		let mut scratch = [0; UDP_VECTORED_SEND_LEN];
+		let datagram = gather_datagram(buffers, &mut scratch).ok_or(UdpVectoredError::TooLong)?;
+		self.send(socket, datagram)
+			.map_err(|e| e.map(UdpVectoredError::Send))
+	}
+
 	/// Read a datagram the remote host has sent to us.
 	///
 	/// Returns `Ok((n, remote))`, which means a datagram of size `n` has been
@@ -60,6 +132,53 @@ pub trait UdpFullStack: UdpClientStack {
 	) -> nb::Result<(), Self::Error>;
 }
 
+/// This trait is implemented by UDP/IP stacks that can receive multicast datagrams.
+///
+/// A socket bound through [`UdpFullStack::bind`] receives datagrams sent to the multicast groups
+/// it joined, in addition to those sent to the stack's unicast addresses.
+pub trait UdpMulticastStack: UdpFullStack {
+	/// Join the multicast group `group` on a bound socket.
+	///
+	/// The group is joined on the stack's default interface for the group's address family.
+	fn join_multicast_group(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		group: IpAddr,
+	) -> Result<(), Self::Error>;
+
+	/// Leave a multicast group previously joined through
+	/// [`.join_multicast_group()`](UdpMulticastStack::join_multicast_group).
+	fn leave_multicast_group(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		group: IpAddr,
+	) -> Result<(), Self::Error>;
+}
+
+/// This trait is implemented by UDP/IP stacks that can tell when a datagram arrived.
+///
+/// The timestamp is taken by the hardware or the driver when the datagram is received, and is
+/// thus independent of when the application gets around to calling
+/// [`.receive_timestamped()`](UdpTimestampStack::receive_timestamped). It is given in a clock
+/// domain defined by the stack, which can be queried through [`.now()`](UdpTimestampStack::now).
+pub trait UdpTimestampStack: UdpClientStack {
+	/// A point in time in the stack's clock domain
+	type Instant: Copy + Ord + core::fmt::Debug;
+
+	/// Query the current time of the clock that receive timestamps are taken from.
+	fn now(&mut self) -> Self::Instant;
+
+	/// Read a datagram the remote host has sent to us, along with the time it was received.
+	///
+	/// This behaves like [`UdpClientStack::receive`], and additionally returns the receive
+	/// timestamp of the datagram.
+	fn receive_timestamped(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<(usize, SocketAddr, Self::Instant), Self::Error>;
+}
+
 impl<T: UdpClientStack> UdpClientStack for &mut T {
 	type Error = T::Error;
 
@@ -81,6 +200,14 @@ impl<T: UdpClientStack> UdpClientStack for &mut T {
 		T::send(self, socket, buffer)
 	}
 
+	fn send_vectored(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		buffers: &[&[u8]],
+	) -> nb::Result<(), UdpVectoredError<Self::Error>> {
+		T::send_vectored(self, socket, buffers)
+	}
+
 	fn receive(
 		&mut self,
 		socket: &mut Self::UdpSocket,
@@ -108,3 +235,58 @@ impl<T: UdpFullStack> UdpFullStack for &mut T {
 		T::send_to(self, socket, remote, buffer)
 	}
 }
+
+impl<T: UdpMulticastStack> UdpMulticastStack for &mut T {
+	fn join_multicast_group(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		group: IpAddr,
+	) -> Result<(), Self::Error> {
+		T::join_multicast_group(self, socket, group)
+	}
+
+	fn leave_multicast_group(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		group: IpAddr,
+	) -> Result<(), Self::Error> {
+		T::leave_multicast_group(self, socket, group)
+	}
+}
+
+impl<T: UdpTimestampStack> UdpTimestampStack for &mut T {
+	type Instant = T::Instant;
+
+	fn now(&mut self) -> Self::Instant {
+		T::now(self)
+	}
+
+	fn receive_timestamped(
+		&mut self,
+		socket: &mut Self::UdpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<(usize, SocketAddr, Self::Instant), Self::Error> {
+		T::receive_timestamped(self, socket, buffer)
+	}
+}
+
+/// Gather the datagram made of the concatenation of `buffers`, for vectored sends.
+///
+/// A datagram held in a single non-empty buffer is returned as is; others are copied into
+/// `scratch`. Returns `None` if the datagram does not fit into `scratch`.
+pub fn gather_datagram<'a>(buffers: &[&'a [u8]], scratch: &'a mut [u8]) -> Option<&'a [u8]> {
+	let mut non_empty = buffers.iter().filter(|buffer| !buffer.is_empty());
+	match (non_empty.next(), non_empty.next()) {
+		(None, _) => return Some(&[]),
+		(Some(buffer), None) => return Some(*buffer),
+		_ => {}
+	}
+
+	let mut len = 0;
+	for buffer in buffers {
+		let end = len + buffer.len();
+		scratch.get_mut(len..end)?.copy_from_slice(buffer);
+		len = end;
+	}
+	Some(&scratch[..len])
+}
@@ -0,0 +1,83 @@
+use crate::InterfaceAddress;
+use core::net::IpAddr;
+
+/// An IP configuration applied to the network stack, as reported by
+/// [`NetworkStatus::ip_config`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IpConfig {
+	/// The local address, along with the prefix length of its subnet
+	pub address: InterfaceAddress,
+	/// The default gateway, if any
+	pub gateway: Option<IpAddr>,
+}
+
+/// This trait is implemented by network stacks that can report the state of their link and IP
+/// configuration.
+///
+/// Applications can use it to wait for the network to become usable before connecting, and to
+/// react to the link going down or the configuration changing (e.g. when a DHCP lease is renewed
+/// with a different address) through [`.wait_config_change()`](NetworkStatus::wait_config_change).
+pub trait NetworkStatus {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Wait until the link is up and an IP configuration has been applied.
+	///
+	/// Returns `Ok` once the stack is configured. Otherwise, this function should return
+	/// [`nb::Error::WouldBlock`].
+	fn wait_config_up(&mut self) -> nb::Result<(), Self::Error>;
+
+	/// Wait until the link is down.
+	///
+	/// Returns `Ok` once the link is down. Otherwise, this function should return
+	/// [`nb::Error::WouldBlock`].
+	fn wait_link_down(&mut self) -> nb::Result<(), Self::Error>;
+
+	/// Wait until the IP configurations or DNS servers change.
+	///
+	/// Returns `Ok` once they changed since this function last returned `Ok`, or since the stack
+	/// was created, so that no change is missed between calls; the first configuration applied
+	/// counts as a change, as does losing it when the link goes down. Otherwise, this function
+	/// should return [`nb::Error::WouldBlock`]. The new configuration can then be queried through
+	/// [`.ip_config()`](NetworkStatus::ip_config) and
+	/// [`.dns_servers()`](NetworkStatus::dns_servers).
+	fn wait_config_change(&mut self) -> nb::Result<(), Self::Error>;
+
+	/// List the IP configurations currently applied to the stack.
+	///
+	/// A stack typically has at most one IPv4 configuration, and may have several IPv6
+	/// configurations. The configurations are stored at the beginning of `result`. The total
+	/// number of configurations is returned, which is 0 while the stack is not configured; if it
+	/// exceeds the length of `result`, the remaining configurations are not stored.
+	fn ip_config(&mut self, result: &mut [IpConfig]) -> Result<usize, Self::Error>;
+
+	/// List the DNS servers currently used by the stack.
+	///
+	/// The addresses are stored at the beginning of `result`. The total number of servers is
+	/// returned; if it exceeds the length of `result`, the remaining addresses are not stored.
+	fn dns_servers(&mut self, result: &mut [IpAddr]) -> Result<usize, Self::Error>;
+}
+
+impl<T: NetworkStatus> NetworkStatus for &mut T {
+	type Error = T::Error;
+
+	fn wait_config_up(&mut self) -> nb::Result<(), Self::Error> {
+		T::wait_config_up(self)
+	}
+
+	fn wait_link_down(&mut self) -> nb::Result<(), Self::Error> {
+		T::wait_link_down(self)
+	}
+
+	fn wait_config_change(&mut self) -> nb::Result<(), Self::Error> {
+		T::wait_config_change(self)
+	}
+
+	fn ip_config(&mut self, result: &mut [IpConfig]) -> Result<usize, Self::Error> {
+		T::ip_config(self, result)
+	}
+
+	fn dns_servers(&mut self, result: &mut [IpAddr]) -> Result<usize, Self::Error> {
+		T::dns_servers(self, result)
+	}
+}
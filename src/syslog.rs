@@ -0,0 +1,770 @@
+use crate::{nb, TcpClientStack, UdpClientStack};
+use core::fmt::{self, Write};
+use core::net::SocketAddr;
+use core::time::Duration;
+
+/// The well-known port of syslog collectors over UDP, see [RFC 5426]
+///
+/// [RFC 5426]: https://tools.ietf.org/html/rfc5426
+pub const SYSLOG_PORT: u16 = 514;
+
+/// The well-known port of syslog collectors over TCP, see [RFC 6587]
+///
+/// [RFC 6587]: https://tools.ietf.org/html/rfc6587
+pub const SYSLOG_TCP_PORT: u16 = 601;
+
+/// Length messages are truncated to, half of what collectors should accept according to
+/// [RFC 5424, section 6.1]
+///
+/// [RFC 5424, section 6.1]: https://tools.ietf.org/html/rfc5424#section-6.1
+pub const SYSLOG_MESSAGE_LEN: usize = 1024;
+
+/// The value of header fields and structured data that are left out
+const NIL: &str = "-";
+
+/// Maximum length of the octet count preceding messages sent over TCP, with the space
+/// following it
+pub const SYSLOG_OCTET_COUNT_LEN: usize = 6;
+
+/// The facility of a syslog message, which tells what kind of program sent it, see
+/// [RFC 5424, section 6.2.1].
+///
+/// [RFC 5424, section 6.2.1]: https://tools.ietf.org/html/rfc5424#section-6.2.1
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyslogFacility {
+	Kernel = 0,
+	User = 1,
+	Mail = 2,
+	Daemon = 3,
+	Auth = 4,
+	Syslog = 5,
+	Printer = 6,
+	News = 7,
+	Uucp = 8,
+	Cron = 9,
+	AuthPriv = 10,
+	Ftp = 11,
+	Ntp = 12,
+	Audit = 13,
+	Alert = 14,
+	Clock = 15,
+	Local0 = 16,
+	Local1 = 17,
+	Local2 = 18,
+	Local3 = 19,
+	Local4 = 20,
+	Local5 = 21,
+	Local6 = 22,
+	Local7 = 23,
+}
+
+/// The severity of a syslog message, see [RFC 5424, section 6.2.1].
+///
+/// [RFC 5424, section 6.2.1]: https://tools.ietf.org/html/rfc5424#section-6.2.1
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SyslogSeverity {
+	/// The system is unusable
+	Emergency = 0,
+	/// Action must be taken immediately
+	Alert = 1,
+	/// Critical conditions
+	Critical = 2,
+	/// Error conditions
+	Error = 3,
+	/// Warning conditions
+	Warning = 4,
+	/// Normal but significant conditions
+	Notice = 5,
+	/// Informational messages
+	Informational = 6,
+	/// Debug-level messages
+	Debug = 7,
+}
+
+/// An element of the structured data of a syslog message, see [RFC 5424, section 6.3].
+///
+/// Identifiers other than those registered with IANA take the form `name@<enterprise number>`.
+///
+/// [RFC 5424, section 6.3]: https://tools.ietf.org/html/rfc5424#section-6.3
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SyslogStructuredData<'a> {
+	/// The identifier of the element, like `meta` or `sensor@32473`
+	pub id: &'a str,
+	/// The parameters of the element, as their name and value
+	pub params: &'a [(&'a str, &'a str)],
+}
+
+/// A syslog message in the format of [RFC 5424].
+///
+/// Header fields that are empty are sent as the nil value `-`; characters that are not allowed
+/// in them are replaced by `_`, and they are cut off at the length limits of the RFC.
+///
+/// ```
+/// use embedded_nal::{SyslogFacility, SyslogMessage, SyslogSeverity, SyslogStructuredData};
+/// use core::time::Duration;
+///
+/// let reading = [SyslogStructuredData {
+///     id: "sensor@32473",
+///     params: &[("id", "t1"), ("value", "21.5")],
+/// }];
+/// let mut message = SyslogMessage::new(
+///     SyslogFacility::Local0,
+///     SyslogSeverity::Notice,
+///     format_args!("temperature {} above limit", "21.5"),
+/// );
+/// message.timestamp = Some(Duration::from_millis(1_700_000_000_250));
+/// message.hostname = "sensor-7";
+/// message.app_name = "monitor";
+/// message.structured_data = &reading;
+///
+/// let mut buffer = [0; 256];
+/// let len = message.write(&mut buffer);
+/// assert_eq!(
+///     &buffer[..len],
+///     b"<133>1 2023-11-14T22:13:20.250000Z sensor-7 monitor - - \
+///       [sensor@32473 id=\"t1\" value=\"21.5\"] temperature 21.5 above limit",
+/// );
+/// ```
+///
+/// [RFC 5424]: https://tools.ietf.org/html/rfc5424
+#[derive(Copy, Clone, Debug)]
+pub struct SyslogMessage<'a> {
+	/// The kind of program sending the message
+	pub facility: SyslogFacility,
+	/// The severity of the message
+	pub severity: SyslogSeverity,
+	/// The time since the Unix epoch at which the message was created, if it is known
+	pub timestamp: Option<Duration>,
+	/// The name or address of the device sending the message
+	pub hostname: &'a str,
+	/// The name of the program sending the message
+	pub app_name: &'a str,
+	/// The process ID or other identifier of the instance of the program
+	pub proc_id: &'a str,
+	/// The type of the message
+	pub msg_id: &'a str,
+	/// The structured data of the message
+	pub structured_data: &'a [SyslogStructuredData<'a>],
+	/// The free-form text of the message
+	pub message: fmt::Arguments<'a>,
+}
+
+impl<'a> SyslogMessage<'a> {
+	/// Create a message with the given text, leaving out the other fields.
+	pub fn new(
+		facility: SyslogFacility,
+		severity: SyslogSeverity,
+		message: fmt::Arguments<'a>,
+	) -> Self {
+		SyslogMessage {
+			facility,
+			severity,
+			timestamp: None,
+			hostname: "",
+			app_name: "",
+			proc_id: "",
+			msg_id: "",
+			structured_data: &[],
+			message,
+		}
+	}
+
+	/// Write the message into `buffer`, returning its length.
+	///
+	/// Messages that do not fit into `buffer` are truncated.
+	pub fn write(&self, buffer: &mut [u8]) -> usize {
+		let mut out = Truncating { buffer, len: 0 };
+		self.write_to(&mut out).ok();
+		out.len
+	}
+
+	fn write_to(&self, out: &mut Truncating<'_>) -> fmt::Result {
+		let priority = self.facility as u8 * 8 + self.severity as u8;
+		write!(out, "<{priority}>1 ")?;
+		match self.timestamp {
+			Some(timestamp) => write_timestamp(out, timestamp)?,
+			None => out.write_str(NIL)?,
+		}
+		for (field, max_len) in [
+			(self.hostname, 255),
+			(self.app_name, 48),
+			(self.proc_id, 128),
+			(self.msg_id, 32),
+		] {
+			out.write_char(' ')?;
+			write_name(out, field, max_len, "")?;
+		}
+
+		out.write_char(' ')?;
+		if self.structured_data.is_empty() {
+			out.write_str(NIL)?;
+		}
+		for element in self.structured_data {
+			out.write_char('[')?;
+			write_name(out, element.id, 32, "=]\"")?;
+			for (name, value) in element.params {
+				out.write_char(' ')?;
+				write_name(out, name, 32, "=]\"")?;
+				out.write_str("=\"")?;
+				for c in value.chars() {
+					if matches!(c, '"' | '\\' | ']') {
+						out.write_char('\\')?;
+					}
+					out.write_char(c)?;
+				}
+				out.write_char('"')?;
+			}
+			out.write_char(']')?;
+		}
+
+		// The message is left out along with the space before it if it is empty.
+		let len = out.len;
+		out.write_char(' ')?;
+		out.write_fmt(self.message)?;
+		if out.len == len + 1 {
+			out.len = len;
+		}
+		Ok(())
+	}
+}
+
+/// Write a header field or structured data name, replacing characters that are not printable
+/// ASCII or in `forbidden`.
+fn write_name(
+	out: &mut Truncating<'_>,
+	name: &str,
+	max_len: usize,
+	forbidden: &str,
+) -> fmt::Result {
+	if name.is_empty() {
+		return out.write_str(NIL);
+	}
+	for c in name.chars().take(max_len) {
+		match c {
+			'!'..='~' if !forbidden.contains(c) => out.write_char(c)?,
+			_ => out.write_char('_')?,
+		}
+	}
+	Ok(())
+}
+
+/// Write the time since the Unix epoch as an RFC 3339 timestamp in UTC.
+fn write_timestamp(out: &mut Truncating<'_>, since_epoch: Duration) -> fmt::Result {
+	let secs = since_epoch.as_secs();
+	let (year, month, day) = civil_from_days(secs / 86400);
+	let time = secs % 86400;
+	write!(
+		out,
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+		time / 3600,
+		time / 60 % 60,
+		time % 60,
+		since_epoch.subsec_micros()
+	)
+}
+
+/// The date of the day `days` after the Unix epoch, in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+	// Counting from 0000-03-01, so that leap days end a year, in eras of 400 years
+	let days = days + 719_468;
+	let era = days / 146_097;
+	let day_of_era = days % 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let year = era * 400 + year_of_era;
+	match month {
+		0..=9 => (year, month + 3, day),
+		_ => (year + 1, month - 9, day),
+	}
+}
+
+/// Writes into a buffer, cutting off what does not fit at a character boundary.
+struct Truncating<'a> {
+	buffer: &'a mut [u8],
+	len: usize,
+}
+
+impl Write for Truncating<'_> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let space = self.buffer.len() - self.len;
+		let mut len = s.len().min(space);
+		while !s.is_char_boundary(len) {
+			len -= 1;
+		}
+		self.buffer[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+		self.len += len;
+		match len == s.len() {
+			true => Ok(()),
+			false => Err(fmt::Error),
+		}
+	}
+}
+
+/// A queue of formatted syslog messages held in `Q` bytes, which drops the oldest messages
+/// when a new one does not fit, so that logging never blocks on a slow network.
+///
+/// Each message takes its length and 2 bytes; messages longer than the queue allows are
+/// truncated.
+///
+/// ```
+/// use embedded_nal::{SyslogFacility, SyslogMessage, SyslogQueue, SyslogSeverity};
+/// use embedded_nal::SYSLOG_OCTET_COUNT_LEN;
+///
+/// let mut queue = SyslogQueue::<64>::new();
+/// for i in 0..3 {
+///     let text = format_args!("reading {i}");
+///     queue.push(&SyslogMessage::new(SyslogFacility::User, SyslogSeverity::Informational, text));
+/// }
+/// // Only two messages fit, so the first one was dropped.
+/// assert_eq!(queue.dropped(), 1);
+/// assert_eq!(queue.front(), Some(&b"<14>1 - - - - - - reading 1"[..]));
+///
+/// // Over a stream, each message is preceded by its length.
+/// let mut count = [0; SYSLOG_OCTET_COUNT_LEN];
+/// let len = queue.front_octet_count(&mut count);
+/// assert_eq!(&count[..len], b"27 ");
+/// queue.pop();
+/// assert_eq!(queue.front(), Some(&b"<14>1 - - - - - - reading 2"[..]));
+/// queue.pop();
+/// assert!(queue.is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyslogQueue<const Q: usize> {
+	buffer: [u8; Q],
+	len: usize,
+	held: bool,
+	dropped: usize,
+}
+
+impl<const Q: usize> Default for SyslogQueue<Q> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const Q: usize> SyslogQueue<Q> {
+	/// Create an empty queue.
+	pub const fn new() -> Self {
+		SyslogQueue {
+			buffer: [0; Q],
+			len: 0,
+			held: false,
+			dropped: 0,
+		}
+	}
+
+	/// Format `message` and append it, dropping the oldest messages if there is no room.
+	pub fn push(&mut self, message: &SyslogMessage<'_>) {
+		let mut formatted = [0; SYSLOG_MESSAGE_LEN];
+		let max_len = SYSLOG_MESSAGE_LEN.min(Q.saturating_sub(2));
+		let len = message.write(&mut formatted[..max_len]);
+		while Q - self.len < 2 + len {
+			// A message that is partly sent is kept, as the collector would not be able to
+			// tell where the next one starts.
+			let oldest = match self.held {
+				true => self.record_len(0),
+				false => 0,
+			};
+			if oldest >= self.len {
+				self.dropped += 1;
+				return;
+			}
+			let end = oldest + self.record_len(oldest);
+			self.buffer.copy_within(end..self.len, oldest);
+			self.len -= end - oldest;
+			self.dropped += 1;
+		}
+		self.buffer[self.len..self.len + 2].copy_from_slice(&(len as u16).to_be_bytes());
+		self.buffer[self.len + 2..self.len + 2 + len].copy_from_slice(&formatted[..len]);
+		self.len += 2 + len;
+	}
+
+	/// The oldest message, if any.
+	pub fn front(&self) -> Option<&[u8]> {
+		match self.len {
+			0 => None,
+			_ => Some(&self.buffer[2..self.record_len(0)]),
+		}
+	}
+
+	/// Remove the oldest message, once it was sent.
+	pub fn pop(&mut self) {
+		if self.len > 0 {
+			let end = self.record_len(0);
+			self.buffer.copy_within(end..self.len, 0);
+			self.len -= end;
+		}
+		self.held = false;
+	}
+
+	/// Keep the oldest message from being dropped (until it is [popped](SyslogQueue::pop)),
+	/// while it is partly sent over a stream.
+	pub fn hold_front(&mut self, hold: bool) {
+		self.held = hold && self.len > 0;
+	}
+
+	/// Write the octet count framing the oldest message on a stream into `buffer`, see
+	/// [RFC 6587, section 3.4.1], returning its length, or 0 if the queue is empty.
+	///
+	/// [RFC 6587, section 3.4.1]: https://tools.ietf.org/html/rfc6587#section-3.4.1
+	pub fn front_octet_count(&self, buffer: &mut [u8; SYSLOG_OCTET_COUNT_LEN]) -> usize {
+		let Some(message) = self.front() else {
+			return 0;
+		};
+		let mut out = Truncating { buffer, len: 0 };
+		write!(out, "{} ", message.len()).ok();
+		out.len
+	}
+
+	/// Whether there are no messages in the queue.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// The number of messages that were dropped, as they did not fit into the queue.
+	pub fn dropped(&self) -> usize {
+		self.dropped
+	}
+
+	/// The length of the message stored at `offset`, with its length.
+	fn record_len(&self, offset: usize) -> usize {
+		2 + usize::from(u16::from_be_bytes([
+			self.buffer[offset],
+			self.buffer[offset + 1],
+		]))
+	}
+}
+
+/// A syslog client sending messages to a collector in UDP datagrams through any
+/// [`UdpClientStack`], see [RFC 5426].
+///
+/// Messages are queued in a [`SyslogQueue`] of `Q` bytes and sent as far as the stack accepts
+/// them without blocking; when the stack falls behind, the oldest messages are dropped.
+///
+/// ```
+/// use embedded_nal::{SyslogClient, SyslogFacility, SyslogMessage, SyslogSeverity};
+/// use embedded_nal::{UdpClientStack, SYSLOG_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+///
+/// fn report<S: UdpClientStack>(stack: S, temperature: f32) -> Result<(), S::Error> {
+///     let collector = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), SYSLOG_PORT);
+///     let mut client: SyslogClient<_> = SyslogClient::new(stack, collector);
+///
+///     let text = format_args!("temperature {temperature}");
+///     let mut message = SyslogMessage::new(SyslogFacility::Local0, SyslogSeverity::Notice, text);
+///     message.app_name = "monitor";
+///     client.send(&message)
+/// }
+/// ```
+///
+/// [RFC 5426]: https://tools.ietf.org/html/rfc5426
+pub struct SyslogClient<S: UdpClientStack, const Q: usize = 2048> {
+	stack: S,
+	collector: SocketAddr,
+	socket: Option<S::UdpSocket>,
+	queue: SyslogQueue<Q>,
+}
+
+impl<S: UdpClientStack, const Q: usize> SyslogClient<S, Q> {
+	/// Create a client sending messages through `stack` to `collector`, which usually listens
+	/// on [`SYSLOG_PORT`].
+	pub fn new(stack: S, collector: SocketAddr) -> Self {
+		SyslogClient {
+			stack,
+			collector,
+			socket: None,
+			queue: SyslogQueue::new(),
+		}
+	}
+
+	/// Queue `message`, and send the queued messages as far as the stack accepts them.
+	///
+	/// Messages stay queued if sending them fails.
+	pub fn send(&mut self, message: &SyslogMessage<'_>) -> Result<(), S::Error> {
+		self.queue.push(message);
+		self.flush()
+	}
+
+	/// Send the queued messages as far as the stack accepts them.
+	pub fn flush(&mut self) -> Result<(), S::Error> {
+		let socket = match &mut self.socket {
+			Some(socket) => socket,
+			None => {
+				let mut socket = self.stack.socket()?;
+				if let Err(e) = self.stack.connect(&mut socket, self.collector) {
+					self.stack.close(socket).ok();
+					return Err(e);
+				}
+				self.socket.insert(socket)
+			}
+		};
+		while let Some(message) = self.queue.front() {
+			match self.stack.send(socket, message) {
+				Ok(()) => self.queue.pop(),
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => return Err(e),
+			}
+		}
+		Ok(())
+	}
+
+	/// The queue of messages waiting to be sent
+	pub fn queue(&self) -> &SyslogQueue<Q> {
+		&self.queue
+	}
+
+	/// Release the stack, closing the client's socket; queued messages are discarded.
+	pub fn free(mut self) -> S {
+		if let Some(socket) = self.socket.take() {
+			self.stack.close(socket).ok();
+		}
+		self.stack
+	}
+}
+
+/// A syslog client sending messages to a collector over a TCP connection through any
+/// [`TcpClientStack`], framed by octet counting as in [RFC 6587].
+///
+/// Messages are queued in a [`SyslogQueue`] of `Q` bytes and sent as far as the stack accepts
+/// them without blocking; when the stack falls behind, the oldest messages are dropped. The
+/// client connects when it first sends, and again on the next send after the connection
+/// failed.
+///
+/// ```
+/// use embedded_nal::{SyslogFacility, SyslogMessage, SyslogSeverity, SyslogTcpClient};
+/// use embedded_nal::{TcpClientStack, SYSLOG_TCP_PORT, nb};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// # use std::{cell::RefCell, rc::Rc};
+/// # #[derive(Default)]
+/// # struct Wire { received: Vec<u8>, accept: Vec<usize>, connections: usize }
+/// # struct Collector(Rc<RefCell<Wire>>);
+/// # #[derive(Debug)] struct Error;
+/// # impl embedded_nal::TcpError for Error {
+/// #   fn kind(&self) -> embedded_nal::TcpErrorKind { embedded_nal::TcpErrorKind::Other }
+/// # }
+/// # fn addr() -> SocketAddr { SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 601) }
+/// # impl TcpClientStack for Collector {
+/// #   type TcpSocket = ();
+/// #   type Error = Error;
+/// #   fn socket(&mut self) -> Result<(), Error> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Error> {
+/// #     self.0.borrow_mut().connections += 1;
+/// #     Ok(())
+/// #   }
+/// #   // Takes as many bytes as the next entry of `accept` allows, or all once it is empty.
+/// #   fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Error> {
+/// #     let mut wire = self.0.borrow_mut();
+/// #     let len = match wire.accept.is_empty() {
+/// #       true => buffer.len(),
+/// #       false => wire.accept.remove(0).min(buffer.len()),
+/// #     };
+/// #     wire.received.extend_from_slice(&buffer[..len]);
+/// #     Ok(len)
+/// #   }
+/// #   fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<usize, Error> {
+/// #     Err(nb::Error::WouldBlock)
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), Error> { Ok(()) }
+/// #   fn local_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// #   fn peer_addr(&mut self, _: &()) -> Result<SocketAddr, Error> { Ok(addr()) }
+/// # }
+/// # let wire = Rc::new(RefCell::new(Wire::default()));
+/// # let stack = Collector(wire.clone());
+/// let collector = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), SYSLOG_TCP_PORT);
+/// let mut client: SyslogTcpClient<_, 64> = SyslogTcpClient::new(stack, collector);
+/// let message = |text| SyslogMessage::new(SyslogFacility::User, SyslogSeverity::Notice, text);
+///
+/// // The stack takes only the first 4 bytes, and then nothing.
+/// # wire.borrow_mut().accept = vec![4, 0];
+/// client.send(&message(format_args!("first")))?;
+/// # assert_eq!(wire.borrow().received, b"23 <");
+///
+/// // While the stack is busy the queue fills up, but the partly sent message is kept.
+/// # wire.borrow_mut().accept = vec![0, 0];
+/// client.send(&message(format_args!("other")))?;
+/// client.send(&message(format_args!("third")))?;
+/// assert_eq!(client.queue().dropped(), 1);
+///
+/// // Each message is preceded by its length once the stack catches up.
+/// client.flush()?;
+/// assert!(client.queue().is_empty());
+/// # assert_eq!(wire.borrow().received, b"23 <13>1 - - - - - - first23 <13>1 - - - - - - third");
+/// # assert_eq!(wire.borrow().connections, 1);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [RFC 6587]: https://tools.ietf.org/html/rfc6587
+pub struct SyslogTcpClient<S: TcpClientStack, const Q: usize = 2048> {
+	stack: S,
+	collector: SocketAddr,
+	socket: Option<S::TcpSocket>,
+	connected: bool,
+	/// The bytes of the oldest message sent already, with its octet count
+	sent: usize,
+	queue: SyslogQueue<Q>,
+}
+
+impl<S: TcpClientStack, const Q: usize> SyslogTcpClient<S, Q> {
+	/// Create a client sending messages through `stack` to `collector`, which usually listens
+	/// on [`SYSLOG_TCP_PORT`].
+	pub fn new(stack: S, collector: SocketAddr) -> Self {
+		SyslogTcpClient {
+			stack,
+			collector,
+			socket: None,
+			connected: false,
+			sent: 0,
+			queue: SyslogQueue::new(),
+		}
+	}
+
+	/// Queue `message`, and send the queued messages as far as the stack accepts them.
+	///
+	/// Messages stay queued if sending them fails, and are sent on a new connection.
+	pub fn send(&mut self, message: &SyslogMessage<'_>) -> Result<(), S::Error> {
+		self.queue.push(message);
+		self.flush()
+	}
+
+	/// Send the queued messages as far as the stack accepts them.
+	pub fn flush(&mut self) -> Result<(), S::Error> {
+		if self.queue.is_empty() && self.socket.is_none() {
+			return Ok(());
+		}
+		let result = self.transmit();
+		if result.is_err() {
+			self.disconnect();
+		}
+		result
+	}
+
+	/// The queue of messages waiting to be sent
+	pub fn queue(&self) -> &SyslogQueue<Q> {
+		&self.queue
+	}
+
+	/// Release the stack, closing the connection; queued messages are discarded.
+	pub fn free(mut self) -> S {
+		self.disconnect();
+		self.stack
+	}
+
+	fn transmit(&mut self) -> Result<(), S::Error> {
+		let socket = match &mut self.socket {
+			Some(socket) => socket,
+			None => self.socket.insert(self.stack.socket()?),
+		};
+		if !self.connected {
+			match self.stack.connect(socket, self.collector) {
+				Ok(()) => self.connected = true,
+				Err(nb::Error::WouldBlock) => return Ok(()),
+				Err(nb::Error::Other(e)) => return Err(e),
+			}
+		}
+		while let Some(message) = self.queue.front() {
+			let mut count = [0; SYSLOG_OCTET_COUNT_LEN];
+			let count_len = self.queue.front_octet_count(&mut count);
+			let sent = match self.sent.checked_sub(count_len) {
+				None => self
+					.stack
+					.send_vectored(socket, &[&count[self.sent..count_len], message]),
+				Some(sent) => self.stack.send(socket, &message[sent..]),
+			};
+			match sent {
+				// A stack that takes nothing is treated as busy, rather than asked again.
+				Ok(0) | Err(nb::Error::WouldBlock) => break,
+				Ok(len) => self.sent += len,
+				Err(nb::Error::Other(e)) => return Err(e),
+			}
+			if self.sent == count_len + message.len() {
+				self.sent = 0;
+				self.queue.pop();
+			} else {
+				self.queue.hold_front(true);
+			}
+		}
+		Ok(())
+	}
+
+	/// Close the connection, so that the next send starts a new one with a complete message.
+	fn disconnect(&mut self) {
+		if let Some(socket) = self.socket.take() {
+			self.stack.close(socket).ok();
+		}
+		self.connected = false;
+		self.sent = 0;
+		self.queue.hold_front(false);
+	}
+}
+
+/// A [`log`] backend formatting records as syslog messages, and handing them to a closure,
+/// which usually queues them in a [`SyslogClient`] or [`SyslogTcpClient`] behind a lock.
+///
+/// Log levels are mapped to the severities error, warning, informational and debug; the target
+/// of a record is sent as the message type, cut off at 32 characters.
+///
+/// ```
+/// use embedded_nal::{SyslogFacility, SyslogLogger, SyslogMessage};
+///
+/// static LOGGER: SyslogLogger<fn(&SyslogMessage<'_>)> =
+///     SyslogLogger::new(SyslogFacility::User, "sensor-7", "monitor", |_message| {
+///         // ... lock the client and queue `message` ...
+///     });
+///
+/// log::set_logger(&LOGGER).unwrap();
+/// log::set_max_level(log::LevelFilter::Info);
+/// log::info!("started");
+/// ```
+#[cfg(feature = "log")]
+pub struct SyslogLogger<F> {
+	facility: SyslogFacility,
+	hostname: &'static str,
+	app_name: &'static str,
+	emit: F,
+}
+
+#[cfg(feature = "log")]
+impl<F> SyslogLogger<F> {
+	/// Create a backend sending records as messages of `facility` from the program `app_name`
+	/// on `hostname` through `emit`.
+	pub const fn new(
+		facility: SyslogFacility,
+		hostname: &'static str,
+		app_name: &'static str,
+		emit: F,
+	) -> Self {
+		SyslogLogger {
+			facility,
+			hostname,
+			app_name,
+			emit,
+		}
+	}
+}
+
+#[cfg(feature = "log")]
+impl<F: Fn(&SyslogMessage<'_>) + Send + Sync> log::Log for SyslogLogger<F> {
+	fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+		true
+	}
+
+	fn log(&self, record: &log::Record<'_>) {
+		let severity = match record.level() {
+			log::Level::Error => SyslogSeverity::Error,
+			log::Level::Warn => SyslogSeverity::Warning,
+			log::Level::Info => SyslogSeverity::Informational,
+			log::Level::Debug | log::Level::Trace => SyslogSeverity::Debug,
+		};
+		let mut message = SyslogMessage::new(self.facility, severity, *record.args());
+		message.hostname = self.hostname;
+		message.app_name = self.app_name;
+		message.msg_id = record.target();
+		(self.emit)(&message);
+	}
+
+	fn flush(&self) {}
+}
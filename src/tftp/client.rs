@@ -0,0 +1,547 @@
+use super::{
+	fill, send_error, timeout_secs, transfer_port, write_data_header, Options, Packet,
+	PacketWriter, TftpErrorCode, TftpSink, TftpSource, DATA_HEADER_LEN, DEFAULT_BLOCK_SIZE,
+	DEFAULT_RETRIES, DEFAULT_TIMEOUT, MAX_BLOCK_SIZE, MIN_BLOCK_SIZE, OPTION_BLOCK_SIZE,
+	OPTION_TIMEOUT, REQUEST_LEN,
+};
+use crate::{nb, Clock, UdpFullStack};
+use core::net::SocketAddr;
+use core::time::Duration;
+
+/// Errors returned by the transfers of a [`TftpClient`].
+#[derive(Debug)]
+pub enum TftpError<E, F> {
+	/// The stack failed.
+	Transport(E),
+	/// The sink or source of the file failed, and the transfer was aborted.
+	File(F),
+	/// The server aborted the transfer with an error.
+	Remote(TftpErrorCode),
+	/// The server stopped responding.
+	Timeout,
+	/// The server sent a packet that is not valid in the transfer, or acknowledged options
+	/// that were not asked for.
+	Protocol,
+	/// The request does not fit into a packet, or the blocks of the transfer do not fit into
+	/// the buffer.
+	BufferTooSmall,
+}
+
+/// What to do with a packet received from the peer of a transfer
+enum Verdict {
+	/// The packet is the one waited for
+	Accept,
+	/// The packet is stale, and dropped
+	Ignore,
+	/// The packet shows that the last packet sent was lost, which is sent again
+	Retransmit,
+}
+
+/// The server of a transfer
+struct Peer {
+	/// The address the request is sent to
+	server: SocketAddr,
+	/// The address the server replied from, which identifies the transfer
+	transfer: Option<SocketAddr>,
+}
+
+impl Peer {
+	fn addr(&self) -> SocketAddr {
+		self.transfer.unwrap_or(self.server)
+	}
+}
+
+/// A TFTP client (see [RFC 1350]) reading files from and writing them to servers through any
+/// [`UdpFullStack`], for example to fetch a firmware image.
+///
+/// Files are streamed into a [`TftpSink`] and out of a [`TftpSource`] one block at a time, so
+/// they never need to fit into memory; the caller provides a buffer for a single block. The
+/// block size and timeout are negotiated with servers supporting options ([RFC 2347],
+/// [RFC 2348], [RFC 2349]). Only the `octet` mode is supported.
+///
+/// Each transfer blocks (by spinning on [`nb::Error::WouldBlock`]) until it is complete, with
+/// lost packets retransmitted after the timeout of `clock`.
+///
+/// ```
+/// use embedded_nal::{TftpClient, TftpError, TFTP_PORT};
+/// use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// use core::time::Duration;
+/// # use embedded_nal::nb;
+/// # use std::collections::VecDeque;
+/// # /// A server answering each packet sent to it with the next packets of a script
+/// # struct Network { script: VecDeque<Vec<&'static [u8]>>, incoming: VecDeque<&'static [u8]>, sent: Vec<Vec<u8>> }
+/// # impl embedded_nal::UdpClientStack for Network {
+/// #   type UdpSocket = ();
+/// #   type Error = ();
+/// #   fn socket(&mut self) -> Result<(), ()> { Ok(()) }
+/// #   fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn send(&mut self, _: &mut (), _: &[u8]) -> nb::Result<(), ()> { Ok(()) }
+/// #   fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+/// #     let packet = self.incoming.pop_front().ok_or(nb::Error::WouldBlock)?;
+/// #     buffer[..packet.len()].copy_from_slice(packet);
+/// #     Ok((packet.len(), "192.168.1.10:50000".parse().unwrap()))
+/// #   }
+/// #   fn close(&mut self, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # impl embedded_nal::UdpFullStack for Network {
+/// #   fn bind(&mut self, _: &mut (), _: u16) -> Result<(), ()> { Ok(()) }
+/// #   fn send_to(&mut self, _: &mut (), _: SocketAddr, packet: &[u8]) -> nb::Result<(), ()> {
+/// #     self.sent.push(packet.to_vec());
+/// #     self.incoming.extend(self.script.pop_front().unwrap_or_default());
+/// #     Ok(())
+/// #   }
+/// # }
+/// # let mut network = Network {
+/// #   script: VecDeque::from([
+/// #     vec![&b"\0\x06blksize\08\0timeout\01\0"[..]],
+/// #     vec![&b"\0\x03\0\x01firmware"[..]],
+/// #     vec![],
+/// #     vec![&b"\0\x03\0\x02bin"[..]],
+/// #     vec![],
+/// #   ]),
+/// #   incoming: VecDeque::new(),
+/// #   sent: Vec::new(),
+/// # };
+/// # let mut ticks = 0;
+/// # let clock = move || { ticks += 1; Duration::from_millis(100 * ticks) };
+/// let server = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), TFTP_PORT);
+/// let mut client = TftpClient::new(&mut network, clock);
+/// client.set_block_size(8);
+///
+/// let mut image = Vec::new();
+/// let mut store = |data: &[u8]| Ok::<_, ()>(image.extend_from_slice(data));
+/// let len = client.get(server, "firmware.bin", &mut [0; 32], &mut store)?;
+/// assert_eq!(len, 11);
+/// assert_eq!(image, b"firmwarebin");
+///
+/// // The block size was negotiated, and the lost acknowledgment of block 1 sent again.
+/// # drop(client);
+/// assert_eq!(
+///     network.sent,
+///     [
+///         &b"\0\x01firmware.bin\0octet\0blksize\08\0timeout\01\0"[..],
+///         b"\0\x04\0\0",
+///         b"\0\x04\0\x01",
+///         b"\0\x04\0\x01",
+///         b"\0\x04\0\x02",
+///     ],
+/// );
+///
+/// // Writing a file, the server acknowledges block 1 twice, which does not make the client
+/// // send block 2 twice.
+/// # network.script = VecDeque::from([
+/// #   vec![&b"\0\x06blksize\08\0"[..]],
+/// #   vec![&b"\0\x04\0\x01"[..]],
+/// #   vec![&b"\0\x04\0\x01"[..], b"\0\x04\0\x02"],
+/// #   vec![&b"\0\x04\0\x03"[..]],
+/// # ]);
+/// # network.sent.clear();
+/// # let mut ticks = 0;
+/// # let clock = move || { ticks += 1; Duration::from_millis(100 * ticks) };
+/// let mut client = TftpClient::new(&mut network, clock);
+/// client.set_block_size(8);
+/// let mut log: &[u8] = b"boot ok\nselftest ok\n";
+/// let mut load = |buffer: &mut [u8]| {
+///     let len = buffer.len().min(log.len());
+///     buffer[..len].copy_from_slice(&log[..len]);
+///     log = &log[len..];
+///     Ok::<_, ()>(len)
+/// };
+/// assert_eq!(client.put(server, "log.txt", &mut [0; 32], &mut load)?, 20);
+/// # drop(client);
+/// assert_eq!(
+///     network.sent,
+///     [
+///         &b"\0\x02log.txt\0octet\0blksize\08\0timeout\01\0"[..],
+///         b"\0\x03\0\x01boot ok\n",
+///         b"\0\x03\0\x02selftest",
+///         b"\0\x03\0\x03 ok\n",
+///     ],
+/// );
+///
+/// // A server that acknowledges another timeout than asked for is refused.
+/// # network.script = VecDeque::from([vec![&b"\0\x06timeout\05\0"[..]]]);
+/// # network.sent.clear();
+/// # let mut ticks = 0;
+/// # let clock = move || { ticks += 1; Duration::from_millis(100 * ticks) };
+/// let mut client = TftpClient::new(&mut network, clock);
+/// let mut discard = |_: &[u8]| Ok::<_, ()>(());
+/// let result = client.get(server, "firmware.bin", &mut [0; 516], &mut discard);
+/// assert!(matches!(result, Err(TftpError::Protocol)));
+/// # drop(client);
+/// assert!(network.sent.last().unwrap().starts_with(b"\0\x05\0\x08"));
+/// # Ok::<(), TftpError<(), ()>>(())
+/// ```
+///
+/// [RFC 1350]: https://tools.ietf.org/html/rfc1350
+/// [RFC 2347]: https://tools.ietf.org/html/rfc2347
+/// [RFC 2348]: https://tools.ietf.org/html/rfc2348
+/// [RFC 2349]: https://tools.ietf.org/html/rfc2349
+pub struct TftpClient<S: UdpFullStack, C> {
+	stack: S,
+	clock: C,
+	block_size: usize,
+	timeout: Duration,
+	retries: u8,
+	seed: u32,
+}
+
+impl<S: UdpFullStack, C: Clock> TftpClient<S, C> {
+	/// Create a client that transfers files through `stack`, timing retransmissions with
+	/// `clock`.
+	pub fn new(stack: S, clock: C) -> Self {
+		TftpClient {
+			stack,
+			clock,
+			block_size: DEFAULT_BLOCK_SIZE,
+			timeout: DEFAULT_TIMEOUT,
+			retries: DEFAULT_RETRIES,
+			seed: 0,
+		}
+	}
+
+	/// Set the block size asked for, which is limited to what fits into the buffer of a
+	/// transfer; the default is 512. Larger blocks speed up transfers, with 1428 bytes being
+	/// the most that fit into an unfragmented packet on an Ethernet link.
+	pub fn set_block_size(&mut self, block_size: u16) {
+		self.block_size = usize::from(block_size).clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE);
+	}
+
+	/// Set the time after which a packet is sent again if the server did not respond, which is
+	/// also asked of the server in whole seconds; the default is 1 second.
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
+	/// Set the number of times a packet is sent again before the transfer is given up; the
+	/// default is 5.
+	pub fn set_retries(&mut self, retries: u8) {
+		self.retries = retries;
+	}
+
+	/// Read the file `filename` from `server` (usually on [`TFTP_PORT`](crate::TFTP_PORT)),
+	/// writing its data into `sink`, and return its length.
+	///
+	/// `buffer` holds a single data packet, which is a block and 4 bytes; it limits the block
+	/// size asked for, and has to hold at least 516 bytes if the server may not support
+	/// options.
+	pub fn get<K: TftpSink + ?Sized>(
+		&mut self,
+		server: SocketAddr,
+		filename: &str,
+		buffer: &mut [u8],
+		sink: &mut K,
+	) -> Result<usize, TftpError<S::Error, K::Error>> {
+		let mut socket = self.open().map_err(TftpError::Transport)?;
+		let result = self.receive_file(&mut socket, server, filename, buffer, sink);
+		self.stack.close(socket).ok();
+		result
+	}
+
+	/// Write the file `filename` to `server` (usually on [`TFTP_PORT`](crate::TFTP_PORT)),
+	/// reading its data from `source`, and return its length.
+	///
+	/// `buffer` holds a single data packet as for [`.get()`](TftpClient::get).
+	pub fn put<R: TftpSource + ?Sized>(
+		&mut self,
+		server: SocketAddr,
+		filename: &str,
+		buffer: &mut [u8],
+		source: &mut R,
+	) -> Result<usize, TftpError<S::Error, R::Error>> {
+		let mut socket = self.open().map_err(TftpError::Transport)?;
+		let result = self.send_file(&mut socket, server, filename, buffer, source);
+		self.stack.close(socket).ok();
+		result
+	}
+
+	/// Release the stack and clock.
+	pub fn free(self) -> (S, C) {
+		(self.stack, self.clock)
+	}
+
+	/// Open a socket on a random port, whose number identifies the transfer.
+	fn open(&mut self) -> Result<S::UdpSocket, S::Error> {
+		let now = self.clock.now();
+		let port = transfer_port(&mut self.seed, now);
+		let mut socket = self.stack.socket()?;
+		if let Err(e) = self.stack.bind(&mut socket, port) {
+			self.stack.close(socket).ok();
+			return Err(e);
+		}
+		Ok(socket)
+	}
+
+	fn receive_file<K: TftpSink + ?Sized>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		server: SocketAddr,
+		filename: &str,
+		buffer: &mut [u8],
+		sink: &mut K,
+	) -> Result<usize, TftpError<S::Error, K::Error>> {
+		let requested = self.requested_block_size(buffer)?;
+		let mut outgoing = [0; REQUEST_LEN];
+		let mut outgoing_len = self.request(&mut outgoing, false, filename, requested)?;
+		let mut peer = Peer {
+			server,
+			transfer: None,
+		};
+		let mut block_size = None;
+		let mut block: u16 = 1;
+		let mut len = 0;
+		loop {
+			let negotiating = block_size.is_none();
+			let received = self.exchange(
+				socket,
+				&mut peer,
+				&outgoing[..outgoing_len],
+				buffer,
+				|packet| match *packet {
+					Packet::Data { block: data, .. } if data == block => Verdict::Accept,
+					Packet::Data { block: data, .. } if data == block.wrapping_sub(1) => {
+						Verdict::Retransmit
+					}
+					Packet::OptionAck { .. } if negotiating => Verdict::Accept,
+					_ => Verdict::Ignore,
+				},
+			)?;
+			match Packet::parse(&buffer[..received]) {
+				Some(Packet::OptionAck { options }) => {
+					block_size = Some(self.negotiated(socket, &peer, options, requested)?);
+					self.check_buffer(socket, &peer, block_size, buffer.len())?;
+					outgoing_len = PacketWriter::ack(&mut outgoing, 0).unwrap_or_default();
+				}
+				Some(Packet::Data { data, .. }) => {
+					// Servers not supporting options send blocks of the default size.
+					let size = *block_size.get_or_insert(DEFAULT_BLOCK_SIZE);
+					self.check_buffer(socket, &peer, block_size, buffer.len())?;
+					if let Err(e) = sink.write(data) {
+						self.abort(socket, &peer, TftpErrorCode::NOT_DEFINED);
+						return Err(TftpError::File(e));
+					}
+					len += data.len();
+					outgoing_len = PacketWriter::ack(&mut outgoing, block).unwrap_or_default();
+					if data.len() < size {
+						self.send(socket, peer.addr(), &outgoing[..outgoing_len])?;
+						return Ok(len);
+					}
+					block = block.wrapping_add(1);
+				}
+				_ => return Err(TftpError::Protocol),
+			}
+		}
+	}
+
+	fn send_file<R: TftpSource + ?Sized>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		server: SocketAddr,
+		filename: &str,
+		buffer: &mut [u8],
+		source: &mut R,
+	) -> Result<usize, TftpError<S::Error, R::Error>> {
+		let requested = self.requested_block_size(buffer)?;
+		let mut request = [0; REQUEST_LEN];
+		let request_len = self.request(&mut request, true, filename, requested)?;
+		let mut peer = Peer {
+			server,
+			transfer: None,
+		};
+		let mut received = [0; REQUEST_LEN];
+		let reply_len = self.exchange(
+			socket,
+			&mut peer,
+			&request[..request_len],
+			&mut received,
+			|packet| match packet {
+				Packet::Ack { block: 0 } | Packet::OptionAck { .. } => Verdict::Accept,
+				_ => Verdict::Ignore,
+			},
+		)?;
+		let block_size = match Packet::parse(&received[..reply_len]) {
+			Some(Packet::OptionAck { options }) => {
+				self.negotiated(socket, &peer, options, requested)?
+			}
+			// Servers not supporting options acknowledge the request right away.
+			_ => DEFAULT_BLOCK_SIZE,
+		};
+		self.check_buffer(socket, &peer, Some(block_size), buffer.len())?;
+
+		let mut block: u16 = 1;
+		let mut len = 0;
+		loop {
+			let (header, data) = buffer.split_at_mut(DATA_HEADER_LEN);
+			let data_len = match fill(&mut data[..block_size], |data| source.read(data)) {
+				Ok(data_len) => data_len,
+				Err(e) => {
+					self.abort(socket, &peer, TftpErrorCode::NOT_DEFINED);
+					return Err(TftpError::File(e));
+				}
+			};
+			write_data_header(header, block);
+			self.exchange(
+				socket,
+				&mut peer,
+				&buffer[..DATA_HEADER_LEN + data_len],
+				&mut received,
+				// Duplicate acknowledgments are ignored, to not send every block twice from
+				// then on (the "Sorcerer's Apprentice" problem of RFC 1123, section 4.2.3.1).
+				|packet| match *packet {
+					Packet::Ack { block: acked } if acked == block => Verdict::Accept,
+					_ => Verdict::Ignore,
+				},
+			)?;
+			len += data_len;
+			if data_len < block_size {
+				return Ok(len);
+			}
+			block = block.wrapping_add(1);
+		}
+	}
+
+	/// The block size asked for in a transfer into or out of `buffer`.
+	fn requested_block_size<F>(&self, buffer: &[u8]) -> Result<usize, TftpError<S::Error, F>> {
+		let block_size = self
+			.block_size
+			.min(buffer.len().saturating_sub(DATA_HEADER_LEN));
+		match block_size >= MIN_BLOCK_SIZE {
+			true => Ok(block_size),
+			false => Err(TftpError::BufferTooSmall),
+		}
+	}
+
+	/// Write a request asking for `block_size` and the timeout into `buffer`.
+	fn request<F>(
+		&self,
+		buffer: &mut [u8],
+		write: bool,
+		filename: &str,
+		block_size: usize,
+	) -> Result<usize, TftpError<S::Error, F>> {
+		let options = [
+			(OPTION_BLOCK_SIZE, block_size as u32),
+			(OPTION_TIMEOUT, timeout_secs(self.timeout)),
+		];
+		PacketWriter::request(buffer, write, filename, &options).ok_or(TftpError::BufferTooSmall)
+	}
+
+	/// The block size acknowledged by the server, aborting the transfer if it acknowledged
+	/// other options than were asked for.
+	fn negotiated<F>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		peer: &Peer,
+		options: Options<'_>,
+		requested: usize,
+	) -> Result<usize, TftpError<S::Error, F>> {
+		let block_size = match options.get(OPTION_BLOCK_SIZE) {
+			Some(size) => usize::try_from(size)
+				.ok()
+				.filter(|size| (MIN_BLOCK_SIZE..=requested).contains(size)),
+			None => Some(DEFAULT_BLOCK_SIZE),
+		};
+		let timeout = options.get(OPTION_TIMEOUT);
+		match block_size {
+			Some(block_size)
+				if timeout.is_none() || timeout == Some(timeout_secs(self.timeout)) =>
+			{
+				Ok(block_size)
+			}
+			_ => {
+				self.abort(socket, peer, TftpErrorCode::OPTION_REFUSED);
+				Err(TftpError::Protocol)
+			}
+		}
+	}
+
+	/// Abort the transfer if its blocks do not fit into a buffer of `len` bytes.
+	fn check_buffer<F>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		peer: &Peer,
+		block_size: Option<usize>,
+		len: usize,
+	) -> Result<(), TftpError<S::Error, F>> {
+		match block_size {
+			Some(block_size) if DATA_HEADER_LEN + block_size > len => {
+				self.abort(socket, peer, TftpErrorCode::NOT_DEFINED);
+				Err(TftpError::BufferTooSmall)
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Send `outgoing` to the peer, and wait for a packet from it that is accepted, which is
+	/// received into `incoming`; returns the length of that packet.
+	///
+	/// The first packet from the server fixes the port it uses for the transfer. Packets from
+	/// other ports are rejected, and an error from the server aborts the transfer.
+	fn exchange<F>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		peer: &mut Peer,
+		outgoing: &[u8],
+		incoming: &mut [u8],
+		mut accept: impl FnMut(&Packet<'_>) -> Verdict,
+	) -> Result<usize, TftpError<S::Error, F>> {
+		self.send(socket, peer.addr(), outgoing)?;
+		let mut sent = self.clock.now();
+		let mut attempts = 0;
+		loop {
+			match self.stack.receive(socket, incoming) {
+				Ok((len, remote)) => {
+					if peer.transfer.is_none() && remote.ip() == peer.server.ip() {
+						peer.transfer = Some(remote);
+					}
+					if peer.transfer != Some(remote) {
+						self.reject(socket, remote, TftpErrorCode::UNKNOWN_TRANSFER_ID);
+						continue;
+					}
+					let Some(packet) = Packet::parse(&incoming[..len]) else {
+						continue;
+					};
+					if let Packet::Error { code } = packet {
+						return Err(TftpError::Remote(code));
+					}
+					match accept(&packet) {
+						Verdict::Accept => return Ok(len),
+						Verdict::Ignore => (),
+						Verdict::Retransmit => self.send(socket, remote, outgoing)?,
+					}
+				}
+				Err(nb::Error::WouldBlock) => (),
+				Err(nb::Error::Other(e)) => return Err(TftpError::Transport(e)),
+			}
+
+			let now = self.clock.now();
+			if now.saturating_sub(sent) >= self.timeout {
+				if attempts >= self.retries {
+					return Err(TftpError::Timeout);
+				}
+				attempts += 1;
+				self.send(socket, peer.addr(), outgoing)?;
+				sent = now;
+			}
+		}
+	}
+
+	fn send<F>(
+		&mut self,
+		socket: &mut S::UdpSocket,
+		remote: SocketAddr,
+		packet: &[u8],
+	) -> Result<(), TftpError<S::Error, F>> {
+		nb::block!(self.stack.send_to(socket, remote, packet)).map_err(TftpError::Transport)
+	}
+
+	/// Tell the peer that the transfer is aborted.
+	fn abort(&mut self, socket: &mut S::UdpSocket, peer: &Peer, code: TftpErrorCode) {
+		self.reject(socket, peer.addr(), code);
+	}
+
+	/// Send an error to `remote` that does not take part in the transfer.
+	fn reject(&mut self, socket: &mut S::UdpSocket, remote: SocketAddr, code: TftpErrorCode) {
+		send_error(&mut self.stack, socket, remote, code, code.message());
+	}
+}
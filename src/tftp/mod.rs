@@ -0,0 +1,391 @@
+use crate::UdpFullStack;
+use core::net::SocketAddr;
+use core::time::Duration;
+
+mod client;
+mod server;
+
+pub use client::{TftpClient, TftpError};
+pub use server::{TftpFiles, TftpServer};
+
+/// The well-known port of TFTP servers
+pub const TFTP_PORT: u16 = 69;
+
+/// The block size of transfers without a negotiated block size
+const DEFAULT_BLOCK_SIZE: usize = 512;
+
+/// The smallest block size that can be negotiated, see [RFC 2348]
+///
+/// [RFC 2348]: https://tools.ietf.org/html/rfc2348
+const MIN_BLOCK_SIZE: usize = 8;
+
+/// The largest block size that can be negotiated, see [RFC 2348]
+///
+/// [RFC 2348]: https://tools.ietf.org/html/rfc2348
+const MAX_BLOCK_SIZE: usize = 65464;
+
+/// Length of the header of data packets
+const DATA_HEADER_LEN: usize = 4;
+
+/// Size of the buffers requests, option acknowledgments and errors are written to
+const REQUEST_LEN: usize = 512;
+
+/// Retransmission timeout unless configured otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of retransmissions before a transfer is given up unless configured otherwise
+const DEFAULT_RETRIES: u8 = 5;
+
+/// The only transfer mode supported
+const MODE_OCTET: &str = "octet";
+
+/// The block size option, see [RFC 2348]
+///
+/// [RFC 2348]: https://tools.ietf.org/html/rfc2348
+const OPTION_BLOCK_SIZE: &str = "blksize";
+
+/// The timeout option, see [RFC 2349]
+///
+/// [RFC 2349]: https://tools.ietf.org/html/rfc2349
+const OPTION_TIMEOUT: &str = "timeout";
+
+const OPCODE_READ: u16 = 1;
+const OPCODE_WRITE: u16 = 2;
+const OPCODE_DATA: u16 = 3;
+const OPCODE_ACK: u16 = 4;
+const OPCODE_ERROR: u16 = 5;
+const OPCODE_OPTION_ACK: u16 = 6;
+
+/// The code of a TFTP error, see [RFC 1350, appendix I] and [RFC 2347].
+///
+/// [RFC 1350, appendix I]: https://tools.ietf.org/html/rfc1350#appendix-I
+/// [RFC 2347]: https://tools.ietf.org/html/rfc2347
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TftpErrorCode(pub u16);
+
+impl TftpErrorCode {
+	/// An error described by the message only
+	pub const NOT_DEFINED: Self = TftpErrorCode(0);
+	/// The file does not exist
+	pub const FILE_NOT_FOUND: Self = TftpErrorCode(1);
+	/// The file may not be read or written
+	pub const ACCESS_VIOLATION: Self = TftpErrorCode(2);
+	/// There is no room left for the file
+	pub const DISK_FULL: Self = TftpErrorCode(3);
+	/// The packet is not valid in the transfer
+	pub const ILLEGAL_OPERATION: Self = TftpErrorCode(4);
+	/// The packet was sent from or to a port not taking part in the transfer
+	pub const UNKNOWN_TRANSFER_ID: Self = TftpErrorCode(5);
+	/// The file exists already
+	pub const FILE_EXISTS: Self = TftpErrorCode(6);
+	/// The user does not exist
+	pub const NO_SUCH_USER: Self = TftpErrorCode(7);
+	/// The options of the request were not accepted
+	pub const OPTION_REFUSED: Self = TftpErrorCode(8);
+
+	/// The standard description of the error.
+	fn message(self) -> &'static str {
+		match self {
+			TftpErrorCode::FILE_NOT_FOUND => "file not found",
+			TftpErrorCode::ACCESS_VIOLATION => "access violation",
+			TftpErrorCode::DISK_FULL => "disk full",
+			TftpErrorCode::ILLEGAL_OPERATION => "illegal operation",
+			TftpErrorCode::UNKNOWN_TRANSFER_ID => "unknown transfer ID",
+			TftpErrorCode::FILE_EXISTS => "file exists",
+			TftpErrorCode::NO_SUCH_USER => "no such user",
+			TftpErrorCode::OPTION_REFUSED => "option refused",
+			_ => "transfer failed",
+		}
+	}
+}
+
+/// Where the data received in a TFTP transfer goes, such as a flash region being programmed.
+///
+/// It is implemented for closures taking the data.
+pub trait TftpSink {
+	/// The error returned when the data can not be stored
+	type Error;
+
+	/// Store the next `data` of the file.
+	fn write(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), E>, E> TftpSink for F {
+	type Error = E;
+
+	fn write(&mut self, data: &[u8]) -> Result<(), E> {
+		self(data)
+	}
+}
+
+/// Where the data sent in a TFTP transfer comes from, such as a flash region being read out.
+///
+/// It is implemented for closures filling a buffer.
+pub trait TftpSource {
+	/// The error returned when the data can not be read
+	type Error;
+
+	/// Read the next data of the file into `buffer`, returning its length.
+	///
+	/// Reading less than the length of `buffer` is fine; the end of the file is signalled by
+	/// returning 0.
+	fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+impl<F: FnMut(&mut [u8]) -> Result<usize, E>, E> TftpSource for F {
+	type Error = E;
+
+	fn read(&mut self, buffer: &mut [u8]) -> Result<usize, E> {
+		self(buffer)
+	}
+}
+
+/// Fill `buffer` by reading repeatedly, returning its length unless the end of the data was
+/// reached.
+fn fill<E>(
+	buffer: &mut [u8],
+	mut read: impl FnMut(&mut [u8]) -> Result<usize, E>,
+) -> Result<usize, E> {
+	let mut len = 0;
+	while len < buffer.len() {
+		match read(&mut buffer[len..])? {
+			0 => break,
+			read => len += read.min(buffer.len() - len),
+		}
+	}
+	Ok(len)
+}
+
+/// A received TFTP packet, see [RFC 1350, section 5] and [RFC 2347].
+///
+/// [RFC 1350, section 5]: https://tools.ietf.org/html/rfc1350#section-5
+/// [RFC 2347]: https://tools.ietf.org/html/rfc2347
+#[derive(Copy, Clone, Debug)]
+enum Packet<'a> {
+	Request {
+		write: bool,
+		filename: &'a str,
+		mode: &'a str,
+		options: Options<'a>,
+	},
+	Data {
+		block: u16,
+		data: &'a [u8],
+	},
+	Ack {
+		block: u16,
+	},
+	Error {
+		code: TftpErrorCode,
+	},
+	OptionAck {
+		options: Options<'a>,
+	},
+}
+
+impl<'a> Packet<'a> {
+	fn parse(data: &'a [u8]) -> Option<Self> {
+		let opcode = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+		let rest = &data[2..];
+		let block = || Some(u16::from_be_bytes([*rest.first()?, *rest.get(1)?]));
+		Some(match opcode {
+			OPCODE_READ | OPCODE_WRITE => {
+				let mut strings = rest.split_inclusive(|byte| *byte == 0);
+				let filename = string(strings.next()?)?;
+				let mode = string(strings.next()?)?;
+				let options = &rest[filename.len() + mode.len() + 2..];
+				Packet::Request {
+					write: opcode == OPCODE_WRITE,
+					filename,
+					mode,
+					options: Options(options),
+				}
+			}
+			OPCODE_DATA => Packet::Data {
+				block: block()?,
+				data: &rest[2..],
+			},
+			OPCODE_ACK => Packet::Ack { block: block()? },
+			OPCODE_ERROR => Packet::Error {
+				code: TftpErrorCode(block()?),
+			},
+			OPCODE_OPTION_ACK => Packet::OptionAck {
+				options: Options(rest),
+			},
+			_ => return None,
+		})
+	}
+}
+
+/// A NUL-terminated string of a packet, without the terminator.
+fn string(data: &[u8]) -> Option<&str> {
+	let (0, data) = data.split_last()? else {
+		return None;
+	};
+	core::str::from_utf8(data).ok()
+}
+
+/// The options of a request or option acknowledgment, see [RFC 2347].
+///
+/// [RFC 2347]: https://tools.ietf.org/html/rfc2347
+#[derive(Copy, Clone, Debug)]
+struct Options<'a>(&'a [u8]);
+
+impl<'a> Options<'a> {
+	/// The options with a numeric value, as their name and value; others are skipped.
+	fn iter(&self) -> impl Iterator<Item = (&'a str, u32)> + 'a {
+		let mut strings = self.0.split_inclusive(|byte| *byte == 0);
+		core::iter::from_fn(move || loop {
+			let name = string(strings.next()?)?;
+			let value = string(strings.next()?)?;
+			if let Some(value) = number(value) {
+				return Some((name, value));
+			}
+		})
+	}
+
+	/// The value of the option `name`, which is matched case-insensitively.
+	fn get(&self, name: &str) -> Option<u32> {
+		self.iter()
+			.find(|(option, _)| option.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value)
+	}
+}
+
+/// Parse a decimal option value.
+fn number(value: &str) -> Option<u32> {
+	if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+		return None;
+	}
+	value.parse().ok()
+}
+
+/// Writes a TFTP packet other than a data packet into a buffer.
+struct PacketWriter<'a> {
+	buffer: &'a mut [u8],
+	len: usize,
+}
+
+impl<'a> PacketWriter<'a> {
+	fn new(buffer: &'a mut [u8], opcode: u16) -> Option<Self> {
+		let mut writer = PacketWriter { buffer, len: 0 };
+		writer.bytes(&opcode.to_be_bytes())?;
+		Some(writer)
+	}
+
+	/// A request for `filename` with the given options.
+	fn request(
+		buffer: &'a mut [u8],
+		write: bool,
+		filename: &str,
+		options: &[(&str, u32)],
+	) -> Option<usize> {
+		let opcode = match write {
+			true => OPCODE_WRITE,
+			false => OPCODE_READ,
+		};
+		let mut writer = PacketWriter::new(buffer, opcode)?;
+		writer.string(filename)?;
+		writer.string(MODE_OCTET)?;
+		writer.options(options)?;
+		Some(writer.len)
+	}
+
+	/// An acknowledgment of the data packet `block`, which always fits into 4 bytes.
+	fn ack(buffer: &'a mut [u8], block: u16) -> Option<usize> {
+		let mut writer = PacketWriter::new(buffer, OPCODE_ACK)?;
+		writer.bytes(&block.to_be_bytes())?;
+		Some(writer.len)
+	}
+
+	/// An error with a description.
+	fn error(buffer: &'a mut [u8], code: TftpErrorCode, message: &str) -> Option<usize> {
+		let mut writer = PacketWriter::new(buffer, OPCODE_ERROR)?;
+		writer.bytes(&code.0.to_be_bytes())?;
+		writer.string(message)?;
+		Some(writer.len)
+	}
+
+	/// An acknowledgment of the given options.
+	fn option_ack(buffer: &'a mut [u8], options: &[(&str, u32)]) -> Option<usize> {
+		let mut writer = PacketWriter::new(buffer, OPCODE_OPTION_ACK)?;
+		writer.options(options)?;
+		Some(writer.len)
+	}
+
+	fn options(&mut self, options: &[(&str, u32)]) -> Option<()> {
+		for (name, value) in options {
+			self.string(name)?;
+			let mut digits = [0; 10];
+			let mut start = digits.len();
+			let mut value = *value;
+			loop {
+				start -= 1;
+				digits[start] = b'0' + (value % 10) as u8;
+				value /= 10;
+				if value == 0 {
+					break;
+				}
+			}
+			self.bytes(&digits[start..])?;
+			self.bytes(&[0])?;
+		}
+		Some(())
+	}
+
+	fn string(&mut self, string: &str) -> Option<()> {
+		if string.as_bytes().contains(&0) {
+			return None;
+		}
+		self.bytes(string.as_bytes())?;
+		self.bytes(&[0])
+	}
+
+	fn bytes(&mut self, data: &[u8]) -> Option<()> {
+		let out = self.buffer.get_mut(self.len..self.len + data.len())?;
+		out.copy_from_slice(data);
+		self.len += data.len();
+		Some(())
+	}
+}
+
+/// Write the header of the data packet `block` into `buffer`, which holds at least
+/// [`DATA_HEADER_LEN`] bytes.
+fn write_data_header(buffer: &mut [u8], block: u16) {
+	buffer[..2].copy_from_slice(&OPCODE_DATA.to_be_bytes());
+	buffer[2..DATA_HEADER_LEN].copy_from_slice(&block.to_be_bytes());
+}
+
+/// Pick a random port for the transfer ID of a transfer starting at `now`, see
+/// [RFC 1350, section 4].
+///
+/// [RFC 1350, section 4]: https://tools.ietf.org/html/rfc1350#section-4
+fn transfer_port(seed: &mut u32, now: Duration) -> u16 {
+	// xorshift32, seeded with the time
+	let mut state = *seed ^ now.subsec_nanos() ^ 0x9e37_79b9;
+	state ^= state << 13;
+	state ^= state >> 17;
+	state ^= state << 5;
+	*seed = state;
+	// The dynamic port range of RFC 6335
+	49152 + (state % 16384) as u16
+}
+
+/// The timeout option value for a retransmission timeout, in whole seconds.
+fn timeout_secs(timeout: Duration) -> u32 {
+	timeout.as_secs().clamp(1, 255) as u32
+}
+
+/// Send an error to `remote`, on a best-effort basis.
+fn send_error<S: UdpFullStack>(
+	stack: &mut S,
+	socket: &mut S::UdpSocket,
+	remote: SocketAddr,
+	code: TftpErrorCode,
+	message: &str,
+) {
+	let mut packet = [0; REQUEST_LEN];
+	if let Some(len) = PacketWriter::error(&mut packet, code, message) {
+		stack.send_to(socket, remote, &packet[..len]).ok();
+	}
+}
@@ -0,0 +1,567 @@
+use super::{
+	fill, send_error, transfer_port, write_data_header, Packet, PacketWriter, TftpErrorCode,
+	DATA_HEADER_LEN, DEFAULT_BLOCK_SIZE, DEFAULT_RETRIES, DEFAULT_TIMEOUT, MAX_BLOCK_SIZE,
+	MIN_BLOCK_SIZE, MODE_OCTET, OPTION_BLOCK_SIZE, OPTION_TIMEOUT,
+};
+use crate::{nb, Clock, UdpFullStack};
+use core::net::SocketAddr;
+use core::time::Duration;
+
+/// The message refusing requests during a transfer
+const BUSY: &str = "server busy";
+
+/// The message refusing requests in other modes than `octet`
+const ONLY_OCTET: &str = "only octet mode is supported";
+
+/// The files served by a [`TftpServer`], such as firmware images in flash.
+///
+/// A single file is open at a time: it is opened when a request is accepted, read or written
+/// in order while the transfer goes on, and closed when it ends. Failing operations return the
+/// error that is sent to the client, which ends the transfer.
+pub trait TftpFiles {
+	/// Open `filename` to be read by a client.
+	fn open_read(&mut self, filename: &str) -> Result<(), TftpErrorCode>;
+
+	/// Open `filename` to be written by a client.
+	fn open_write(&mut self, filename: &str) -> Result<(), TftpErrorCode>;
+
+	/// Read the next data of the file opened for reading into `buffer`, returning its length.
+	///
+	/// Reading less than the length of `buffer` is fine; the end of the file is signalled by
+	/// returning 0.
+	fn read(&mut self, buffer: &mut [u8]) -> Result<usize, TftpErrorCode>;
+
+	/// Store the next `data` of the file opened for writing.
+	fn write(&mut self, data: &[u8]) -> Result<(), TftpErrorCode>;
+
+	/// Close the open file; `complete` tells whether all of it was transferred, as a file that
+	/// was written is only to be used then.
+	fn close(&mut self, complete: bool);
+}
+
+/// A transfer in progress
+struct Transfer<T> {
+	socket: T,
+	/// The address of the client, whose port identifies the transfer
+	peer: SocketAddr,
+	/// Whether the client writes the file
+	write: bool,
+	/// The block last sent, or last acknowledged when the client writes the file; 0 before
+	/// the first block
+	block: u16,
+	block_size: usize,
+	timeout: Duration,
+	/// When the last packet was sent
+	sent: Duration,
+	/// Retransmissions of the last packet
+	attempts: u8,
+	/// Whether the last block of the file was sent, or received and acknowledged
+	last: bool,
+}
+
+/// A TFTP server (see [RFC 1350]) on a port of any [`UdpFullStack`], serving [`TftpFiles`] one
+/// transfer at a time, for example to bootloaders or factory test stations.
+///
+/// Files are streamed from and to the [`TftpFiles`] one block at a time, so they never need
+/// to fit into memory. The block size and timeout are negotiated with clients supporting
+/// options ([RFC 2347], [RFC 2348], [RFC 2349]), with blocks of up to `B - 4` bytes; only the
+/// `octet` mode is supported. Requests arriving during a transfer are refused.
+///
+/// The server does not block on incoming packets; it is driven by calling
+/// [`.poll()`](TftpServer::poll) repeatedly, which also retransmits lost packets after the
+/// timeout of `clock`.
+///
+/// ```
+/// use embedded_nal::{TftpErrorCode, TftpFiles, TftpServer, TFTP_PORT};
+/// use core::net::SocketAddr;
+/// use core::time::Duration;
+/// # use embedded_nal::nb;
+/// # use std::{cell::{Cell, RefCell}, collections::VecDeque, rc::Rc};
+/// # /// Packets on their way to a port of the server, and the packets sent from its ports
+/// # #[derive(Default)]
+/// # struct Wire { incoming: VecDeque<(u16, SocketAddr, Vec<u8>)>, sent: Vec<(u16, Vec<u8>)> }
+/// # #[derive(Clone, Default)]
+/// # struct Network(Rc<RefCell<Wire>>);
+/// # impl Network {
+/// #   fn deliver(&self, port: u16, packet: &[u8]) {
+/// #     let client = "192.168.1.20:3000".parse().unwrap();
+/// #     self.0.borrow_mut().incoming.push_back((port, client, packet.to_vec()));
+/// #   }
+/// #   fn sent(&self) -> Vec<(u16, Vec<u8>)> { core::mem::take(&mut self.0.borrow_mut().sent) }
+/// # }
+/// # impl embedded_nal::UdpClientStack for Network {
+/// #   type UdpSocket = u16;
+/// #   type Error = ();
+/// #   fn socket(&mut self) -> Result<u16, ()> { Ok(0) }
+/// #   fn connect(&mut self, _: &mut u16, _: SocketAddr) -> Result<(), ()> { Ok(()) }
+/// #   fn send(&mut self, _: &mut u16, _: &[u8]) -> nb::Result<(), ()> { Ok(()) }
+/// #   fn receive(&mut self, port: &mut u16, buffer: &mut [u8]) -> nb::Result<(usize, SocketAddr), ()> {
+/// #     let incoming = &mut self.0.borrow_mut().incoming;
+/// #     let index = incoming.iter().position(|(to, ..)| to == port).ok_or(nb::Error::WouldBlock)?;
+/// #     let (_, remote, packet) = incoming.remove(index).unwrap();
+/// #     buffer[..packet.len()].copy_from_slice(&packet);
+/// #     Ok((packet.len(), remote))
+/// #   }
+/// #   fn close(&mut self, _: u16) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # impl embedded_nal::UdpFullStack for Network {
+/// #   fn bind(&mut self, socket: &mut u16, port: u16) -> Result<(), ()> { Ok(*socket = port) }
+/// #   fn send_to(&mut self, port: &mut u16, _: SocketAddr, packet: &[u8]) -> nb::Result<(), ()> {
+/// #     self.0.borrow_mut().sent.push((*port, packet.to_vec()));
+/// #     Ok(())
+/// #   }
+/// # }
+/// /// Serves a firmware image for reading, and takes a log for writing.
+/// struct Files {
+///     image: &'static [u8],
+///     offset: usize,
+///     log: Vec<u8>,
+///     complete: Option<bool>,
+/// }
+///
+/// impl TftpFiles for Files {
+///     fn open_read(&mut self, filename: &str) -> Result<(), TftpErrorCode> {
+///         match filename {
+///             "firmware.bin" => Ok(self.offset = 0),
+///             _ => Err(TftpErrorCode::FILE_NOT_FOUND),
+///         }
+///     }
+///
+///     fn open_write(&mut self, filename: &str) -> Result<(), TftpErrorCode> {
+///         match filename {
+///             "log.txt" => Ok(self.log.clear()),
+///             _ => Err(TftpErrorCode::ACCESS_VIOLATION),
+///         }
+///     }
+///
+///     fn read(&mut self, buffer: &mut [u8]) -> Result<usize, TftpErrorCode> {
+///         let data = &self.image[self.offset..];
+///         let len = data.len().min(buffer.len());
+///         buffer[..len].copy_from_slice(&data[..len]);
+///         self.offset += len;
+///         Ok(len)
+///     }
+///
+///     fn write(&mut self, data: &[u8]) -> Result<(), TftpErrorCode> {
+///         Ok(self.log.extend_from_slice(data))
+///     }
+///
+///     fn close(&mut self, complete: bool) {
+///         self.complete = Some(complete);
+///     }
+/// }
+///
+/// # let network = Network::default();
+/// # let stack = network.clone();
+/// # let time = Rc::new(Cell::new(Duration::ZERO));
+/// # let clock = { let time = time.clone(); move || time.get() };
+/// let mut files = Files { image: b"firmwarebin", offset: 0, log: Vec::new(), complete: None };
+/// let mut server: TftpServer<_, _> = TftpServer::new(stack, clock, TFTP_PORT)?;
+///
+/// // A client reads the image in blocks of 8 bytes, from the port the server picked for the
+/// // transfer.
+/// network.deliver(TFTP_PORT, b"\0\x01firmware.bin\0octet\0blksize\08\0");
+/// server.poll(&mut files)?;
+/// let sent = network.sent();
+/// let [(port, oack)] = &sent[..] else { panic!() };
+/// assert_eq!(oack, b"\0\x06blksize\08\0");
+/// network.deliver(*port, b"\0\x04\0\0");
+/// server.poll(&mut files)?;
+/// assert_eq!(network.sent(), [(*port, b"\0\x03\0\x01firmware".to_vec())]);
+///
+/// // The block is sent again when it was not acknowledged in time.
+/// time.set(Duration::from_secs(1));
+/// server.poll(&mut files)?;
+/// assert_eq!(network.sent(), [(*port, b"\0\x03\0\x01firmware".to_vec())]);
+///
+/// // Only the first of duplicate acknowledgments is answered.
+/// network.deliver(*port, b"\0\x04\0\x01");
+/// network.deliver(*port, b"\0\x04\0\x01");
+/// server.poll(&mut files)?;
+/// assert_eq!(network.sent(), [(*port, b"\0\x03\0\x02bin".to_vec())]);
+/// network.deliver(*port, b"\0\x04\0\x02");
+/// server.poll(&mut files)?;
+/// assert!(!server.is_busy());
+/// assert_eq!(files.complete, Some(true));
+///
+/// // A client without options writes the log.
+/// network.deliver(TFTP_PORT, b"\0\x02log.txt\0octet\0");
+/// server.poll(&mut files)?;
+/// let sent = network.sent();
+/// let [(port, ack)] = &sent[..] else { panic!() };
+/// assert_eq!(ack, b"\0\x04\0\0");
+/// network.deliver(*port, b"\0\x03\0\x01boot ok\n");
+/// server.poll(&mut files)?;
+/// assert_eq!(network.sent(), [(*port, b"\0\x04\0\x01".to_vec())]);
+/// assert_eq!(files.log, b"boot ok\n");
+/// assert_eq!(files.complete, Some(true));
+///
+/// // The transfer ends once the client stopped sending the last block again.
+/// assert!(server.is_busy());
+/// time.set(Duration::from_secs(2));
+/// server.poll(&mut files)?;
+/// assert!(!server.is_busy());
+///
+/// // Reading other files is refused.
+/// network.deliver(TFTP_PORT, b"\0\x01secret.key\0octet\0");
+/// server.poll(&mut files)?;
+/// let sent = network.sent();
+/// let [(TFTP_PORT, error)] = &sent[..] else { panic!() };
+/// assert!(error.starts_with(b"\0\x05\0\x01"));
+/// # Ok::<(), ()>(())
+/// ```
+///
+/// [RFC 1350]: https://tools.ietf.org/html/rfc1350
+/// [RFC 2347]: https://tools.ietf.org/html/rfc2347
+/// [RFC 2348]: https://tools.ietf.org/html/rfc2348
+/// [RFC 2349]: https://tools.ietf.org/html/rfc2349
+pub struct TftpServer<S: UdpFullStack, C, const B: usize = 516> {
+	stack: S,
+	clock: C,
+	socket: S::UdpSocket,
+	transfer: Option<Transfer<S::UdpSocket>>,
+	/// The last packet sent in the transfer, kept for retransmission
+	packet: [u8; B],
+	packet_len: usize,
+	timeout: Duration,
+	retries: u8,
+	seed: u32,
+}
+
+impl<S: UdpFullStack, C: Clock, const B: usize> TftpServer<S, C, B> {
+	/// Create a server listening on `port` of `stack`, usually [`TFTP_PORT`], timing
+	/// retransmissions with `clock`.
+	///
+	/// # Panics
+	///
+	/// Panics if `B` is less than 516, which does not hold a block of the default size.
+	///
+	/// [`TFTP_PORT`]: crate::TFTP_PORT
+	pub fn new(mut stack: S, clock: C, port: u16) -> Result<Self, S::Error> {
+		assert!(
+			B >= DATA_HEADER_LEN + DEFAULT_BLOCK_SIZE,
+			"TFTP packet buffer too small"
+		);
+		let mut socket = stack.socket()?;
+		if let Err(e) = stack.bind(&mut socket, port) {
+			stack.close(socket).ok();
+			return Err(e);
+		}
+		Ok(TftpServer {
+			stack,
+			clock,
+			socket,
+			transfer: None,
+			packet: [0; B],
+			packet_len: 0,
+			timeout: DEFAULT_TIMEOUT,
+			retries: DEFAULT_RETRIES,
+			seed: 0,
+		})
+	}
+
+	/// Set the time after which a packet is sent again if the client did not respond, unless
+	/// the client asks for another timeout; the default is 1 second.
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
+	/// Set the number of times a packet is sent again before a transfer is given up; the
+	/// default is 5.
+	pub fn set_retries(&mut self, retries: u8) {
+		self.retries = retries;
+	}
+
+	/// Whether a transfer is in progress.
+	pub fn is_busy(&self) -> bool {
+		self.transfer.is_some()
+	}
+
+	/// Handle the requests and packets received since the last call, reading and writing
+	/// `files`, and retransmit the last packet of the transfer if it timed out.
+	///
+	/// Sending a packet blocks (by spinning on [`nb::Error::WouldBlock`]) until the stack
+	/// accepted it.
+	pub fn poll<F: TftpFiles + ?Sized>(&mut self, files: &mut F) -> Result<(), S::Error> {
+		let mut incoming = [0; B];
+		// Packets of the transfer go first, so that a transfer that ended does not make the server
+		// refuse the next request.
+		while let Some(transfer) = self.transfer.as_mut() {
+			let (len, remote) = match self.stack.receive(&mut transfer.socket, &mut incoming) {
+				Ok(received) => received,
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => return Err(e),
+			};
+			if remote != transfer.peer {
+				let socket = &mut transfer.socket;
+				let code = TftpErrorCode::UNKNOWN_TRANSFER_ID;
+				send_error(&mut self.stack, socket, remote, code, code.message());
+				continue;
+			}
+			self.handle_packet(files, &incoming[..len])?;
+		}
+
+		loop {
+			let (len, remote) = match self.stack.receive(&mut self.socket, &mut incoming) {
+				Ok(received) => received,
+				Err(nb::Error::WouldBlock) => break,
+				Err(nb::Error::Other(e)) => return Err(e),
+			};
+			self.handle_request(files, &incoming[..len], remote)?;
+		}
+
+		let now = self.clock.now();
+		let Some(transfer) = self.transfer.as_mut() else {
+			return Ok(());
+		};
+		if now.saturating_sub(transfer.sent) < transfer.timeout {
+			return Ok(());
+		}
+		if transfer.write && transfer.last {
+			// The final acknowledgment was not sent again for a while, so it arrived.
+			self.finish(files, true, None);
+		} else if transfer.attempts >= self.retries {
+			self.finish(files, false, None);
+		} else {
+			transfer.attempts += 1;
+			self.transmit()?;
+		}
+		Ok(())
+	}
+
+	/// Release the stack and clock, closing the server's sockets.
+	///
+	/// A transfer in progress is dropped without closing its file.
+	pub fn free(mut self) -> (S, C) {
+		if let Some(transfer) = self.transfer.take() {
+			self.stack.close(transfer.socket).ok();
+		}
+		self.stack.close(self.socket).ok();
+		(self.stack, self.clock)
+	}
+
+	/// Start a transfer for a request received on the server's port.
+	fn handle_request<F: TftpFiles + ?Sized>(
+		&mut self,
+		files: &mut F,
+		request: &[u8],
+		remote: SocketAddr,
+	) -> Result<(), S::Error> {
+		let Some(Packet::Request {
+			write,
+			filename,
+			mode,
+			options,
+		}) = Packet::parse(request)
+		else {
+			return Ok(());
+		};
+		match &self.transfer {
+			// The client sent its request again, as the first reply was lost or is late.
+			Some(transfer) if transfer.peer == remote => return Ok(()),
+			// A written file is complete, and its last acknowledgment only kept for a while;
+			// a client that read the last block moved on, even if its acknowledgment was lost.
+			Some(transfer)
+				if transfer.last && (transfer.write || transfer.peer.ip() == remote.ip()) =>
+			{
+				self.finish(files, true, None)
+			}
+			Some(_) => {
+				let code = TftpErrorCode::NOT_DEFINED;
+				send_error(&mut self.stack, &mut self.socket, remote, code, BUSY);
+				return Ok(());
+			}
+			None => (),
+		}
+		if !mode.eq_ignore_ascii_case(MODE_OCTET) {
+			let code = TftpErrorCode::ILLEGAL_OPERATION;
+			send_error(&mut self.stack, &mut self.socket, remote, code, ONLY_OCTET);
+			return Ok(());
+		}
+		let opened = match write {
+			true => files.open_write(filename),
+			false => files.open_read(filename),
+		};
+		if let Err(code) = opened {
+			send_error(
+				&mut self.stack,
+				&mut self.socket,
+				remote,
+				code,
+				code.message(),
+			);
+			return Ok(());
+		}
+
+		let now = self.clock.now();
+		let socket = match self.open(now) {
+			Ok(socket) => socket,
+			Err(e) => {
+				files.close(false);
+				return Err(e);
+			}
+		};
+		let mut transfer = Transfer {
+			socket,
+			peer: remote,
+			write,
+			block: 0,
+			block_size: DEFAULT_BLOCK_SIZE,
+			timeout: self.timeout,
+			sent: now,
+			attempts: 0,
+			last: false,
+		};
+		let mut acknowledged = [("", 0); 2];
+		let mut count = 0;
+		let block_size = options.get(OPTION_BLOCK_SIZE).map(|size| size as usize);
+		if let Some(size) = block_size.filter(|size| *size >= MIN_BLOCK_SIZE) {
+			transfer.block_size = size.min(B - DATA_HEADER_LEN).min(MAX_BLOCK_SIZE);
+			acknowledged[count] = (OPTION_BLOCK_SIZE, transfer.block_size as u32);
+			count += 1;
+		}
+		if let Some(secs) = options
+			.get(OPTION_TIMEOUT)
+			.filter(|secs| (1..=255).contains(secs))
+		{
+			transfer.timeout = Duration::from_secs(secs.into());
+			acknowledged[count] = (OPTION_TIMEOUT, secs);
+			count += 1;
+		}
+
+		let packet = &mut self.packet;
+		let loaded = match (count, write) {
+			(0, true) => Ok(PacketWriter::ack(packet, 0).unwrap_or_default()),
+			(0, false) => load(packet, &mut transfer, files, 1),
+			// The option acknowledgment takes the place of the acknowledgment of block 0, or
+			// of block 1 that is sent once it is acknowledged.
+			_ => Ok(PacketWriter::option_ack(packet, &acknowledged[..count]).unwrap_or_default()),
+		};
+		self.transfer = Some(transfer);
+		match loaded {
+			Ok(len) => {
+				self.packet_len = len;
+				self.transmit()
+			}
+			Err(code) => {
+				self.finish(files, false, Some(code));
+				Ok(())
+			}
+		}
+	}
+
+	/// Act on a packet received from the client of the transfer.
+	fn handle_packet<F: TftpFiles + ?Sized>(
+		&mut self,
+		files: &mut F,
+		packet: &[u8],
+	) -> Result<(), S::Error> {
+		let Some(transfer) = self.transfer.as_mut() else {
+			return Ok(());
+		};
+		match (transfer.write, Packet::parse(packet)) {
+			(_, Some(Packet::Error { .. })) => self.finish(files, false, None),
+			(false, Some(Packet::Ack { block })) if block == transfer.block => {
+				if transfer.last {
+					self.finish(files, true, None);
+					return Ok(());
+				}
+				let next = block.wrapping_add(1);
+				match load(&mut self.packet, transfer, files, next) {
+					Ok(len) => {
+						self.packet_len = len;
+						return self.transmit();
+					}
+					Err(code) => self.finish(files, false, Some(code)),
+				}
+			}
+			(true, Some(Packet::Data { block, data }))
+				if block == transfer.block.wrapping_add(1) && !transfer.last =>
+			{
+				if data.len() > transfer.block_size {
+					self.finish(files, false, Some(TftpErrorCode::ILLEGAL_OPERATION));
+					return Ok(());
+				}
+				if let Err(code) = files.write(data) {
+					self.finish(files, false, Some(code));
+					return Ok(());
+				}
+				transfer.block = block;
+				transfer.attempts = 0;
+				transfer.last = data.len() < transfer.block_size;
+				if transfer.last {
+					files.close(true);
+				}
+				self.packet_len = PacketWriter::ack(&mut self.packet, block).unwrap_or_default();
+				return self.transmit();
+			}
+			// The client did not receive the last acknowledgment.
+			(true, Some(Packet::Data { block, .. })) if block == transfer.block => {
+				return self.transmit();
+			}
+			_ => (),
+		}
+		Ok(())
+	}
+
+	/// Open a socket on a random port, whose number identifies a transfer starting at `now`.
+	fn open(&mut self, now: Duration) -> Result<S::UdpSocket, S::Error> {
+		let port = transfer_port(&mut self.seed, now);
+		let mut socket = self.stack.socket()?;
+		if let Err(e) = self.stack.bind(&mut socket, port) {
+			self.stack.close(socket).ok();
+			return Err(e);
+		}
+		Ok(socket)
+	}
+
+	/// Send the last packet of the transfer (again).
+	fn transmit(&mut self) -> Result<(), S::Error> {
+		let now = self.clock.now();
+		let Some(transfer) = self.transfer.as_mut() else {
+			return Ok(());
+		};
+		transfer.sent = now;
+		let packet = &self.packet[..self.packet_len];
+		nb::block!(self
+			.stack
+			.send_to(&mut transfer.socket, transfer.peer, packet))
+	}
+
+	/// End the transfer, sending `error` to the client if it failed here.
+	fn finish<F: TftpFiles + ?Sized>(
+		&mut self,
+		files: &mut F,
+		complete: bool,
+		error: Option<TftpErrorCode>,
+	) {
+		let Some(mut transfer) = self.transfer.take() else {
+			return;
+		};
+		if let Some(code) = error {
+			let socket = &mut transfer.socket;
+			send_error(&mut self.stack, socket, transfer.peer, code, code.message());
+		}
+		// A written file is closed as soon as its last block arrived.
+		if !(transfer.write && transfer.last) {
+			files.close(complete);
+		}
+		self.stack.close(transfer.socket).ok();
+	}
+}
+
+/// Read the data packet `block` of the transfer into `packet`, returning its length.
+fn load<F: TftpFiles + ?Sized, T>(
+	packet: &mut [u8],
+	transfer: &mut Transfer<T>,
+	files: &mut F,
+	block: u16,
+) -> Result<usize, TftpErrorCode> {
+	let (header, data) = packet.split_at_mut(DATA_HEADER_LEN);
+	let len = fill(&mut data[..transfer.block_size], |data| files.read(data))?;
+	write_data_header(header, block);
+	transfer.block = block;
+	transfer.attempts = 0;
+	transfer.last = len < transfer.block_size;
+	Ok(DATA_HEADER_LEN + len)
+}
@@ -0,0 +1,489 @@
+use crate::{nb, TcpClientStack, TcpError, TcpErrorKind};
+use core::marker::PhantomData;
+use core::net::SocketAddr;
+
+/// A client certificate and its private key, both DER encoded.
+#[derive(Copy, Clone, Debug)]
+pub struct ClientCertificate<'a> {
+	/// The DER encoded certificate
+	pub certificate: &'a [u8],
+	/// The DER encoded private key matching the certificate
+	pub private_key: &'a [u8],
+}
+
+/// A pre-shared key and the identity under which it is known to the server.
+#[derive(Copy, Clone, Debug)]
+pub struct Psk<'a> {
+	/// The PSK identity sent to the server
+	pub identity: &'a [u8],
+	/// The secret key
+	pub key: &'a [u8],
+}
+
+/// Library independent configuration of a TLS client session.
+///
+/// Settings the plugged in TLS library does not support should make
+/// [`TlsClient::session`] fail rather than be ignored.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TlsConfig<'a> {
+	/// DER encoded CA certificates the server certificate is verified against
+	///
+	/// If empty, the TLS library's default trust anchors are used.
+	pub ca_certificates: &'a [&'a [u8]],
+	/// Certificate to authenticate the client with, if requested by the server
+	pub client_certificate: Option<ClientCertificate<'a>>,
+	/// Pre-shared key to use instead of certificate based authentication
+	pub psk: Option<Psk<'a>>,
+}
+
+/// The state of a single TLS connection, as provided by a TLS library.
+///
+/// This crate does not implement TLS itself; TLS libraries are plugged in through this trait and
+/// [`TlsClient`]. They follow the "sans-I/O" model: the session does not perform any I/O, and
+/// the wrappers built on top of it move data between the session and the network. Records
+/// received from the network are passed in through [`.read_tls()`](TlsSession::read_tls), and
+/// records to be sent are taken out through [`.write_tls()`](TlsSession::write_tls);
+/// application data is exchanged through [`.read()`](TlsSession::read) and
+/// [`.write()`](TlsSession::write).
+pub trait TlsSession {
+	/// The type returned when we have an error
+	type Error: core::fmt::Debug;
+
+	/// Whether the handshake is still in progress.
+	fn is_handshaking(&self) -> bool;
+
+	/// Whether the peer has closed the session.
+	fn is_closed(&self) -> bool;
+
+	/// Process data received from the network.
+	///
+	/// Returns the number of bytes consumed, which must be at least one if `data` is not empty.
+	/// An error is returned if the data is not a valid continuation of the session, including any
+	/// failure of the handshake.
+	fn read_tls(&mut self, data: &[u8]) -> Result<usize, Self::Error>;
+
+	/// Take out data to be sent over the network.
+	///
+	/// The data is stored at the beginning of `buffer`, and its length is returned; 0 indicates
+	/// that there is nothing to send.
+	fn write_tls(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+	/// Read decrypted application data.
+	///
+	/// Returns the number of bytes stored at the beginning of `buffer`, which is 0 if no
+	/// application data is available.
+	fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+	/// Queue application data for encryption.
+	///
+	/// Returns the number of bytes accepted, which may be less than `data.len()` (or 0) if the
+	/// session's buffers are full; they are freed up by taking data out through
+	/// [`.write_tls()`](TlsSession::write_tls).
+	fn write(&mut self, data: &[u8]) -> Result<usize, Self::Error>;
+
+	/// Queue a closure alert, to be taken out through [`.write_tls()`](TlsSession::write_tls).
+	fn close(&mut self);
+}
+
+/// A TLS library's factory for client sessions.
+pub trait TlsClient {
+	/// Type of the sessions created by this client
+	type Session: TlsSession;
+
+	/// Create a session that connects to `server_name`, which is also sent through the Server Name
+	/// Indication (SNI) extension and used to verify the server certificate.
+	fn session(
+		&self,
+		server_name: &str,
+		config: &TlsConfig<'_>,
+	) -> Result<Self::Session, <Self::Session as TlsSession>::Error>;
+}
+
+impl<T: TlsClient> TlsClient for &T {
+	type Session = T::Session;
+
+	fn session(
+		&self,
+		server_name: &str,
+		config: &TlsConfig<'_>,
+	) -> Result<Self::Session, <Self::Session as TlsSession>::Error> {
+		T::session(self, server_name, config)
+	}
+}
+
+/// Errors returned by [`TlsStack`].
+#[derive(Debug)]
+pub enum TlsError<T, S> {
+	/// The underlying TCP socket failed.
+	Transport(T),
+	/// The TLS session failed, e.g. because the handshake was rejected.
+	Tls(S),
+	/// The socket is not connected, or the peer closed the connection.
+	Closed,
+	/// The socket was connected without a server name, see [`TlsSocket::configure`].
+	NoServerName,
+}
+
+impl<T: TcpError, S: core::fmt::Debug> TcpError for TlsError<T, S> {
+	fn kind(&self) -> TcpErrorKind {
+		match self {
+			TlsError::Transport(e) => e.kind(),
+			TlsError::Tls(_) => TcpErrorKind::Tls,
+			TlsError::Closed => TcpErrorKind::PipeClosed,
+			TlsError::NoServerName => TcpErrorKind::Other,
+		}
+	}
+}
+
+/// Error type of a [`TlsStack`]
+type Error<T, C> =
+	TlsError<<T as TcpClientStack>::Error, <<C as TlsClient>::Session as TlsSession>::Error>;
+
+/// A TLS client on top of any [`TcpClientStack`].
+///
+/// The TLS library is plugged in as a [`TlsClient`]. Sockets are configured with the server name
+/// and [`TlsConfig`] through [`TlsSocket::configure`] before connecting, which fails with
+/// [`TlsError::NoServerName`] otherwise; the handshake is then performed by
+/// [`.connect()`](TcpClientStack::connect), which returns [`nb::Error::WouldBlock`] until it
+/// completed. Each socket holds two buffers of `N` bytes for
+/// moving data between the TLS session and the TCP socket; they do not need to fit whole TLS
+/// records.
+///
+/// Failures of the TLS session are reported as [`TcpErrorKind::Tls`], and the peer closing the
+/// TCP connection as [`TlsError::Closed`].
+///
+/// ```
+/// use embedded_nal::{nb, TcpClientStack, TlsClient, TlsConfig, TlsError, TlsSession, TlsStack};
+/// # use embedded_nal::{TcpError, TcpErrorKind};
+/// # use core::net::SocketAddr;
+/// #
+/// # /// A stack that replays `reply` in pieces of 3 bytes, then closes the connection, and
+/// # /// accepts at most 5 bytes on every other send
+/// # struct Mock { reply: &'static [u8], sent: Vec<u8>, sends: usize }
+/// # #[derive(Debug)]
+/// # struct Unreachable;
+/// # impl TcpError for Unreachable {
+/// #     fn kind(&self) -> TcpErrorKind { TcpErrorKind::Other }
+/// # }
+/// # impl TcpClientStack for Mock {
+/// #     type TcpSocket = ();
+/// #     type Error = Unreachable;
+/// #     fn socket(&mut self) -> Result<(), Unreachable> { Ok(()) }
+/// #     fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Unreachable> { Ok(()) }
+/// #     fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Unreachable> {
+/// #         self.sends += 1;
+/// #         let len = match self.sends % 2 { 0 => 0, _ => buffer.len().min(5) };
+/// #         self.sent.extend_from_slice(&buffer[..len]);
+/// #         Ok(len)
+/// #     }
+/// #     fn receive(&mut self, _: &mut (), buffer: &mut [u8]) -> nb::Result<usize, Unreachable> {
+/// #         let len = buffer.len().min(self.reply.len()).min(3);
+/// #         buffer[..len].copy_from_slice(&self.reply[..len]);
+/// #         self.reply = &self.reply[len..];
+/// #         Ok(len)
+/// #     }
+/// #     fn close(&mut self, _: ()) -> Result<(), Unreachable> { Ok(()) }
+/// #     fn local_addr(&mut self, _: &()) -> Result<SocketAddr, Unreachable> { Err(Unreachable) }
+/// #     fn peer_addr(&mut self, _: &()) -> Result<SocketAddr, Unreachable> { Err(Unreachable) }
+/// # }
+///
+/// /// A stand-in for a TLS library, which greets the server with the SNI in plain text and
+/// /// passes application data through unchanged.
+/// struct PlainText;
+///
+/// struct Session {
+///     hello: Option<String>,
+///     welcome: usize,
+///     received: Vec<u8>,
+///     pending: Vec<u8>,
+/// }
+///
+/// impl TlsClient for PlainText {
+///     type Session = Session;
+///     fn session(&self, server_name: &str, _: &TlsConfig<'_>) -> Result<Session, ()> {
+///         let hello = Some(format!("HELLO {server_name}\n"));
+///         Ok(Session { hello, welcome: 0, received: Vec::new(), pending: Vec::new() })
+///     }
+/// }
+///
+/// impl TlsSession for Session {
+///     type Error = ();
+///     fn is_handshaking(&self) -> bool { self.welcome < b"WELCOME\n".len() }
+///     fn is_closed(&self) -> bool { false }
+///     fn read_tls(&mut self, data: &[u8]) -> Result<usize, ()> {
+///         if !self.is_handshaking() {
+///             self.received.extend_from_slice(data);
+///             return Ok(data.len());
+///         }
+///         let len = data.len().min(b"WELCOME\n".len() - self.welcome);
+///         match data[..len] == b"WELCOME\n"[self.welcome..][..len] {
+///             true => self.welcome += len,
+///             false => return Err(()),
+///         }
+///         Ok(len)
+///     }
+///     fn write_tls(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+///         let pending = match self.hello.take() {
+///             Some(hello) => hello.into_bytes(),
+///             None => core::mem::take(&mut self.pending),
+///         };
+///         buffer[..pending.len()].copy_from_slice(&pending);
+///         Ok(pending.len())
+///     }
+///     fn read(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+///         let len = buffer.len().min(self.received.len());
+///         buffer[..len].copy_from_slice(&self.received[..len]);
+///         self.received.drain(..len);
+///         Ok(len)
+///     }
+///     fn write(&mut self, data: &[u8]) -> Result<usize, ()> {
+///         self.pending.extend_from_slice(data);
+///         Ok(data.len())
+///     }
+///     fn close(&mut self) {}
+/// }
+///
+/// let mut mock = Mock { reply: b"WELCOME\npong", sent: Vec::new(), sends: 0 };
+/// let mut stack: TlsStack<_, _> = TlsStack::new(&mut mock, PlainText);
+/// let mut socket = stack.socket().unwrap();
+/// // Without a server name, there is nothing to verify the server's certificate against.
+/// let result = stack.connect(&mut socket, ([10, 0, 0, 1], 443).into());
+/// assert!(matches!(result, Err(nb::Error::Other(TlsError::NoServerName))));
+///
+/// socket.configure("example.com", TlsConfig::default());
+/// nb::block!(stack.connect(&mut socket, ([10, 0, 0, 1], 443).into())).unwrap();
+///
+/// nb::block!(stack.send(&mut socket, b"ping")).unwrap();
+/// let mut reply = [0; 4];
+/// let mut len = 0;
+/// while len < reply.len() {
+///     len += nb::block!(stack.receive(&mut socket, &mut reply[len..])).unwrap();
+/// }
+/// assert_eq!(&reply, b"pong");
+///
+/// // The server closed the connection after its reply.
+/// let result = nb::block!(stack.receive(&mut socket, &mut reply));
+/// assert!(matches!(result, Err(TlsError::Closed)));
+/// assert_eq!(mock.sent, b"HELLO example.com\nping");
+/// ```
+pub struct TlsStack<'c, T, C, const N: usize = 1024> {
+	stack: T,
+	client: C,
+	_config: PhantomData<TlsConfig<'c>>,
+}
+
+impl<'c, T, C, const N: usize> TlsStack<'c, T, C, N>
+where
+	T: TcpClientStack,
+	C: TlsClient,
+{
+	/// Create a TLS client that connects through `stack`, with sessions created by `client`.
+	pub fn new(stack: T, client: C) -> Self {
+		TlsStack {
+			stack,
+			client,
+			_config: PhantomData,
+		}
+	}
+
+	/// Send everything the socket's session has pending.
+	fn flush_tls(
+		&mut self,
+		socket: &mut TlsSocket<'c, T::TcpSocket, C::Session, N>,
+	) -> nb::Result<(), Error<T, C>> {
+		let session = socket.session.as_mut().ok_or(TlsError::Closed)?;
+		loop {
+			if socket.tx_start == socket.tx_end {
+				let len = session.write_tls(&mut socket.tx).map_err(TlsError::Tls)?;
+				if len == 0 {
+					return Ok(());
+				}
+				socket.tx_start = 0;
+				socket.tx_end = len;
+			}
+
+			let sent = self
+				.stack
+				.send(
+					&mut socket.socket,
+					&socket.tx[socket.tx_start..socket.tx_end],
+				)
+				.map_err(|e| e.map(TlsError::Transport))?;
+			if sent == 0 {
+				return Err(nb::Error::WouldBlock);
+			}
+			socket.tx_start += sent;
+		}
+	}
+
+	/// Pass received data to the socket's session, receiving from the network if none is
+	/// buffered.
+	///
+	/// Fails with [`TlsError::Closed`] if the peer closed the TCP connection.
+	fn fill(
+		&mut self,
+		socket: &mut TlsSocket<'c, T::TcpSocket, C::Session, N>,
+	) -> nb::Result<(), Error<T, C>> {
+		let session = socket.session.as_mut().ok_or(TlsError::Closed)?;
+		if socket.rx_start == socket.rx_end {
+			let len = self
+				.stack
+				.receive(&mut socket.socket, &mut socket.rx)
+				.map_err(|e| e.map(TlsError::Transport))?;
+			if len == 0 {
+				return Err(nb::Error::Other(TlsError::Closed));
+			}
+			socket.rx_start = 0;
+			socket.rx_end = len;
+		}
+
+		let used = session
+			.read_tls(&socket.rx[socket.rx_start..socket.rx_end])
+			.map_err(TlsError::Tls)?;
+		socket.rx_start += used;
+		Ok(())
+	}
+}
+
+/// A socket of a [`TlsStack`].
+pub struct TlsSocket<'c, T, S, const N: usize> {
+	socket: T,
+	server_name: Option<&'c str>,
+	config: TlsConfig<'c>,
+	session: Option<S>,
+	rx: [u8; N],
+	rx_start: usize,
+	rx_end: usize,
+	tx: [u8; N],
+	tx_start: usize,
+	tx_end: usize,
+}
+
+impl<'c, T, S, const N: usize> TlsSocket<'c, T, S, N> {
+	/// Set the server name and configuration used when the socket is connected.
+	///
+	/// The `server_name` is sent through the Server Name Indication (SNI) extension, and the
+	/// server's certificate is verified against it.
+	pub fn configure(&mut self, server_name: &'c str, config: TlsConfig<'c>) {
+		self.server_name = Some(server_name);
+		self.config = config;
+	}
+
+	/// Access the TLS session, e.g. for inspecting the negotiated parameters.
+	///
+	/// This is `None` until the socket started connecting.
+	pub fn session(&self) -> Option<&S> {
+		self.session.as_ref()
+	}
+}
+
+impl<'c, T, C, const N: usize> TcpClientStack for TlsStack<'c, T, C, N>
+where
+	T: TcpClientStack,
+	C: TlsClient,
+{
+	type TcpSocket = TlsSocket<'c, T::TcpSocket, C::Session, N>;
+	type Error = Error<T, C>;
+
+	fn socket(&mut self) -> Result<Self::TcpSocket, Self::Error> {
+		Ok(TlsSocket {
+			socket: self.stack.socket().map_err(TlsError::Transport)?,
+			server_name: None,
+			config: TlsConfig::default(),
+			session: None,
+			rx: [0; N],
+			rx_start: 0,
+			rx_end: 0,
+			tx: [0; N],
+			tx_start: 0,
+			tx_end: 0,
+		})
+	}
+
+	fn connect(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		remote: SocketAddr,
+	) -> nb::Result<(), Self::Error> {
+		if socket.session.is_none() {
+			let server_name = socket.server_name.ok_or(TlsError::NoServerName)?;
+			self.stack
+				.connect(&mut socket.socket, remote)
+				.map_err(|e| e.map(TlsError::Transport))?;
+			let session = self
+				.client
+				.session(server_name, &socket.config)
+				.map_err(TlsError::Tls)?;
+			socket.session = Some(session);
+		}
+
+		loop {
+			self.flush_tls(socket)?;
+			if !socket.session.as_ref().is_some_and(|s| s.is_handshaking()) {
+				return Ok(());
+			}
+			self.fill(socket)?;
+		}
+	}
+
+	fn send(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffer: &[u8],
+	) -> nb::Result<usize, Self::Error> {
+		self.flush_tls(socket)?;
+
+		let session = socket.session.as_mut().ok_or(TlsError::Closed)?;
+		let len = session.write(buffer).map_err(TlsError::Tls)?;
+		match self.flush_tls(socket) {
+			Ok(()) | Err(nb::Error::WouldBlock) => {}
+			Err(e) => return Err(e),
+		}
+		match len {
+			0 if !buffer.is_empty() => Err(nb::Error::WouldBlock),
+			len => Ok(len),
+		}
+	}
+
+	fn receive(
+		&mut self,
+		socket: &mut Self::TcpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<usize, Self::Error> {
+		loop {
+			let session = socket.session.as_mut().ok_or(TlsError::Closed)?;
+			let len = session.read(buffer).map_err(TlsError::Tls)?;
+			if len > 0 || buffer.is_empty() {
+				return Ok(len);
+			}
+			if session.is_closed() {
+				return Err(nb::Error::Other(TlsError::Closed));
+			}
+
+			// Records such as key updates may need a response before data can flow again.
+			self.flush_tls(socket)?;
+			self.fill(socket)?;
+		}
+	}
+
+	fn close(&mut self, mut socket: Self::TcpSocket) -> Result<(), Self::Error> {
+		if let Some(session) = socket.session.as_mut() {
+			session.close();
+			// Notifying the peer is best effort; the connection is closed either way.
+			let _ = self.flush_tls(&mut socket);
+		}
+		self.stack.close(socket.socket).map_err(TlsError::Transport)
+	}
+
+	fn local_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		self.stack
+			.local_addr(&socket.socket)
+			.map_err(TlsError::Transport)
+	}
+
+	fn peer_addr(&mut self, socket: &Self::TcpSocket) -> Result<SocketAddr, Self::Error> {
+		self.stack
+			.peer_addr(&socket.socket)
+			.map_err(TlsError::Transport)
+	}
+}
@@ -0,0 +1,131 @@
+//! An `HttpServer` on the loopback network, for testing HTTP clients.
+
+use super::{StdError, StdStack};
+use embedded_nal::{HttpMethod, HttpRequest, HttpResponder, HttpRoute, HttpServer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// The state of a server
+#[derive(Default)]
+pub struct Context {
+	pub port: u16,
+	pub posts: usize,
+}
+
+type Responder<'r> = HttpResponder<'r, StdStack>;
+
+fn greeting(_: &mut Context, _: &HttpRequest<'_>, out: &mut Responder<'_>) -> Result<(), StdError> {
+	out.respond(200, &[], b"Hello world!")
+}
+
+fn chunked(_: &mut Context, _: &HttpRequest<'_>, out: &mut Responder<'_>) -> Result<(), StdError> {
+	out.start_chunked(200, &[])?;
+	out.write_chunk(b"Hello ")?;
+	out.write_chunk(b"world!")
+}
+
+/// Echo the `Authorization` header, or `-` without one.
+fn headers(
+	_: &mut Context,
+	request: &HttpRequest<'_>,
+	out: &mut Responder<'_>,
+) -> Result<(), StdError> {
+	let authorization = request.header("Authorization").unwrap_or("-");
+	out.respond(200, &[], authorization.as_bytes())
+}
+
+fn redirect_here(
+	_: &mut Context,
+	_: &HttpRequest<'_>,
+	out: &mut Responder<'_>,
+) -> Result<(), StdError> {
+	out.respond(302, &[("Location", "/headers")], &[])
+}
+
+/// Redirect to the same server under another name, which is another origin.
+fn redirect_elsewhere(
+	context: &mut Context,
+	_: &HttpRequest<'_>,
+	out: &mut Responder<'_>,
+) -> Result<(), StdError> {
+	let location = format!("http://localhost:{}/headers", context.port);
+	out.respond(302, &[("Location", &location)], &[])
+}
+
+fn count(
+	context: &mut Context,
+	request: &HttpRequest<'_>,
+	out: &mut Responder<'_>,
+) -> Result<(), StdError> {
+	context.posts += 1;
+	out.respond(200, &[], request.body)
+}
+
+/// A server running in a thread of its own, serving:
+///
+/// * `GET /greeting` and `GET /chunked` with "Hello world!"
+/// * `GET /headers` with the `Authorization` header
+/// * `GET /redirect/here` and `GET /redirect/elsewhere` with redirects to `/headers`
+/// * `POST /count` with the request body, counting the requests
+pub struct Server {
+	stop: Arc<AtomicBool>,
+	thread: thread::JoinHandle<Context>,
+}
+
+impl Server {
+	pub fn start(port: u16) -> Self {
+		let mut server: HttpServer<_> = HttpServer::new(StdStack, port).unwrap();
+		let stop = Arc::new(AtomicBool::new(false));
+		let stopped = stop.clone();
+		let thread = thread::spawn(move || {
+			let routes = [
+				HttpRoute {
+					method: HttpMethod::Get,
+					path: "/greeting",
+					handler: greeting,
+				},
+				HttpRoute {
+					method: HttpMethod::Get,
+					path: "/chunked",
+					handler: chunked,
+				},
+				HttpRoute {
+					method: HttpMethod::Get,
+					path: "/headers",
+					handler: headers,
+				},
+				HttpRoute {
+					method: HttpMethod::Get,
+					path: "/redirect/here",
+					handler: redirect_here,
+				},
+				HttpRoute {
+					method: HttpMethod::Get,
+					path: "/redirect/elsewhere",
+					handler: redirect_elsewhere,
+				},
+				HttpRoute {
+					method: HttpMethod::Post,
+					path: "/count",
+					handler: count,
+				},
+			];
+			let mut context = Context { port, posts: 0 };
+			while !stopped.load(Ordering::Relaxed) {
+				server.poll(&routes, &mut context).unwrap();
+				thread::sleep(Duration::from_millis(1));
+			}
+			// Closes all connections.
+			server.free();
+			context
+		});
+		Server { stop, thread }
+	}
+
+	pub fn stop(self) -> Context {
+		self.stop.store(true, Ordering::Relaxed);
+		self.thread.join().unwrap()
+	}
+}
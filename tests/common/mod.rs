@@ -0,0 +1,214 @@
+//! A stack on top of `std::net`, for running the clients and servers of this crate against each
+//! other on the loopback network.
+#![allow(dead_code)]
+
+pub mod http_server;
+
+use embedded_nal::{
+	nb, TcpClientStack, TcpError, TcpErrorKind, TcpFullStack, UdpClientStack, UdpFullStack,
+};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+
+/// A port on the loopback network that is not in use at the moment.
+pub fn free_port() -> u16 {
+	let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+	listener.local_addr().unwrap().port()
+}
+
+/// Sockets never block, and are bound to the loopback address, except for bound UDP sockets that
+/// also receive datagrams to the other addresses of the loopback network. Datagrams to the
+/// limited broadcast address are delivered on the loopback network instead.
+#[derive(Default)]
+pub struct StdStack;
+
+#[derive(Debug)]
+pub struct StdError(pub io::Error);
+
+impl TcpError for StdError {
+	fn kind(&self) -> TcpErrorKind {
+		match self.0.kind() {
+			io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset => TcpErrorKind::PipeClosed,
+			_ => TcpErrorKind::Other,
+		}
+	}
+}
+
+/// Turn the errors of non-blocking sockets into their `nb` counterparts.
+fn nb<T>(result: io::Result<T>) -> nb::Result<T, StdError> {
+	result.map_err(|e| match e.kind() {
+		io::ErrorKind::WouldBlock => nb::Error::WouldBlock,
+		_ => nb::Error::Other(StdError(e)),
+	})
+}
+
+pub enum StdTcpSocket {
+	Unbound,
+	Bound(u16),
+	Listener(TcpListener),
+	Stream(TcpStream),
+}
+
+impl StdTcpSocket {
+	fn stream(&mut self) -> Result<&mut TcpStream, StdError> {
+		match self {
+			StdTcpSocket::Stream(stream) => Ok(stream),
+			_ => Err(StdError(io::ErrorKind::NotConnected.into())),
+		}
+	}
+}
+
+impl TcpClientStack for StdStack {
+	type TcpSocket = StdTcpSocket;
+	type Error = StdError;
+
+	fn socket(&mut self) -> Result<StdTcpSocket, StdError> {
+		Ok(StdTcpSocket::Unbound)
+	}
+
+	fn connect(
+		&mut self,
+		socket: &mut StdTcpSocket,
+		remote: SocketAddr,
+	) -> nb::Result<(), StdError> {
+		let stream = TcpStream::connect(remote).map_err(StdError)?;
+		stream.set_nonblocking(true).map_err(StdError)?;
+		*socket = StdTcpSocket::Stream(stream);
+		Ok(())
+	}
+
+	fn send(&mut self, socket: &mut StdTcpSocket, buffer: &[u8]) -> nb::Result<usize, StdError> {
+		nb(socket.stream()?.write(buffer))
+	}
+
+	fn receive(
+		&mut self,
+		socket: &mut StdTcpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<usize, StdError> {
+		nb(socket.stream()?.read(buffer))
+	}
+
+	fn close(&mut self, _: StdTcpSocket) -> Result<(), StdError> {
+		Ok(())
+	}
+
+	fn local_addr(&mut self, socket: &StdTcpSocket) -> Result<SocketAddr, StdError> {
+		match socket {
+			StdTcpSocket::Listener(listener) => listener.local_addr().map_err(StdError),
+			StdTcpSocket::Stream(stream) => stream.local_addr().map_err(StdError),
+			_ => Err(StdError(io::ErrorKind::NotConnected.into())),
+		}
+	}
+
+	fn peer_addr(&mut self, socket: &StdTcpSocket) -> Result<SocketAddr, StdError> {
+		match socket {
+			StdTcpSocket::Stream(stream) => stream.peer_addr().map_err(StdError),
+			_ => Err(StdError(io::ErrorKind::NotConnected.into())),
+		}
+	}
+}
+
+impl TcpFullStack for StdStack {
+	fn bind(&mut self, socket: &mut StdTcpSocket, local_port: u16) -> Result<(), StdError> {
+		*socket = StdTcpSocket::Bound(local_port);
+		Ok(())
+	}
+
+	fn listen(&mut self, socket: &mut StdTcpSocket) -> Result<(), StdError> {
+		let StdTcpSocket::Bound(port) = *socket else {
+			return Err(StdError(io::ErrorKind::InvalidInput.into()));
+		};
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(StdError)?;
+		listener.set_nonblocking(true).map_err(StdError)?;
+		*socket = StdTcpSocket::Listener(listener);
+		Ok(())
+	}
+
+	fn accept(
+		&mut self,
+		socket: &mut StdTcpSocket,
+	) -> nb::Result<(StdTcpSocket, SocketAddr), StdError> {
+		let StdTcpSocket::Listener(listener) = socket else {
+			return Err(nb::Error::Other(StdError(
+				io::ErrorKind::InvalidInput.into(),
+			)));
+		};
+		let (stream, remote) = nb(listener.accept())?;
+		stream.set_nonblocking(true).map_err(StdError)?;
+		Ok((StdTcpSocket::Stream(stream), remote))
+	}
+}
+
+pub enum StdUdpSocket {
+	Unbound,
+	Bound(UdpSocket),
+}
+
+impl StdUdpSocket {
+	fn socket(&mut self) -> Result<&mut UdpSocket, StdError> {
+		match self {
+			StdUdpSocket::Bound(socket) => Ok(socket),
+			StdUdpSocket::Unbound => Err(StdError(io::ErrorKind::NotConnected.into())),
+		}
+	}
+}
+
+/// Bind a non-blocking UDP socket on `address`.
+fn bind_udp(address: Ipv4Addr, port: u16) -> Result<StdUdpSocket, StdError> {
+	let socket = UdpSocket::bind((address, port)).map_err(StdError)?;
+	socket.set_nonblocking(true).map_err(StdError)?;
+	Ok(StdUdpSocket::Bound(socket))
+}
+
+impl UdpClientStack for StdStack {
+	type UdpSocket = StdUdpSocket;
+	type Error = StdError;
+
+	fn socket(&mut self) -> Result<StdUdpSocket, StdError> {
+		Ok(StdUdpSocket::Unbound)
+	}
+
+	fn connect(&mut self, socket: &mut StdUdpSocket, remote: SocketAddr) -> Result<(), StdError> {
+		*socket = bind_udp(Ipv4Addr::LOCALHOST, 0)?;
+		socket.socket()?.connect(remote).map_err(StdError)
+	}
+
+	fn send(&mut self, socket: &mut StdUdpSocket, buffer: &[u8]) -> nb::Result<(), StdError> {
+		nb(socket.socket()?.send(buffer)).map(drop)
+	}
+
+	fn receive(
+		&mut self,
+		socket: &mut StdUdpSocket,
+		buffer: &mut [u8],
+	) -> nb::Result<(usize, SocketAddr), StdError> {
+		nb(socket.socket()?.recv_from(buffer))
+	}
+
+	fn close(&mut self, _: StdUdpSocket) -> Result<(), StdError> {
+		Ok(())
+	}
+}
+
+impl UdpFullStack for StdStack {
+	fn bind(&mut self, socket: &mut StdUdpSocket, local_port: u16) -> Result<(), StdError> {
+		*socket = bind_udp(Ipv4Addr::UNSPECIFIED, local_port)?;
+		Ok(())
+	}
+
+	fn send_to(
+		&mut self,
+		socket: &mut StdUdpSocket,
+		remote: SocketAddr,
+		buffer: &[u8],
+	) -> nb::Result<(), StdError> {
+		let remote = match remote {
+			SocketAddr::V4(remote) if *remote.ip() == Ipv4Addr::BROADCAST => {
+				SocketAddr::new(Ipv4Addr::LOCALHOST.into(), remote.port())
+			}
+			remote => remote,
+		};
+		nb(socket.socket()?.send_to(buffer, remote)).map(drop)
+	}
+}
@@ -0,0 +1,107 @@
+//! Runs the `DhcpClient` against a `DhcpServer` on unprivileged ports of the loopback network.
+
+mod common;
+
+use common::{free_port, StdStack};
+use core::time::Duration;
+use embedded_nal::{DhcpClient, DhcpEvent, DhcpServer, DhcpServerConfig};
+use std::cell::Cell;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::rc::Rc;
+use std::thread;
+
+const MAC: [u8; 6] = [2, 0, 0, 0, 0, 1];
+
+/// Poll `client` and `server` in turns until the client reports an event, or a second passed.
+fn run<C, S>(
+	client: &mut DhcpClient<StdStack, C>,
+	server: &mut DhcpServer<StdStack, S, 4>,
+	config: &DhcpServerConfig<'_>,
+) -> Option<DhcpEvent>
+where
+	C: FnMut() -> Duration,
+	S: FnMut() -> Duration,
+{
+	for _ in 0..1000 {
+		if let Some(event) = client.poll().unwrap() {
+			return Some(event);
+		}
+		server.poll(config).unwrap();
+		thread::sleep(Duration::from_millis(1));
+	}
+	None
+}
+
+#[test]
+fn obtains_renews_and_releases_a_lease() {
+	let server_address = Ipv4Addr::LOCALHOST;
+	let dns_servers = [server_address];
+	let config = DhcpServerConfig {
+		server: server_address,
+		pool_start: Ipv4Addr::new(127, 0, 0, 100),
+		netmask: Ipv4Addr::new(255, 0, 0, 0),
+		router: Some(server_address),
+		dns_servers: &dns_servers,
+		lease_time: Duration::from_secs(3600),
+	};
+	let (server_port, client_port) = (free_port(), free_port());
+	let time = Rc::new(Cell::new(Duration::from_secs(10)));
+
+	let clock = time.clone();
+	let mut server: DhcpServer<_, _, 4> =
+		DhcpServer::new(StdStack, move || clock.get(), server_port).unwrap();
+	server.set_client_port(client_port);
+	let clock = time.clone();
+	let mut client = DhcpClient::new(StdStack, move || clock.get(), MAC);
+	client.set_local_port(client_port);
+	client.set_server_addr(SocketAddr::new(server_address.into(), server_port));
+
+	// The offer and acknowledgment are broadcast, as the client has no address yet.
+	assert_eq!(
+		run(&mut client, &mut server, &config),
+		Some(DhcpEvent::Bound)
+	);
+	let lease = *client.lease().unwrap();
+	assert_eq!(lease.address(), Ipv4Addr::new(127, 0, 0, 100));
+	assert_eq!(lease.netmask(), config.netmask);
+	assert_eq!(lease.router(), Some(server_address));
+	assert_eq!(lease.dns_servers(), &dns_servers);
+	assert_eq!(lease.server(), server_address);
+	assert_eq!(lease.lease_time(), config.lease_time);
+	let leases: Vec<_> = server.leases(&config).collect();
+	assert_eq!(leases.len(), 1);
+	assert_eq!(leases[0].hardware_address, MAC);
+	assert_eq!(leases[0].address, lease.address());
+	assert_eq!(leases[0].expires, Duration::from_secs(3610));
+
+	// Renewing is unicast between the server and the leased address, and changes nothing but
+	// the times of the lease.
+	time.set(Duration::from_secs(10) + lease.renewal_time());
+	let renewed = Duration::from_secs(1810) + config.lease_time;
+	for _ in 0..1000 {
+		assert_eq!(client.poll().unwrap(), None);
+		server.poll(&config).unwrap();
+		if server.leases(&config).all(|lease| lease.expires == renewed) {
+			break;
+		}
+		thread::sleep(Duration::from_millis(1));
+	}
+	let leases: Vec<_> = server.leases(&config).collect();
+	assert_eq!(leases.len(), 1);
+	assert_eq!(leases[0].expires, renewed);
+	// The client took the acknowledgment, and holds the lease past its first expiry.
+	time.set(Duration::from_secs(3610));
+	assert_eq!(client.poll().unwrap(), None);
+	assert_eq!(client.lease().unwrap().address(), lease.address());
+
+	client.release().unwrap();
+	assert!(client.lease().is_none());
+	for _ in 0..1000 {
+		server.poll(&config).unwrap();
+		if server.leases(&config).next().is_none() {
+			break;
+		}
+		thread::sleep(Duration::from_millis(1));
+	}
+	assert_eq!(server.leases(&config).count(), 0);
+}
@@ -0,0 +1,140 @@
+//! Runs the `HttpClient` against an `HttpServer` on the loopback network.
+
+mod common;
+
+use common::http_server::Server;
+use common::{free_port, StdStack};
+use embedded_nal::{nb, AddrType, Dns, HttpClient, HttpMethod};
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpStream};
+
+/// Resolves every name to the loopback address.
+struct Localhost;
+
+impl Dns for Localhost {
+	type Error = ();
+
+	fn get_host_by_name(&mut self, _: &str, _: AddrType) -> nb::Result<IpAddr, ()> {
+		Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+	}
+
+	fn get_host_by_address(&mut self, _: IpAddr, _: &mut [u8]) -> nb::Result<usize, ()> {
+		Err(nb::Error::Other(()))
+	}
+}
+
+#[test]
+fn receives_responses() {
+	let port = free_port();
+	let server = Server::start(port);
+	let mut client = HttpClient::new(StdStack, Localhost);
+	let mut buffer = [0; 512];
+
+	let url = format!("http://127.0.0.1:{port}/greeting");
+	let response = client.get(&url, &mut buffer).unwrap();
+	assert_eq!(response.status, 200);
+	assert_eq!(response.body, b"Hello world!");
+
+	let url = format!("http://localhost:{port}/chunked");
+	let response = client.get(&url, &mut buffer).unwrap();
+	assert_eq!(response.body, b"Hello world!");
+
+	let url = format!("http://localhost:{port}/count");
+	let response = client
+		.request(HttpMethod::Post, &url, &[], b"1", &mut buffer)
+		.unwrap();
+	assert_eq!(response.body, b"1");
+
+	let url = format!("http://localhost:{port}/missing");
+	assert_eq!(client.get(&url, &mut buffer).unwrap().status, 404);
+	client.close().unwrap();
+	assert_eq!(server.stop().posts, 1);
+}
+
+#[test]
+fn sends_header_fields_only_to_their_origin() {
+	let port = free_port();
+	let server = Server::start(port);
+	let mut client = HttpClient::new(StdStack, Localhost);
+	let mut buffer = [0; 512];
+	let authorization = [("Authorization", "Bearer secret")];
+
+	for (path, expected) in [
+		("headers", &b"Bearer secret"[..]),
+		("redirect/here", b"Bearer secret"),
+		("redirect/elsewhere", b"-"),
+	] {
+		let url = format!("http://127.0.0.1:{port}/{path}");
+		let response = client
+			.request(HttpMethod::Get, &url, &authorization, &[], &mut buffer)
+			.unwrap();
+		assert_eq!(response.status, 200);
+		assert_eq!(response.body, expected, "{path}");
+	}
+	server.stop();
+}
+
+#[test]
+fn repeats_only_get_requests_on_closed_connections() {
+	let port = free_port();
+	let url = format!("http://127.0.0.1:{port}/greeting");
+	let mut client = HttpClient::new(StdStack, Localhost);
+	let mut buffer = [0; 512];
+
+	let server = Server::start(port);
+	client.get(&url, &mut buffer).unwrap();
+	server.stop();
+
+	// The kept-open connection was closed by the server in the meantime.
+	let server = Server::start(port);
+	let response = client.get(&url, &mut buffer).unwrap();
+	assert_eq!(response.body, b"Hello world!");
+	server.stop();
+
+	let server = Server::start(port);
+	let url = format!("http://127.0.0.1:{port}/count");
+	assert!(client
+		.request(HttpMethod::Post, &url, &[], b"1", &mut buffer)
+		.is_err());
+	let response = client
+		.request(HttpMethod::Post, &url, &[], b"2", &mut buffer)
+		.unwrap();
+	assert_eq!(response.body, b"2");
+	assert_eq!(server.stop().posts, 1);
+}
+
+/// Send `request` on a new connection, and receive everything until the server closes it.
+fn exchange(port: u16, request: &[u8]) -> Vec<u8> {
+	let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+	stream.write_all(request).unwrap();
+	let mut response = Vec::new();
+	stream.read_to_end(&mut response).unwrap();
+	response
+}
+
+#[test]
+fn sends_chunked_bodies_only_to_http_11_clients() {
+	let port = free_port();
+	let server = Server::start(port);
+
+	let response = exchange(
+		port,
+		b"GET /chunked HTTP/1.1\r\nHost: device\r\nConnection: close\r\n\r\n",
+	);
+	assert_eq!(
+		response,
+		b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+		6\r\nHello \r\n6\r\nworld!\r\n0\r\n\r\n"
+	);
+
+	// The body is delimited by closing the connection, even though the client asked to keep it.
+	let response = exchange(
+		port,
+		b"GET /chunked HTTP/1.0\r\nConnection: keep-alive\r\n\r\n",
+	);
+	assert_eq!(
+		response,
+		b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nHello world!"
+	);
+	server.stop();
+}